@@ -0,0 +1,38 @@
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use quoridor_core::notation::parse_position_with_moves;
+
+#[path = "../src/nn_bot.rs"]
+#[allow(dead_code)]
+mod nn_bot;
+
+use nn_bot::{QuoridorNet, encode, predict_batch};
+
+// Network encode and inference benchmarks. Search benchmarks live in
+// quoridor-core/benches/hot_paths.rs.
+
+const BATCH_SIZES: [usize; 3] = [1, 16, 64];
+
+fn bench_inference(c: &mut Criterion) {
+    let network = QuoridorNet::new();
+    let game = parse_position_with_moves("startpos moves e2 e8 e3 e7 e3h e6h").unwrap();
+    let mut group = c.benchmark_group("nn_inference");
+    for batch_size in BATCH_SIZES {
+        group.throughput(Throughput::Elements(batch_size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(batch_size),
+            &batch_size,
+            |b, &batch_size| {
+                b.iter(|| {
+                    let batch = vec![encode(black_box(&game)); batch_size];
+                    predict_batch(&network, &batch)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_inference);
+criterion_main!(benches);
@@ -0,0 +1,119 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use quoridor_core::{
+    a_star::a_star,
+    all_moves::ALL_MOVES,
+    bot::{Evaluation, best_move_alpha_beta},
+    data_model::{Game, PlayerMove},
+    encoding::encode,
+    engine::legal_moves,
+    game_logic::room_for_wall_placement,
+    notation::parse_position_with_moves,
+    transposition::TranspositionTable,
+};
+
+// Benchmarks for the hot paths of the search. Every benchmark runs on each of
+// the fixture positions so optimizations can be compared position by position.
+
+const FIXTURES: [(&str, &str); 3] = [
+    ("opening", "startpos"),
+    (
+        "middlegame",
+        "startpos moves e2 e8 e3 e7 e3h e6h c5v f4v d7h",
+    ),
+    (
+        "walled",
+        "e4 e6 2 3 w c2h,e2h,g2h,b5v,d6h,f6h,h5v,c8h,g8h,d4v",
+    ),
+];
+
+fn fixtures() -> Vec<(&'static str, Game)> {
+    FIXTURES
+        .iter()
+        .map(|(name, position)| (*name, parse_position_with_moves(position).unwrap()))
+        .collect()
+}
+
+fn bench_a_star(c: &mut Criterion) {
+    let mut group = c.benchmark_group("a_star");
+    for (name, game) in fixtures() {
+        group.bench_function(name, |b| b.iter(|| a_star(black_box(&game), game.player)));
+    }
+    group.finish();
+}
+
+fn bench_wall_legality(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wall_legality");
+    for (name, game) in fixtures() {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                ALL_MOVES
+                    .iter()
+                    .filter(|player_move| match player_move {
+                        PlayerMove::PlaceWall {
+                            orientation,
+                            position,
+                        } => room_for_wall_placement(
+                            black_box(&game),
+                            *orientation,
+                            position.x as isize,
+                            position.y as isize,
+                        ),
+                        PlayerMove::MovePiece(_) => false,
+                    })
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_move_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("move_generation");
+    for (name, game) in fixtures() {
+        group.bench_function(name, |b| b.iter(|| legal_moves(black_box(&game))));
+    }
+    group.finish();
+}
+
+fn bench_alpha_beta(c: &mut Criterion) {
+    let mut group = c.benchmark_group("alpha_beta_depth_2");
+    group.sample_size(10);
+    for (name, game) in fixtures() {
+        // A fresh transposition table for every search, so nothing carries over between
+        // iterations. A small one, so clearing it costs little next to the search
+        let search = |game: &Game| {
+            best_move_alpha_beta(
+                game,
+                game.player,
+                2,
+                Evaluation::PathDifference,
+                None,
+                &mut TranspositionTable::new(1),
+            )
+        };
+        // Report nodes per second rather than searches per second
+        group.throughput(Throughput::Elements(search(&game).nodes));
+        group.bench_function(name, |b| b.iter(|| search(black_box(&game))));
+    }
+    group.finish();
+}
+
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for (name, game) in fixtures() {
+        group.bench_function(name, |b| b.iter(|| encode(black_box(&game))));
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_a_star,
+    bench_wall_legality,
+    bench_move_generation,
+    bench_alpha_beta,
+    bench_encode
+);
+criterion_main!(benches);
@@ -2,39 +2,37 @@ use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::hash::Hash;
 
-use crate::data_model::{Board, MovePiece, PIECE_GRID_HEIGHT, PiecePosition, Player};
+use crate::data_model::{Game, MovePiece, PiecePosition, Player};
 use crate::game_logic::{
     is_move_piece_legal_with_player_at_position, new_position_after_move_piece_unchecked,
 };
+use crate::rules::Rules;
 
-pub fn heuristic(pos: &PiecePosition, player: Player) -> usize {
-    match player {
-        Player::White => PIECE_GRID_HEIGHT - 1 - pos.y(),
-        Player::Black => pos.y(),
-    }
+pub fn heuristic(rules: &Rules, pos: &PiecePosition, player: Player) -> usize {
+    pos.y().abs_diff(rules.goal_row(player))
 }
 
-pub fn a_star(board: &Board, player: Player) -> Option<Vec<PiecePosition>> {
-    let start = board.player_position(player).clone();
+pub fn a_star(game: &Game, player: Player) -> Option<Vec<PiecePosition>> {
+    let start = game.board.player_position(player).clone();
     let mut open_set = PriorityQueue::new();
     let mut came_from = HashMap::<PiecePosition, PiecePosition>::new();
     let mut g_score = HashMap::<PiecePosition, usize>::new();
     let mut f_score = HashMap::<PiecePosition, usize>::new();
     g_score.insert(start.clone(), 0);
-    let h = heuristic(&start, player);
+    let h = heuristic(&game.rules, &start, player);
     f_score.insert(start.clone(), h);
     open_set.insert(h, start.clone());
 
     while let Some((_, current)) = open_set.pop() {
-        if heuristic(&current, player) == 0 {
+        if heuristic(&game.rules, &current, player) == 0 {
             return Some(reconstruct_path(&came_from, &current));
         }
-        for neighbor in neighbors(board, player, &current) {
+        for neighbor in neighbors(game, player, &current) {
             let tentative_g_score = g_score[&current] + 1;
             if tentative_g_score < *g_score.get(&neighbor).unwrap_or(&usize::MAX) {
                 came_from.insert(neighbor.clone(), current.clone());
                 g_score.insert(neighbor.clone(), tentative_g_score);
-                let f = tentative_g_score + heuristic(&neighbor, player);
+                let f = tentative_g_score + heuristic(&game.rules, &neighbor, player);
                 f_score.insert(neighbor.clone(), f);
 
                 open_set.insert(f, neighbor.clone());
@@ -100,15 +98,15 @@ fn reconstruct_path(
     total_path
 }
 
-fn neighbors(board: &Board, player: Player, player_position: &PiecePosition) -> Vec<PiecePosition> {
+fn neighbors(game: &Game, player: Player, player_position: &PiecePosition) -> Vec<PiecePosition> {
     MovePiece::iter()
         .filter_map(|move_piece| {
-            is_move_piece_legal_with_player_at_position(board, player, player_position, &move_piece)
+            is_move_piece_legal_with_player_at_position(game, player, player_position, &move_piece)
                 .then(|| {
                     new_position_after_move_piece_unchecked(
                         player_position,
                         &move_piece,
-                        board.player_position(player.opponent()),
+                        game.board.player_position(player.opponent()),
                     )
                 })
         })
@@ -118,13 +116,13 @@ fn neighbors(board: &Board, player: Player, player_position: &PiecePosition) ->
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::data_model::{Game, WallOrientation};
+    use crate::data_model::WallOrientation;
 
     #[test]
     fn single_wall_test() {
         let mut game = Game::new();
-        game.board.walls[3][2] = Some(WallOrientation::Horizontal);
-        let path = a_star(&game.board, Player::White);
+        game.board.walls[3][2].set(WallOrientation::Horizontal, true);
+        let path = a_star(&game, Player::White);
         assert!(path.is_some());
         let path = path.unwrap();
         assert_eq!(
@@ -148,13 +146,13 @@ mod tests {
         let mut game = Game::new();
         game.board.player_positions[Player::White.as_index()] = PiecePosition::new(4, 4);
         game.board.player_positions[Player::Black.as_index()] = PiecePosition::new(3, 4);
-        game.board.walls[2][3] = Some(WallOrientation::Vertical);
-        game.board.walls[3][3] = Some(WallOrientation::Vertical);
-        game.board.walls[2][5] = Some(WallOrientation::Vertical);
-        game.board.walls[4][3] = Some(WallOrientation::Horizontal);
-        game.board.walls[4][4] = Some(WallOrientation::Horizontal);
-        game.board.walls[5][5] = Some(WallOrientation::Vertical);
-        let path = a_star(&game.board, Player::White);
+        game.board.walls[2][3].set(WallOrientation::Vertical, true);
+        game.board.walls[3][3].set(WallOrientation::Vertical, true);
+        game.board.walls[2][5].set(WallOrientation::Vertical, true);
+        game.board.walls[4][3].set(WallOrientation::Horizontal, true);
+        game.board.walls[4][4].set(WallOrientation::Horizontal, true);
+        game.board.walls[5][5].set(WallOrientation::Vertical, true);
+        let path = a_star(&game, Player::White);
         assert!(path.is_some());
     }
 
@@ -162,7 +160,7 @@ mod tests {
     fn on_goal_test() {
         let mut game = Game::new();
         game.board.player_positions[0] = PiecePosition::new(4, 8);
-        let path = a_star(&game.board, Player::White);
+        let path = a_star(&game, Player::White);
         assert!(path.is_some());
         let path = path.unwrap();
         assert_eq!(path.len(), 0);
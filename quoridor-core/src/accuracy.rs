@@ -0,0 +1,250 @@
+use std::fmt::Display;
+
+use crate::{
+    bot::{Blunder, check_blunder},
+    data_model::{Game, Player, PlayerMove},
+};
+
+// Accuracy reports for finished games. Every move is compared with the best move of a
+// fixed depth search, the number of steps it is worse by being its eval loss:
+//
+//     1 step              inaccuracy  ?!
+//     2 or 3 steps        mistake     ?
+//     4 or more steps     blunder     ??
+//
+// Moves missing a win or walking into a lost game are always blunders. Their loss
+// counts as `DECISIVE_LOSS` steps towards the average.
+//
+// The key moments of a game are the moves where the eval swung the most, by the loss
+// of the move, and the positions where only one move kept the eval, every other move
+// losing at least `ONLY_MOVE_GAP` steps, found or not. A position weighs by the larger
+// of the two, so a game of 80 plies is summed up by its few decisions that mattered.
+
+pub const DECISIVE_LOSS: isize = 10;
+pub const ONLY_MOVE_GAP: isize = 2;
+/// Key moments shown in reports.
+pub const KEY_MOMENTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Severity {
+    pub fn of(blunder: &Blunder) -> Option<Self> {
+        match blunder.loss {
+            _ if blunder.misses_win || blunder.loses_game => Some(Self::Blunder),
+            0 => None,
+            1 => Some(Self::Inaccuracy),
+            2..=3 => Some(Self::Mistake),
+            _ => Some(Self::Blunder),
+        }
+    }
+}
+
+/// A move of the game compared with the engine's choice.
+pub struct MoveReview {
+    pub ply: usize,
+    /// The position before the move.
+    pub game: Game,
+    /// The comparison with the best move, even when the move was the best.
+    pub comparison: Blunder,
+    pub severity: Option<Severity>,
+}
+
+impl MoveReview {
+    /// The eval loss, capped at `DECISIVE_LOSS`.
+    pub fn loss(&self) -> isize {
+        if self.comparison.misses_win || self.comparison.loses_game {
+            DECISIVE_LOSS
+        } else {
+            self.comparison.loss.min(DECISIVE_LOSS)
+        }
+    }
+
+    /// Whether only the best move kept the eval.
+    pub fn only_move(&self) -> bool {
+        self.comparison.best_gap >= ONLY_MOVE_GAP
+    }
+
+    /// How much the move mattered, the larger of its loss and of how far the only move
+    /// was ahead.
+    pub fn importance(&self) -> isize {
+        let gap = if self.only_move() {
+            self.comparison.best_gap.min(DECISIVE_LOSS)
+        } else {
+            0
+        };
+        self.loss().max(gap)
+    }
+
+    /// A line on the move as a key moment, e.g. "12. e5: the only move, found".
+    pub fn moment_text(&self) -> String {
+        match (self.loss(), self.only_move()) {
+            (0, _) => format!(
+                "{}. {}: the only move, found",
+                self.ply + 1,
+                self.comparison.played
+            ),
+            (_, true) => format!("{}. {} (only move)", self.ply + 1, self.comparison),
+            (_, false) => format!("{}. {}", self.ply + 1, self.comparison),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PlayerReport {
+    pub moves: usize,
+    pub total_loss: isize,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+}
+
+impl PlayerReport {
+    pub fn average_loss(&self) -> f64 {
+        if self.moves == 0 {
+            0.0
+        } else {
+            self.total_loss as f64 / self.moves as f64
+        }
+    }
+}
+
+pub struct GameReport {
+    pub moves: Vec<MoveReview>,
+    pub players: [PlayerReport; 2],
+}
+
+impl GameReport {
+    /// The move losing the most, where the game turned.
+    pub fn turning_point(&self) -> Option<&MoveReview> {
+        self.moves
+            .iter()
+            .filter(|review| review.loss() > 0)
+            .max_by_key(|review| (review.loss(), std::cmp::Reverse(review.ply)))
+    }
+
+    /// The `count` moves that mattered the most, in the order they were played.
+    pub fn key_moments(&self, count: usize) -> Vec<&MoveReview> {
+        let mut moments: Vec<&MoveReview> = self
+            .moves
+            .iter()
+            .filter(|review| review.importance() > 0)
+            .collect();
+        moments.sort_by_key(|review| (std::cmp::Reverse(review.importance()), review.ply));
+        moments.truncate(count);
+        moments.sort_by_key(|review| review.ply);
+        moments
+    }
+
+    /// The key moments as a summary, a line per moment.
+    pub fn key_moments_text(&self, count: usize) -> String {
+        let moments = self.key_moments(count);
+        if moments.is_empty() {
+            return "No key moments".to_string();
+        }
+        let lines: Vec<String> = moments.iter().map(|review| review.moment_text()).collect();
+        format!("Key moments:\n{}", lines.join("\n"))
+    }
+}
+
+impl Display for GameReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for player in [Player::White, Player::Black] {
+            let report = &self.players[player.as_index()];
+            writeln!(
+                f,
+                "{}: average loss {:.2}, {} inaccuracies, {} mistakes, {} blunders",
+                player.to_string(),
+                report.average_loss(),
+                report.inaccuracies,
+                report.mistakes,
+                report.blunders
+            )?;
+        }
+        match self.turning_point() {
+            Some(review) => write!(
+                f,
+                "Turning point at ply {}: {}",
+                review.ply + 1,
+                review.comparison
+            ),
+            None => write!(f, "No move lost anything"),
+        }
+    }
+}
+
+/// Re-analyses every move of a game with a `depth` ply search, `game_states` being the
+/// positions the moves were played in.
+pub fn analyse_game<'a>(
+    game_states: &[Game],
+    player_moves: impl IntoIterator<Item = &'a PlayerMove>,
+    depth: usize,
+) -> GameReport {
+    let mut players = [PlayerReport::default(); 2];
+    let moves: Vec<MoveReview> = game_states
+        .iter()
+        .zip(player_moves)
+        .enumerate()
+        .filter_map(|(ply, (game, player_move))| {
+            let comparison = check_blunder(game, player_move, depth, 0)?;
+            Some(MoveReview {
+                ply,
+                game: game.clone(),
+                severity: Severity::of(&comparison),
+                comparison,
+            })
+        })
+        .collect();
+    for review in &moves {
+        let report = &mut players[review.game.player.as_index()];
+        report.moves += 1;
+        report.total_loss += review.loss();
+        match review.severity {
+            Some(Severity::Inaccuracy) => report.inaccuracies += 1,
+            Some(Severity::Mistake) => report.mistakes += 1,
+            Some(Severity::Blunder) => report.blunders += 1,
+            None => {}
+        }
+    }
+    GameReport { moves, players }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game_record::GameRecord,
+        notation::{parse_notation_move, parse_position},
+    };
+
+    #[test]
+    fn report_test() {
+        // Black steps sideways instead of into the goal row, White then wins
+        let game = parse_position("e8 e2 0 0 b -").unwrap();
+        let sideways = parse_notation_move(&game, "d2").unwrap();
+        let comparison = check_blunder(&game, &sideways, 2, 0).unwrap();
+        assert!(comparison.misses_win);
+        assert_eq!(Severity::of(&comparison), Some(Severity::Blunder));
+
+        let record = GameRecord::parse("mdu;mud;").unwrap();
+        let report = analyse_game(&record.game_states(), record.player_moves(), 1);
+        assert_eq!(report.moves.len(), 2);
+        assert_eq!(report.moves[0].comparison.played, "e2");
+        assert_eq!(report.players[Player::White.as_index()].moves, 1);
+        assert!(report.to_string().starts_with("White: average loss"));
+
+        // Stepping into the goal row is the only move, found the second time
+        let winning = parse_notation_move(&game, "e1").unwrap();
+        let report = analyse_game(&[game.clone(), game], [&sideways, &winning], 2);
+        assert!(report.moves.iter().all(MoveReview::only_move));
+        let moments = report.key_moments(1);
+        assert_eq!(moments.len(), 1);
+        assert_eq!(moments[0].ply, 0);
+        assert_eq!(report.moves[1].moment_text(), "2. e1: the only move, found");
+        assert!(report.key_moments_text(2).starts_with("Key moments:"));
+    }
+}
@@ -1,4 +1,16 @@
-use crate::data_model::{Direction, MovePiece, PlayerMove, WallOrientation, WallPosition};
+use crate::data_model::{
+    Direction, MovePiece, PlayerMove, WALL_GRID_HEIGHT, WALL_GRID_WIDTH, WallOrientation,
+    WallPosition,
+};
+
+// The canonical action space: the id of a move is its index in `ALL_MOVES`. The 16 pawn
+// moves come first, then the walls by orientation, x and y. The wall grid of the table
+// is one larger than the board's in both directions, so walls with x or y 8 are never
+// legal. The network, the RL environment and the protocol layers all index moves here.
+
+/// The pawn moves at the start of `ALL_MOVES`.
+pub const PAWN_MOVES: usize = 16;
+
 #[rustfmt::skip]
 pub const ALL_MOVES: [PlayerMove; 178] = [
     PlayerMove::MovePiece(MovePiece{direction: Direction::Up, direction_on_collision: Direction::Up}),
@@ -180,3 +192,105 @@ pub const ALL_MOVES: [PlayerMove; 178] = [
     PlayerMove::PlaceWall { orientation: WallOrientation::Vertical, position: WallPosition { x: 8, y: 7 }},
     PlayerMove::PlaceWall { orientation: WallOrientation::Vertical, position: WallPosition { x: 8, y: 8 }},
 ];
+
+/// The id of a move, its index in `ALL_MOVES`, worked out from the layout so the search
+/// can index tables by it. None for walls outside the table's grid.
+pub fn action_id(player_move: &PlayerMove) -> Option<usize> {
+    match player_move {
+        PlayerMove::MovePiece(move_piece) => {
+            Some(move_piece.direction as usize * 4 + move_piece.direction_on_collision as usize)
+        }
+        PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } => {
+            let (width, height) = (WALL_GRID_WIDTH + 1, WALL_GRID_HEIGHT + 1);
+            (position.x < width && position.y < height).then(|| {
+                PAWN_MOVES
+                    + *orientation as usize * width * height
+                    + position.x * height
+                    + position.y
+            })
+        }
+    }
+}
+
+/// Whether the move can be legal in some position of the standard board.
+pub fn is_playable(player_move: &PlayerMove) -> bool {
+    match player_move {
+        PlayerMove::MovePiece(_) => true,
+        PlayerMove::PlaceWall { position, .. } => {
+            position.x < WALL_GRID_WIDTH && position.y < WALL_GRID_HEIGHT
+        }
+    }
+}
+
+/// The moves `ALL_MOVES` must hold, in its order.
+fn expected_moves() -> Vec<PlayerMove> {
+    let pawn_moves = Direction::iter().flat_map(|direction| {
+        Direction::iter().map(move |direction_on_collision| {
+            PlayerMove::MovePiece(MovePiece {
+                direction,
+                direction_on_collision,
+            })
+        })
+    });
+    let walls = [WallOrientation::Horizontal, WallOrientation::Vertical]
+        .into_iter()
+        .flat_map(|orientation| {
+            (0..=WALL_GRID_WIDTH).flat_map(move |x| {
+                (0..=WALL_GRID_HEIGHT).map(move |y| PlayerMove::PlaceWall {
+                    orientation,
+                    position: WallPosition { x, y },
+                })
+            })
+        });
+    pawn_moves.chain(walls).collect()
+}
+
+/// Checks that every id of `ALL_MOVES` maps to the move the canonical layout puts there,
+/// so no playable move is missing or repeated.
+pub fn validate_all_moves() -> Result<(), String> {
+    let expected = expected_moves();
+    if expected.len() != ALL_MOVES.len() {
+        return Err(format!(
+            "ALL_MOVES has {} moves, the action space {}",
+            ALL_MOVES.len(),
+            expected.len()
+        ));
+    }
+    for (id, (actual, expected)) in ALL_MOVES.iter().zip(&expected).enumerate() {
+        if actual.to_string() != expected.to_string() {
+            return Err(format!(
+                "Action {id} is {actual} in ALL_MOVES, {expected} in the action space"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_space_test() {
+        assert_eq!(validate_all_moves(), Ok(()));
+        assert!(
+            ALL_MOVES[..PAWN_MOVES]
+                .iter()
+                .all(|player_move| matches!(player_move, PlayerMove::MovePiece(_)))
+        );
+        for (id, player_move) in ALL_MOVES.iter().enumerate() {
+            assert_eq!(action_id(player_move), Some(id));
+        }
+        let playable = ALL_MOVES
+            .iter()
+            .filter(|player_move| is_playable(player_move))
+            .count();
+        assert_eq!(
+            playable,
+            PAWN_MOVES + 2 * WALL_GRID_WIDTH * WALL_GRID_HEIGHT
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::{SearchInfo, decided},
+    data_model::{Game, Player, PlayerMove},
+    game_logic::execute_move_unchecked,
+    notation::move_to_notation,
+    win_probability::WinProbabilityModel,
+};
+
+// Machine readable analysis output. These types are part of the output
+// format of the CLI `--json` flags and the engine server, so fields should
+// only ever be added, not renamed or removed.
+
+/// Engine score from White's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Score {
+    /// Difference in shortest path lengths in hundredths of a step.
+    Centipaths(i64),
+    /// The game is decided. `plies` is the distance to the end of the game when known.
+    Win { winner: Player, plies: Option<u32> },
+}
+
+impl Score {
+    pub fn from_engine_score(score: isize) -> Self {
+        match decided(score) {
+            Some((winner, plies)) => Score::Win {
+                winner,
+                plies: Some(plies as u32),
+            },
+            None => Score::Centipaths(score as i64 * 100),
+        }
+    }
+}
+
+/// Information about one completed search iteration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisInfo {
+    pub depth: usize,
+    pub score: Score,
+    pub nodes: u64,
+    pub time_ms: u64,
+    /// Permille of the transposition table in use.
+    #[serde(default)]
+    pub hashfull: usize,
+    /// Principal variation in standard notation.
+    pub pv: Vec<String>,
+    /// Probability that White wins, from the default win probability model.
+    #[serde(default)]
+    pub win_probability: Option<f64>,
+}
+
+/// Final result of a search, the best move in both notations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub best_move: Option<String>,
+    pub best_move_notation: Option<String>,
+    pub info: AnalysisInfo,
+}
+
+/// Converts a sequence of moves starting in `game` to standard notation.
+pub fn line_to_notation(game: &Game, line: &[PlayerMove]) -> Vec<String> {
+    let mut game = game.clone();
+    line.iter()
+        .map(|player_move| {
+            let notation = move_to_notation(&game, player_move);
+            let player = game.player;
+            execute_move_unchecked(&mut game, player, player_move);
+            notation
+        })
+        .collect()
+}
+
+impl AnalysisInfo {
+    pub fn new(game: &Game, search_info: &SearchInfo, elapsed: Duration) -> Self {
+        Self {
+            depth: search_info.depth,
+            score: Score::from_engine_score(search_info.score),
+            nodes: search_info.nodes,
+            time_ms: elapsed.as_millis() as u64,
+            hashfull: search_info.hashfull,
+            pv: line_to_notation(game, &search_info.principal_variation),
+            win_probability: Some(
+                WinProbabilityModel::default().white_win_probability(game, search_info.score),
+            ),
+        }
+    }
+}
+
+impl SearchResult {
+    pub fn new(game: &Game, search_info: &SearchInfo, elapsed: Duration) -> Self {
+        Self {
+            best_move: search_info.best_move().map(|m| m.to_string()),
+            best_move_notation: search_info
+                .best_move()
+                .map(|m| move_to_notation(game, m)),
+            info: AnalysisInfo::new(game, search_info, elapsed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bot::{Evaluation, best_move_alpha_beta, win_score},
+        transposition::TranspositionTable,
+    };
+
+    #[test]
+    fn search_result_json_test() {
+        let game = Game::new();
+        let search_info = best_move_alpha_beta(
+            &game,
+            Player::White,
+            1,
+            Evaluation::PathDifference,
+            None,
+            &mut TranspositionTable::default(),
+        );
+        let result = SearchResult::new(&game, &search_info, Duration::from_millis(5));
+        assert_eq!(result.info.pv.len(), 1);
+        assert!(result.info.nodes > 1);
+        let json = serde_json::to_string(&result).unwrap();
+        let parsed: SearchResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, result);
+    }
+
+    #[test]
+    fn decisive_score_test() {
+        assert_eq!(
+            serde_json::to_string(&Score::from_engine_score(win_score(Player::Black, 3))).unwrap(),
+            r#"{"win":{"winner":"Black","plies":3}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Score::from_engine_score(-2)).unwrap(),
+            r#"{"centipaths":-200}"#
+        );
+    }
+}
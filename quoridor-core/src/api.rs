@@ -0,0 +1,70 @@
+// The stable public surface of the crate, for bindings, servers and other frontends.
+// The modules next to this one are internals that change with the engine; what is
+// re-exported here only changes with a major version. Frontends should import from
+// `quoridor_core::api` alone.
+
+// The game state and moves.
+pub use crate::data_model::{
+    Board, Direction, Game, MovePiece, PiecePosition, Player, PlayerMove, WallOrientation,
+    WallPosition,
+};
+// The rule variants.
+pub use crate::rules::{JumpRule, Rules, VARIANT_NAMES};
+
+// Playing moves.
+pub use crate::engine::{check_playable, legal_moves};
+pub use crate::game_logic::{execute_move_unchecked, is_move_legal, winner};
+
+// Engines and their searches.
+pub use crate::analysis::{AnalysisInfo, Score, SearchResult};
+pub use crate::bot::SearchInfo;
+pub use crate::engine::{
+    AlphaBetaEngine, Clock, Engine, EngineMove, GameEvent, GreedyEngine, RandomEngine, SearchLimits,
+};
+pub use crate::protocol::ExternalEngine;
+
+// Move and position notation, see notation.rs for the formats.
+pub use crate::notation::{
+    WallNotation, convert_wall, move_to_notation, parse_game_log, parse_notation_move,
+    parse_player_move, parse_position, parse_position_with_moves, position_to_string,
+};
+
+// Game records, the serialization of whole games with their tags and annotations.
+pub use crate::game_record::{GameRecord, MoveAnnotation, RecordedMove, ThinkTime};
+
+// The rated games of humans, see leaderboard.rs for the format.
+pub use crate::leaderboard::{Leaderboard, LeaderboardGame, today};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_test() {
+        // A game played and written through the facade only
+        let mut game = parse_position("e1 e9 10 10 w -").unwrap();
+        let mut engine = AlphaBetaEngine::new();
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let mut record = GameRecord::default();
+        for _ in 0..4 {
+            check_playable(&game).unwrap();
+            let engine_move = engine.choose_move(&game, &limits);
+            assert!(is_move_legal(&game, game.player, &engine_move.player_move));
+            let player = game.player;
+            execute_move_unchecked(&mut game, player, &engine_move.player_move);
+            record.moves.push(RecordedMove {
+                annotation: engine_move.annotation(),
+                player_move: engine_move.player_move,
+            });
+        }
+        assert_eq!(winner(&game), None);
+        let parsed = GameRecord::parse(&record.to_string()).unwrap();
+        assert_eq!(
+            position_to_string(parsed.game_states().last().unwrap()),
+            position_to_string(&game)
+        );
+    }
+}
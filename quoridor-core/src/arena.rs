@@ -0,0 +1,328 @@
+use crate::{
+    data_model::{Game, Player},
+    engine::{Engine, GameEvent, SearchLimits, legal_moves},
+    game_logic::{execute_move_unchecked, is_move_legal, winner},
+    game_record::{GameRecord, RecordedMove, ThinkTime},
+    rules::Rules,
+};
+
+// Match runner playing engines against each other.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Player),
+    /// The game hit the ply limit.
+    Draw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    GoalReached,
+    IllegalMove,
+    /// The side to move had no legal move, which loses like in the searches.
+    NoLegalMoves,
+    PlyLimit,
+    /// The engine to move failed, like an external program that crashed, which forfeits.
+    EngineFailure,
+}
+
+pub struct FinishedGame {
+    pub record: GameRecord,
+    pub rules: Rules,
+    pub outcome: Outcome,
+    pub termination: Termination,
+}
+
+impl Outcome {
+    /// The result in the usual "1-0" notation.
+    pub fn result(&self) -> &'static str {
+        match self {
+            Outcome::Win(Player::White) => "1-0",
+            Outcome::Win(Player::Black) => "0-1",
+            Outcome::Draw => "1/2-1/2",
+        }
+    }
+
+    /// The outcome of a result in the notation of `result`, as in a record's result tag.
+    pub fn parse(result: &str) -> Option<Self> {
+        match result.trim() {
+            "1-0" => Some(Outcome::Win(Player::White)),
+            "0-1" => Some(Outcome::Win(Player::Black)),
+            "1/2-1/2" => Some(Outcome::Draw),
+            _ => None,
+        }
+    }
+}
+
+/// Match score from the point of view of the first engine.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    /// Think time of the first and the second engine over all games.
+    pub think_times: [ThinkTime; 2],
+}
+
+impl MatchScore {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Points scored by the first engine, a draw counts as half a point.
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+}
+
+/// A move of a game being played, see `play_game_with`.
+pub struct PlayedMove<'a> {
+    /// The position the move was played in.
+    pub game: &'a Game,
+    /// 1 based.
+    pub ply: usize,
+    pub recorded_move: &'a RecordedMove,
+}
+
+/// Plays one game from the initial position. An engine returning an illegal move or
+/// failing to return one loses.
+pub fn play_game(
+    white: &mut dyn Engine,
+    black: &mut dyn Engine,
+    rules: &Rules,
+    limits: &SearchLimits,
+    max_plies: usize,
+) -> FinishedGame {
+    play_game_with(white, black, rules, limits, max_plies, |_| {})
+}
+
+/// Like `play_game`, calling `on_move` with every legal move as it is played.
+pub fn play_game_with(
+    white: &mut dyn Engine,
+    black: &mut dyn Engine,
+    rules: &Rules,
+    limits: &SearchLimits,
+    max_plies: usize,
+    mut on_move: impl FnMut(&PlayedMove),
+) -> FinishedGame {
+    let mut game = Game::with_rules(*rules);
+    let mut record = GameRecord::default();
+    white.new_game();
+    black.new_game();
+    for _ in 0..max_plies {
+        let player = game.player;
+        if legal_moves(&game).is_empty() {
+            return FinishedGame {
+                record,
+                rules: *rules,
+                outcome: Outcome::Win(player.opponent()),
+                termination: Termination::NoLegalMoves,
+            };
+        }
+        let engine_move = match player {
+            Player::White => white.try_choose_move(&game, limits),
+            Player::Black => black.try_choose_move(&game, limits),
+        };
+        let engine_move = match engine_move {
+            Ok(engine_move) => engine_move,
+            Err(error) => {
+                eprintln!("{error}");
+                return FinishedGame {
+                    record,
+                    rules: *rules,
+                    outcome: Outcome::Win(player.opponent()),
+                    termination: Termination::EngineFailure,
+                };
+            }
+        };
+        if !is_move_legal(&game, player, &engine_move.player_move) {
+            return FinishedGame {
+                record,
+                rules: *rules,
+                outcome: Outcome::Win(player.opponent()),
+                termination: Termination::IllegalMove,
+            };
+        }
+        let recorded_move = RecordedMove {
+            annotation: engine_move.annotation(),
+            player_move: engine_move.player_move,
+        };
+        on_move(&PlayedMove {
+            game: &game,
+            ply: record.moves.len() + 1,
+            recorded_move: &recorded_move,
+        });
+        execute_move_unchecked(&mut game, player, &recorded_move.player_move);
+        record.moves.push(recorded_move);
+        let event = GameEvent::MovePlayed {
+            game: &game,
+            ply: record.moves.len(),
+        };
+        white.game_event(&event);
+        black.game_event(&event);
+        if let Some(winner) = winner(&game) {
+            return FinishedGame {
+                record,
+                rules: *rules,
+                outcome: Outcome::Win(winner),
+                termination: Termination::GoalReached,
+            };
+        }
+    }
+    FinishedGame {
+        record,
+        rules: *rules,
+        outcome: Outcome::Draw,
+        termination: Termination::PlyLimit,
+    }
+}
+
+/// Plays `games` games, alternating colors with the first engine starting as White.
+/// `on_game` is called after every game with the game index and whether the first engine was White,
+/// and stops the match early by returning false.
+pub fn play_match(
+    first: &mut dyn Engine,
+    second: &mut dyn Engine,
+    games: usize,
+    rules: &Rules,
+    limits: &SearchLimits,
+    max_plies: usize,
+    on_game: impl FnMut(usize, bool, &FinishedGame) -> bool,
+) -> MatchScore {
+    play_match_with(
+        first,
+        second,
+        games,
+        rules,
+        limits,
+        max_plies,
+        |_, _, _| {},
+        on_game,
+    )
+}
+
+/// Like `play_match`, calling `on_move` with the game index, whether the first engine is
+/// White and every move as it is played.
+#[allow(clippy::too_many_arguments)]
+pub fn play_match_with(
+    first: &mut dyn Engine,
+    second: &mut dyn Engine,
+    games: usize,
+    rules: &Rules,
+    limits: &SearchLimits,
+    max_plies: usize,
+    mut on_move: impl FnMut(usize, bool, &PlayedMove),
+    mut on_game: impl FnMut(usize, bool, &FinishedGame) -> bool,
+) -> MatchScore {
+    let mut score = MatchScore::default();
+    for game_index in 0..games {
+        let first_is_white = game_index % 2 == 0;
+        let on_move = |played_move: &PlayedMove| on_move(game_index, first_is_white, played_move);
+        let finished_game = if first_is_white {
+            play_game_with(first, second, rules, limits, max_plies, on_move)
+        } else {
+            play_game_with(second, first, rules, limits, max_plies, on_move)
+        };
+        let first_color = if first_is_white {
+            Player::White
+        } else {
+            Player::Black
+        };
+        match finished_game.outcome {
+            Outcome::Win(winner) if winner == first_color => score.wins += 1,
+            Outcome::Win(_) => score.losses += 1,
+            Outcome::Draw => score.draws += 1,
+        }
+        let think_times = finished_game.record.think_times();
+        score.think_times[0] += think_times[first_color.as_index()];
+        score.think_times[1] += think_times[first_color.opponent().as_index()];
+        if !on_game(game_index, first_is_white, &finished_game) {
+            break;
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{AlphaBetaEngine, EngineMove, GreedyEngine, RandomEngine};
+
+    #[test]
+    fn alpha_beta_beats_random_test() {
+        let mut alpha_beta = AlphaBetaEngine::new();
+        let mut random = RandomEngine::new(Some(7));
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let mut records = Vec::new();
+        let score = play_match(
+            &mut alpha_beta,
+            &mut random,
+            2,
+            &Rules::default(),
+            &limits,
+            200,
+            |_, _, game| {
+                records.push(game.record.to_string());
+                true
+            },
+        );
+        assert_eq!(score.games(), 2);
+        assert_eq!(score.wins, 2);
+        assert!(score.think_times[0].moves > 0);
+        assert_eq!(records.len(), 2);
+        assert!(GameRecord::parse(&records[0]).is_some());
+    }
+
+    /// Fails to choose a move, like an external engine that crashed.
+    struct FailingEngine;
+
+    impl Engine for FailingEngine {
+        fn name(&self) -> String {
+            "failing".to_string()
+        }
+
+        fn choose_move(&mut self, _game: &Game, _limits: &SearchLimits) -> EngineMove {
+            unreachable!("the match runner asks with try_choose_move")
+        }
+
+        fn try_choose_move(
+            &mut self,
+            _game: &Game,
+            _limits: &SearchLimits,
+        ) -> Result<EngineMove, String> {
+            Err("crashed".to_string())
+        }
+    }
+
+    #[test]
+    fn engine_failure_test() {
+        let finished_game = play_game(
+            &mut GreedyEngine,
+            &mut FailingEngine,
+            &Rules::default(),
+            &SearchLimits::default(),
+            200,
+        );
+        assert_eq!(finished_game.outcome, Outcome::Win(Player::White));
+        assert_eq!(finished_game.termination, Termination::EngineFailure);
+        assert_eq!(finished_game.record.moves.len(), 1);
+    }
+
+    #[test]
+    fn greedy_beats_random_test() {
+        let score = play_match(
+            &mut GreedyEngine,
+            &mut RandomEngine::new(Some(3)),
+            4,
+            &Rules::default(),
+            &SearchLimits::default(),
+            200,
+            |game_index, _, _| game_index < 2,
+        );
+        // Stopped after the third game
+        assert_eq!(score.wins, 3);
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    io::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    accuracy::{GameReport, analyse_game},
+    data_model::Player,
+    game_record::GameRecord,
+};
+
+// Accuracy reports for many games at once, as in accuracy.rs. A pool of workers takes
+// one game at a time, so a directory of game exports is analysed on all cores.
+//
+// The analysed records get every move annotated with its eval and its loss against
+// the best move, and tags with the players' average loss and blunders. The summary
+// has a CSV row per game:
+//
+//     file,line,white,black,result,white_average_loss,white_inaccuracies,white_mistakes,white_blunders,black_...
+//     club.records,3,alice,bob,1-0,0.42,3,1,0,1.25,4,2,1
+
+/// A game read from a file of game records, one per line.
+pub struct BatchGame {
+    pub file: String,
+    /// 1 based.
+    pub line: usize,
+    pub record: GameRecord,
+}
+
+/// Analyses every game with a `depth` ply search on `workers` threads, returning the
+/// reports in the order of the games. `progress` is called with each finished game.
+pub fn analyse_games(
+    games: &[BatchGame],
+    depth: usize,
+    workers: usize,
+    progress: impl Fn(&BatchGame, &GameReport) + Sync,
+) -> Vec<GameReport> {
+    let next = AtomicUsize::new(0);
+    let mut reports: Vec<(usize, GameReport)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..workers.clamp(1, games.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut reports = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(game) = games.get(index) else {
+                            break reports;
+                        };
+                        let record = &game.record;
+                        let report =
+                            analyse_game(&record.game_states(), record.player_moves(), depth);
+                        progress(game, &report);
+                        reports.push((index, report));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    reports.sort_by_key(|&(index, _)| index);
+    reports.into_iter().map(|(_, report)| report).collect()
+}
+
+/// The record with the analysis of `report`, made with a `depth` ply search, in its
+/// move annotations and tags.
+pub fn annotate(record: &GameRecord, report: &GameReport, depth: usize) -> GameRecord {
+    let mut annotated = record.clone();
+    for review in &report.moves {
+        let annotation = &mut annotated.moves[review.ply].annotation;
+        annotation.eval = Some(review.comparison.played_score);
+        annotation.depth = Some(depth);
+        annotation.loss = Some(review.loss());
+    }
+    for player in [Player::White, Player::Black] {
+        let name = player.to_string().to_lowercase();
+        let player_report = &report.players[player.as_index()];
+        annotated.set_tag(
+            &format!("{name}_average_loss"),
+            &format!("{:.2}", player_report.average_loss()),
+        );
+        annotated.set_tag(
+            &format!("{name}_blunders"),
+            &player_report.blunders.to_string(),
+        );
+    }
+    annotated
+}
+
+/// Writes the summary CSV of the games and their reports.
+pub fn write_summary(
+    games: &[BatchGame],
+    reports: &[GameReport],
+    output: &mut impl Write,
+) -> std::io::Result<()> {
+    let mut header = "file,line,white,black,result".to_string();
+    for name in ["white", "black"] {
+        header +=
+            &format!(",{name}_average_loss,{name}_inaccuracies,{name}_mistakes,{name}_blunders");
+    }
+    writeln!(output, "{header}")?;
+    for (game, report) in games.iter().zip(reports) {
+        let tag = |key| game.record.tag(key).unwrap_or_default();
+        let mut row = format!(
+            "{},{},{},{},{}",
+            game.file,
+            game.line,
+            tag("white"),
+            tag("black"),
+            tag("result")
+        );
+        for player_report in &report.players {
+            row += &format!(
+                ",{:.2},{},{},{}",
+                player_report.average_loss(),
+                player_report.inaccuracies,
+                player_report.mistakes,
+                player_report.blunders
+            );
+        }
+        writeln!(output, "{row}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_analysis_test() {
+        let games: Vec<BatchGame> = ["[white=alice,black=bob]mdu;mud;", "mdu;", "mdu;mud;mdu;"]
+            .into_iter()
+            .enumerate()
+            .map(|(index, record)| BatchGame {
+                file: "club.records".to_string(),
+                line: index + 1,
+                record: GameRecord::parse(record).unwrap(),
+            })
+            .collect();
+        let finished = AtomicUsize::new(0);
+        let reports = analyse_games(&games, 1, 2, |_, _| {
+            finished.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(finished.into_inner(), 3);
+        // The reports come back in the order of the games, whichever worker made them
+        let moves: Vec<usize> = reports.iter().map(|report| report.moves.len()).collect();
+        assert_eq!(moves, [2, 1, 3]);
+
+        let annotated = annotate(&games[0].record, &reports[0], 1);
+        let annotation = &annotated.moves[0].annotation;
+        assert_eq!(annotation.depth, Some(1));
+        assert_eq!(annotation.loss, Some(reports[0].moves[0].loss()));
+        assert!(annotated.tag("white_average_loss").is_some());
+        assert!(GameRecord::parse(&annotated.to_string()).is_some());
+
+        let mut csv = Vec::new();
+        write_summary(&games, &reports, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows[1].starts_with("club.records,1,alice,bob,,"));
+        assert_eq!(rows[1].split(',').count(), rows[0].split(',').count());
+    }
+}
@@ -0,0 +1,1647 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use rayon::prelude::*;
+use smallvec::SmallVec;
+
+use crate::{
+    a_star::a_star,
+    all_moves::{PAWN_MOVES, action_id},
+    corridors::{CorridorMap, corridor_map},
+    data_model::{
+        Direction, Game, MovePiece, PiecePosition, Player, PlayerMove, WALL_GRID_HEIGHT,
+        WALL_GRID_WIDTH, WallOrientation, WallPosition, Walls,
+    },
+    enclosure::{enclosure_winner, find_enclosure},
+    engine::legal_moves,
+    game_logic::{
+        execute_move_unchecked, is_move_direction_legal_with_player_at_position,
+        is_move_piece_legal_with_player_at_position, room_for_wall_placement, winner,
+    },
+    ladder::{MAX_LADDER_PLIES, ladder_search},
+    locale::{Locale, Message},
+    notation::move_to_notation,
+    personality::Personality,
+    playout::playout_score,
+    race::race_distances,
+    square_outline_iterator::SquareOutlineIterator,
+    tablebase::result_score,
+    transposition::{Bound, HistoryTable, TranspositionTable, key_after_move, position_key},
+    wall_effects::{countered_walls, self_harming_walls, wall_effects},
+};
+pub const WHITE_LOSES_BLACK_WINS: isize = isize::MIN + 1;
+pub const WHITE_WINS_BLACK_LOSES: isize = -WHITE_LOSES_BLACK_WINS;
+/// Wins further away than this score as wins in this many plies.
+pub const MAX_WIN_PLIES: usize = 1 << 16;
+
+// A won game scores the win score less the plies to the win, so the search goes for the
+// fastest win and, losing, holds out the longest. Scores are relative to the position
+// scored: a child's win in 3 is the parent's win in 4, see `plies_later`.
+
+/// The score of `winner` winning in `plies`, from White's point of view.
+pub fn win_score(winner: Player, plies: usize) -> isize {
+    let score = WHITE_WINS_BLACK_LOSES - plies.min(MAX_WIN_PLIES) as isize;
+    if winner.is_maximizer(Player::White) {
+        score
+    } else {
+        -score
+    }
+}
+
+/// The winner and the plies to the win of a win score, None when the game is open.
+pub fn decided(score: isize) -> Option<(Player, usize)> {
+    let plies = WHITE_WINS_BLACK_LOSES - score.abs();
+    let winner = if score > 0 {
+        Player::White
+    } else {
+        Player::Black
+    };
+    (plies <= MAX_WIN_PLIES as isize).then_some((winner, plies as usize))
+}
+
+/// `score` as seen `plies` plies before its position, wins and losses that much further.
+pub fn plies_later(score: isize, plies: usize) -> isize {
+    match decided(score) {
+        Some((winner, distance)) => win_score(winner, distance + plies),
+        None => score,
+    }
+}
+
+/// The inverse of `plies_later`, for the windows searched `plies` plies deeper.
+pub fn plies_earlier(score: isize, plies: usize) -> isize {
+    match decided(score) {
+        Some((winner, distance)) => win_score(winner, distance.saturating_sub(plies)),
+        None => score,
+    }
+}
+
+/// Every pawn move and wall placement, plus the move searched first.
+const MAX_GENERATED_MOVES: usize = PAWN_MOVES + 2 * WALL_GRID_WIDTH * WALL_GRID_HEIGHT + 1;
+
+/// The moves of a search node, kept on the stack to spare an allocation per node.
+type MoveList = SmallVec<[PlayerMove; MAX_GENERATED_MOVES]>;
+
+/// The path difference from White's point of view, the side to move a step ahead as it
+/// wins a race of equal paths, and the walls left, with the default `EvalWeights`.
+pub fn heuristic_board_score(game: &Game) -> isize {
+    EvalWeights::default().score(game)
+}
+
+/// The path difference and wall difference from White's point of view, weighted.
+pub fn weighted_board_score(game: &Game, distance_priority: isize, wall_priority: isize) -> isize {
+    match board_terms(game) {
+        Ok(terms) => distance_priority * terms.distance + wall_priority * terms.walls,
+        Err(score) => score,
+    }
+}
+
+// Walls left are only worth something while there are places to spend them on. A wall
+// across a choke of the opponent's path, a step out of a square walled in or at the
+// board edge on both sides, forces a detour back, while a wall in the open is walked
+// around in a step. So the wall pressure counts a player's walls up to the chokes left
+// on the opponent's path: many early on, few once the opponent runs for the goal in
+// the open, when unspent walls lose their value.
+
+/// The weights of the heuristic's terms in hundredths of a step, see `EvalWeights::score`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalWeights {
+    /// Per step the opponent's path is longer.
+    pub distance: isize,
+    /// Per wall left more than the opponent.
+    pub walls: isize,
+    /// Per wall left with a choke on the opponent's path to spend it on, more than the
+    /// opponent has.
+    pub wall_pressure: isize,
+    /// For the side to move.
+    pub tempo: isize,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        Self {
+            distance: 100,
+            walls: 0,
+            wall_pressure: 50,
+            tempo: 100,
+        }
+    }
+}
+
+impl EvalWeights {
+    /// Scores `game` from White's point of view in steps, rounded towards zero.
+    pub fn score(self, game: &Game) -> isize {
+        let terms = match board_terms(game) {
+            Ok(terms) => terms,
+            Err(score) => return score,
+        };
+        let tempo = if game.player == Player::White { 1 } else { -1 };
+        (self.distance * terms.distance
+            + self.walls * terms.walls
+            + self.wall_pressure * terms.wall_pressure
+            + self.tempo * tempo)
+            / 100
+    }
+}
+
+impl FromStr for EvalWeights {
+    type Err = String;
+
+    /// Parses the weights in field order, e.g. "100,0,50,100".
+    fn from_str(weights: &str) -> Result<Self, Self::Err> {
+        let weights: Vec<isize> = weights
+            .split(',')
+            .map(|weight| weight.trim().parse().map_err(|e| format!("{weight}: {e}")))
+            .collect::<Result<_, _>>()?;
+        let [distance, walls, wall_pressure, tempo] = weights[..] else {
+            return Err(format!(
+                "Expected 4 weights, distance,walls,wall_pressure,tempo, got {}",
+                weights.len()
+            ));
+        };
+        Ok(Self {
+            distance,
+            walls,
+            wall_pressure,
+            tempo,
+        })
+    }
+}
+
+/// The terms of the heuristic from White's point of view, see `EvalWeights`.
+struct BoardTerms {
+    distance: isize,
+    walls: isize,
+    wall_pressure: isize,
+}
+
+/// The terms of `game`, or the score of a decided game.
+fn board_terms(game: &Game) -> Result<BoardTerms, isize> {
+    let black_path = a_star(game, Player::Black);
+    let white_path = a_star(game, Player::White);
+    // A walled in player, only possible with blocking allowed or in positions set up by
+    // hand, counts as being as far away as possible
+    let blocked_distance = (game.rules.board_size * game.rules.board_size) as isize;
+    let distance = |player: Player, path: &Option<Vec<_>>| {
+        path.as_ref()
+            .map_or(blocked_distance, |path| path_steps(game, player, path))
+    };
+    let black_distance = distance(Player::Black, &black_path);
+    if black_distance == 0 {
+        return Err(WHITE_LOSES_BLACK_WINS);
+    }
+    let white_distance = distance(Player::White, &white_path);
+    if white_distance == 0 {
+        return Err(WHITE_WINS_BLACK_LOSES);
+    }
+    // Races replace the path lengths in the late game, see `race`
+    let distances = [white_distance, black_distance];
+    let [white_distance, black_distance] = race_distances(game, distances).unwrap_or(distances);
+    // Enclosures are as good as won, see `enclosure`
+    match enclosure_winner(game) {
+        Some(Player::White) => return Err(WHITE_WINS_BLACK_LOSES),
+        Some(Player::Black) => return Err(WHITE_LOSES_BLACK_WINS),
+        None => {}
+    }
+    let white_walls_left = game.walls_left[Player::White.as_index()] as isize;
+    let black_walls_left = game.walls_left[Player::Black.as_index()] as isize;
+    let chokes = |player: Player, path: &Option<Vec<_>>| {
+        path.as_ref().map_or(0, |path| chokes(game, player, path))
+    };
+    Ok(BoardTerms {
+        distance: black_distance - white_distance,
+        walls: white_walls_left - black_walls_left,
+        wall_pressure: white_walls_left.min(chokes(Player::Black, &black_path))
+            - black_walls_left.min(chokes(Player::White, &white_path)),
+    })
+}
+
+/// The steps of `player`'s shortest path out of a square walled in or at the board edge
+/// on both sides across the step, where a wall leaves no way around next to it.
+fn chokes(game: &Game, player: Player, path: &[PiecePosition]) -> isize {
+    let start = game.board.player_position(player);
+    std::iter::once(start)
+        .chain(path)
+        .zip(path)
+        .filter(|(from, to)| {
+            let sides = if from.x() == to.x() {
+                [Direction::Left, Direction::Right]
+            } else {
+                [Direction::Up, Direction::Down]
+            };
+            from.x().abs_diff(to.x()) + from.y().abs_diff(to.y()) == 1
+                && sides
+                    .iter()
+                    .all(|side| !is_move_direction_legal_with_player_at_position(game, from, side))
+        })
+        .count() as isize
+}
+
+/// The length of `player`'s shortest path. The path goes around the other pawn where it
+/// stands now, but only the side to move can jump it right away, any later jump counts
+/// as the two steps it covers as the jumped pawn will have moved on.
+fn path_steps(game: &Game, player: Player, path: &[PiecePosition]) -> isize {
+    let start = game.board.player_position(player);
+    let jumps = std::iter::once(start)
+        .chain(path)
+        .zip(path)
+        .enumerate()
+        .filter(|(step, (from, to))| {
+            let squares = from.x().abs_diff(to.x()) + from.y().abs_diff(to.y());
+            squares > 1 && !(*step == 0 && game.player == player)
+        })
+        .count();
+    (path.len() + jumps) as isize
+}
+
+/// How the alpha-beta search scores the positions at its leaves.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Evaluation {
+    /// The difference between the players' shortest paths, the side to move a step ahead,
+    /// and the walls left, see `EvalWeights`.
+    #[default]
+    PathDifference,
+    /// The average result of this many playouts, see `playout`.
+    Playouts(usize),
+    /// Weights and move order of a playing style, see `personality`.
+    Personality(Personality),
+    /// The default evaluation with other weights, for tuning.
+    Weights(EvalWeights),
+}
+
+impl Evaluation {
+    /// Scores `game` from White's point of view.
+    pub fn evaluate(self, game: &Game) -> isize {
+        match self {
+            Evaluation::PathDifference => heuristic_board_score(game),
+            Evaluation::Playouts(count) => playout_score(game, count),
+            Evaluation::Personality(personality) => personality.score(game),
+            Evaluation::Weights(weights) => weights.score(game),
+        }
+    }
+
+    /// Whether the search tries walls before pawn moves.
+    pub fn walls_first(self) -> bool {
+        matches!(self, Evaluation::Personality(personality) if personality.walls_first())
+    }
+}
+
+/// Result of a completed search. Scores are from White's point of view.
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub score: isize,
+    pub principal_variation: Vec<PlayerMove>,
+    pub depth: usize,
+    pub nodes: u64,
+    /// Permille of the transposition table in use, see `TranspositionTable::hashfull`.
+    pub hashfull: usize,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<&PlayerMove> {
+        self.principal_variation.first()
+    }
+}
+
+/// Half width of the aspiration windows of `BotConfig::default`.
+pub const DEFAULT_ASPIRATION_WINDOW: isize = 2;
+
+/// Settings of the bot's iterative deepening search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BotConfig {
+    pub evaluation: Evaluation,
+    /// Walls lengthening the own path by more than this many steps beyond the opponent's
+    /// are skipped, see `self_harming_walls`.
+    pub wall_pruning: Option<isize>,
+    /// Every depth after the first is searched with a window this far either side of
+    /// the score of the depth before, widened when the score falls outside, 0 for full
+    /// windows. In the units of the evaluation, steps for the heuristic.
+    pub aspiration_window: isize,
+    /// Threads splitting the moves of the root between them, see `parallel_alpha_beta`.
+    pub threads: usize,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            evaluation: Evaluation::PathDifference,
+            wall_pruning: None,
+            aspiration_window: DEFAULT_ASPIRATION_WINDOW,
+            threads: 1,
+        }
+    }
+}
+
+/// Deepens the search of `game` with `table` until `deadline` has passed, with the
+/// result of the last depth searched in full.
+pub fn best_move_with_deadline(
+    game: &Game,
+    player: Player,
+    deadline: Duration,
+    table: &mut TranspositionTable,
+) -> SearchInfo {
+    let start = Instant::now();
+    let stop = |_| start.elapsed() > deadline;
+    iterative_deepening(game, player, None, BotConfig::default(), table, &stop)
+}
+
+/// Searches one ply deeper at a time until `stop`, given the nodes searched so far,
+/// returns true or `max_depth` is searched. A depth cut short by `stop` proves nothing,
+/// so the result is that of the last depth searched in full, or of the cut first one.
+/// With `config.threads` above one, the other threads get tables of their own for the
+/// search, as large as `table`, see `iterative_deepening_with` to keep them.
+pub fn iterative_deepening(
+    game: &Game,
+    player: Player,
+    max_depth: Option<usize>,
+    config: BotConfig,
+    table: &mut TranspositionTable,
+    stop: &(dyn Fn(u64) -> bool + Sync),
+) -> SearchInfo {
+    let mut helpers = Vec::new();
+    iterative_deepening_with(game, player, max_depth, config, table, &mut helpers, stop)
+}
+
+/// `iterative_deepening` with the other threads searching with `helpers`, kept between
+/// searches like `table`. Missing ones are added as large as `table`.
+pub fn iterative_deepening_with(
+    game: &Game,
+    player: Player,
+    max_depth: Option<usize>,
+    config: BotConfig,
+    table: &mut TranspositionTable,
+    helpers: &mut Vec<TranspositionTable>,
+    stop: &(dyn Fn(u64) -> bool + Sync),
+) -> SearchInfo {
+    let mut completed: Option<SearchInfo> = None;
+    let mut nodes = 0;
+    let mut depth = 1;
+    helpers.resize_with(config.threads.saturating_sub(1), || {
+        TranspositionTable::with_capacity(table.capacity())
+    });
+    for helper in helpers.iter_mut() {
+        helper.tablebase = table.tablebase.clone();
+    }
+    let stopped = AtomicBool::new(false);
+    let watched_stop = |nodes| {
+        let stop = stop(nodes);
+        if stop {
+            stopped.store(true, Ordering::Relaxed);
+        }
+        stop
+    };
+    loop {
+        let mut search_first = completed
+            .as_ref()
+            .and_then(|info| info.best_move().cloned());
+        let (mut alpha, mut beta) = aspiration_window(completed.as_ref(), config);
+        let mut widening = config.aspiration_window;
+        let (score, principal_variation) = loop {
+            let (score, principal_variation) = if helpers.is_empty() {
+                alpha_beta(
+                    game,
+                    depth,
+                    alpha,
+                    beta,
+                    player,
+                    config.evaluation,
+                    config.wall_pruning,
+                    search_first,
+                    Some(&watched_stop),
+                    &mut nodes,
+                    table,
+                )
+            } else {
+                parallel_alpha_beta(
+                    game,
+                    depth,
+                    alpha,
+                    beta,
+                    player,
+                    config,
+                    search_first,
+                    &watched_stop,
+                    &mut nodes,
+                    table,
+                    helpers,
+                )
+            };
+            // A score outside the window only bounds the true one, which is searched
+            // again with the window widened on that side, twice as far every time
+            let failed_low = score <= alpha && alpha > WHITE_LOSES_BLACK_WINS;
+            let failed_high = score >= beta && beta < WHITE_WINS_BLACK_LOSES;
+            if stopped.load(Ordering::Relaxed) || !failed_low && !failed_high {
+                break (score, principal_variation);
+            }
+            widening = widening.saturating_mul(2);
+            if failed_low {
+                alpha = alpha.saturating_sub(widening).max(WHITE_LOSES_BLACK_WINS);
+            } else {
+                beta = beta.saturating_add(widening).min(WHITE_WINS_BLACK_LOSES);
+            }
+            search_first = principal_variation.first().cloned();
+        };
+        let info = SearchInfo {
+            score,
+            principal_variation,
+            depth,
+            nodes,
+            hashfull: table.hashfull(),
+        };
+        if stopped.load(Ordering::Relaxed) {
+            break completed.map_or(info, |completed| SearchInfo { nodes, ..completed });
+        }
+        if stop(nodes) || max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            break info;
+        }
+        completed = Some(info);
+        depth += 1;
+    }
+}
+
+/// A search deepening in the background until stopped, on the position the opponent is
+/// expected to reach while they think, filling a table the next search starts from.
+pub struct Ponder {
+    stop: Arc<AtomicBool>,
+    search: Option<JoinHandle<PonderResult>>,
+}
+
+/// The table and the helper tables lent to a ponder search, with its result.
+pub type PonderResult = (TranspositionTable, Vec<TranspositionTable>, SearchInfo);
+
+impl Ponder {
+    /// Starts searching `game` with `table` and `helpers` on a thread of its own, see
+    /// `iterative_deepening_with`.
+    pub fn start(
+        game: Game,
+        config: BotConfig,
+        mut table: TranspositionTable,
+        mut helpers: Vec<TranspositionTable>,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let search_stop = stop.clone();
+        let search = std::thread::spawn(move || {
+            for table in std::iter::once(&mut table).chain(&mut helpers) {
+                table.new_search();
+            }
+            let stop = |_| search_stop.load(Ordering::Relaxed);
+            let info = iterative_deepening_with(
+                &game,
+                game.player,
+                None,
+                config,
+                &mut table,
+                &mut helpers,
+                &stop,
+            );
+            (table, helpers, info)
+        });
+        Self {
+            stop,
+            search: Some(search),
+        }
+    }
+
+    /// Asks the search to return, from any thread.
+    pub fn cancel(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Cancels the search and waits for it, returning the tables and the result of the
+    /// last depth searched in full.
+    pub fn stop(mut self) -> PonderResult {
+        self.cancel();
+        let search = self
+            .search
+            .take()
+            .expect("A ponder search is only stopped once");
+        search.join().expect("The ponder search panicked")
+    }
+}
+
+impl Drop for Ponder {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// The window to search the next depth with after `completed`, the full one for the
+/// first depth, without an aspiration window and after a win or a loss.
+fn aspiration_window(completed: Option<&SearchInfo>, config: BotConfig) -> (isize, isize) {
+    let full_window = (WHITE_LOSES_BLACK_WINS, WHITE_WINS_BLACK_LOSES);
+    let Some(completed) = completed else {
+        return full_window;
+    };
+    if config.aspiration_window <= 0 || decided(completed.score).is_some() {
+        return full_window;
+    }
+    (
+        completed
+            .score
+            .saturating_sub(config.aspiration_window)
+            .max(WHITE_LOSES_BLACK_WINS),
+        completed
+            .score
+            .saturating_add(config.aspiration_window)
+            .min(WHITE_WINS_BLACK_LOSES),
+    )
+}
+
+pub fn best_move_alpha_beta(
+    game: &Game,
+    player: Player,
+    depth: usize,
+    evaluation: Evaluation,
+    wall_pruning: Option<isize>,
+    table: &mut TranspositionTable,
+) -> SearchInfo {
+    let mut nodes = 0;
+    let (score, principal_variation) = alpha_beta(
+        game,
+        depth,
+        WHITE_LOSES_BLACK_WINS,
+        WHITE_WINS_BLACK_LOSES,
+        player,
+        evaluation,
+        wall_pruning,
+        None,
+        None,
+        &mut nodes,
+        table,
+    );
+    SearchInfo {
+        score,
+        principal_variation,
+        depth,
+        nodes,
+        hashfull: table.hashfull(),
+    }
+}
+
+/// Scores every legal move with a `depth` ply search, best move for the side to move first.
+/// Scores are from White's point of view.
+pub fn move_scores(game: &Game, depth: usize) -> Vec<(PlayerMove, isize)> {
+    move_scores_with(
+        game,
+        depth,
+        Evaluation::PathDifference,
+        None,
+        &mut TranspositionTable::for_depth(depth),
+    )
+}
+
+/// Like `move_scores`, with an evaluation, a wall pruning margin and a table that may hold
+/// earlier searches.
+pub fn move_scores_with(
+    game: &Game,
+    depth: usize,
+    evaluation: Evaluation,
+    wall_pruning: Option<isize>,
+    table: &mut TranspositionTable,
+) -> Vec<(PlayerMove, isize)> {
+    let player = game.player;
+    let mut nodes = 0;
+    // Pawn moves reaching the same square only differ in their collision direction
+    let mut seen = HashSet::new();
+    let mut scores: Vec<(PlayerMove, isize)> = legal_moves(game)
+        .into_iter()
+        .filter(|player_move| seen.insert(move_to_notation(game, player_move)))
+        .map(|player_move| {
+            let mut child_game_state = game.clone();
+            execute_move_unchecked(&mut child_game_state, player, &player_move);
+            let (score, _) = alpha_beta(
+                &child_game_state,
+                depth.saturating_sub(1),
+                WHITE_LOSES_BLACK_WINS,
+                WHITE_WINS_BLACK_LOSES,
+                player.opponent(),
+                evaluation,
+                wall_pruning,
+                None,
+                None,
+                &mut nodes,
+                table,
+            );
+            let score = plies_later(score, 1);
+            (player_move, score)
+        })
+        .collect();
+    if player.is_maximizer(Player::White) {
+        scores.sort_by_key(|(_, score)| -score);
+    } else {
+        scores.sort_by_key(|(_, score)| *score);
+    }
+    scores
+}
+
+/// The best `multipv` moves of `player` in `game`, best first, each with the score and the
+/// principal variation of a `depth` ply search, scores from White's point of view. Every
+/// line searches the root again without the moves of the lines before it, pawn moves
+/// reaching the same square counting as one.
+pub fn analyze(game: &Game, player: Player, depth: usize, multipv: usize) -> Vec<SearchInfo> {
+    let mut table = TranspositionTable::for_depth(depth);
+    let key = position_key(game);
+    let mut lines: Vec<SearchInfo> = Vec::new();
+    let mut searched = HashSet::new();
+    while lines.len() < multipv {
+        let mut nodes = 0;
+        let in_root =
+            |player_move: &PlayerMove| !searched.contains(&move_to_notation(game, player_move));
+        let (score, principal_variation) = negamax(
+            game,
+            key,
+            depth.max(1),
+            WHITE_LOSES_BLACK_WINS,
+            WHITE_WINS_BLACK_LOSES,
+            player,
+            Evaluation::PathDifference,
+            None,
+            None,
+            None,
+            Some(&in_root),
+            &mut nodes,
+            &mut table,
+        );
+        let Some(best_move) = principal_variation.first() else {
+            break;
+        };
+        searched.insert(move_to_notation(game, best_move));
+        lines.push(SearchInfo {
+            score: side_sign(player) * score,
+            principal_variation,
+            depth,
+            nodes,
+            hashfull: table.hashfull(),
+        });
+    }
+    lines
+}
+
+/// Returns the score and the principal variation, starting with the best move. The
+/// window and the score are from White's point of view, like the evaluation.
+#[allow(clippy::too_many_arguments)]
+pub fn alpha_beta(
+    game: &Game,
+    depth: usize,
+    alpha: isize,
+    beta: isize,
+    player: Player,
+    evaluation: Evaluation,
+    wall_pruning: Option<isize>,
+    search_first: Option<PlayerMove>,
+    stop: Option<&dyn Fn(u64) -> bool>,
+    nodes: &mut u64,
+    table: &mut TranspositionTable,
+) -> (isize, Vec<PlayerMove>) {
+    let (alpha, beta) = window_of(player, alpha, beta);
+    let (score, principal_variation) = negamax(
+        game,
+        position_key(game),
+        depth,
+        alpha,
+        beta,
+        player,
+        evaluation,
+        wall_pruning,
+        search_first,
+        stop,
+        None,
+        nodes,
+        table,
+    );
+    (side_sign(player) * score, principal_variation)
+}
+
+/// `alpha_beta` splitting the moves of the root between this thread, searching with
+/// `table`, and a thread for each of `helpers`, searching with that table. The first
+/// move, the best of the depth before when there is one, is searched on its own to
+/// narrow the window the others are searched with, split between the threads by their
+/// action ids. Every thread counts its nodes from `nodes` on, so node limits hold per
+/// thread.
+#[allow(clippy::too_many_arguments)]
+fn parallel_alpha_beta(
+    game: &Game,
+    depth: usize,
+    alpha: isize,
+    beta: isize,
+    player: Player,
+    config: BotConfig,
+    search_first: Option<PlayerMove>,
+    stop: &(dyn Fn(u64) -> bool + Sync),
+    nodes: &mut u64,
+    table: &mut TranspositionTable,
+    helpers: &mut [TranspositionTable],
+) -> (isize, Vec<PlayerMove>) {
+    let key = position_key(game);
+    let (original_alpha, beta) = window_of(player, alpha, beta);
+    let threads = helpers.len() + 1;
+    let search = |alpha: isize,
+                  in_root: &dyn Fn(&PlayerMove) -> bool,
+                  nodes: &mut u64,
+                  table: &mut TranspositionTable| {
+        negamax(
+            game,
+            key,
+            depth,
+            alpha,
+            beta,
+            player,
+            config.evaluation,
+            config.wall_pruning,
+            search_first.clone(),
+            Some(stop),
+            Some(in_root),
+            nodes,
+            table,
+        )
+    };
+    // Moves are told apart by their action ids
+    let first_id = search_first.as_ref().and_then(action_id);
+    let mut best = first_id.map(|first_id| {
+        search(
+            original_alpha,
+            &|player_move| action_id(player_move) == Some(first_id),
+            nodes,
+            table,
+        )
+    });
+    let alpha = best
+        .as_ref()
+        .map_or(original_alpha, |(score, _)| original_alpha.max(*score));
+    if alpha < beta {
+        let start_nodes = *nodes;
+        let searches: Vec<_> = std::iter::once(&mut *table)
+            .chain(helpers.iter_mut())
+            .enumerate()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(thread, table)| {
+                let in_share = |player_move: &PlayerMove| {
+                    let id = action_id(player_move);
+                    id != first_id && id.unwrap_or(0) % threads == thread
+                };
+                let mut thread_nodes = start_nodes;
+                let searched = search(alpha, &in_share, &mut thread_nodes, table);
+                (searched, thread_nodes - start_nodes)
+            })
+            .collect();
+        for (searched, thread_nodes) in searches {
+            *nodes += thread_nodes;
+            // Shares without a move searched leave no variation
+            let better = !searched.1.is_empty()
+                && best.as_ref().is_none_or(|(score, principal_variation)| {
+                    searched.0 > *score || principal_variation.is_empty()
+                });
+            if better {
+                best = Some(searched);
+            }
+        }
+    }
+    let (value, principal_variation) = best.unwrap_or((WHITE_LOSES_BLACK_WINS, Vec::new()));
+    let bound = if value <= original_alpha {
+        Bound::Upper
+    } else if value >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    table.store(
+        key,
+        depth,
+        value,
+        bound,
+        principal_variation.first().cloned(),
+    );
+    (side_sign(player) * value, principal_variation)
+}
+
+/// 1 for White and -1 for Black, turning White's scores into the player's and back.
+fn side_sign(player: Player) -> isize {
+    if player.is_maximizer(Player::White) {
+        1
+    } else {
+        -1
+    }
+}
+
+/// The window `alpha`..`beta` of White's scores as seen by `player`, and back.
+fn window_of(player: Player, alpha: isize, beta: isize) -> (isize, isize) {
+    if player.is_maximizer(Player::White) {
+        (alpha, beta)
+    } else {
+        (-beta, -alpha)
+    }
+}
+
+/// `alpha_beta` as a negamax principal variation search of a position with Zobrist key
+/// `key`, the window and the scores, also those in the table, from the point of view of
+/// the side to move. The first move is searched with the full window, the others with
+/// a null window proving them no better, searched again with the full window when they
+/// are. Children get their keys updated by the move instead of hashing every node from
+/// scratch. Given `in_root`, only the moves of the root it accepts are searched, and the
+/// result, saying nothing of the other moves, is not stored.
+#[allow(clippy::too_many_arguments)]
+fn negamax(
+    game: &Game,
+    key: u64,
+    depth: usize,
+    alpha: isize,
+    beta: isize,
+    player: Player,
+    evaluation: Evaluation,
+    wall_pruning: Option<isize>,
+    search_first: Option<PlayerMove>,
+    stop: Option<&dyn Fn(u64) -> bool>,
+    in_root: Option<&dyn Fn(&PlayerMove) -> bool>,
+    nodes: &mut u64,
+    table: &mut TranspositionTable,
+) -> (isize, Vec<PlayerMove>) {
+    *nodes += 1;
+    debug_assert_eq!(key, position_key(game));
+    let sign = side_sign(player);
+    if let Some(winner) = winner(game) {
+        return (sign * win_score(winner, 0), Vec::new());
+    }
+    if depth == 0 {
+        let score = table
+            .evals
+            .get_or_insert_with(key, || evaluation.evaluate(game));
+        return (sign * score, Vec::new());
+    }
+    let mut search_first = search_first;
+    if let Some(entry) = table.probe(key) {
+        let cutoff = match entry.bound {
+            Bound::Exact => true,
+            Bound::Lower => entry.score >= beta,
+            Bound::Upper => entry.score <= alpha,
+        };
+        // The entry of a position searched in full may hold a root move left out
+        if cutoff && entry.depth >= depth && in_root.is_none() {
+            return (entry.score, entry.best_move.iter().cloned().collect());
+        }
+        search_first = search_first.or_else(|| entry.best_move.clone());
+    }
+    if game.rules.allow_blocking && search_first.is_none() {
+        // An enclosure the evaluation sees at the horizon is played out from here
+        search_first =
+            find_enclosure(game, player).and_then(|enclosure| enclosure.walls.into_iter().next());
+    }
+    let original_alpha = alpha;
+    let mut alpha = alpha;
+    let mut principal_variation = Vec::new();
+    let mut value = WHITE_LOSES_BLACK_WINS;
+    // Walls at the horizon lengthening the path of an opponent out of walls may start a
+    // ladder, see `ladder`
+    let opponent_distance = (depth == 1 && game.walls_left[player.opponent().as_index()] == 0)
+        .then(|| a_star(game, player.opponent()).map_or(0, |path| path.len()));
+    // The batch evaluation of every wall costs less than searching the walls from here on
+    let effects =
+        (depth >= 2 && game.walls_left[player.as_index()] > 0).then(|| wall_effects(game));
+    // Walls hurting the side to move more than the opponent are skipped
+    let self_harming = wall_pruning
+        .zip(effects.as_ref())
+        .map(|(margin, effects)| self_harming_walls(effects, margin));
+    let searched_first = search_first.is_some() as usize;
+    let mut moves = moves_ordered_by_heuristic_quality(game, player, search_first, &table.history);
+    if evaluation.walls_first() {
+        moves[searched_first..]
+            .sort_by_key(|player_move| matches!(player_move, PlayerMove::MovePiece(_)));
+    }
+    if let Some(effects) = &effects {
+        // Walls the opponent can answer with a counter wall are tried last
+        let countered = countered_walls(game, effects);
+        moves[searched_first..].sort_by_key(|player_move| has_wall(&countered, player_move));
+    }
+    for player_move in moves {
+        if self_harming
+            .as_ref()
+            .is_some_and(|self_harming| has_wall(self_harming, &player_move))
+            || in_root.is_some_and(|in_root| !in_root(&player_move))
+        {
+            continue;
+        }
+        let mut child_game_state = game.clone();
+        execute_move_unchecked(&mut child_game_state, player, &player_move);
+        let is_wall = matches!(player_move, PlayerMove::PlaceWall { .. });
+        let opponent_path = (!game.rules.allow_blocking || opponent_distance.is_some() && is_wall)
+            .then(|| a_star(&child_game_state, player.opponent()));
+        if !game.rules.allow_blocking
+            && (a_star(&child_game_state, player).is_none()
+                || opponent_path.as_ref().is_some_and(Option::is_none))
+        {
+            continue;
+        }
+        let starts_ladder = is_wall
+            && matches!(
+                (opponent_distance, &opponent_path),
+                (Some(distance), Some(Some(path))) if path.len() > distance
+            );
+        let child_key = key_after_move(key, game, player, &player_move);
+        let search_child =
+            |alpha: isize, beta: isize, nodes: &mut u64, table: &mut TranspositionTable| {
+                let (score, child_variation) = negamax(
+                    &child_game_state,
+                    child_key,
+                    depth - 1,
+                    -plies_earlier(beta, 1),
+                    -plies_earlier(alpha, 1),
+                    player.opponent(),
+                    evaluation,
+                    wall_pruning,
+                    None,
+                    stop,
+                    None,
+                    nodes,
+                    table,
+                );
+                (plies_later(-score, 1), child_variation)
+            };
+        // The positions of the tablebase need no search, their results are exact
+        let probed = table
+            .tablebase
+            .as_ref()
+            .and_then(|tablebase| tablebase.probe(&child_game_state));
+        let (score, child_variation) = if let Some(result) = probed {
+            let score = side_sign(player.opponent()) * result_score(&child_game_state, result);
+            (plies_later(-score, 1), Vec::new())
+        } else if starts_ladder {
+            let (white_alpha, white_beta) = window_of(player, alpha, beta);
+            let score = ladder_search(
+                &child_game_state,
+                plies_earlier(white_alpha, 1),
+                plies_earlier(white_beta, 1),
+                MAX_LADDER_PLIES,
+                evaluation,
+                nodes,
+                table,
+            );
+            (plies_later(sign * score, 1), Vec::new())
+        } else if principal_variation.is_empty() {
+            search_child(alpha, beta, nodes, table)
+        } else {
+            let (score, child_variation) =
+                search_child(alpha, alpha.saturating_add(1), nodes, table);
+            if score > alpha && score < beta {
+                search_child(alpha, beta, nodes, table)
+            } else {
+                (score, child_variation)
+            }
+        };
+        if stop.is_some_and(|f| f(*nodes)) {
+            // A search cut short proves nothing about this position, nor its children,
+            // though the first move still gives the root a move to play
+            if principal_variation.is_empty() {
+                principal_variation = std::iter::once(player_move)
+                    .chain(child_variation)
+                    .collect();
+                value = score;
+            }
+            return (value, principal_variation);
+        }
+        if score > value || principal_variation.is_empty() {
+            principal_variation = std::iter::once(player_move)
+                .chain(child_variation)
+                .collect();
+        }
+        value = isize::max(value, score);
+        alpha = isize::max(alpha, value);
+        if value >= beta {
+            // The move cutting off improved the value, so it leads the variation
+            if is_wall {
+                table.history.record(player, &principal_variation[0], depth);
+            }
+            break;
+        }
+    }
+    if in_root.is_some() {
+        return (value, principal_variation);
+    }
+    let bound = if value <= original_alpha {
+        Bound::Upper
+    } else if value >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    table.store(
+        key,
+        depth,
+        value,
+        bound,
+        principal_variation.first().cloned(),
+    );
+    (value, principal_variation)
+}
+
+/// Whether `player_move` places one of `walls`.
+fn has_wall(walls: &Walls, player_move: &PlayerMove) -> bool {
+    matches!(
+        player_move,
+        PlayerMove::PlaceWall { orientation, position }
+            if walls[position.x][position.y].has(*orientation)
+    )
+}
+
+/// The search first move, the pawn moves, then the walls by their cutoffs in `history`,
+/// ties by distance to the opponent's pawn.
+fn moves_ordered_by_heuristic_quality(
+    game: &Game,
+    player: Player,
+    search_first: Option<PlayerMove>,
+    history: &HistoryTable,
+) -> MoveList {
+    let mut moves = MoveList::new();
+    if let Some(search_first) = search_first {
+        moves.push(search_first); // TODO: Could ensure that the code below does not also add this mode. Unclear if this is worth it.
+    }
+    let player_position = game.board.player_position(player);
+    let opponent_position = game.board.player_position(player.opponent());
+    let x_diff = opponent_position.x() as isize - player_position.x() as isize;
+    let y_diff = opponent_position.y() as isize - player_position.y() as isize;
+
+    let push_if_move_piece_is_legal =
+        |moves: &mut MoveList, direction: Direction, direction_on_collision: Direction| {
+            let move_piece = MovePiece {
+                direction,
+                direction_on_collision,
+            };
+            if is_move_piece_legal_with_player_at_position(
+                game,
+                player,
+                player_position,
+                &move_piece,
+            ) {
+                moves.push(PlayerMove::MovePiece(move_piece));
+            }
+        };
+
+    if let Some(jump_direction) = match (x_diff, y_diff) {
+        (0, 1) => Some(Direction::Down),
+        (0, -1) => Some(Direction::Up),
+        (1, 0) => Some(Direction::Right),
+        (-1, 0) => Some(Direction::Left),
+        _ => None,
+    } {
+        for direction in Direction::iter() {
+            push_if_move_piece_is_legal(&mut moves, jump_direction, direction);
+        }
+        for direction in Direction::iter().filter(|&d| d != jump_direction) {
+            push_if_move_piece_is_legal(&mut moves, direction, Direction::Up);
+        }
+    } else {
+        for direction in Direction::iter() {
+            push_if_move_piece_is_legal(&mut moves, direction, Direction::Up);
+        }
+    }
+    let first_wall = moves.len();
+    if game.walls_left[player.as_index()] > 0 {
+        let origin = opponent_position;
+        for i in 1.. {
+            let top_left_x = origin.x() as isize - i as isize;
+            let top_left_y = origin.y() as isize - i as isize;
+            let side_length = 2 * i;
+            let mut some_in_bounds = false;
+            for (x, y) in SquareOutlineIterator::new(top_left_x, top_left_y, side_length) {
+                let in_bounds = x >= 0
+                    && y >= 0
+                    && x < WALL_GRID_WIDTH as isize
+                    && y < WALL_GRID_HEIGHT as isize;
+                if !in_bounds {
+                    continue;
+                }
+                some_in_bounds = true;
+                for orientation in [WallOrientation::Horizontal, WallOrientation::Vertical] {
+                    let player_move = PlayerMove::PlaceWall {
+                        orientation,
+                        position: WallPosition {
+                            x: x as usize,
+                            y: y as usize,
+                        },
+                    };
+                    if room_for_wall_placement(game, orientation, x, y) {
+                        moves.push(player_move);
+                    }
+                }
+            }
+            if !some_in_bounds {
+                break;
+            }
+        }
+    }
+    moves[first_wall..]
+        .sort_by_key(|player_move| std::cmp::Reverse(history.score(player, player_move)));
+    moves
+}
+
+/// The effect of a move on both players' shortest paths, see `explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveExplanation {
+    /// The move in standard notation.
+    pub notation: String,
+    pub player: Player,
+    /// Shortest path lengths, `None` when a player is walled in.
+    pub own_path_before: Option<usize>,
+    pub own_path_after: Option<usize>,
+    pub opponent_path_before: Option<usize>,
+    pub opponent_path_after: Option<usize>,
+    pub walls_spent: usize,
+    /// Steps won in the race to the goal rows: the opponent's detour plus own progress.
+    pub tempo_gained: isize,
+    /// The wall leaves the opponent walking between walls on both sides for longer.
+    pub creates_corridor: bool,
+    /// The wall closes off a corridor on the opponent's shortest path.
+    pub blocks_corridor: bool,
+}
+
+/// The squares of `path` in the corridors of `map`, see `corridors`.
+fn corridor_squares(map: &CorridorMap, path: &[PiecePosition]) -> usize {
+    path.iter()
+        .filter(|position| map.corridor_of(position).is_some())
+        .count()
+}
+
+/// Whether a step between neighbouring squares of `path` into or out of a corridor of
+/// `map`, the corridors of `game`, is blocked in `child_game`.
+fn blocks_corridor_step(
+    map: &CorridorMap,
+    child_game: &Game,
+    start: &PiecePosition,
+    path: &[PiecePosition],
+) -> bool {
+    std::iter::once(start)
+        .chain(path)
+        .zip(path)
+        .any(|(from, to)| {
+            let direction = match (
+                to.x() as isize - from.x() as isize,
+                to.y() as isize - from.y() as isize,
+            ) {
+                (0, -1) => Direction::Up,
+                (0, 1) => Direction::Down,
+                (-1, 0) => Direction::Left,
+                (1, 0) => Direction::Right,
+                _ => return false,
+            };
+            (map.corridor_of(from).is_some() || map.corridor_of(to).is_some())
+                && !is_move_direction_legal_with_player_at_position(child_game, from, &direction)
+        })
+}
+
+/// Explains `chosen_move` for the side to move in terms of the race to the goal rows.
+pub fn explain(game: &Game, chosen_move: &PlayerMove) -> MoveExplanation {
+    let player = game.player;
+    let opponent = player.opponent();
+    let mut child_game = game.clone();
+    execute_move_unchecked(&mut child_game, player, chosen_move);
+    let own_before = a_star(game, player);
+    let own_after = a_star(&child_game, player);
+    let opponent_before = a_star(game, opponent);
+    let opponent_after = a_star(&child_game, opponent);
+    let length = |path: &Option<Vec<PiecePosition>>| path.as_ref().map(|path| path.len());
+    let walls_spent = game.walls_left[player.as_index()] - child_game.walls_left[player.as_index()];
+    let is_wall = matches!(chosen_move, PlayerMove::PlaceWall { .. });
+    let (creates_corridor, blocks_corridor) = match (&opponent_before, &opponent_after) {
+        (Some(before), Some(after)) if is_wall => {
+            let map = corridor_map(game);
+            (
+                corridor_squares(&corridor_map(&child_game), after)
+                    > corridor_squares(&map, before),
+                blocks_corridor_step(
+                    &map,
+                    &child_game,
+                    game.board.player_position(opponent),
+                    before,
+                ),
+            )
+        }
+        _ => (false, false),
+    };
+    let difference = |before: Option<usize>, after: Option<usize>| match (before, after) {
+        (Some(before), Some(after)) => after as isize - before as isize,
+        _ => 0,
+    };
+    MoveExplanation {
+        notation: move_to_notation(game, chosen_move),
+        player,
+        own_path_before: length(&own_before),
+        own_path_after: length(&own_after),
+        opponent_path_before: length(&opponent_before),
+        opponent_path_after: length(&opponent_after),
+        walls_spent,
+        tempo_gained: difference(length(&opponent_before), length(&opponent_after))
+            - difference(length(&own_before), length(&own_after)),
+        creates_corridor,
+        blocks_corridor,
+    }
+}
+
+impl MoveExplanation {
+    pub fn text(&self, locale: Locale) -> String {
+        let path_change = |player: Player, before: Option<usize>, after: Option<usize>| {
+            if before == after {
+                Message::PathStays {
+                    player,
+                    length: before,
+                }
+            } else {
+                Message::PathChanges {
+                    player,
+                    before,
+                    after,
+                }
+            }
+            .text(locale)
+        };
+        let mut text = format!("{}: ", self.notation);
+        if self.walls_spent > 0 {
+            text += &format!("{}, ", Message::SpendsWalls(self.walls_spent).text(locale));
+        }
+        text += &format!(
+            "{}, {}; {}",
+            path_change(self.player, self.own_path_before, self.own_path_after),
+            path_change(
+                self.player.opponent(),
+                self.opponent_path_before,
+                self.opponent_path_after
+            ),
+            Message::GainsTempo(self.tempo_gained).text(locale)
+        );
+        if self.creates_corridor {
+            text += &format!(", {}", Message::CreatesCorridor.text(locale));
+        }
+        if self.blocks_corridor {
+            text += &format!(", {}", Message::BlocksCorridor.text(locale));
+        }
+        text
+    }
+}
+
+impl Display for MoveExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text(Locale::English))
+    }
+}
+
+/// A played move scoring clearly worse than the engine's choice, see `check_blunder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blunder {
+    /// The played and the best move in standard notation.
+    pub played: String,
+    pub best: String,
+    /// How many steps worse the played move is, saturated when a win is involved.
+    pub loss: isize,
+    /// Score of the played move from White's point of view.
+    pub played_score: isize,
+    pub misses_win: bool,
+    pub loses_game: bool,
+    /// How many steps the best move is ahead of the second best, 0 with a single move.
+    pub best_gap: isize,
+    /// How many moves score better than the played one, 0 for a best move.
+    pub rank: usize,
+}
+
+impl Blunder {
+    pub fn text(&self, locale: Locale) -> String {
+        let (mark, message) = if self.misses_win {
+            ("??", Message::MissesWin)
+        } else if self.loses_game {
+            ("??", Message::LosesGame)
+        } else {
+            ("?!", Message::LostTempi(self.loss))
+        };
+        format!(
+            "{}{} {} - {}",
+            self.played,
+            mark,
+            message.text(locale),
+            Message::BetterWas(&self.best).text(locale)
+        )
+    }
+}
+
+impl Display for Blunder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text(Locale::English))
+    }
+}
+
+/// Compares `played` with the best move of a `depth` ply search and returns a blunder
+/// when it is at least `threshold` steps worse for the side to move.
+pub fn check_blunder(
+    game: &Game,
+    played: &PlayerMove,
+    depth: usize,
+    threshold: isize,
+) -> Option<Blunder> {
+    let player = game.player;
+    let scores = move_scores(game, depth);
+    let played_notation = move_to_notation(game, played);
+    let (best_move, best_score) = scores.first()?;
+    let &(_, white_played_score) = scores
+        .iter()
+        .find(|(player_move, _)| move_to_notation(game, player_move) == played_notation)?;
+    let second_score = scores.get(1).map_or(*best_score, |&(_, score)| score);
+    // Scores from the point of view of the side to move
+    let (best_score, played_score, second_score) = if player.is_maximizer(Player::White) {
+        (*best_score, white_played_score, second_score)
+    } else {
+        (-best_score, -white_played_score, -second_score)
+    };
+    let loss = score_loss(best_score, played_score);
+    let rank = scores
+        .iter()
+        .filter(|&&(_, score)| {
+            if player.is_maximizer(Player::White) {
+                score > white_played_score
+            } else {
+                score < white_played_score
+            }
+        })
+        .count();
+    (loss >= threshold).then(|| Blunder {
+        played: played_notation,
+        best: move_to_notation(game, best_move),
+        loss,
+        played_score: white_played_score,
+        misses_win: best_score > 0 && decided(best_score).is_some() && loss > 0,
+        loses_game: played_score < 0 && decided(played_score).is_some(),
+        best_gap: score_loss(best_score, second_score),
+        rank,
+    })
+}
+
+/// How much worse `score` is than `best`, both from the point of view of the side to
+/// move. Wins or losses both are as good as each other, whatever their plies.
+fn score_loss(best: isize, score: isize) -> isize {
+    match (decided(best), decided(score)) {
+        (Some((best_winner, _)), Some((winner, _))) if best_winner == winner => 0,
+        _ => best.saturating_sub(score),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{parse_notation_move, parse_position};
+
+    #[test]
+    fn explain_test() {
+        let game = parse_position("e1 a9 10 10 w -").unwrap();
+        let explanation = explain(&game, &parse_notation_move(&game, "e2").unwrap());
+        assert_eq!(explanation.own_path_before, Some(8));
+        assert_eq!(explanation.own_path_after, Some(7));
+        assert_eq!((explanation.walls_spent, explanation.tempo_gained), (0, 1));
+        assert_eq!(
+            explanation.to_string(),
+            "e2: White's path 8 -> 7, Black's path stays 8; gains 1 tempo"
+        );
+
+        // Black walks down the d file between two vertical walls, a wall below closes it off
+        let game = parse_position("e1 d7 10 8 w c6v,d6v").unwrap();
+        let explanation = explain(&game, &parse_notation_move(&game, "d5h").unwrap());
+        assert_eq!(explanation.walls_spent, 1);
+        assert!(explanation.tempo_gained > 1);
+        assert!(explanation.blocks_corridor);
+
+        // A wall along the a file leaves Black walking between it and the board edge
+        let game = parse_position("e1 a7 10 10 w -").unwrap();
+        let explanation = explain(&game, &parse_notation_move(&game, "a4v").unwrap());
+        assert!(explanation.creates_corridor);
+        assert!(!explanation.blocks_corridor);
+    }
+
+    #[test]
+    fn tempo_test() {
+        // Equal paths, the side to move wins the race
+        let white_to_move = parse_position("e2 e8 10 10 w -").unwrap();
+        assert_eq!(heuristic_board_score(&white_to_move), 1);
+        let black_to_move = parse_position("e2 e8 10 10 b -").unwrap();
+        assert_eq!(heuristic_board_score(&black_to_move), -1);
+
+        // Facing pawns, only the side to move gets to jump
+        let white_to_move = parse_position("e5 e6 10 10 w -").unwrap();
+        assert_eq!(heuristic_board_score(&white_to_move), 3);
+        let black_to_move = parse_position("e5 e6 10 10 b -").unwrap();
+        assert_eq!(heuristic_board_score(&black_to_move), -1);
+    }
+
+    #[test]
+    fn eval_weights_test() {
+        // Black walks down the e file between vertical walls, White on the open a file
+        let game = parse_position("a2 e8 3 3 w d6v,e6v,d4v,e4v").unwrap();
+        let terms = board_terms(&game).unwrap();
+        assert_eq!(
+            (terms.distance, terms.walls, terms.wall_pressure),
+            (0, 0, 3)
+        );
+        // Three walls to spend on Black's chokes and the tempo
+        assert_eq!(heuristic_board_score(&game), 2);
+        let one_wall = parse_position("a2 e8 1 3 w d6v,e6v,d4v,e4v").unwrap();
+        assert_eq!(heuristic_board_score(&one_wall), 1);
+
+        let weights: EvalWeights = "100, 0, 0, 100".parse().unwrap();
+        assert_eq!(weights.score(&game), 1);
+        assert_eq!(
+            Evaluation::Weights(EvalWeights::default()).evaluate(&game),
+            heuristic_board_score(&game)
+        );
+        assert!("100,0,50".parse::<EvalWeights>().is_err());
+    }
+
+    #[test]
+    fn wall_pruning_test() {
+        let game = parse_position("e2 e8 10 10 w d8h").unwrap();
+        let search = |wall_pruning| {
+            best_move_alpha_beta(
+                &game,
+                game.player,
+                2,
+                Evaluation::PathDifference,
+                wall_pruning,
+                &mut TranspositionTable::default(),
+            )
+        };
+        let (pruned, unpruned) = (search(Some(0)), search(None));
+        assert!(pruned.nodes < unpruned.nodes);
+        assert_eq!(pruned.score, unpruned.score);
+    }
+
+    #[test]
+    fn blunder_test() {
+        let game = parse_position("e8 e2 0 1 b -").unwrap();
+        let blunder = check_blunder(&game, &parse_notation_move(&game, "d2").unwrap(), 2, 3);
+        assert_eq!(
+            blunder.unwrap().to_string(),
+            "d2?? misses a win - better was e1"
+        );
+        let winning_move = parse_notation_move(&game, "e1").unwrap();
+        assert!(check_blunder(&game, &winning_move, 2, 3).is_none());
+        assert_eq!(check_blunder(&game, &winning_move, 2, 0).unwrap().rank, 0);
+    }
+
+    #[test]
+    fn iterative_deepening_test() {
+        let game = parse_position("e2 e8 10 10 w d8h").unwrap();
+        let depth_two = best_move_alpha_beta(
+            &game,
+            game.player,
+            2,
+            Evaluation::PathDifference,
+            None,
+            &mut TranspositionTable::default(),
+        );
+        // Stopped within depth three, the search keeps the result of depth two
+        let stop = |nodes| nodes > depth_two.nodes + 10;
+        let info = iterative_deepening(
+            &game,
+            game.player,
+            None,
+            BotConfig::default(),
+            &mut TranspositionTable::default(),
+            &stop,
+        );
+        assert_eq!(info.depth, 2);
+        assert_eq!(info.score, depth_two.score);
+        assert!(info.nodes > depth_two.nodes);
+
+        // Narrow windows missing the score are widened to the score of full windows
+        let game = parse_position("e5 e6 3 2 b c5h,f4v,d7h").unwrap();
+        let search = |aspiration_window| {
+            let config = BotConfig {
+                aspiration_window,
+                ..BotConfig::default()
+            };
+            let table = &mut TranspositionTable::default();
+            iterative_deepening(&game, game.player, Some(2), config, table, &|_| false)
+        };
+        let (narrow, full) = (search(1), search(0));
+        assert_eq!(narrow.score, full.score);
+        assert_eq!(
+            move_to_notation(&game, narrow.best_move().unwrap()),
+            move_to_notation(&game, full.best_move().unwrap())
+        );
+        let won = SearchInfo {
+            score: WHITE_WINS_BLACK_LOSES,
+            ..full.clone()
+        };
+        let config = BotConfig::default();
+        assert_eq!(
+            aspiration_window(Some(&full), config),
+            (full.score - 2, full.score + 2)
+        );
+        assert_eq!(
+            aspiration_window(Some(&won), config),
+            (WHITE_LOSES_BLACK_WINS, WHITE_WINS_BLACK_LOSES)
+        );
+
+        // White wins at once with e9
+        let game = parse_position("e8 e2 10 10 w -").unwrap();
+        let table = &mut TranspositionTable::default();
+        let info = best_move_with_deadline(&game, game.player, Duration::from_millis(50), table);
+        assert_eq!(info.score, win_score(Player::White, 1));
+        assert_eq!(move_to_notation(&game, info.best_move().unwrap()), "e9");
+    }
+
+    #[test]
+    fn win_score_test() {
+        let score = win_score(Player::Black, 4);
+        assert_eq!(decided(score), Some((Player::Black, 4)));
+        assert_eq!(decided(plies_later(score, 2)), Some((Player::Black, 6)));
+        assert_eq!(plies_earlier(plies_later(score, 2), 2), score);
+        assert_eq!(plies_later(3, 2), 3);
+        assert_eq!(decided(WHITE_WINS_BLACK_LOSES), Some((Player::White, 0)));
+        assert_eq!(decided(-3), None);
+
+        // Both pawns a step from the goal: White wins at once or loses the next ply
+        let game = parse_position("e8 e2 10 10 w -").unwrap();
+        let scores = move_scores(&game, 3);
+        assert_eq!(move_to_notation(&game, &scores[0].0), "e9");
+        assert_eq!(scores[0].1, win_score(Player::White, 1));
+        assert_eq!(scores.last().unwrap().1, win_score(Player::Black, 2));
+    }
+
+    #[test]
+    fn principal_variation_search_test() {
+        let game = parse_position("d5 f5 5 6 b c4h,e6v,g3h,a6h,d7v").unwrap();
+        let search = |alpha, beta| {
+            alpha_beta(
+                &game,
+                2,
+                alpha,
+                beta,
+                game.player,
+                Evaluation::PathDifference,
+                None,
+                None,
+                None,
+                &mut 0,
+                &mut TranspositionTable::default(),
+            )
+        };
+        let (score, principal_variation) = search(WHITE_LOSES_BLACK_WINS, WHITE_WINS_BLACK_LOSES);
+        let (best_move, best_score) = move_scores(&game, 2).remove(0);
+        assert_eq!(score, best_score);
+        assert_eq!(
+            move_to_notation(&game, &principal_variation[0]),
+            move_to_notation(&game, &best_move)
+        );
+        // Windows missing the score only bound it, from White's point of view
+        assert!(search(score + 1, WHITE_WINS_BLACK_LOSES).0 <= score + 1);
+        assert!(search(WHITE_LOSES_BLACK_WINS, score - 1).0 >= score - 1);
+    }
+
+    #[test]
+    fn parallel_search_test() {
+        let search = |game: &Game, threads| {
+            let config = BotConfig {
+                threads,
+                ..BotConfig::default()
+            };
+            let table = &mut TranspositionTable::default();
+            iterative_deepening(game, game.player, Some(2), config, table, &|_| false)
+        };
+        // Splitting the moves of the root between threads finds the same score
+        let game = parse_position("e5 e6 3 2 b c5h,f4v,d7h").unwrap();
+        let (single, parallel) = (search(&game, 1), search(&game, 4));
+        assert_eq!(parallel.depth, 2);
+        assert_eq!(parallel.score, single.score);
+        assert_eq!(
+            move_to_notation(&game, parallel.best_move().unwrap()),
+            move_to_notation(&game, single.best_move().unwrap())
+        );
+        assert!(parallel.nodes > 0);
+
+        // White wins at once with e9, whichever thread searches it
+        let game = parse_position("e8 e2 10 10 w -").unwrap();
+        let info = search(&game, 3);
+        assert_eq!(info.score, win_score(Player::White, 1));
+        assert_eq!(move_to_notation(&game, info.best_move().unwrap()), "e9");
+    }
+
+    #[test]
+    fn analyze_test() {
+        let game = parse_position("e5 e6 3 2 b c5h,f4v,d7h").unwrap();
+        let lines = analyze(&game, game.player, 2, 3);
+        assert_eq!(lines.len(), 3);
+        for (line, (_, score)) in lines.iter().zip(move_scores(&game, 2)) {
+            assert_eq!(line.score, score);
+        }
+        let first_moves: HashSet<String> = lines
+            .iter()
+            .map(|line| move_to_notation(&game, &line.principal_variation[0]))
+            .collect();
+        assert_eq!(first_moves.len(), 3);
+
+        // Out of walls, White only has its four pawn moves
+        let game = parse_position("e5 e2 0 10 w -").unwrap();
+        assert_eq!(analyze(&game, game.player, 1, 10).len(), 4);
+    }
+}
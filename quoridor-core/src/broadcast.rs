@@ -0,0 +1,146 @@
+use std::io::Write;
+
+use crate::{
+    arena::{FinishedGame, PlayedMove},
+    notation::move_to_notation,
+};
+
+// Live broadcasts of engine matches. Every move is written as a line while the games
+// are played, so other tools can follow a match by tailing the stream:
+//
+//     game    1  bot depth 4  greedy
+//     move    1  1  e2  0  4  120
+//     move    1  2  e3h  1  4  95
+//     result  1  1-0  GoalReached  41
+//
+// Fields are separated by tabs:
+//
+//     game    game number, White, Black
+//     move    game number, ply, move in standard notation, eval after the move from
+//             White's point of view in steps, search depth, think time in milliseconds
+//     result  game number, result, termination, plies
+//
+// Fields an engine does not report, like the eval of a random mover, are empty. Every
+// line is flushed as it is written. Readers should skip lines of other kinds, the match
+// output shares stdout with the stream when following a match there.
+
+pub struct Broadcast<W: Write> {
+    output: W,
+    /// The game whose moves are being written.
+    game: Option<usize>,
+}
+
+impl<W: Write> Broadcast<W> {
+    pub fn new(output: W) -> Self {
+        Self { output, game: None }
+    }
+
+    /// Writes a move of game `game_index`, first starting the game with the names of
+    /// White and Black when it is its first move.
+    pub fn move_played(
+        &mut self,
+        game_index: usize,
+        players: [&str; 2],
+        played_move: &PlayedMove,
+    ) -> std::io::Result<()> {
+        if self.game != Some(game_index) {
+            self.game = Some(game_index);
+            self.write_line(&[
+                "game",
+                &(game_index + 1).to_string(),
+                players[0],
+                players[1],
+            ])?;
+        }
+        let annotation = &played_move.recorded_move.annotation;
+        let field = |value: Option<String>| value.unwrap_or_default();
+        self.write_line(&[
+            "move",
+            &(game_index + 1).to_string(),
+            &played_move.ply.to_string(),
+            &move_to_notation(played_move.game, &played_move.recorded_move.player_move),
+            &field(annotation.eval.map(|eval| eval.to_string())),
+            &field(annotation.depth.map(|depth| depth.to_string())),
+            &field(annotation.time.map(|time| time.as_millis().to_string())),
+        ])
+    }
+
+    /// Writes the result of game `game_index`.
+    pub fn game_finished(
+        &mut self,
+        game_index: usize,
+        finished_game: &FinishedGame,
+    ) -> std::io::Result<()> {
+        self.game = None;
+        self.write_line(&[
+            "result",
+            &(game_index + 1).to_string(),
+            finished_game.outcome.result(),
+            &format!("{:?}", finished_game.termination),
+            &finished_game.record.moves.len().to_string(),
+        ])
+    }
+
+    fn write_line(&mut self, fields: &[&str]) -> std::io::Result<()> {
+        writeln!(self.output, "{}", fields.join("\t"))?;
+        self.output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::{
+        arena::play_match_with,
+        engine::{AlphaBetaEngine, GreedyEngine, SearchLimits},
+        rules::Rules,
+    };
+
+    #[test]
+    fn broadcast_test() {
+        // Both callbacks write to the stream
+        let broadcast = RefCell::new(Broadcast::new(Vec::new()));
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let names = ["bot", "greedy"];
+        play_match_with(
+            &mut AlphaBetaEngine::new(),
+            &mut GreedyEngine,
+            2,
+            &Rules::default(),
+            &limits,
+            6,
+            |game_index, first_is_white, played_move| {
+                let players = if first_is_white {
+                    names
+                } else {
+                    [names[1], names[0]]
+                };
+                broadcast
+                    .borrow_mut()
+                    .move_played(game_index, players, played_move)
+                    .unwrap();
+            },
+            |game_index, _, finished_game| {
+                broadcast
+                    .borrow_mut()
+                    .game_finished(game_index, finished_game)
+                    .unwrap();
+                true
+            },
+        );
+        let stream = String::from_utf8(broadcast.into_inner().output).unwrap();
+        let lines: Vec<&str> = stream.lines().collect();
+        // A game line, six moves and a result per game
+        assert_eq!(lines.len(), 16);
+        assert_eq!(lines[0], "game\t1\tbot\tgreedy");
+        assert!(lines[1].starts_with("move\t1\t1\t"));
+        assert_eq!(lines[1].split('\t').count(), 7);
+        assert_eq!(lines[7], "result\t1\t1/2-1/2\tPlyLimit\t6");
+        assert_eq!(lines[8], "game\t2\tgreedy\tbot");
+    }
+}
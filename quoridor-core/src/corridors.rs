@@ -0,0 +1,247 @@
+use crate::{
+    data_model::{Direction, Game, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, PiecePosition, Player},
+    game_logic::{
+        is_move_direction_legal_with_player_at_position, new_position_after_direction_unchecked,
+    },
+};
+
+// Corridors, the open board contracted to a graph. Squares with exactly two open sides
+// are chained into corridors, every other square is a junction: crossings with three or
+// four open sides, and dead ends with one or none. A corridor ends at the junctions next
+// to its first and last squares, or nowhere when it is a loop of its own.
+//
+// Per player, the board is seen from the pawn's region on, pawns ignored:
+//
+//     widths     for every row boundary between the pawn and its goal row, the open
+//                steps across it towards the goal. A width of one is a choke point, a
+//                wall there makes the player walk around
+//     corridors  the corridors the player passes walking forward only, their rows
+//                never turning back, by length
+//
+// The contraction only depends on the walls, so one map serves everything asking about
+// a position's corridors. The move explanations tell walls lengthening or closing off the
+// opponent's corridors by it, see `bot::explain`.
+
+const SQUARES: usize = PIECE_GRID_WIDTH * PIECE_GRID_HEIGHT;
+
+#[derive(Debug, Clone)]
+pub struct Corridor {
+    /// From one end to the other.
+    pub squares: Vec<PiecePosition>,
+    /// The junctions next to the first and the last square, None for a loop.
+    pub ends: [Option<PiecePosition>; 2],
+}
+
+impl Corridor {
+    pub fn length(&self) -> usize {
+        self.squares.len()
+    }
+
+    /// Whether a pawn walks the corridor without turning back from the goal row of
+    /// `player`, in the one direction leading to it.
+    pub fn forward_only(&self, player: Player, goal_row: usize) -> bool {
+        let rows: Vec<usize> = self.squares.iter().map(PiecePosition::y).collect();
+        let towards_goal = |from: usize, to: usize| match player {
+            Player::White => from <= to,
+            Player::Black => from >= to,
+        };
+        let forward = rows.windows(2).all(|step| towards_goal(step[0], step[1]));
+        let backward = rows.windows(2).all(|step| towards_goal(step[1], step[0]));
+        // A corridor along the goal row is passed on reaching it
+        forward || backward || rows.iter().all(|&row| row == goal_row)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlayerCorridors {
+    /// Open steps towards the goal across each row boundary, from the pawn's row on.
+    pub widths: Vec<usize>,
+    /// Indices of the forward only corridors in the pawn's region, longest first.
+    pub corridors: Vec<usize>,
+}
+
+impl PlayerCorridors {
+    /// The narrowest row boundary left, None on the goal row.
+    pub fn min_width(&self) -> Option<usize> {
+        self.widths.iter().copied().min()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CorridorMap {
+    pub corridors: Vec<Corridor>,
+    pub junctions: Vec<PiecePosition>,
+    /// By `Player::as_index`.
+    pub players: [PlayerCorridors; 2],
+    corridor_of: [Option<usize>; SQUARES],
+}
+
+impl CorridorMap {
+    /// The corridor `square` is part of, None for junctions.
+    pub fn corridor_of(&self, square: &PiecePosition) -> Option<&Corridor> {
+        self.corridor_of[square.index].map(|index| &self.corridors[index])
+    }
+
+    /// The lengths of the forward only corridors of `player`, longest first.
+    pub fn lengths(&self, player: Player) -> Vec<usize> {
+        self.players[player.as_index()]
+            .corridors
+            .iter()
+            .map(|&index| self.corridors[index].length())
+            .collect()
+    }
+}
+
+/// Contracts the open board of `game` into corridors and junctions.
+pub fn corridor_map(game: &Game) -> CorridorMap {
+    let board_size = game.rules.board_size;
+    let squares = (0..board_size)
+        .flat_map(|y| (0..board_size).map(move |x| PiecePosition::new(x, y)))
+        .collect::<Vec<_>>();
+    let is_junction = |square: &PiecePosition| neighbours(game, square).len() != 2;
+    let mut corridors = Vec::new();
+    let mut corridor_of = [None; SQUARES];
+    for square in &squares {
+        if is_junction(square) || corridor_of[square.index].is_some() {
+            continue;
+        }
+        // Walks both ways from the square, up to the junctions
+        let mut sides = neighbours(game, square).into_iter().map(|first| {
+            let mut walk = Vec::new();
+            let (mut previous, mut current) = (square.clone(), first);
+            while !is_junction(&current) && &current != square {
+                let next = neighbours(game, &current)
+                    .into_iter()
+                    .find(|next| next != &previous)
+                    .unwrap();
+                walk.push(current.clone());
+                (previous, current) = (current, next);
+            }
+            // Back at the square, the corridor is a loop without junctions
+            let end = (&current != square).then_some(current);
+            (walk, end)
+        });
+        let (mut before, first_end) = sides.next().unwrap();
+        let (after, last_end) = sides.next().unwrap();
+        let corridor_squares = if first_end.is_none() {
+            // A loop, walked once around already
+            std::iter::once(square.clone()).chain(before).collect()
+        } else {
+            before.reverse();
+            before
+                .into_iter()
+                .chain(std::iter::once(square.clone()))
+                .chain(after)
+                .collect::<Vec<_>>()
+        };
+        for corridor_square in &corridor_squares {
+            corridor_of[corridor_square.index] = Some(corridors.len());
+        }
+        corridors.push(Corridor {
+            squares: corridor_squares,
+            ends: [first_end, last_end],
+        });
+    }
+    let junctions = squares.into_iter().filter(is_junction).collect();
+    let mut map = CorridorMap {
+        corridors,
+        junctions,
+        players: Default::default(),
+        corridor_of,
+    };
+    for player in [Player::White, Player::Black] {
+        map.players[player.as_index()] = player_corridors(game, &map, player);
+    }
+    map
+}
+
+fn player_corridors(game: &Game, map: &CorridorMap, player: Player) -> PlayerCorridors {
+    let goal_row = game.rules.goal_row(player);
+    let start = game.board.player_position(player);
+    let region = region(game, start);
+    let forward_row = |row: usize| match player {
+        Player::White => row + 1,
+        Player::Black => row - 1,
+    };
+    let mut widths = Vec::new();
+    let mut row = start.y();
+    while row != goal_row {
+        let next_row = forward_row(row);
+        let width = (0..game.rules.board_size)
+            .map(|x| PiecePosition::new(x, row))
+            .filter(|square| region[square.index])
+            .filter(|square| {
+                neighbours(game, square)
+                    .iter()
+                    .any(|next| next.y() == next_row)
+            })
+            .count();
+        widths.push(width);
+        row = next_row;
+    }
+    let mut corridors: Vec<usize> = (0..map.corridors.len())
+        .filter(|&index| {
+            let corridor = &map.corridors[index];
+            region[corridor.squares[0].index] && corridor.forward_only(player, goal_row)
+        })
+        .collect();
+    corridors.sort_by_key(|&index| std::cmp::Reverse(map.corridors[index].length()));
+    PlayerCorridors { widths, corridors }
+}
+
+/// The squares one open step from `square`.
+fn neighbours(game: &Game, square: &PiecePosition) -> Vec<PiecePosition> {
+    Direction::iter()
+        .filter(|direction| {
+            is_move_direction_legal_with_player_at_position(game, square, direction)
+        })
+        .map(|direction| new_position_after_direction_unchecked(square, direction))
+        .collect()
+}
+
+/// The squares reachable from `start`, walking around walls only.
+fn region(game: &Game, start: &PiecePosition) -> [bool; SQUARES] {
+    let mut region = [false; SQUARES];
+    region[start.index] = true;
+    let mut stack = vec![start.clone()];
+    while let Some(square) = stack.pop() {
+        for next in neighbours(game, &square) {
+            if !region[next.index] {
+                region[next.index] = true;
+                stack.push(next);
+            }
+        }
+    }
+    region
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_position;
+
+    #[test]
+    fn corridor_map_test() {
+        // Only the corners have two open sides on an open board
+        let map = corridor_map(&Game::new());
+        assert_eq!(map.corridors.len(), 4);
+        assert_eq!(map.junctions.len(), 77);
+        assert_eq!(map.players[Player::White.as_index()].widths, vec![9; 8]);
+
+        // Two vertical walls either side of the e file, from e3 to e6
+        let game = parse_position("e1 e9 8 8 w d3v,d5v,e3v,e5v").unwrap();
+        let map = corridor_map(&game);
+        let corridor = map.corridor_of(&PiecePosition::new(4, 4)).unwrap();
+        assert_eq!(corridor.length(), 4);
+        assert!(corridor.forward_only(Player::White, 8));
+        assert_eq!(map.lengths(Player::White), vec![4, 1, 1, 1, 1]);
+        assert_eq!(map.players[Player::Black.as_index()].min_width(), Some(9));
+
+        // Horizontal walls across the board but for the i file
+        let game = parse_position("e1 e9 6 6 w a5h,c5h,e5h,g5h").unwrap();
+        let map = corridor_map(&game);
+        for player in [Player::White, Player::Black] {
+            assert_eq!(map.players[player.as_index()].min_width(), Some(1));
+        }
+    }
+}
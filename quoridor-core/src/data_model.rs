@@ -1,5 +1,9 @@
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Rules;
+
 pub const PIECE_GRID_WIDTH: usize = 9;
 pub const PIECE_GRID_HEIGHT: usize = 9;
 pub const WALL_GRID_WIDTH: usize = PIECE_GRID_WIDTH - 1;
@@ -48,19 +52,50 @@ pub struct WallPosition {
     pub y: usize,
 }
 
-pub type Walls = [[Option<WallOrientation>; WALL_GRID_HEIGHT]; WALL_GRID_WIDTH];
+/// The walls centred on one point of the wall grid. Only crossing wall rules
+/// put both orientations on the same centre.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WallCentre {
+    pub horizontal: bool,
+    pub vertical: bool,
+}
 
-#[derive(Default, Debug, Clone)]
+impl WallCentre {
+    pub fn has(&self, orientation: WallOrientation) -> bool {
+        match orientation {
+            WallOrientation::Horizontal => self.horizontal,
+            WallOrientation::Vertical => self.vertical,
+        }
+    }
+
+    pub fn set(&mut self, orientation: WallOrientation, present: bool) {
+        match orientation {
+            WallOrientation::Horizontal => self.horizontal = present,
+            WallOrientation::Vertical => self.vertical = present,
+        }
+    }
+
+    pub fn orientations(&self) -> impl Iterator<Item = WallOrientation> {
+        [WallOrientation::Horizontal, WallOrientation::Vertical]
+            .into_iter()
+            .filter(|&orientation| self.has(orientation))
+    }
+}
+
+pub type Walls = [[WallCentre; WALL_GRID_HEIGHT]; WALL_GRID_WIDTH];
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Board {
     pub walls: Walls,
     pub player_positions: [PiecePosition; PLAYER_COUNT],
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct Game {
     pub player: Player,
     pub board: Board,
     pub walls_left: [usize; PLAYER_COUNT],
+    pub rules: Rules,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, strum::EnumIter)]
@@ -105,7 +140,7 @@ impl Display for PlayerMove {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Player {
     #[default]
     White = 0,
@@ -116,7 +151,10 @@ impl Board {
     pub fn new() -> Self {
         Self {
             walls: Default::default(),
-            player_positions: [PiecePosition::new(4, 0), PiecePosition::new(4, 8)],
+            player_positions: [
+                Player::White.start_position(PIECE_GRID_HEIGHT),
+                Player::Black.start_position(PIECE_GRID_HEIGHT),
+            ],
         }
     }
     pub fn new_with_initial_moves_skipped() -> Self {
@@ -136,10 +174,7 @@ impl Board {
             && wall_pos_y >= 0
             && wall_pos_x < WALL_GRID_WIDTH as isize
             && wall_pos_y < WALL_GRID_HEIGHT as isize
-            && matches!(
-                &self.walls[wall_pos_x as usize][wall_pos_y as usize],
-                Some(o) if *o == wall_orientation
-            )
+            && self.walls[wall_pos_x as usize][wall_pos_y as usize].has(wall_orientation)
     }
 
     pub fn player_position(&self, player: Player) -> &PiecePosition {
@@ -148,12 +183,21 @@ impl Board {
 }
 
 impl Game {
-    pub fn 
-    new() -> Self {
+    pub fn new() -> Self {
+        Self::with_rules(Rules::default())
+    }
+
+    pub fn with_rules(rules: Rules) -> Self {
+        let mut board = Board::new();
+        board.player_positions = [
+            rules.start_position(Player::White),
+            rules.start_position(Player::Black),
+        ];
         Self {
             player: Player::default(),
-            board: Board::new(),
-            walls_left: [10, 10],
+            board,
+            walls_left: [rules.walls_per_player; PLAYER_COUNT],
+            rules,
         }
     }
 }
@@ -192,6 +236,23 @@ impl Player {
         self as usize
     }
 
+    /// The row this player has to reach on a board with `board_size` rows.
+    pub fn goal_row(self, board_size: usize) -> usize {
+        match self {
+            Player::White => board_size - 1,
+            Player::Black => 0,
+        }
+    }
+
+    pub fn start_position(self, board_size: usize) -> PiecePosition {
+        PiecePosition::new(board_size / 2, self.opponent().goal_row(board_size))
+    }
+
+    /// Whether this player is trying to maximize scores given from `perspective`'s point of view.
+    pub fn is_maximizer(self, perspective: Player) -> bool {
+        self == perspective
+    }
+
     pub fn to_string(self) -> &'static str {
         match self {
             Player::White => "White",
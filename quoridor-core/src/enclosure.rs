@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+use crate::{
+    data_model::{
+        Direction, Game, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, PiecePosition, Player, PlayerMove,
+        WallOrientation, WallPosition,
+    },
+    game_logic::{
+        execute_move_unchecked, is_move_direction_legal_with_player_at_position, is_move_legal,
+        new_position_after_direction_unchecked,
+    },
+};
+
+// Enclosures, boxing a pawn into a region without its goal row, which wins when walls may
+// cut off a player's last path. Every enclosure cuts the pawn's shortest path, so the walls
+// tried are the ones across a step of it, at most `MAX_ENCLOSURE_WALLS` deep.
+//
+// The walls go down one per turn and the pawn walks in between, so an enclosure only counts
+// when the pawn can't walk out of the region before the last wall is down. Pawns are ignored
+// in the regions and distances, as are walls the enclosed side places, which can only take
+// slots the enclosure needs, never open the region.
+
+/// Walls tried for an enclosure, deeper searches costing too much in the evaluation.
+pub const MAX_ENCLOSURE_WALLS: usize = 2;
+
+/// Walls boxing a pawn in, see `find_enclosure`.
+#[derive(Debug, Clone)]
+pub struct Enclosure {
+    pub enclosed: Player,
+    /// In the order to place them in.
+    pub walls: Vec<PlayerMove>,
+    /// Squares the pawn is left with.
+    pub region: usize,
+}
+
+/// The player winning by an enclosure, the side to move first, when walls may cut off a
+/// player's last path.
+pub fn enclosure_winner(game: &Game) -> Option<Player> {
+    if !game.rules.allow_blocking {
+        return None;
+    }
+    [game.player, game.player.opponent()]
+        .into_iter()
+        .find(|&player| find_enclosure(game, player).is_some())
+}
+
+/// Walls of `encloser` boxing the opponent in for good, at most as many as it has left and
+/// `MAX_ENCLOSURE_WALLS`. A pawn already boxed in takes no walls.
+pub fn find_enclosure(game: &Game, encloser: Player) -> Option<Enclosure> {
+    let max_walls = game.walls_left[encloser.as_index()].min(MAX_ENCLOSURE_WALLS);
+    let mut walls = Vec::new();
+    search(game, game, encloser, max_walls, &mut walls)
+}
+
+fn search(
+    start: &Game,
+    game: &Game,
+    encloser: Player,
+    walls_left: usize,
+    walls: &mut Vec<PlayerMove>,
+) -> Option<Enclosure> {
+    let enclosed = encloser.opponent();
+    let Some(path) = path_to_goal(game, enclosed) else {
+        return holds(start, game, encloser, walls).then(|| Enclosure {
+            enclosed,
+            walls: walls.clone(),
+            region: region(game, game.board.player_position(enclosed))
+                .iter()
+                .filter(|&&inside| inside)
+                .count(),
+        });
+    };
+    if walls_left == 0 {
+        return None;
+    }
+    for (from, direction) in path {
+        for wall in walls_across(game, &from, direction) {
+            if !is_move_legal(game, encloser, &wall) {
+                continue;
+            }
+            let mut child = game.clone();
+            execute_move_unchecked(&mut child, encloser, &wall);
+            walls.push(wall);
+            if let Some(enclosure) = search(start, &child, encloser, walls_left - 1, walls) {
+                return Some(enclosure);
+            }
+            walls.pop();
+        }
+    }
+    None
+}
+
+/// Whether the enclosure made by `walls`, reaching `game` from `start`, holds: the pawn can't
+/// walk out of its region in the moves it gets before the last wall, and the encloser still
+/// reaches its goal row.
+fn holds(start: &Game, game: &Game, encloser: Player, walls: &[PlayerMove]) -> bool {
+    let enclosed = encloser.opponent();
+    let moves = walls.len().saturating_sub(1) + (start.player == enclosed) as usize;
+    let inside = region(game, game.board.player_position(enclosed));
+    let escape = distances(start, start.board.player_position(enclosed))
+        .into_iter()
+        .zip(&inside)
+        .filter(|&(_, &inside)| !inside)
+        .filter_map(|(distance, _)| distance)
+        .min();
+    escape.is_none_or(|escape| escape > moves) && path_to_goal(game, encloser).is_some()
+}
+
+/// The walls that could close the step in `direction` from `from`.
+fn walls_across(game: &Game, from: &PiecePosition, direction: Direction) -> Vec<PlayerMove> {
+    let (x, y) = (from.x() as isize, from.y() as isize);
+    let (orientation, centres) = match direction {
+        Direction::Up => (WallOrientation::Horizontal, [(x - 1, y - 1), (x, y - 1)]),
+        Direction::Down => (WallOrientation::Horizontal, [(x - 1, y), (x, y)]),
+        Direction::Left => (WallOrientation::Vertical, [(x - 1, y - 1), (x - 1, y)]),
+        Direction::Right => (WallOrientation::Vertical, [(x, y - 1), (x, y)]),
+    };
+    let wall_grid_size = game.rules.board_size as isize - 1;
+    centres
+        .into_iter()
+        .filter(|&(x, y)| x >= 0 && y >= 0 && x < wall_grid_size && y < wall_grid_size)
+        .map(|(x, y)| PlayerMove::PlaceWall {
+            orientation,
+            position: WallPosition {
+                x: x as usize,
+                y: y as usize,
+            },
+        })
+        .collect()
+}
+
+const SQUARES: usize = PIECE_GRID_WIDTH * PIECE_GRID_HEIGHT;
+
+/// The steps from `start` to every square, walking around walls only.
+fn distances(game: &Game, start: &PiecePosition) -> [Option<usize>; SQUARES] {
+    let mut distances = [None; SQUARES];
+    distances[start.index] = Some(0);
+    let mut queue = VecDeque::from([start.clone()]);
+    while let Some(square) = queue.pop_front() {
+        let distance = distances[square.index].unwrap();
+        for direction in Direction::iter() {
+            if !is_move_direction_legal_with_player_at_position(game, &square, &direction) {
+                continue;
+            }
+            let next = new_position_after_direction_unchecked(&square, direction);
+            if distances[next.index].is_none() {
+                distances[next.index] = Some(distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// The squares reachable from `start`.
+fn region(game: &Game, start: &PiecePosition) -> [bool; SQUARES] {
+    distances(game, start).map(|distance| distance.is_some())
+}
+
+/// The steps of a shortest walk of `player` to its goal row, pawns ignored.
+fn path_to_goal(game: &Game, player: Player) -> Option<Vec<(PiecePosition, Direction)>> {
+    let goal_row = game.rules.goal_row(player);
+    let start = game.board.player_position(player);
+    let mut came_from: [Option<(PiecePosition, Direction)>; SQUARES] =
+        std::array::from_fn(|_| None);
+    let mut seen = [false; SQUARES];
+    seen[start.index] = true;
+    let mut queue = VecDeque::from([start.clone()]);
+    while let Some(square) = queue.pop_front() {
+        if square.y() == goal_row {
+            let mut path = Vec::new();
+            let mut current = square;
+            while let Some((from, direction)) = came_from[current.index].clone() {
+                path.push((from.clone(), direction));
+                current = from;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for direction in Direction::iter() {
+            if !is_move_direction_legal_with_player_at_position(game, &square, &direction) {
+                continue;
+            }
+            let next = new_position_after_direction_unchecked(&square, direction);
+            if !seen[next.index] {
+                seen[next.index] = true;
+                came_from[next.index] = Some((square.clone(), direction));
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bot::{WHITE_WINS_BLACK_LOSES, heuristic_board_score},
+        notation::parse_position,
+        rules::Rules,
+    };
+
+    fn blocking_position(position: &str) -> Game {
+        let mut game = parse_position(position).unwrap();
+        game.rules = Rules::variant("blocking").unwrap();
+        game
+    }
+
+    #[test]
+    fn enclosure_test() {
+        // A wall under a8 and b8 leaves Black the a file corner
+        let game = blocking_position("e1 a9 10 10 b a8v");
+        let enclosure = find_enclosure(&game, Player::White).unwrap();
+        assert_eq!(enclosure.enclosed, Player::Black);
+        assert_eq!(enclosure.region, 2);
+        assert_eq!(enclosure.walls.len(), 1);
+        assert_eq!(enclosure_winner(&game), Some(Player::White));
+        assert!(find_enclosure(&game, Player::Black).is_none());
+        assert_eq!(heuristic_board_score(&game), WHITE_WINS_BLACK_LOSES);
+        // Walls may not cut off a path in the standard rules
+        let mut standard = game.clone();
+        standard.rules = Rules::default();
+        assert_eq!(enclosure_winner(&standard), None);
+        // Without walls left
+        assert!(find_enclosure(&blocking_position("e1 a9 0 10 b a8v"), Player::White).is_none());
+
+        // Boxing the a9 corner in takes two walls, Black walks out in between only when
+        // it moves first
+        let enclosure = find_enclosure(&blocking_position("e1 a9 10 10 w -"), Player::White);
+        assert_eq!(enclosure.map(|enclosure| enclosure.walls.len()), Some(2));
+        assert!(find_enclosure(&blocking_position("e1 a9 10 10 b -"), Player::White).is_none());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::a_star::a_star;
+use crate::data_model::{
+    Game, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, Player, WALL_GRID_HEIGHT, WALL_GRID_WIDTH,
+    WallOrientation,
+};
+
+pub const ENCODED_CHANNELS: usize = 8;
+/// What the channels of `encode` hold, in order.
+pub const CHANNEL_NAMES: [&str; ENCODED_CHANNELS] = [
+    "white pawn",
+    "black pawn",
+    "horizontal walls",
+    "vertical walls",
+    "white walls left",
+    "black walls left",
+    "white to move",
+    "off board",
+];
+const PLANE_LEN: usize = PIECE_GRID_WIDTH * PIECE_GRID_HEIGHT;
+/// Values of the planes of a position, C x 9 x 9.
+pub const ENCODED_LEN: usize = ENCODED_CHANNELS * PLANE_LEN;
+
+/// Encoded input of the NN, flat in row-major order so that batches are filled by
+/// copying whole states.
+#[derive(Clone)]
+pub struct EncodedState {
+    pub values: Vec<f32>,
+    /// Channels, height and width.
+    pub shape: [usize; 3],
+}
+
+impl EncodedState {
+    pub fn new(values: Vec<f32>, shape: [usize; 3]) -> Self {
+        assert_eq!(values.len(), shape.iter().product::<usize>());
+        Self { values, shape }
+    }
+
+    /// The values of one channel, row-major.
+    pub fn plane(&self, channel: usize) -> &[f32] {
+        let len = self.shape[1] * self.shape[2];
+        &self.values[channel * len..(channel + 1) * len]
+    }
+}
+
+pub fn encode(game: &Game) -> EncodedState {
+    let mut values = vec![0.0; ENCODED_LEN];
+    encode_into(game, &mut values);
+    EncodedState::new(
+        values,
+        [ENCODED_CHANNELS, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH],
+    )
+}
+
+/// Writes the planes of `game` to `out`, of length `ENCODED_LEN`, for instance a slot
+/// of a batch buffer.
+pub fn encode_into(game: &Game, out: &mut [f32]) {
+    assert_eq!(out.len(), ENCODED_LEN);
+    out.fill(0.0);
+    let mut planes = out.chunks_exact_mut(PLANE_LEN);
+    let mut plane = || planes.next().unwrap();
+    let square = |x: usize, y: usize| y * PIECE_GRID_WIDTH + x;
+
+    // player pawns
+    for p in [Player::White, Player::Black] {
+        let pos = game.board.player_position(p);
+        plane()[square(pos.x(), pos.y())] = 1.0;
+    }
+
+    // walls (just fill in as 1.0 where a wall is placed)
+    let horizontal = plane();
+    let vertical = plane();
+    for (x, column) in game.board.walls.iter().enumerate() {
+        for (y, centre) in column.iter().enumerate() {
+            for orientation in centre.orientations() {
+                match orientation {
+                    WallOrientation::Horizontal => horizontal[square(x, y)] = 1.0,
+                    WallOrientation::Vertical => vertical[square(x, y)] = 1.0,
+                }
+            }
+        }
+    }
+
+    // walls left (normalized by 10)
+    plane().fill(game.walls_left[0] as f32 / 10.0);
+    plane().fill(game.walls_left[1] as f32 / 10.0);
+
+    // player-to-move plane
+    let white_to_move = game.player == Player::White;
+    plane().fill(if white_to_move { 1.0 } else { 0.0 });
+
+    // squares outside a smaller board, which uses the top left corner of the grid
+    let board_size = game.rules.board_size;
+    for (index, v) in plane().iter_mut().enumerate() {
+        let (x, y) = (index % PIECE_GRID_WIDTH, index / PIECE_GRID_WIDTH);
+        if x >= board_size || y >= board_size {
+            *v = 1.0;
+        }
+    }
+}
+
+// Handcrafted features, a much smaller input than the planes for tiny networks that
+// play fast on a CPU. As in chess's half-KP features the pawns are encoded as their
+// conjunction, one feature per (White square, Black square) pair, so that even a
+// single layer sees where the pawns stand relative to each other:
+//
+//   [0, 6561)       pawns, white_square * 81 + black_square, square = y * 9 + x
+//   [6561, 6625)    horizontal walls, x * 8 + y of the wall centre
+//   [6625, 6689)    vertical walls
+//   6689, 6690      shortest path of White and Black to the goal / 10
+//   6691, 6692      walls left of White and Black / 10
+//   6693            1 when White is to move
+//
+// The features are returned as an `EncodedState` of one channel with a single row,
+// so that they go through training shards like the planes.
+
+const SQUARES: usize = PIECE_GRID_WIDTH * PIECE_GRID_HEIGHT;
+const WALL_SEGMENTS: usize = WALL_GRID_WIDTH * WALL_GRID_HEIGHT;
+const HORIZONTAL_WALLS: usize = SQUARES * SQUARES;
+const VERTICAL_WALLS: usize = HORIZONTAL_WALLS + WALL_SEGMENTS;
+const DISTANCES: usize = VERTICAL_WALLS + WALL_SEGMENTS;
+
+pub const FEATURE_COUNT: usize = DISTANCES + 5;
+
+pub fn encode_features(game: &Game) -> EncodedState {
+    let mut values = vec![0.0; FEATURE_COUNT];
+    encode_features_into(game, &mut values);
+    EncodedState::new(values, [1, 1, FEATURE_COUNT])
+}
+
+/// Writes the features of `game` to `features`, of length `FEATURE_COUNT`.
+pub fn encode_features_into(game: &Game, features: &mut [f32]) {
+    assert_eq!(features.len(), FEATURE_COUNT);
+    features.fill(0.0);
+    let square = |player| {
+        let pos = game.board.player_position(player);
+        pos.y() * PIECE_GRID_WIDTH + pos.x()
+    };
+    features[square(Player::White) * SQUARES + square(Player::Black)] = 1.0;
+
+    for (x, column) in game.board.walls.iter().enumerate() {
+        for (y, centre) in column.iter().enumerate() {
+            for orientation in centre.orientations() {
+                let offset = match orientation {
+                    WallOrientation::Horizontal => HORIZONTAL_WALLS,
+                    WallOrientation::Vertical => VERTICAL_WALLS,
+                };
+                features[offset + x * WALL_GRID_HEIGHT + y] = 1.0;
+            }
+        }
+    }
+
+    for player in [Player::White, Player::Black] {
+        // A walled in player, only possible with blocking allowed, counts as being as far away as possible
+        let distance = a_star(game, player).map_or(SQUARES, |path| path.len());
+        features[DISTANCES + player.as_index()] = distance as f32 / 10.0;
+        features[DISTANCES + 2 + player.as_index()] =
+            game.walls_left[player.as_index()] as f32 / 10.0;
+    }
+    features[DISTANCES + 4] = if game.player == Player::White {
+        1.0
+    } else {
+        0.0
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_position;
+    use crate::rules::Rules;
+
+    #[test]
+    fn encode_features_test() {
+        let game = parse_position("e1 e9 10 9 b -").unwrap();
+        let features = encode_features(&game).values;
+        assert_eq!(features.len(), FEATURE_COUNT);
+        assert_eq!(features[4 * SQUARES + 8 * 9 + 4], 1.0);
+        assert_eq!(features[..HORIZONTAL_WALLS].iter().sum::<f32>(), 1.0);
+        assert_eq!(
+            features[HORIZONTAL_WALLS..DISTANCES].iter().sum::<f32>(),
+            0.0
+        );
+        assert_eq!(features[DISTANCES..], [0.8, 0.8, 1.0, 0.9, 0.0]);
+    }
+
+    #[test]
+    fn board_size_plane_test() {
+        let outside = |game: &Game| encode(game).plane(7).iter().sum::<f32>();
+        assert_eq!(outside(&Game::new()), 0.0);
+        let small = Game::with_rules(Rules::variant("small").unwrap());
+        assert_eq!(outside(&small), 81.0 - 25.0);
+    }
+
+    #[test]
+    fn encode_into_test() {
+        let game = parse_position("c3 e7 8 9 b d6h,e2v").unwrap();
+        let encoded = encode(&game);
+        assert_eq!(encoded.plane(0)[2 * 9 + 2], 1.0);
+        assert_eq!(encoded.plane(2)[5 * 9 + 3], 1.0);
+        assert_eq!(encoded.plane(3)[9 + 4], 1.0);
+        // A reused batch slot holds the same values whatever was written before
+        let mut batch = vec![0.5; 2 * ENCODED_LEN];
+        encode_into(&game, &mut batch[ENCODED_LEN..]);
+        assert_eq!(batch[ENCODED_LEN..], encoded.values);
+    }
+}
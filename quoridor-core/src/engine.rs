@@ -0,0 +1,989 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use rand::{SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+use crate::{
+    a_star::a_star,
+    all_moves::ALL_MOVES,
+    analysis::SearchResult,
+    bot::{
+        BotConfig, Evaluation, Ponder, SearchInfo, best_move_alpha_beta, heuristic_board_score,
+        iterative_deepening_with, move_scores_with,
+    },
+    data_model::{Game, PLAYER_COUNT, Player, PlayerMove},
+    game_logic::{
+        execute_move_unchecked, is_move_legal, new_position_after_move_piece_unchecked, winner,
+    },
+    game_record::MoveAnnotation,
+    notation::move_to_notation,
+    tablebase::{DRAW, Tablebase, result_score},
+    transposition::{TranspositionTable, key_after_move, position_key},
+};
+
+pub const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(3);
+/// Moves a player is assumed to have left when sharing out the time on their clock.
+const CLOCK_MOVES_TO_GO: u32 = 20;
+
+/// Nodes the adaptive depth expects a search to need at most, see `adaptive_depth`.
+pub const ADAPTIVE_NODES: f64 = 50_000.0;
+pub const MAX_ADAPTIVE_DEPTH: usize = 16;
+
+/// Limits for a single search. Engines pick their own default when no limit is given,
+/// and stop at whichever limit they reach first otherwise.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    /// Positions searched by alpha-beta engines, simulations of tree searches.
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub clock: Option<Clock>,
+}
+
+impl SearchLimits {
+    /// The time to spend on a move of `player`: the move time or a share of the clock,
+    /// whichever is shorter.
+    pub fn time_budget(&self, player: Player) -> Option<Duration> {
+        let clock_time = self.clock.map(|clock| clock.move_time(player));
+        match (self.movetime, clock_time) {
+            (Some(movetime), Some(clock_time)) => Some(movetime.min(clock_time)),
+            (movetime, clock_time) => movetime.or(clock_time),
+        }
+    }
+}
+
+/// The game clock of both players: the time they have left and the time added after
+/// each of their moves.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    pub remaining: [Duration; PLAYER_COUNT],
+    pub increment: [Duration; PLAYER_COUNT],
+}
+
+impl Clock {
+    /// A share of the time `player` has left plus their increment, never more than half
+    /// the time left.
+    pub fn move_time(&self, player: Player) -> Duration {
+        let remaining = self.remaining[player.as_index()];
+        (remaining / CLOCK_MOVES_TO_GO + self.increment[player.as_index()]).min(remaining / 2)
+    }
+}
+
+/// A move chosen by an engine, with whatever information the engine has about it.
+#[derive(Debug, Clone)]
+pub struct EngineMove {
+    pub player_move: PlayerMove,
+    /// Present for searching engines.
+    pub search_info: Option<SearchInfo>,
+    /// Present for engines sampling from a policy.
+    pub policy_prior: Option<f32>,
+    /// Visits of every root move, present for tree searches.
+    pub root_visits: Option<Vec<(PlayerMove, u32)>>,
+    /// Searched value of the position for the side to move, in [-1, 1], present for tree searches.
+    pub root_value: Option<f32>,
+    pub planned_duration: Option<Duration>,
+    pub actual_duration: Duration,
+}
+
+/// A change to the game an engine plays in, see `Engine::game_event`.
+#[derive(Debug, Clone, Copy)]
+pub enum GameEvent<'a> {
+    /// A move was played, reaching `game` after `ply` moves.
+    MovePlayed { game: &'a Game, ply: usize },
+    /// Moves were taken back, returning to `game` after `ply` moves.
+    Undone { game: &'a Game, ply: usize },
+}
+
+/// A move picker for the side to move. Frontends, the match runner and the
+/// server only talk to engines through this trait.
+pub trait Engine: Send {
+    fn name(&self) -> String;
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> EngineMove;
+
+    /// Like `choose_move` for engines that can fail, like an external program that
+    /// crashed, telling why. The match runner and frontends ask for moves this way, so a
+    /// failing engine forfeits its game instead of taking the program down.
+    fn try_choose_move(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+    ) -> Result<EngineMove, String> {
+        Ok(self.choose_move(game, limits))
+    }
+
+    fn analyse(&mut self, game: &Game, limits: &SearchLimits) -> SearchResult {
+        self.choose_move(game, limits).to_search_result(game)
+    }
+
+    /// Asks a running search to return as soon as possible.
+    fn stop(&self) {}
+
+    /// Forgets what was learned about the previous game, like cached search results.
+    fn new_game(&mut self) {}
+
+    /// Gets ready to search `game` before any clock runs, like warming up a network's
+    /// backend, so the first move isn't slower than the rest.
+    fn warm_up(&mut self, _game: &Game) {}
+
+    /// Told by frontends owning the game about moves played and taken back, so state
+    /// tied to the game's history follows it. Engines keeping nothing between moves, like
+    /// a tree search building a new tree for every move, have nothing to roll back.
+    fn game_event(&mut self, _event: &GameEvent) {}
+
+    /// Told by frontends while the opponent to move in `game` thinks, so the engine may
+    /// search ahead in the background until it is called again.
+    fn ponder(&mut self, _game: &Game) {}
+}
+
+impl EngineMove {
+    pub fn unsearched(player_move: PlayerMove, start_time: Instant) -> Self {
+        Self {
+            player_move,
+            search_info: None,
+            policy_prior: None,
+            root_visits: None,
+            root_value: None,
+            planned_duration: None,
+            actual_duration: start_time.elapsed(),
+        }
+    }
+
+    /// The search info, or a depth 0 evaluation of the move for engines that don't search.
+    pub fn search_info(&self, game: &Game) -> SearchInfo {
+        self.search_info.clone().unwrap_or_else(|| {
+            let mut child_game_state = game.clone();
+            execute_move_unchecked(&mut child_game_state, game.player, &self.player_move);
+            SearchInfo {
+                score: heuristic_board_score(&child_game_state),
+                principal_variation: vec![self.player_move.clone()],
+                depth: 0,
+                nodes: 1,
+                hashfull: 0,
+            }
+        })
+    }
+
+    pub fn to_search_result(&self, game: &Game) -> SearchResult {
+        SearchResult::new(game, &self.search_info(game), self.actual_duration)
+    }
+
+    pub fn annotation(&self) -> MoveAnnotation {
+        MoveAnnotation {
+            eval: self.search_info.as_ref().map(|info| info.score),
+            depth: self.search_info.as_ref().map(|info| info.depth),
+            time: Some(self.actual_duration),
+            policy_prior: self.policy_prior,
+            loss: None,
+        }
+    }
+}
+
+impl Display for EngineMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.player_move)?;
+        if let Some(search_info) = &self.search_info {
+            write!(f, " score:{}", search_info.score)?;
+            write!(f, " depth:{}", search_info.depth)?;
+            write!(f, " nodes:{}", search_info.nodes)?;
+            write!(f, " hashfull:{}", search_info.hashfull)?;
+        }
+        if let Some(prior) = self.policy_prior {
+            write!(f, " prior:{prior}")?;
+        }
+        write!(f, " {:?}", self.actual_duration)?;
+        if let Some(d) = self.planned_duration {
+            write!(f, "({:?})", d)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn legal_moves(game: &Game) -> Vec<PlayerMove> {
+    ALL_MOVES
+        .iter()
+        .filter(|player_move| is_move_legal(game, game.player, player_move))
+        .cloned()
+        .collect()
+}
+
+/// Checks that the side to move has a move to search for: the game is not over and some
+/// move is legal. The searches score a position without legal moves as lost for the side
+/// to move, the frontends report it instead of asking an engine for a move.
+pub fn check_playable(game: &Game) -> Result<(), String> {
+    if let Some(winner) = winner(game) {
+        return Err(format!("The game is over, {} won", winner.to_string()));
+    }
+    if legal_moves(game).is_empty() {
+        return Err(format!("{} has no legal moves", game.player.to_string()));
+    }
+    Ok(())
+}
+
+/// Moves of the side to move, counting pawn moves reaching the same square once.
+fn branching_factor(game: &Game) -> usize {
+    let mut seen = HashSet::new();
+    legal_moves(game)
+        .iter()
+        .filter(|player_move| seen.insert(move_to_notation(game, player_move)))
+        .count()
+}
+
+/// The deepest search of `game` expected to stay within `ADAPTIVE_NODES` nodes.
+///
+/// With perfect move ordering alpha-beta searches b1^ceil(d/2) + b2^floor(d/2) nodes,
+/// b1 and b2 being the branching factors of the side to move and the opponent. Early
+/// on both have over a hundred wall moves, while a player out of walls only has a few
+/// pawn moves, so the depth grows as the walls run out.
+pub fn adaptive_depth(game: &Game) -> usize {
+    let mut opponent_to_move = game.clone();
+    opponent_to_move.player = game.player.opponent();
+    let branching = [game, &opponent_to_move].map(|game| branching_factor(game).max(1) as f64);
+    let nodes = |depth: usize| {
+        branching[0].powi(depth.div_ceil(2) as i32) + branching[1].powi((depth / 2) as i32)
+    };
+    (1..=MAX_ADAPTIVE_DEPTH)
+        .take_while(|&depth| depth == 1 || nodes(depth) <= ADAPTIVE_NODES)
+        .last()
+        .unwrap()
+}
+
+/// The alpha-beta bot. Searches to a fixed depth when one is given, otherwise
+/// deepens until the move time runs out or `stop` is called. Endgames covered by
+/// its tablebase are played perfectly without searching, and score exactly where the
+/// search reaches them.
+///
+/// With opening randomization the bot plays a random move among those scoring close to
+/// the best one during the first plies of a game, so repeated games differ.
+///
+/// With adaptive depth the bot picks the depth of every move with `adaptive_depth`
+/// instead, the move time or `DEFAULT_MOVE_TIME` capping searches that take longer
+/// than expected.
+///
+/// With wall pruning the search skips walls lengthening the bot's own path by more than
+/// the margin beyond the opponent's, see `self_harming_walls`.
+///
+/// Deepening searches every depth after the first with an aspiration window around the
+/// score of the depth before, see `BotConfig`. With more than one thread, searches to a
+/// fixed depth deepen too, splitting the moves of the root between the threads.
+///
+/// With pondering the bot searches the position after the reply it expects while the
+/// opponent thinks, lending the search its table, which the next search starts from
+/// once the pondering is stopped by any call into the engine.
+#[derive(Default)]
+pub struct AlphaBetaEngine {
+    stop: Arc<AtomicBool>,
+    tablebase: Option<Arc<Tablebase>>,
+    config: BotConfig,
+    adaptive_depth: bool,
+    /// Kept between moves, see `transposition`.
+    table: TranspositionTable,
+    /// The tables of the other threads of parallel searches, kept like `table`. The hash
+    /// size is shared out between all of them.
+    helpers: Vec<TranspositionTable>,
+    /// The table generation when each ply of the game was reached, see `game_event`.
+    ply_generations: Vec<(usize, u32)>,
+    /// Moves played in the current game, as far as `game_event` tells.
+    ply: usize,
+    opening: Option<(OpeningRandomization, StdRng)>,
+    pondering: bool,
+    /// The running ponder search, holding the table.
+    ponder: Option<Ponder>,
+    /// The key of the position after the last move chosen, with the reply the search
+    /// expected there.
+    expected_reply: Option<(u64, PlayerMove)>,
+}
+
+/// Which opening moves the bot picks at random, see `AlphaBetaEngine`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpeningRandomization {
+    /// Plies of every game the bot randomizes its moves in.
+    pub plies: usize,
+    /// Steps a move may score worse than the best one to be picked.
+    pub margin: isize,
+}
+
+impl AlphaBetaEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tablebase(self, tablebase: Arc<Tablebase>) -> Self {
+        Self {
+            tablebase: Some(tablebase),
+            ..self
+        }
+    }
+
+    pub fn with_evaluation(self, evaluation: Evaluation) -> Self {
+        Self {
+            config: BotConfig {
+                evaluation,
+                ..self.config
+            },
+            ..self
+        }
+    }
+
+    pub fn with_adaptive_depth(self) -> Self {
+        Self {
+            adaptive_depth: true,
+            ..self
+        }
+    }
+
+    /// Skips walls lengthening the own path by more than `margin` steps beyond the
+    /// opponent's in the searches.
+    pub fn with_wall_pruning(self, margin: isize) -> Self {
+        Self {
+            config: BotConfig {
+                wall_pruning: Some(margin),
+                ..self.config
+            },
+            ..self
+        }
+    }
+
+    /// Searches every depth after the first with a window `window` either side of the
+    /// score of the depth before, 0 for full windows, see `BotConfig::aspiration_window`.
+    pub fn with_aspiration_window(self, window: isize) -> Self {
+        Self {
+            config: BotConfig {
+                aspiration_window: window,
+                ..self.config
+            },
+            ..self
+        }
+    }
+
+    /// Searches with `threads` threads splitting the moves of the root between them, see
+    /// `BotConfig::threads`.
+    pub fn with_threads(self, threads: usize) -> Self {
+        let slots = self.table.capacity() * (self.helpers.len() + 1);
+        Self {
+            config: BotConfig {
+                threads,
+                ..self.config
+            },
+            ..self
+        }
+        .with_slots(slots)
+    }
+
+    /// Replaces the transposition tables with ones of about `hash_mb` megabytes in all.
+    pub fn with_hash_mb(self, hash_mb: usize) -> Self {
+        self.with_slots(TranspositionTable::slots(hash_mb))
+    }
+
+    /// Shares `slots` out between a table for every thread.
+    fn with_slots(self, slots: usize) -> Self {
+        let threads = self.config.threads.max(1);
+        Self {
+            table: TranspositionTable::with_capacity(slots / threads),
+            helpers: (1..threads)
+                .map(|_| TranspositionTable::with_capacity(slots / threads))
+                .collect(),
+            ..self
+        }
+    }
+
+    fn tables(&mut self) -> impl Iterator<Item = &mut TranspositionTable> {
+        std::iter::once(&mut self.table).chain(&mut self.helpers)
+    }
+
+    /// Randomizes the first moves of every game, from a fresh seed when none is given.
+    pub fn with_opening_randomization(
+        self,
+        opening: OpeningRandomization,
+        seed: Option<u64>,
+    ) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self {
+            opening: Some((opening, rng)),
+            ..self
+        }
+    }
+
+    /// Searches the expected reply while the opponent thinks, see `Engine::ponder`.
+    pub fn with_pondering(self) -> Self {
+        Self {
+            pondering: true,
+            ..self
+        }
+    }
+
+    /// Stops the ponder search, taking the table back.
+    fn stop_pondering(&mut self) {
+        if let Some(ponder) = self.ponder.take() {
+            (self.table, self.helpers, _) = ponder.stop();
+        }
+    }
+
+    /// The reply to expect in `game`: the one of the last search, or the best move the
+    /// table holds.
+    fn expected_reply(&mut self, game: &Game) -> Option<PlayerMove> {
+        let key = position_key(game);
+        let reply = match &self.expected_reply {
+            Some((expected_key, reply)) if *expected_key == key => Some(reply.clone()),
+            _ => self
+                .table
+                .probe(key)
+                .and_then(|entry| entry.best_move.clone()),
+        };
+        reply.filter(|reply| is_move_legal(game, game.player, reply))
+    }
+
+    /// A handle that stops the running search from another thread.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+}
+
+impl Engine for AlphaBetaEngine {
+    fn name(&self) -> String {
+        "alpha-beta".to_string()
+    }
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> EngineMove {
+        let start_time = Instant::now();
+        self.stop_pondering();
+        if let Some((player_move, plies)) = self
+            .tablebase
+            .as_ref()
+            .and_then(|tablebase| tablebase.best_move(game))
+        {
+            return EngineMove {
+                search_info: Some(SearchInfo {
+                    score: result_score(game, plies),
+                    principal_variation: vec![player_move.clone()],
+                    depth: if plies == DRAW { 0 } else { plies as usize },
+                    nodes: 0,
+                    hashfull: 0,
+                }),
+                ..EngineMove::unsearched(player_move, start_time)
+            };
+        }
+        self.stop.store(false, Ordering::Relaxed);
+        self.tables().for_each(TranspositionTable::new_search);
+        // Lent to the table for the search to probe, whichever table it has by now
+        self.table.tablebase = self.tablebase.clone();
+        let mut movetime = limits.time_budget(game.player);
+        let mut depth = limits.depth;
+        if self.adaptive_depth {
+            depth = Some(adaptive_depth(game));
+            movetime = Some(movetime.unwrap_or(DEFAULT_MOVE_TIME));
+        } else if depth.is_none() && limits.nodes.is_none() {
+            movetime = Some(movetime.unwrap_or(DEFAULT_MOVE_TIME));
+        }
+        let (search_info, planned_duration) = match (depth, movetime, limits.nodes) {
+            // Searching in parallel takes the deepening, see `iterative_deepening`
+            (Some(depth), None, None) if self.config.threads <= 1 => (
+                best_move_alpha_beta(
+                    game,
+                    game.player,
+                    depth,
+                    self.config.evaluation,
+                    self.config.wall_pruning,
+                    &mut self.table,
+                ),
+                None,
+            ),
+            (depth, movetime, max_nodes) => {
+                let stop = |nodes: u64| {
+                    self.stop.load(Ordering::Relaxed)
+                        || movetime.is_some_and(|movetime| start_time.elapsed() > movetime)
+                        || max_nodes.is_some_and(|max_nodes| nodes >= max_nodes)
+                };
+                (
+                    iterative_deepening_with(
+                        game,
+                        game.player,
+                        depth,
+                        self.config,
+                        &mut self.table,
+                        &mut self.helpers,
+                        &stop,
+                    ),
+                    movetime,
+                )
+            }
+        };
+        let search_info = match &mut self.opening {
+            Some((opening, rng)) if self.ply < opening.plies => opening_move(
+                game,
+                search_info,
+                opening.margin,
+                rng,
+                self.config.evaluation,
+                self.config.wall_pruning,
+                &mut self.table,
+            ),
+            _ => search_info,
+        };
+        // Without legal moves there is nothing to play, see `check_playable`
+        let player_move = search_info
+            .best_move()
+            .cloned()
+            .or_else(|| legal_moves(game).into_iter().next())
+            .expect("Searched a position without legal moves");
+        self.expected_reply = search_info.principal_variation.get(1).map(|reply| {
+            let key = key_after_move(position_key(game), game, game.player, &player_move);
+            (key, reply.clone())
+        });
+        EngineMove {
+            search_info: Some(search_info),
+            planned_duration,
+            ..EngineMove::unsearched(player_move, start_time)
+        }
+    }
+
+    fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(ponder) = &self.ponder {
+            ponder.cancel();
+        }
+    }
+
+    fn new_game(&mut self) {
+        self.stop_pondering();
+        self.expected_reply = None;
+        self.tables().for_each(TranspositionTable::clear);
+        self.ply_generations.clear();
+        self.ply = 0;
+    }
+
+    /// Taking moves back forgets the table entries of the searches made after the restored
+    /// position, which are keyed by positions that may never be reached again.
+    fn game_event(&mut self, event: &GameEvent) {
+        self.stop_pondering();
+        match *event {
+            GameEvent::MovePlayed { ply, .. } => {
+                self.ply = ply;
+                self.ply_generations.retain(|&(reached, _)| reached < ply);
+                self.ply_generations.push((ply, self.table.generation()));
+            }
+            GameEvent::Undone { ply, .. } => {
+                self.ply = ply;
+                if let Some(&(_, generation)) = self
+                    .ply_generations
+                    .iter()
+                    .find(|&&(reached, _)| reached > ply)
+                {
+                    self.tables()
+                        .for_each(|table| table.forget_after(generation));
+                }
+                self.ply_generations.retain(|&(reached, _)| reached <= ply);
+            }
+        }
+    }
+
+    fn ponder(&mut self, game: &Game) {
+        self.stop_pondering();
+        if !self.pondering || winner(game).is_some() {
+            return;
+        }
+        let Some(reply) = self.expected_reply(game) else {
+            return;
+        };
+        let mut expected = game.clone();
+        execute_move_unchecked(&mut expected, game.player, &reply);
+        if winner(&expected).is_some() || legal_moves(&expected).is_empty() {
+            return;
+        }
+        // The tables' slots go to the ponder search until it is stopped
+        let table = std::mem::replace(&mut self.table, TranspositionTable::with_capacity(0));
+        let helpers = std::mem::take(&mut self.helpers);
+        self.ponder = Some(Ponder::start(expected, self.config, table, helpers));
+    }
+}
+
+/// A random move among those scoring at most `margin` steps worse than the best one in a
+/// search as deep as `search_info`'s, whose entries the table still holds.
+fn opening_move(
+    game: &Game,
+    search_info: SearchInfo,
+    margin: isize,
+    rng: &mut StdRng,
+    evaluation: Evaluation,
+    wall_pruning: Option<isize>,
+    table: &mut TranspositionTable,
+) -> SearchInfo {
+    let scores = move_scores_with(game, search_info.depth, evaluation, wall_pruning, table);
+    // Scores from the point of view of the side to move
+    let sign = if game.player.is_maximizer(Player::White) {
+        1
+    } else {
+        -1
+    };
+    let Some(&(_, best_score)) = scores.first() else {
+        return search_info;
+    };
+    let candidates: Vec<&(PlayerMove, isize)> = scores
+        .iter()
+        .filter(|(_, score)| (sign * best_score).saturating_sub(sign * score) <= margin)
+        .collect();
+    let (player_move, score) = candidates.choose(rng).unwrap();
+    SearchInfo {
+        score: *score,
+        principal_variation: vec![player_move.clone()],
+        ..search_info
+    }
+}
+
+/// Plays a uniformly random legal move.
+pub struct RandomEngine {
+    rng: StdRng,
+}
+
+impl RandomEngine {
+    pub fn new(seed: Option<u64>) -> Self {
+        Self {
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_os_rng(),
+            },
+        }
+    }
+}
+
+impl Engine for RandomEngine {
+    fn name(&self) -> String {
+        "random".to_string()
+    }
+
+    fn choose_move(&mut self, game: &Game, _limits: &SearchLimits) -> EngineMove {
+        let start_time = Instant::now();
+        let player_move = legal_moves(game).choose(&mut self.rng).unwrap().clone();
+        EngineMove::unsearched(player_move, start_time)
+    }
+}
+
+/// Always steps along its own shortest path and never places walls.
+#[derive(Default)]
+pub struct GreedyEngine;
+
+impl Engine for GreedyEngine {
+    fn name(&self) -> String {
+        "greedy".to_string()
+    }
+
+    fn choose_move(&mut self, game: &Game, _limits: &SearchLimits) -> EngineMove {
+        let start_time = Instant::now();
+        let player_position = game.board.player_position(game.player);
+        let opponent_position = game.board.player_position(game.player.opponent());
+        let next_square = a_star(game, game.player).and_then(|path| path.first().cloned());
+        let legal_moves = legal_moves(game);
+        let player_move = legal_moves
+            .iter()
+            .find(|player_move| match player_move {
+                PlayerMove::MovePiece(move_piece) => {
+                    Some(new_position_after_move_piece_unchecked(
+                        player_position,
+                        move_piece,
+                        opponent_position,
+                    )) == next_square
+                }
+                PlayerMove::PlaceWall { .. } => false,
+            })
+            .unwrap_or(&legal_moves[0])
+            .clone();
+        EngineMove::unsearched(player_move, start_time)
+    }
+}
+
+/// Searches with its own limits whatever the frontend asks for, so the players of one
+/// game or match can search to different depths. The frontend's clock is kept.
+pub struct OwnLimitsEngine {
+    engine: Box<dyn Engine>,
+    limits: SearchLimits,
+}
+
+impl OwnLimitsEngine {
+    pub fn new(engine: Box<dyn Engine>, limits: SearchLimits) -> Self {
+        Self { engine, limits }
+    }
+
+    fn limits(&self, limits: &SearchLimits) -> SearchLimits {
+        SearchLimits {
+            clock: limits.clock,
+            ..self.limits
+        }
+    }
+}
+
+impl Engine for OwnLimitsEngine {
+    fn name(&self) -> String {
+        self.engine.name()
+    }
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> EngineMove {
+        let limits = self.limits(limits);
+        self.engine.choose_move(game, &limits)
+    }
+
+    fn try_choose_move(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+    ) -> Result<EngineMove, String> {
+        let limits = self.limits(limits);
+        self.engine.try_choose_move(game, &limits)
+    }
+
+    fn analyse(&mut self, game: &Game, limits: &SearchLimits) -> SearchResult {
+        let limits = self.limits(limits);
+        self.engine.analyse(game, &limits)
+    }
+
+    fn stop(&self) {
+        self.engine.stop();
+    }
+
+    fn new_game(&mut self) {
+        self.engine.new_game();
+    }
+
+    fn warm_up(&mut self, game: &Game) {
+        self.engine.warm_up(game);
+    }
+
+    fn game_event(&mut self, event: &GameEvent) {
+        self.engine.game_event(event);
+    }
+
+    fn ponder(&mut self, game: &Game) {
+        self.engine.ponder(game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_position;
+    use crate::rules::JumpRule;
+    use crate::transposition::position_key;
+
+    #[test]
+    fn adaptive_depth_test() {
+        assert_eq!(adaptive_depth(&Game::new()), 4);
+        // Out of walls, only the opponent's plies are expensive
+        assert_eq!(adaptive_depth(&parse_position("e4 e6 0 5 w -").unwrap()), 5);
+        assert!(adaptive_depth(&parse_position("e4 e6 0 0 w -").unwrap()) > 10);
+
+        let engine_move = AlphaBetaEngine::new().with_adaptive_depth().choose_move(
+            &parse_position("e4 e6 0 0 w -").unwrap(),
+            &SearchLimits {
+                depth: Some(1),
+                movetime: Some(Duration::from_secs(60)),
+                ..SearchLimits::default()
+            },
+        );
+        assert!(engine_move.search_info.unwrap().depth > 1);
+    }
+
+    #[test]
+    fn node_limit_test() {
+        let limits = SearchLimits {
+            nodes: Some(2000),
+            ..SearchLimits::default()
+        };
+        let search_info = AlphaBetaEngine::new()
+            .choose_move(&Game::new(), &limits)
+            .search_info
+            .unwrap();
+        // The search stops at any depth when reaching the limit
+        assert!(search_info.nodes >= 2000 && search_info.nodes < 2100);
+        assert!(!search_info.principal_variation.is_empty());
+
+        // Even a search stopped within its first move has a move to play
+        let limits = SearchLimits {
+            nodes: Some(3),
+            ..SearchLimits::default()
+        };
+        let search_info = AlphaBetaEngine::new()
+            .choose_move(&Game::new(), &limits)
+            .search_info
+            .unwrap();
+        assert!(search_info.nodes < 10);
+        assert!(!search_info.principal_variation.is_empty());
+    }
+
+    #[test]
+    fn threads_share_hash_test() {
+        let slots = TranspositionTable::slots(4);
+        let mut engine = AlphaBetaEngine::new().with_hash_mb(4).with_threads(4);
+        assert_eq!(engine.helpers.len(), 3);
+        assert_eq!(engine.table.capacity(), slots / 4);
+        let engine_move = engine.choose_move(
+            &Game::new(),
+            &SearchLimits {
+                depth: Some(2),
+                ..SearchLimits::default()
+            },
+        );
+        assert_eq!(engine_move.search_info.unwrap().depth, 2);
+        // The helpers' tables are kept for the next move
+        assert_eq!(engine.helpers.len(), 3);
+        assert!(
+            engine
+                .helpers
+                .iter()
+                .all(|helper| helper.capacity() == slots / 4 && helper.generation() == 1)
+        );
+        let engine = AlphaBetaEngine::new().with_threads(2).with_hash_mb(4);
+        assert_eq!(
+            engine.table.capacity() + engine.helpers[0].capacity(),
+            slots
+        );
+    }
+
+    #[test]
+    fn own_limits_test() {
+        let mut engine = OwnLimitsEngine::new(
+            Box::new(AlphaBetaEngine::new()),
+            SearchLimits {
+                depth: Some(1),
+                ..SearchLimits::default()
+            },
+        );
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        let search_info = engine
+            .choose_move(&Game::new(), &limits)
+            .search_info
+            .unwrap();
+        assert_eq!(search_info.depth, 1);
+    }
+
+    #[test]
+    fn ponder_test() {
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let game = parse_position("e5 e6 8 8 w c5h,f4v,d7h").unwrap();
+        let mut engine = AlphaBetaEngine::new().with_pondering();
+        let engine_move = engine.choose_move(&game, &limits);
+        let mut after_move = game.clone();
+        execute_move_unchecked(&mut after_move, game.player, &engine_move.player_move);
+        let reply = engine_move.search_info.unwrap().principal_variation[1].clone();
+        let mut expected = after_move.clone();
+        execute_move_unchecked(&mut expected, after_move.player, &reply);
+        // A leaf of the search, left out of the table until pondered on
+        assert!(engine.table.probe(position_key(&expected)).is_none());
+
+        engine.ponder(&after_move);
+        assert!(engine.ponder.is_some());
+        std::thread::sleep(Duration::from_millis(200));
+        engine.game_event(&GameEvent::MovePlayed {
+            game: &expected,
+            ply: 2,
+        });
+        assert!(engine.ponder.is_none());
+        let entry = engine.table.probe(position_key(&expected)).unwrap();
+        assert!(entry.depth >= 1);
+        let pondered = engine.choose_move(&expected, &limits);
+        assert!(is_move_legal(
+            &expected,
+            expected.player,
+            &pondered.player_move
+        ));
+
+        // Without pondering the engine waits
+        let mut engine = AlphaBetaEngine::new();
+        engine.choose_move(&game, &limits);
+        engine.ponder(&after_move);
+        assert!(engine.ponder.is_none());
+    }
+
+    #[test]
+    fn check_playable_test() {
+        assert_eq!(check_playable(&Game::new()), Ok(()));
+        assert_eq!(
+            check_playable(&parse_position("e9 e5 10 10 b -").unwrap()),
+            Err("The game is over, White won".to_string())
+        );
+        // Without jumps, Black in the corner is stuck between a wall and White
+        let mut stuck = parse_position("b5 a5 0 0 b a5h,a4h").unwrap();
+        stuck.rules.jumps = JumpRule::NoJumps;
+        assert_eq!(
+            check_playable(&stuck),
+            Err("Black has no legal moves".to_string())
+        );
+    }
+
+    #[test]
+    fn opening_randomization_test() {
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let opening = OpeningRandomization {
+            plies: 2,
+            margin: 100,
+        };
+        let first_move = |seed| {
+            let mut engine = AlphaBetaEngine::new().with_opening_randomization(opening, Some(seed));
+            engine
+                .choose_move(&Game::new(), &limits)
+                .player_move
+                .to_string()
+        };
+        assert_eq!(first_move(1), first_move(1));
+        let moves: HashSet<String> = (0..10).map(first_move).collect();
+        assert!(moves.len() > 1);
+
+        // Past the opening the bot plays its best move again
+        let mut engine = AlphaBetaEngine::new().with_opening_randomization(opening, Some(1));
+        engine.game_event(&GameEvent::MovePlayed {
+            game: &Game::new(),
+            ply: 2,
+        });
+        let best_move = AlphaBetaEngine::new().choose_move(&Game::new(), &limits);
+        assert_eq!(
+            engine
+                .choose_move(&Game::new(), &limits)
+                .player_move
+                .to_string(),
+            best_move.player_move.to_string()
+        );
+    }
+
+    #[test]
+    fn undo_forgets_later_searches_test() {
+        let start = Game::new();
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let mut engine = AlphaBetaEngine::new();
+        let played = engine.choose_move(&start, &limits).player_move;
+        let mut game = start.clone();
+        execute_move_unchecked(&mut game, start.player, &played);
+        engine.game_event(&GameEvent::MovePlayed {
+            game: &game,
+            ply: 1,
+        });
+        engine.choose_move(&game, &limits);
+        assert!(engine.table.probe(position_key(&game)).is_some());
+
+        engine.game_event(&GameEvent::Undone {
+            game: &start,
+            ply: 0,
+        });
+        assert!(engine.table.probe(position_key(&game)).is_none());
+        assert!(engine.ply_generations.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::{
+    data_model::Game,
+    engine::{Engine, EngineMove, GameEvent, SearchLimits},
+    notation::{move_to_notation, position_to_string},
+    transposition::position_key,
+};
+
+// Log of engine searches, for reconstructing what an engine was thinking in long
+// unattended matches. Every search is a JSON line, found by game and ply or by the
+// key of the position:
+//
+//     {"timestamp_ms":1760000000000,"engine":"alpha-beta","game":3,"ply":12,"position":"e3 e7 8 9 w d6h,e2v","key":"8f3a0c2e91b7d455","move":"d3h","score":120,"depth":4,"nodes":18234,"time_ms":210}
+//
+// Games are counted by the engine's new games, the first being 1. When a line would
+// take the log past its size limit, the log is renamed with a ".1" suffix, replacing
+// the previous one, and a new log is started.
+
+#[derive(Serialize)]
+pub struct LogEntry {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub engine: String,
+    pub game: usize,
+    pub ply: usize,
+    pub position: String,
+    /// `position_key` in hex.
+    pub key: String,
+    #[serde(rename = "move")]
+    pub chosen_move: String,
+    /// From White's point of view, present for searching engines.
+    pub score: Option<isize>,
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub time_ms: u64,
+}
+
+impl LogEntry {
+    pub fn new(
+        engine: String,
+        game_number: usize,
+        ply: usize,
+        game: &Game,
+        engine_move: &EngineMove,
+    ) -> Self {
+        let search_info = engine_move.search_info.as_ref();
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_millis() as u64),
+            engine,
+            game: game_number,
+            ply,
+            position: position_to_string(game),
+            key: format!("{:016x}", position_key(game)),
+            chosen_move: move_to_notation(game, &engine_move.player_move),
+            score: search_info.map(|info| info.score),
+            depth: search_info.map(|info| info.depth),
+            nodes: search_info.map(|info| info.nodes),
+            time_ms: engine_move.actual_duration.as_millis() as u64,
+        }
+    }
+}
+
+pub struct EngineLog {
+    path: PathBuf,
+    max_bytes: u64,
+    file: File,
+    size: u64,
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Could not open {}: {e}", path.display()))
+}
+
+impl EngineLog {
+    /// Appends to the log at `path`, rotating it at `max_bytes`.
+    pub fn open(path: &Path, max_bytes: u64) -> Result<Self, String> {
+        let file = open_append(path)?;
+        let size = file.metadata().map_err(|e| e.to_string())?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            file,
+            size,
+        })
+    }
+
+    pub fn write(&mut self, entry: &LogEntry) -> Result<(), String> {
+        let line = serde_json::to_string(entry).map_err(|e| e.to_string())? + "\n";
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            let mut rotated = self.path.clone().into_os_string();
+            rotated.push(".1");
+            std::fs::rename(&self.path, &rotated).map_err(|e| e.to_string())?;
+            self.file = open_append(&self.path)?;
+            self.size = 0;
+        }
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|e| format!("Could not write {}: {e}", self.path.display()))?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Passes moves through from an engine, logging every search. Engines of both players
+/// can share one log.
+pub struct LoggingEngine {
+    engine: Box<dyn Engine>,
+    log: Arc<Mutex<EngineLog>>,
+    game: usize,
+    ply: usize,
+}
+
+impl LoggingEngine {
+    pub fn new(engine: Box<dyn Engine>, log: Arc<Mutex<EngineLog>>) -> Self {
+        Self {
+            engine,
+            log,
+            game: 1,
+            ply: 0,
+        }
+    }
+
+    fn write_entry(&self, game: &Game, engine_move: &EngineMove) {
+        let entry = LogEntry::new(self.name(), self.game, self.ply, game, engine_move);
+        // A full disk shouldn't stop the game
+        if let Err(e) = self.log.lock().unwrap().write(&entry) {
+            eprintln!("{e}");
+        }
+    }
+}
+
+impl Engine for LoggingEngine {
+    fn name(&self) -> String {
+        self.engine.name()
+    }
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> EngineMove {
+        let engine_move = self.engine.choose_move(game, limits);
+        self.write_entry(game, &engine_move);
+        engine_move
+    }
+
+    fn try_choose_move(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+    ) -> Result<EngineMove, String> {
+        let engine_move = self.engine.try_choose_move(game, limits)?;
+        self.write_entry(game, &engine_move);
+        Ok(engine_move)
+    }
+
+    fn stop(&self) {
+        self.engine.stop();
+    }
+
+    fn new_game(&mut self) {
+        // The engine is created for the first game, told about new games after that
+        if self.ply > 0 {
+            self.game += 1;
+        }
+        self.ply = 0;
+        self.engine.new_game();
+    }
+
+    fn warm_up(&mut self, game: &Game) {
+        self.engine.warm_up(game);
+    }
+
+    fn game_event(&mut self, event: &GameEvent) {
+        let (GameEvent::MovePlayed { ply, .. } | GameEvent::Undone { ply, .. }) = *event;
+        self.ply = ply;
+        self.engine.game_event(event);
+    }
+
+    fn ponder(&mut self, game: &Game) {
+        self.engine.ponder(game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arena::play_game,
+        engine::{AlphaBetaEngine, GreedyEngine},
+        rules::Rules,
+    };
+
+    #[test]
+    fn engine_log_test() {
+        let path = std::env::temp_dir().join(format!("engine-log-test-{}", std::process::id()));
+        let log = Arc::new(Mutex::new(EngineLog::open(&path, 1 << 20).unwrap()));
+        let mut white = LoggingEngine::new(Box::new(AlphaBetaEngine::new()), log.clone());
+        let mut black = LoggingEngine::new(Box::new(GreedyEngine), log.clone());
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let rules = Rules::default();
+        play_game(&mut white, &mut black, &rules, &limits, 4);
+        play_game(&mut white, &mut black, &rules, &limits, 2);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0]["game"], 1);
+        assert_eq!(entries[0]["position"], "e1 e9 10 10 w -");
+        assert_eq!(entries[0]["depth"], 1);
+        assert_eq!(entries[1]["ply"], 1);
+        assert_eq!(entries[1]["depth"], serde_json::Value::Null);
+        assert_eq!(entries[4]["game"], 2);
+        assert_eq!(entries[4]["ply"], 0);
+
+        // Rotates before going past the limit, keeping the previous log
+        let line_size = contents.lines().next().unwrap().len() as u64 + 1;
+        let mut small = EngineLog::open(&path, line_size + 10).unwrap();
+        let entry = LogEntry::new(
+            "test".to_string(),
+            1,
+            0,
+            &Game::new(),
+            &GreedyEngine.choose_move(&Game::new(), &limits),
+        );
+        small.write(&entry).unwrap();
+        let rotated = PathBuf::from(format!("{}.1", path.display()));
+        assert_eq!(std::fs::read_to_string(&rotated).unwrap(), contents);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&rotated).unwrap();
+    }
+}
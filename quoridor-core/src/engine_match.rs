@@ -0,0 +1,171 @@
+use std::fmt::Display;
+
+use crate::{
+    accuracy::{GameReport, MoveReview},
+    data_model::{Game, Player},
+};
+
+// Engine match rates, a screening for engine assistance in imported games. Every move
+// of an accuracy report is ranked among the moves of the same search, and a player's
+// rate is the share of their moves that were the engine's first choice, or among its
+// first `TOP_MOVES`. Moves scoring as well as the best one count as first choices.
+//
+// The rates are also split by phase, as strong players match the engine in forced
+// endgames anyway:
+//
+//     opening     the first `OPENING_PLIES` plies
+//     endgame     `ENDGAME_WALLS` walls or fewer left between both players
+//     middlegame  everything in between
+//
+// A high rate is a reason to look at a game, not a proof of anything, short games and
+// one-sided positions match easily.
+
+pub const TOP_MOVES: usize = 3;
+pub const OPENING_PLIES: usize = 12;
+pub const ENDGAME_WALLS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 3] = [Phase::Opening, Phase::Middlegame, Phase::Endgame];
+
+    /// The phase of the position `game` reached at `ply`.
+    pub fn of(ply: usize, game: &Game) -> Self {
+        if game.walls_left.iter().sum::<usize>() <= ENDGAME_WALLS {
+            Phase::Endgame
+        } else if ply < OPENING_PLIES {
+            Phase::Opening
+        } else {
+            Phase::Middlegame
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Opening => "opening",
+            Phase::Middlegame => "middlegame",
+            Phase::Endgame => "endgame",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchRate {
+    pub moves: usize,
+    pub top_move: usize,
+    pub top_moves: usize,
+}
+
+impl MatchRate {
+    fn add(&mut self, review: &MoveReview) {
+        self.moves += 1;
+        if review.comparison.rank == 0 {
+            self.top_move += 1;
+        }
+        if review.comparison.rank < TOP_MOVES {
+            self.top_moves += 1;
+        }
+    }
+
+    /// Percentage of the moves that were the engine's first choice.
+    pub fn top_move_percent(&self) -> f64 {
+        percent(self.top_move, self.moves)
+    }
+
+    /// Percentage of the moves among the engine's first `TOP_MOVES` choices.
+    pub fn top_moves_percent(&self) -> f64 {
+        percent(self.top_moves, self.moves)
+    }
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        100.0 * count as f64 / total as f64
+    }
+}
+
+impl Display for MatchRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "top 1 {:.0}%, top {} {:.0}% of {} moves",
+            self.top_move_percent(),
+            TOP_MOVES,
+            self.top_moves_percent(),
+            self.moves
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerMatch {
+    pub overall: MatchRate,
+    /// By `Phase::ALL`.
+    pub phases: [MatchRate; 3],
+}
+
+pub struct EngineMatchReport {
+    pub players: [PlayerMatch; 2],
+}
+
+/// The match rates of the moves of an accuracy report.
+pub fn engine_match(report: &GameReport) -> EngineMatchReport {
+    let mut players = [PlayerMatch::default(); 2];
+    for review in &report.moves {
+        let player = &mut players[review.game.player.as_index()];
+        player.overall.add(review);
+        let phase = Phase::of(review.ply, &review.game);
+        player.phases[phase as usize].add(review);
+    }
+    EngineMatchReport { players }
+}
+
+impl Display for EngineMatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, player) in [Player::White, Player::Black].into_iter().enumerate() {
+            let player_match = &self.players[player.as_index()];
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}: {}", player.to_string(), player_match.overall)?;
+            for phase in Phase::ALL {
+                let rate = &player_match.phases[phase as usize];
+                if rate.moves > 0 {
+                    write!(f, "\n  {:<10} {rate}", phase.name())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{accuracy::analyse_game, game_record::GameRecord, notation::parse_position};
+
+    #[test]
+    fn engine_match_test() {
+        // Both players walk straight at each other, always among the best moves
+        let record = GameRecord::parse("mdu;mud;mdu;mud;").unwrap();
+        let report = analyse_game(&record.game_states(), record.player_moves(), 1);
+        let match_report = engine_match(&report);
+        let white = &match_report.players[Player::White.as_index()];
+        assert_eq!(white.overall.moves, 2);
+        assert_eq!(white.phases[Phase::Opening as usize], white.overall);
+        assert_eq!(white.overall.top_moves_percent(), 100.0);
+        assert!(match_report.to_string().starts_with("White: top 1"));
+
+        assert_eq!(Phase::of(0, &Game::new()), Phase::Opening);
+        assert_eq!(Phase::of(20, &Game::new()), Phase::Middlegame);
+        let endgame = parse_position("e2 e8 2 1 w -").unwrap();
+        assert_eq!(Phase::of(0, &endgame), Phase::Endgame);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    bot::{Evaluation, WHITE_LOSES_BLACK_WINS, WHITE_WINS_BLACK_LOSES, alpha_beta},
+    data_model::{Game, Player},
+    game_logic::winner,
+    game_record::GameRecord,
+    transposition::TranspositionTable,
+};
+
+// Engine evaluations over the course of a game, for eval graphs. The eval after every
+// move is stored in the move's annotation in the game record, so a game is searched
+// once and its graph drawn again from the record. Evals are from White's point of
+// view like the bot's move scores, won positions scoring the win scores.
+
+const SPARKLINE_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The eval of the position with a `depth` ply search.
+pub fn evaluate(game: &Game, depth: usize, table: &mut TranspositionTable) -> isize {
+    match winner(game) {
+        Some(Player::White) => WHITE_WINS_BLACK_LOSES,
+        Some(Player::Black) => WHITE_LOSES_BLACK_WINS,
+        None => {
+            alpha_beta(
+                game,
+                depth,
+                WHITE_LOSES_BLACK_WINS,
+                WHITE_WINS_BLACK_LOSES,
+                game.player,
+                Evaluation::PathDifference,
+                None,
+                None,
+                None,
+                &mut 0,
+                table,
+            )
+            .0
+        }
+    }
+}
+
+/// Annotates the moves without an eval with the eval of the position after them, searched
+/// `depth` plies deep, `game_states` being the positions of the game. Returns how many
+/// moves were evaluated.
+pub fn annotate_evals(record: &mut GameRecord, game_states: &[Game], depth: usize) -> usize {
+    let mut table = TranspositionTable::for_depth(depth);
+    let mut evaluated = 0;
+    for (recorded, game) in record.moves.iter_mut().zip(&game_states[1..]) {
+        if recorded.annotation.eval.is_none() {
+            recorded.annotation.eval = Some(evaluate(game, depth, &mut table));
+            recorded.annotation.depth = Some(depth);
+            evaluated += 1;
+        }
+    }
+    evaluated
+}
+
+/// The eval after every move, None where the record has none.
+pub fn evals(record: &GameRecord) -> Vec<Option<isize>> {
+    record
+        .moves
+        .iter()
+        .map(|recorded| recorded.annotation.eval)
+        .collect()
+}
+
+/// The evals as a line of block characters, one per move, higher as White gets better.
+/// Evals are capped at `cap` steps either way and missing ones are blank.
+pub fn sparkline(evals: &[Option<isize>], cap: isize) -> String {
+    let top = SPARKLINE_LEVELS.len() as isize - 1;
+    evals
+        .iter()
+        .map(|&eval| match eval {
+            Some(eval) => {
+                let level = (eval.clamp(-cap, cap) + cap) * top / (2 * cap);
+                SPARKLINE_LEVELS[level as usize]
+            }
+            None => ' ',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_graph_test() {
+        let mut record = GameRecord::parse("mdu;mud{eval=3};h33;").unwrap();
+        let game_states = record.game_states();
+        assert_eq!(annotate_evals(&mut record, &game_states, 1), 2);
+        let evals = evals(&record);
+        assert_eq!(evals[1], Some(3));
+        assert_eq!(record.moves[0].annotation.depth, Some(1));
+        // The annotations survive writing the record
+        let parsed = GameRecord::parse(&record.to_string()).unwrap();
+        assert_eq!(super::evals(&parsed), evals);
+
+        assert_eq!(
+            sparkline(&[Some(-20), Some(0), None, Some(isize::MAX)], 10),
+            "▁▄ █"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use crate::{
+    data_model::{Game, Player},
+    game_logic::winner,
+    game_record::GameRecord,
+    notation::{move_to_notation, position_to_string},
+};
+
+// Opening explorer statistics from a database of game records. Positions are keyed by
+// their position string, so transpositions share statistics:
+//
+//     "e1 e9 10 10 w -" -> { "e2": 12 games, 7 White wins, 5 Black wins, ... }
+//
+// Games without a winner count as draws.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MoveStats {
+    pub games: usize,
+    pub white_wins: usize,
+    pub black_wins: usize,
+}
+
+impl MoveStats {
+    pub fn draws(&self) -> usize {
+        self.games - self.white_wins - self.black_wins
+    }
+
+    /// Points per game for `player`, counting draws as half a point.
+    pub fn score(&self, player: Player) -> f64 {
+        let wins = match player {
+            Player::White => self.white_wins,
+            Player::Black => self.black_wins,
+        };
+        (wins as f64 + self.draws() as f64 / 2.0) / self.games as f64
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct OpeningExplorer {
+    positions: HashMap<String, HashMap<String, MoveStats>>,
+    pub games: usize,
+}
+
+impl OpeningExplorer {
+    /// Adds the first `max_plies` moves of a game.
+    pub fn add_game(&mut self, record: &GameRecord, max_plies: usize) {
+        let game_states = record.game_states();
+        let outcome = game_states.last().and_then(winner);
+        for (game, player_move) in game_states
+            .iter()
+            .zip(record.player_moves())
+            .take(max_plies)
+        {
+            let stats = self
+                .positions
+                .entry(position_to_string(game))
+                .or_default()
+                .entry(move_to_notation(game, player_move))
+                .or_default();
+            stats.games += 1;
+            match outcome {
+                Some(Player::White) => stats.white_wins += 1,
+                Some(Player::Black) => stats.black_wins += 1,
+                None => {}
+            }
+        }
+        self.games += 1;
+    }
+
+    /// Adds game records, one per line, returning how many lines were not game records.
+    pub fn add_records(&mut self, input: &str, max_plies: usize) -> usize {
+        let mut skipped = 0;
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            match GameRecord::parse(line) {
+                Some(record) => self.add_game(&record, max_plies),
+                None => skipped += 1,
+            }
+        }
+        skipped
+    }
+
+    /// The moves played from `game` in the database, most played first.
+    pub fn continuations(&self, game: &Game) -> Vec<(String, MoveStats)> {
+        let mut continuations: Vec<_> = self
+            .positions
+            .get(&position_to_string(game))
+            .into_iter()
+            .flatten()
+            .map(|(notation, stats)| (notation.clone(), *stats))
+            .collect();
+        continuations.sort_by(|(a_notation, a), (b_notation, b)| {
+            b.games.cmp(&a.games).then(a_notation.cmp(b_notation))
+        });
+        continuations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explorer_test() {
+        let mut explorer = OpeningExplorer::default();
+        // White walks straight to the goal row while Black dawdles at the side
+        let white_wins = "mdu;mlr;mdu;mrl;mdu;mlr;mdu;mrl;mdu;mlr;mdu;mrl;mdu;mlr;mdu;";
+        let skipped = explorer.add_records(&format!("{white_wins}\n\nmdu;mud;\nbogus\n"), 100);
+        assert_eq!((explorer.games, skipped), (2, 1));
+        let continuations = explorer.continuations(&Game::new());
+        assert_eq!(continuations.len(), 1);
+        let (notation, stats) = &continuations[0];
+        assert_eq!(notation, "e2");
+        assert_eq!((stats.games, stats.white_wins, stats.draws()), (2, 1, 1));
+        assert_eq!(stats.score(Player::White), 0.75);
+    }
+}
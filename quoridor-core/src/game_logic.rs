@@ -0,0 +1,356 @@
+use crate::{
+    a_star::{a_star, heuristic},
+    data_model::{Direction, Game, MovePiece, PiecePosition, Player, PlayerMove, WallOrientation},
+    rules::JumpRule,
+};
+
+pub fn execute_move_unchecked(game: &mut Game, player: Player, player_move: &PlayerMove) {
+    match player_move {
+        PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } => {
+            game.board.walls[position.x][position.y].set(*orientation, true);
+            game.walls_left[player.as_index()] -= 1;
+        }
+        PlayerMove::MovePiece(move_piece) => {
+            let new_position = new_position_after_move_piece_unchecked(
+                game.board.player_position(player),
+                move_piece,
+                game.board.player_position(player.opponent()),
+            );
+            game.board.player_positions[player.as_index()] = new_position;
+        }
+    }
+    game.player = player.opponent();
+}
+
+/// What is needed to take back a move, see `execute_move_with_undo`.
+#[derive(Debug, Clone)]
+pub struct MoveUndo {
+    player: Player,
+    player_move: PlayerMove,
+    previous_position: PiecePosition,
+}
+
+/// Executes a move and returns the information needed to undo it with `undo_move`.
+pub fn execute_move_with_undo(
+    game: &mut Game,
+    player: Player,
+    player_move: &PlayerMove,
+) -> MoveUndo {
+    let undo = MoveUndo {
+        player,
+        player_move: player_move.clone(),
+        previous_position: game.board.player_position(player).clone(),
+    };
+    execute_move_unchecked(game, player, player_move);
+    undo
+}
+
+/// Restores the state from before the move `undo` was returned for.
+pub fn undo_move(game: &mut Game, undo: &MoveUndo) {
+    match &undo.player_move {
+        PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } => {
+            game.board.walls[position.x][position.y].set(*orientation, false);
+            game.walls_left[undo.player.as_index()] += 1;
+        }
+        PlayerMove::MovePiece(_) => {
+            game.board.player_positions[undo.player.as_index()] = undo.previous_position.clone();
+        }
+    }
+    game.player = undo.player;
+}
+
+/// The player that has reached their goal row, if any.
+pub fn winner(game: &Game) -> Option<Player> {
+    [Player::White, Player::Black]
+        .into_iter()
+        .find(|&player| heuristic(&game.rules, game.board.player_position(player), player) == 0)
+}
+
+pub fn is_move_legal(game: &Game, player: Player, player_move: &PlayerMove) -> bool {
+    is_move_legal_with_player_at_position(
+        game,
+        player,
+        game.board.player_position(player),
+        player_move,
+    )
+}
+pub fn is_move_piece_legal_with_player_at_position(
+    game: &Game,
+    player: Player,
+    player_position: &PiecePosition,
+    move_piece: &MovePiece,
+) -> bool {
+    if is_move_direction_legal_with_player_at_position(game, player_position, &move_piece.direction)
+    {
+        let new_position =
+            new_position_after_direction_unchecked(player_position, move_piece.direction);
+        if new_position == *game.board.player_position(player.opponent()) {
+            let jump_allowed = match game.rules.jumps {
+                JumpRule::Diagonal => true,
+                JumpRule::Straight => move_piece.direction_on_collision == move_piece.direction,
+                JumpRule::NoJumps => false,
+            };
+            jump_allowed
+                && is_move_direction_legal_with_player_at_position(
+                    game,
+                    &new_position,
+                    &move_piece.direction_on_collision,
+                )
+        } else {
+            true
+        }
+    } else {
+        false
+    }
+}
+
+pub fn is_move_direction_legal_with_player_at_position(
+    game: &Game,
+    player_position: &PiecePosition,
+    direction: &Direction,
+) -> bool {
+    let board = &game.board;
+    let board_size = game.rules.board_size;
+    match direction {
+        Direction::Up => {
+            player_position.y() > 0
+                && !board.wall_at(
+                    WallOrientation::Horizontal,
+                    player_position.x() as isize - 1,
+                    player_position.y() as isize - 1,
+                )
+                && !board.wall_at(
+                    WallOrientation::Horizontal,
+                    player_position.x() as isize,
+                    player_position.y() as isize - 1,
+                )
+        }
+        Direction::Down => {
+            player_position.y() < board_size - 1
+                && !board.wall_at(
+                    WallOrientation::Horizontal,
+                    player_position.x() as isize - 1,
+                    player_position.y() as isize,
+                )
+                && !board.wall_at(
+                    WallOrientation::Horizontal,
+                    player_position.x() as isize,
+                    player_position.y() as isize,
+                )
+        }
+        Direction::Left => {
+            player_position.x() > 0
+                && !board.wall_at(
+                    WallOrientation::Vertical,
+                    player_position.x() as isize - 1,
+                    player_position.y() as isize,
+                )
+                && !board.wall_at(
+                    WallOrientation::Vertical,
+                    player_position.x() as isize - 1,
+                    player_position.y() as isize - 1,
+                )
+        }
+        Direction::Right => {
+            player_position.x() < board_size - 1
+                && !board.wall_at(
+                    WallOrientation::Vertical,
+                    player_position.x() as isize,
+                    player_position.y() as isize,
+                )
+                && !board.wall_at(
+                    WallOrientation::Vertical,
+                    player_position.x() as isize,
+                    player_position.y() as isize - 1,
+                )
+        }
+    }
+}
+
+pub fn room_for_wall_placement(
+    game: &Game,
+    orientation: WallOrientation,
+    x: isize,
+    y: isize,
+) -> bool {
+    let board = &game.board;
+    let wall_grid_size = game.rules.board_size as isize - 1;
+    let (offsets_to_check, other_orientation) = match orientation {
+        WallOrientation::Horizontal => ([(-1, 0), (0, 0), (1, 0)], WallOrientation::Vertical),
+        WallOrientation::Vertical => ([(0, -1), (0, 0), (0, 1)], WallOrientation::Horizontal),
+    };
+    offsets_to_check
+        .iter()
+        .all(|(dx, dy)| !board.wall_at(orientation, x + dx, y + dy))
+        && (game.rules.allow_crossing_walls || !board.wall_at(other_orientation, x, y))
+        && x >= 0
+        && y >= 0
+        && x < wall_grid_size
+        && y < wall_grid_size
+}
+
+pub fn is_move_legal_with_player_at_position(
+    game: &Game,
+    player: Player,
+    player_position: &PiecePosition,
+    player_move: &PlayerMove,
+) -> bool {
+    match player_move {
+        PlayerMove::MovePiece(move_piece) => {
+            is_move_piece_legal_with_player_at_position(game, player, player_position, move_piece)
+        }
+        PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } => {
+            let blocks_path = |player_to_block_check: Player| {
+                let mut game_copy = game.clone();
+                execute_move_unchecked(
+                    &mut game_copy,
+                    player,
+                    &PlayerMove::PlaceWall {
+                        orientation: *orientation,
+                        position: position.clone(),
+                    },
+                );
+                a_star(&game_copy, player_to_block_check).is_none()
+            };
+            game.walls_left[player.as_index()] > 0
+                && room_for_wall_placement(
+                    game,
+                    *orientation,
+                    position.x as isize,
+                    position.y as isize,
+                )
+                && (game.rules.allow_blocking
+                    || (!blocks_path(player) && !blocks_path(player.opponent())))
+        }
+    }
+}
+
+pub fn new_position_after_direction_unchecked(
+    player_position: &PiecePosition,
+    direction: Direction,
+) -> PiecePosition {
+    let (dx, dy) = direction.to_offset();
+    PiecePosition::new(
+        (player_position.x() as isize + dx) as usize,
+        (player_position.y() as isize + dy) as usize,
+    )
+}
+
+pub fn new_position_after_move_piece_unchecked(
+    player_position: &PiecePosition,
+    move_piece: &MovePiece,
+    opponent_position: &PiecePosition,
+) -> PiecePosition {
+    let new_position =
+        new_position_after_direction_unchecked(player_position, move_piece.direction);
+    if opponent_position == &new_position {
+        new_position_after_direction_unchecked(opponent_position, move_piece.direction_on_collision)
+    } else {
+        new_position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        all_moves::ALL_MOVES,
+        engine::legal_moves,
+        rules::{Rules, VARIANT_NAMES},
+    };
+    use proptest::prelude::*;
+
+    /// Rule variants where every legal wall leaves both players a path.
+    fn arb_rules() -> impl Strategy<Value = Rules> {
+        proptest::sample::select(
+            VARIANT_NAMES
+                .iter()
+                .filter(|&&name| name != "blocking")
+                .map(|name| Rules::variant(name).unwrap())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Random move choices, each an index into `ALL_MOVES`.
+    fn arb_choices() -> impl Strategy<Value = Vec<prop::sample::Index>> {
+        prop::collection::vec(any::<prop::sample::Index>(), 0..60)
+    }
+
+    /// The first legal move at or after the chosen index, wrapping around `ALL_MOVES`.
+    fn chosen_move(game: &Game, choice: &prop::sample::Index) -> PlayerMove {
+        let start = choice.index(ALL_MOVES.len());
+        (0..ALL_MOVES.len())
+            .map(|offset| &ALL_MOVES[(start + offset) % ALL_MOVES.len()])
+            .find(|player_move| is_move_legal(game, game.player, player_move))
+            .unwrap()
+            .clone()
+    }
+
+    /// Plays the choices from the initial position, stopping early when the game is decided.
+    fn play_choices(rules: Rules, choices: &[prop::sample::Index]) -> Game {
+        let mut game = Game::with_rules(rules);
+        for choice in choices {
+            if winner(&game).is_some() {
+                break;
+            }
+            let player_move = chosen_move(&game, choice);
+            let player = game.player;
+            execute_move_unchecked(&mut game, player, &player_move);
+        }
+        game
+    }
+
+    fn arb_game() -> impl Strategy<Value = Game> {
+        (arb_rules(), arb_choices()).prop_map(|(rules, choices)| play_choices(rules, &choices))
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn undo_restores_state_test(game in arb_game(), choice in any::<prop::sample::Index>()) {
+            prop_assume!(winner(&game).is_none());
+            let player = game.player;
+            let mut child_game = game.clone();
+            let undo = execute_move_with_undo(&mut child_game, player, &chosen_move(&game, &choice));
+            undo_move(&mut child_game, &undo);
+            prop_assert_eq!(child_game, game);
+        }
+
+        #[test]
+        fn legal_walls_keep_paths_test(game in arb_game()) {
+            let player = game.player;
+            let walls = legal_moves(&game)
+                .into_iter()
+                .filter(|player_move| matches!(player_move, PlayerMove::PlaceWall { .. }));
+            for player_move in walls {
+                let mut child_game = game.clone();
+                execute_move_unchecked(&mut child_game, player, &player_move);
+                prop_assert!(a_star(&child_game, player).is_some(), "{}", player_move);
+                prop_assert!(a_star(&child_game, player.opponent()).is_some(), "{}", player_move);
+            }
+        }
+
+        #[test]
+        fn legal_moves_match_is_move_legal_test(game in arb_game()) {
+            let legal = legal_moves(&game);
+            let expected: Vec<_> = ALL_MOVES
+                .iter()
+                .filter(|player_move| is_move_legal(&game, game.player, player_move))
+                .collect();
+            prop_assert_eq!(legal.len(), expected.len());
+            for (player_move, expected_move) in legal.iter().zip(expected) {
+                prop_assert_eq!(player_move.to_string(), expected_move.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::{
+    data_model::{Game, Player, PlayerMove},
+    game_logic::execute_move_unchecked,
+    notation::{clean_line, parse_player_move},
+    rules::Rules,
+};
+
+// Game records extend the plain export format ("mdd;muu;h42;") with an
+// optional metadata block after each move:
+//
+//     mdd{eval=0,depth=4,time_ms=1200};muu{prior=0.125};h42;
+//
+// Analyses add the eval loss of a move against the best move, in steps, as `loss`.
+//
+// Unknown keys are ignored when reading so newer writers stay readable.
+//
+// A record may start with a block of tags about the whole game, such as who
+// played which colour:
+//
+//     [white=iter-0004,black=iter-0003,result=0-1]mdd;muu;h42;
+//
+// Exports also tag the think time each player spent on their timed moves, as
+// `white_time_ms` and `black_time_ms`.
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MoveAnnotation {
+    /// Engine evaluation after the move, from White's point of view.
+    pub eval: Option<isize>,
+    pub depth: Option<usize>,
+    pub time: Option<Duration>,
+    /// Network policy probability of the played move.
+    pub policy_prior: Option<f32>,
+    /// Steps the move is worse than the best move, see accuracy.rs.
+    pub loss: Option<isize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedMove {
+    pub player_move: PlayerMove,
+    pub annotation: MoveAnnotation,
+}
+
+/// Time a player spent on their moves, see `GameRecord::think_times`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ThinkTime {
+    pub total: Duration,
+    /// Moves with a recorded time.
+    pub moves: usize,
+}
+
+impl ThinkTime {
+    pub fn per_move(&self) -> Duration {
+        self.total / self.moves.max(1) as u32
+    }
+}
+
+impl std::ops::AddAssign for ThinkTime {
+    fn add_assign(&mut self, other: Self) {
+        self.total += other.total;
+        self.moves += other.moves;
+    }
+}
+
+impl Display for ThinkTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.1}s ({:.2}s per move)",
+            self.total.as_secs_f64(),
+            self.per_move().as_secs_f64()
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct GameRecord {
+    /// Key-value pairs about the game, in the order they are written.
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<RecordedMove>,
+}
+
+impl MoveAnnotation {
+    pub fn is_empty(&self) -> bool {
+        *self == MoveAnnotation::default()
+    }
+
+    fn parse(block: &str) -> Option<Self> {
+        let mut annotation = MoveAnnotation::default();
+        for entry in block.split(',').filter(|entry| !entry.is_empty()) {
+            let (key, value) = entry.split_once('=')?;
+            match key.trim() {
+                "eval" => annotation.eval = Some(value.trim().parse().ok()?),
+                "depth" => annotation.depth = Some(value.trim().parse().ok()?),
+                "time_ms" => {
+                    annotation.time = Some(Duration::from_millis(value.trim().parse().ok()?))
+                }
+                "prior" => annotation.policy_prior = Some(value.trim().parse().ok()?),
+                "loss" => annotation.loss = Some(value.trim().parse().ok()?),
+                _ => {}
+            }
+        }
+        Some(annotation)
+    }
+}
+
+impl Display for MoveAnnotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut entries = Vec::new();
+        if let Some(eval) = self.eval {
+            entries.push(format!("eval={eval}"));
+        }
+        if let Some(depth) = self.depth {
+            entries.push(format!("depth={depth}"));
+        }
+        if let Some(time) = self.time {
+            entries.push(format!("time_ms={}", time.as_millis()));
+        }
+        if let Some(prior) = self.policy_prior {
+            entries.push(format!("prior={prior}"));
+        }
+        if let Some(loss) = self.loss {
+            entries.push(format!("loss={loss}"));
+        }
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.tags.is_empty() {
+            let tags: Vec<_> = self
+                .tags
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            write!(f, "[{}]", tags.join(","))?;
+        }
+        for recorded_move in &self.moves {
+            write!(f, "{}", recorded_move.player_move)?;
+            if !recorded_move.annotation.is_empty() {
+                write!(f, "{{{}}}", recorded_move.annotation)?;
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+impl GameRecord {
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = clean_line(input);
+        let (tags, input) = match input.strip_prefix('[') {
+            Some(rest) => {
+                let (block, rest) = rest.split_once(']')?;
+                let tags = block
+                    .split(',')
+                    .filter(|entry| !entry.is_empty())
+                    .map(|entry| {
+                        let (key, value) = entry.split_once('=')?;
+                        Some((key.trim().to_string(), value.trim().to_string()))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                (tags, rest)
+            }
+            None => (Vec::new(), input),
+        };
+        let moves = input
+            .trim()
+            .trim_matches(';')
+            .split(';')
+            .filter(|token| !token.trim().is_empty())
+            .map(|token| {
+                let token = token.trim();
+                let (move_str, annotation) = match token.split_once('{') {
+                    Some((move_str, block)) => {
+                        (move_str, MoveAnnotation::parse(block.strip_suffix('}')?)?)
+                    }
+                    None => (token, MoveAnnotation::default()),
+                };
+                Some(RecordedMove {
+                    player_move: parse_player_move(move_str)?,
+                    annotation,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { tags, moves })
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_key, _)| tag_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a tag, replacing an earlier value of the same key. Commas and closing
+    /// brackets in the value, which would end the tag block, become spaces.
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        let value = value.replace([',', ']'], " ");
+        match self.tags.iter_mut().find(|(tag_key, _)| tag_key == key) {
+            Some((_, tag_value)) => *tag_value = value,
+            None => self.tags.push((key.to_string(), value)),
+        }
+    }
+
+    /// Think time of each player by `Player::as_index`, White making the first move.
+    pub fn think_times(&self) -> [ThinkTime; 2] {
+        let mut think_times = [ThinkTime::default(); 2];
+        for (ply, recorded_move) in self.moves.iter().enumerate() {
+            if let Some(time) = recorded_move.annotation.time {
+                think_times[ply % 2] += ThinkTime {
+                    total: time,
+                    moves: 1,
+                };
+            }
+        }
+        think_times
+    }
+
+    /// Tags the think times of both players, see `think_times`.
+    pub fn set_think_time_tags(&mut self) {
+        let think_times = self.think_times();
+        for player in [Player::White, Player::Black] {
+            let key = format!("{}_time_ms", player.to_string().to_lowercase());
+            let total = think_times[player.as_index()].total.as_millis();
+            self.set_tag(&key, &total.to_string());
+        }
+    }
+
+    pub fn player_moves(&self) -> impl Iterator<Item = &PlayerMove> {
+        self.moves
+            .iter()
+            .map(|recorded_move| &recorded_move.player_move)
+    }
+
+    /// All positions of the game, starting with the initial position.
+    pub fn game_states(&self) -> Vec<Game> {
+        self.game_states_with_rules(&Rules::default())
+    }
+
+    /// All positions of a game played with `rules`.
+    pub fn game_states_with_rules(&self, rules: &Rules) -> Vec<Game> {
+        let mut game_states = vec![Game::with_rules(*rules)];
+        for player_move in self.player_moves() {
+            let mut game = game_states.last().unwrap().clone();
+            let player = game.player;
+            execute_move_unchecked(&mut game, player, player_move);
+            game_states.push(game);
+        }
+        game_states
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        let input = "mdd{eval=-1,depth=4,time_ms=1200};muu{prior=0.125,loss=2};h42;";
+        let record = GameRecord::parse(input).unwrap();
+        assert_eq!(record.moves.len(), 3);
+        assert_eq!(record.moves[0].annotation.eval, Some(-1));
+        assert_eq!(
+            record.moves[0].annotation.time,
+            Some(Duration::from_millis(1200))
+        );
+        assert_eq!(record.moves[1].annotation.policy_prior, Some(0.125));
+        assert_eq!(record.moves[1].annotation.loss, Some(2));
+        assert!(record.moves[2].annotation.is_empty());
+        assert_eq!(record.to_string(), input);
+    }
+
+    #[test]
+    fn plain_export_test() {
+        let record = GameRecord::parse("mdd;muu;").unwrap();
+        assert_eq!(record.to_string(), "mdd;muu;");
+        let saved_on_windows = GameRecord::parse("\u{feff}mdd;muu;\r\n").unwrap();
+        assert_eq!(saved_on_windows.to_string(), "mdd;muu;");
+        assert_eq!(record.game_states().len(), 3);
+        assert!(GameRecord::parse("mdd{eval=x};").is_none());
+        let small = record.game_states_with_rules(&Rules::variant("small").unwrap());
+        assert_eq!(small[2].board.player_position(Player::Black).y(), 3);
+    }
+
+    #[test]
+    fn tags_test() {
+        let input = "[white=iter-0004,black=iter-0003]mdd;muu;";
+        let mut record = GameRecord::parse(input).unwrap();
+        assert_eq!(record.tag("black"), Some("iter-0003"));
+        assert_eq!(record.moves.len(), 2);
+        assert_eq!(record.to_string(), input);
+        record.set_tag("result", "0-1");
+        record.set_tag("white", "iter-0005");
+        assert_eq!(
+            record.to_string(),
+            "[white=iter-0005,black=iter-0003,result=0-1]mdd;muu;"
+        );
+        assert!(GameRecord::parse("[white]mdd;").is_none());
+    }
+
+    #[test]
+    fn think_times_test() {
+        let mut record =
+            GameRecord::parse("mdd{time_ms=1200};muu{time_ms=300};h42;mu{time_ms=100};").unwrap();
+        let [white, black] = record.think_times();
+        assert_eq!(white.total, Duration::from_millis(1200));
+        assert_eq!(white.moves, 1);
+        assert_eq!(black.per_move(), Duration::from_millis(200));
+        assert_eq!(white.to_string(), "1.2s (1.20s per move)");
+        record.set_think_time_tags();
+        assert_eq!(record.tag("black_time_ms"), Some("400"));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::{
+    accuracy::{DECISIVE_LOSS, GameReport, KEY_MOMENTS, Severity},
+    data_model::{Game, Player, WallOrientation},
+    eval_graph::evals,
+    game_record::GameRecord,
+    notation::move_to_notation,
+};
+
+// Self-contained HTML reports of analysed games, to share post-mortems with a browser
+// only. The page has the players and their accuracy, an eval graph, the move list with
+// the analysis marks and a diagram of the position after the selected move, and
+// diagrams of the key moments, see accuracy.rs, marking the turning point.
+//
+// Every position is an inline SVG, hidden until selected by clicking a move or a point
+// of the graph or with the arrow keys. The graph shows the evals stored in the record,
+// see eval_graph.rs, from White's point of view and capped at `DECISIVE_LOSS` steps.
+
+const SQUARE: usize = 40;
+const GAP: usize = 8;
+/// Room for the coordinates left of and below the board.
+const MARGIN: usize = 20;
+const GRAPH_WIDTH: usize = 600;
+const GRAPH_HEIGHT: usize = 160;
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:960px;margin:auto;padding:1em;color:#222}\
+.layout{display:flex;gap:2em;flex-wrap:wrap}\
+.moves{columns:2;min-width:240px}\
+.moves span{cursor:pointer;padding:0 .3em}\
+.moves span.selected{background:#ffd54f}\
+.inaccuracy{color:#b58900}.mistake{color:#cb4b16}.blunder{color:#dc322f;font-weight:bold}\
+.moment{display:inline-block;margin:0 1em 1em 0;vertical-align:top;max-width:300px}\
+.moment svg{width:100%;height:auto}\
+svg text{font-size:12px;fill:#555}";
+
+const SCRIPT: &str = "let selected=0;\
+function select(ply){\
+document.querySelectorAll('.position').forEach((p,i)=>p.hidden=i!=ply);\
+document.querySelectorAll('.moves span').forEach(m=>m.classList.toggle('selected',m.dataset.ply==ply));\
+selected=ply;}\
+document.querySelectorAll('[data-ply]').forEach(e=>e.onclick=()=>select(+e.dataset.ply));\
+document.onkeydown=e=>{const last=document.querySelectorAll('.position').length-1;\
+if(e.key=='ArrowLeft')select(Math.max(selected-1,0));\
+if(e.key=='ArrowRight')select(Math.min(selected+1,last));};";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn severity_class(severity: Option<Severity>) -> &'static str {
+    match severity {
+        None => "",
+        Some(Severity::Inaccuracy) => "inaccuracy",
+        Some(Severity::Mistake) => "mistake",
+        Some(Severity::Blunder) => "blunder",
+    }
+}
+
+fn severity_mark(severity: Option<Severity>) -> &'static str {
+    match severity {
+        None => "",
+        Some(Severity::Inaccuracy) => "?!",
+        Some(Severity::Mistake) => "?",
+        Some(Severity::Blunder) => "??",
+    }
+}
+
+/// The position as an SVG diagram, with the coordinates of the notation.
+pub fn board_svg(game: &Game) -> String {
+    let size = game.rules.board_size;
+    let board = size * (SQUARE + GAP) - GAP;
+    let corner = |index: usize| MARGIN + index * (SQUARE + GAP);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">",
+        board + MARGIN + 4
+    );
+    for y in 0..size {
+        for x in 0..size {
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{SQUARE}\" height=\"{SQUARE}\" fill=\"#e8d8b8\"/>",
+                corner(x),
+                corner(y) - MARGIN
+            );
+        }
+        svg += &format!(
+            "<text x=\"4\" y=\"{}\">{}</text>",
+            corner(y) - MARGIN + SQUARE / 2 + 4,
+            y + 1
+        );
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\">{}</text>",
+            corner(y) + SQUARE / 2 - 4,
+            board + MARGIN - 4,
+            (b'a' + y as u8) as char
+        );
+    }
+    for x in 0..size - 1 {
+        for y in 0..size - 1 {
+            for orientation in game.board.walls[x][y].orientations() {
+                let (width, height) = match orientation {
+                    WallOrientation::Horizontal => (2 * SQUARE + GAP, GAP),
+                    WallOrientation::Vertical => (GAP, 2 * SQUARE + GAP),
+                };
+                let (left, top) = match orientation {
+                    WallOrientation::Horizontal => (corner(x), corner(y) - MARGIN + SQUARE),
+                    WallOrientation::Vertical => (corner(x) + SQUARE, corner(y) - MARGIN),
+                };
+                svg += &format!(
+                    "<rect x=\"{left}\" y=\"{top}\" width=\"{width}\" height=\"{height}\" fill=\"#6d4c41\"/>"
+                );
+            }
+        }
+    }
+    for (player, fill) in [(Player::White, "#fafafa"), (Player::Black, "#263238")] {
+        let position = game.board.player_position(player);
+        svg += &format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{fill}\" stroke=\"#263238\" stroke-width=\"2\"/>",
+            corner(position.x()) + SQUARE / 2,
+            corner(position.y()) - MARGIN + SQUARE / 2,
+            SQUARE / 2 - 5
+        );
+    }
+    svg + "</svg>"
+}
+
+/// The evals after the moves as a line, White winning upwards, with a point per move
+/// selecting its position. Moves without an eval in the record get the score of the
+/// analysis.
+fn eval_graph(record: &GameRecord, report: &GameReport) -> String {
+    let cap = DECISIVE_LOSS as f64;
+    let x = |ply: usize| (ply + 1) as f64 * GRAPH_WIDTH as f64 / record.moves.len().max(1) as f64;
+    let y = |score: isize| {
+        let score = (score as f64).clamp(-cap, cap);
+        GRAPH_HEIGHT as f64 / 2.0 * (1.0 - score / cap)
+    };
+    let points: Vec<(usize, isize, Option<Severity>)> = evals(record)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(ply, eval)| {
+            let review = report.moves.iter().find(|review| review.ply == ply);
+            let eval = eval.or(review.map(|review| review.comparison.played_score))?;
+            Some((ply, eval, review.and_then(|review| review.severity)))
+        })
+        .collect();
+    let line: Vec<String> = points
+        .iter()
+        .map(|&(ply, eval, _)| format!("{:.1},{:.1}", x(ply), y(eval)))
+        .collect();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{GRAPH_WIDTH}\" height=\"{GRAPH_HEIGHT}\">\
+<rect width=\"{GRAPH_WIDTH}\" height=\"{GRAPH_HEIGHT}\" fill=\"#f5f5f5\"/>\
+<line x1=\"0\" y1=\"{0}\" x2=\"{GRAPH_WIDTH}\" y2=\"{0}\" stroke=\"#bbb\"/>\
+<polyline points=\"{1}\" fill=\"none\" stroke=\"#1e88e5\" stroke-width=\"2\"/>",
+        GRAPH_HEIGHT / 2,
+        line.join(" ")
+    );
+    for (ply, eval, severity) in points {
+        let fill = match severity {
+            Some(Severity::Mistake) => "#cb4b16",
+            Some(Severity::Blunder) => "#dc322f",
+            _ => "#1e88e5",
+        };
+        svg += &format!(
+            "<circle data-ply=\"{}\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"4\" fill=\"{fill}\"><title>{}. {}</title></circle>",
+            ply + 1,
+            x(ply),
+            y(eval),
+            ply + 1,
+            eval.clamp(-DECISIVE_LOSS, DECISIVE_LOSS)
+        );
+    }
+    svg + "</svg>"
+}
+
+/// The game with the analysis of `report` as a self-contained HTML page, `game_states`
+/// being the positions the moves were played in, and the final position.
+pub fn html_report(record: &GameRecord, game_states: &[Game], report: &GameReport) -> String {
+    let name = |player: Player| {
+        let key = player.to_string().to_lowercase();
+        escape(record.tag(&key).unwrap_or(player.to_string()))
+    };
+    let title = format!("{} - {}", name(Player::White), name(Player::Black));
+    let review = |ply: usize| report.moves.iter().find(|review| review.ply == ply);
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title><style>{STYLE}</style></head><body>\n<h1>{title}</h1>\n"
+    );
+    if let Some(result) = record.tag("result") {
+        html += &format!("<p>Result: {}</p>\n", escape(result));
+    }
+    html += "<ul>\n";
+    for player in [Player::White, Player::Black] {
+        let player_report = &report.players[player.as_index()];
+        html += &format!(
+            "<li>{}: average loss {:.2}, {} inaccuracies, {} mistakes, {} blunders</li>\n",
+            name(player),
+            player_report.average_loss(),
+            player_report.inaccuracies,
+            player_report.mistakes,
+            player_report.blunders
+        );
+    }
+    html += "</ul>\n<h2>Evaluation</h2>\n";
+    html += &eval_graph(record, report);
+
+    html += "\n<h2>Moves</h2>\n<div class=\"layout\"><div class=\"moves\">\n<span data-ply=\"0\" class=\"selected\">Start</span><br>\n";
+    for (ply, (game, recorded)) in game_states.iter().zip(&record.moves).enumerate() {
+        let severity = review(ply).and_then(|review| review.severity);
+        html += &format!(
+            "{}. <span data-ply=\"{}\" class=\"{}\">{}{}</span><br>\n",
+            ply + 1,
+            ply + 1,
+            severity_class(severity),
+            move_to_notation(game, &recorded.player_move),
+            severity_mark(severity)
+        );
+    }
+    html += "</div><div>\n";
+    for (ply, game) in game_states.iter().enumerate() {
+        let hidden = if ply == 0 { "" } else { " hidden" };
+        html += &format!(
+            "<div class=\"position\"{hidden}>{}</div>\n",
+            board_svg(game)
+        );
+    }
+    html += "</div></div>\n<h2>Key moments</h2>\n";
+
+    let turning_point = report.turning_point().map(|review| review.ply);
+    let moments = report.key_moments(KEY_MOMENTS);
+    if moments.is_empty() {
+        html += "<p>No key moments.</p>\n";
+    }
+    for review in moments {
+        let heading = if Some(review.ply) == turning_point {
+            " (turning point)"
+        } else {
+            ""
+        };
+        let comment = match (review.loss(), review.only_move()) {
+            (0, _) => "The only move, found.".to_string(),
+            (loss, only_move) => format!(
+                "Lost {loss} steps, better was {}{}.",
+                escape(&review.comparison.best),
+                if only_move { ", the only move" } else { "" }
+            ),
+        };
+        html += &format!(
+            "<div class=\"moment\"><h3>{}. {} {}{}{heading}</h3>{}<p>{comment}</p></div>\n",
+            review.ply + 1,
+            review.game.player.to_string(),
+            escape(&review.comparison.played),
+            severity_mark(review.severity),
+            board_svg(&review.game)
+        );
+    }
+    html + "<script>" + SCRIPT + "</script>\n</body></html>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accuracy::analyse_game;
+
+    #[test]
+    fn html_report_test() {
+        let mut record = GameRecord::parse("mdu;mud;h33;mud;").unwrap();
+        record.set_tag("white", "Ada <3");
+        let game_states = record.game_states();
+        let report = analyse_game(&game_states, record.player_moves(), 1);
+        let html = html_report(&record, &game_states, &report);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Ada &lt;3 - Black</title>"));
+        // A position for the start and after every move
+        assert_eq!(html.matches("class=\"position\"").count(), 5);
+        assert!(html.contains("data-ply=\"4\""));
+
+        let svg = board_svg(&game_states[3]);
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert_eq!(svg.matches("fill=\"#6d4c41\"").count(), 1);
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+
+use crate::{
+    a_star::a_star,
+    all_moves::ALL_MOVES,
+    bot::{Evaluation, plies_earlier, plies_later, win_score},
+    data_model::{Game, Player, PlayerMove, WallOrientation},
+    game_logic::{execute_move_unchecked, is_move_legal, winner},
+    notation::move_to_notation,
+    playout::{path_length_after, pawn_moves},
+    transposition::{TranspositionTable, position_key},
+};
+
+// Ladders: forcing sequences where one player walls off the next step of an opponent
+// out of walls, who has a single step back onto a shortest path, after which the
+// wall is extended:
+//
+//     wall in front of the pawn -> forced sidestep -> wall in front again -> ...
+//
+// A depth limited search stops right after the first wall and misses where the
+// ladder ends. Walls placed at the horizon are therefore followed by `ladder_search`,
+// which only plays forced steps and further blocking walls, the attacker being free
+// to stop walling and take the static evaluation. The ladder is cut off after
+// `MAX_LADDER_PLIES`. A defender with walls left has other answers than its pawn,
+// so its positions are evaluated statically.
+
+pub const MAX_LADDER_PLIES: usize = 8;
+
+/// Walls of the side to move across the opponent's next step that lengthen the
+/// opponent's shortest path, with the new path length.
+pub fn blocking_walls(game: &Game) -> Vec<(PlayerMove, usize)> {
+    let opponent = game.player.opponent();
+    if game.walls_left[game.player.as_index()] == 0 {
+        return Vec::new();
+    }
+    let from = game.board.player_position(opponent);
+    let Some(path) = a_star(game, opponent) else {
+        return Vec::new();
+    };
+    let Some(to) = path.first() else {
+        return Vec::new();
+    };
+    // Walls across the step have their centre at either end of the edge between the squares
+    let (orientation, x, y) = if from.x() == to.x() {
+        (WallOrientation::Horizontal, from.x(), from.y().min(to.y()))
+    } else {
+        (WallOrientation::Vertical, from.x().min(to.x()), from.y())
+    };
+    ALL_MOVES
+        .iter()
+        .filter(|player_move| match player_move {
+            PlayerMove::PlaceWall {
+                orientation: wall_orientation,
+                position,
+            } => {
+                *wall_orientation == orientation
+                    && match orientation {
+                        WallOrientation::Horizontal => {
+                            position.y == y && (position.x == x || position.x + 1 == x)
+                        }
+                        WallOrientation::Vertical => {
+                            position.x == x && (position.y == y || position.y + 1 == y)
+                        }
+                    }
+            }
+            PlayerMove::MovePiece(_) => false,
+        })
+        .filter(|player_move| is_move_legal(game, game.player, player_move))
+        .filter_map(|player_move| {
+            let length = path_length_after(game, player_move, opponent)?;
+            (length > path.len()).then(|| (player_move.clone(), length))
+        })
+        .collect()
+}
+
+/// The only pawn move bringing the side to move closer to its goal, if exactly one does.
+pub fn forced_step(game: &Game) -> Option<PlayerMove> {
+    let player = game.player;
+    let distance = a_star(game, player)?.len();
+    // Pawn moves reaching the same square only differ in their collision direction
+    let mut seen = HashSet::new();
+    let mut steps = pawn_moves(game)
+        .filter(|player_move| seen.insert(move_to_notation(game, player_move)))
+        .filter(|player_move| {
+            path_length_after(game, player_move, player) == Some(distance.saturating_sub(1))
+        });
+    let step = steps.next()?.clone();
+    steps.next().is_none().then_some(step)
+}
+
+/// Scores a position just walled by the opponent of the side to move by playing out
+/// the ladder, if it is one. Scores are from White's point of view.
+pub fn ladder_search(
+    game: &Game,
+    alpha: isize,
+    beta: isize,
+    plies: usize,
+    evaluation: Evaluation,
+    nodes: &mut u64,
+    table: &mut TranspositionTable,
+) -> isize {
+    *nodes += 1;
+    let static_score = |game: &Game, table: &mut TranspositionTable| {
+        table
+            .evals
+            .get_or_insert_with(position_key(game), || evaluation.evaluate(game))
+    };
+    let defender = game.player;
+    let step = match plies {
+        0 => None,
+        _ if game.walls_left[defender.as_index()] > 0 => None,
+        _ => forced_step(game),
+    };
+    let Some(step) = step else {
+        return static_score(game, table);
+    };
+    let mut after_step = game.clone();
+    execute_move_unchecked(&mut after_step, defender, &step);
+    if winner(&after_step) == Some(defender) {
+        return win_score(defender, 1);
+    }
+    // The attacker either continues the ladder or stops and takes the position as it is
+    let maximizing = defender.opponent().is_maximizer(Player::White);
+    let (mut alpha, mut beta) = (alpha, beta);
+    let mut value = plies_later(static_score(&after_step, table), 1);
+    for (wall, _) in blocking_walls(&after_step) {
+        if value >= beta && maximizing || value <= alpha && !maximizing {
+            break;
+        }
+        if maximizing {
+            alpha = alpha.max(value);
+        } else {
+            beta = beta.min(value);
+        }
+        let mut after_wall = after_step.clone();
+        execute_move_unchecked(&mut after_wall, defender.opponent(), &wall);
+        let score = ladder_search(
+            &after_wall,
+            plies_earlier(alpha, 2),
+            plies_earlier(beta, 2),
+            plies.saturating_sub(2),
+            evaluation,
+            nodes,
+            table,
+        );
+        let score = plies_later(score, 2);
+        value = if maximizing {
+            value.max(score)
+        } else {
+            value.min(score)
+        };
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bot::{WHITE_LOSES_BLACK_WINS, WHITE_WINS_BLACK_LOSES, heuristic_board_score},
+        notation::parse_position,
+    };
+
+    #[test]
+    fn ladder_test() {
+        // Walled off in front of the goal, White has to step aside
+        let game = parse_position("e8 a9 0 3 w e8h").unwrap();
+        let step = forced_step(&game).unwrap();
+        assert_eq!(move_to_notation(&game, &step), "d8");
+        assert!(forced_step(&Game::new()).is_some());
+        assert!(blocking_walls(&parse_position("e8 a9 0 3 b -").unwrap()).len() >= 2);
+
+        // Black keeps walling off White's next step, which the static evaluation misses
+        let laddered = ladder_search(
+            &game,
+            WHITE_LOSES_BLACK_WINS,
+            WHITE_WINS_BLACK_LOSES,
+            MAX_LADDER_PLIES,
+            Evaluation::PathDifference,
+            &mut 0,
+            &mut TranspositionTable::new(1),
+        );
+        assert!(laddered < heuristic_board_score(&game));
+    }
+}
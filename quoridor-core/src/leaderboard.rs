@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+// Local leaderboard of games between humans and engines. Every finished game is a JSON
+// line with the human's score, 1 for a win, 0.5 for a draw and 0 for a loss:
+//
+//     {"date":"2026-10-15","player":"ada","engine":"bot depth 4","score":1.0}
+//
+// Games between two players, as on the server's ladder, name the opponent in place of
+// the engine and are marked with "between_players":true.
+//
+// Ratings are Elo ratings, replayed from the games in the order of the file. Players
+// and engine configurations all start at 1500 and are rated alike, so a win against a
+// stronger configuration gains more.
+
+pub const INITIAL_RATING: f64 = 1500.0;
+/// How far a single game moves a rating.
+const K_FACTOR: f64 = 32.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardGame {
+    /// The day the game was played, as YYYY-MM-DD.
+    pub date: String,
+    pub player: String,
+    /// The engine configuration, e.g. "bot depth 4", or the opponent's name in games
+    /// between players.
+    pub engine: String,
+    /// The player's score.
+    pub score: f64,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub between_players: bool,
+}
+
+/// A player's results, with their rating after the last game of every day they played.
+pub struct PlayerStats {
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub rating: f64,
+    pub peak_rating: f64,
+    pub history: Vec<(String, f64)>,
+}
+
+#[derive(Default)]
+pub struct Leaderboard {
+    pub games: Vec<LeaderboardGame>,
+}
+
+/// The expected score against an opponent, between 0 and 1.
+pub fn expected_score(rating: f64, opponent_rating: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent_rating - rating) / 400.0))
+}
+
+/// Today's date as YYYY-MM-DD, in UTC.
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    date_from_days((seconds / 86400) as i64)
+}
+
+/// The civil date `days` days after 1970-01-01.
+fn date_from_days(days: i64) -> String {
+    // Counted in 400 year eras of 146097 days starting at March 1st, so leap days end the years
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+impl Leaderboard {
+    /// Reads the leaderboard at `path`, empty when there is no file yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        let games = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("{}:{}: {e}", path.display(), index + 1))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { games })
+    }
+
+    /// Appends a game to the leaderboard at `path`.
+    pub fn record(path: &Path, game: &LeaderboardGame) -> Result<(), String> {
+        let line = serde_json::to_string(game).map_err(|e| e.to_string())? + "\n";
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    /// The ratings of both sides after every game.
+    fn rating_history(&self) -> Vec<[f64; 2]> {
+        let mut ratings: HashMap<(&str, bool), f64> = HashMap::new();
+        self.games
+            .iter()
+            .map(|game| {
+                let [player, opponent] = game.sides().map(|(name, engine, _)| {
+                    ratings
+                        .get(&(name, engine))
+                        .copied()
+                        .unwrap_or(INITIAL_RATING)
+                });
+                let change = K_FACTOR * (game.score - expected_score(player, opponent));
+                let after = [player + change, opponent - change];
+                for ((name, engine, _), rating) in game.sides().into_iter().zip(after) {
+                    ratings.insert((name, engine), rating);
+                }
+                after
+            })
+            .collect()
+    }
+
+    /// The current ratings and numbers of games of the players, or of the engine
+    /// configurations, best first.
+    pub fn ratings(&self, engines: bool) -> Vec<(String, f64, usize)> {
+        let mut ratings: HashMap<&str, (f64, usize)> = HashMap::new();
+        for (game, after) in self.games.iter().zip(self.rating_history()) {
+            for ((name, engine, _), rating) in game.sides().into_iter().zip(after) {
+                if engine == engines {
+                    let entry = ratings.entry(name).or_default();
+                    *entry = (rating, entry.1 + 1);
+                }
+            }
+        }
+        let mut ratings: Vec<(String, f64, usize)> = ratings
+            .into_iter()
+            .map(|(name, (rating, games))| (name.to_string(), rating, games))
+            .collect();
+        ratings.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ratings
+    }
+
+    /// The player's results, None when they haven't played.
+    pub fn stats(&self, player: &str) -> Option<PlayerStats> {
+        let mut stats = PlayerStats {
+            games: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+            rating: INITIAL_RATING,
+            peak_rating: f64::NEG_INFINITY,
+            history: Vec::new(),
+        };
+        for (game, after) in self.games.iter().zip(self.rating_history()) {
+            for ((name, engine, score), rating) in game.sides().into_iter().zip(after) {
+                if engine || name != player {
+                    continue;
+                }
+                stats.games += 1;
+                match score {
+                    score if score > 0.5 => stats.wins += 1,
+                    score if score < 0.5 => stats.losses += 1,
+                    _ => stats.draws += 1,
+                }
+                stats.rating = rating;
+                stats.peak_rating = stats.peak_rating.max(rating);
+                match stats.history.last_mut() {
+                    Some((date, last)) if *date == game.date => *last = rating,
+                    _ => stats.history.push((game.date.clone(), rating)),
+                }
+            }
+        }
+        (stats.games > 0).then_some(stats)
+    }
+
+    /// The players' and the engines' ratings as two tables.
+    pub fn table(&self) -> String {
+        let mut text = String::new();
+        for (heading, engines) in [("Player", false), ("Engine", true)] {
+            if engines {
+                text += "\n";
+            }
+            text += &format!("{heading:<24}{:>8}{:>7}\n", "Rating", "Games");
+            for (name, rating, games) in self.ratings(engines) {
+                text += &format!("{name:<24}{rating:>8.0}{games:>7}\n");
+            }
+        }
+        text
+    }
+}
+
+impl LeaderboardGame {
+    /// The player and the opponent, with whether they are engines and their scores.
+    fn sides(&self) -> [(&str, bool, f64); 2] {
+        [
+            (&self.player, false, self.score),
+            (&self.engine, !self.between_players, 1.0 - self.score),
+        ]
+    }
+}
+
+impl PlayerStats {
+    /// A stats screen with the player's results and their rating over time.
+    pub fn text(&self, player: &str) -> String {
+        let mut text = format!(
+            "{player}: rating {:.0}, peak {:.0}\n{} games: {} won, {} drawn, {} lost\n",
+            self.rating, self.peak_rating, self.games, self.wins, self.draws, self.losses
+        );
+        for (date, rating) in &self.history {
+            text += &format!("{date}{rating:>8.0}\n");
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaderboard_test() {
+        assert_eq!(date_from_days(0), "1970-01-01");
+        assert_eq!(date_from_days(11016), "2000-02-29");
+        assert_eq!(date_from_days(20376), "2025-10-15");
+
+        let game = |date: &str, player: &str, engine: &str, score| LeaderboardGame {
+            date: date.to_string(),
+            player: player.to_string(),
+            engine: engine.to_string(),
+            score,
+            between_players: false,
+        };
+        let path = std::env::temp_dir().join(format!("leaderboard-test-{}", std::process::id()));
+        for game in [
+            game("2026-10-14", "ada", "bot depth 2", 1.0),
+            game("2026-10-14", "ada", "bot depth 2", 1.0),
+            game("2026-10-15", "bob", "bot depth 2", 0.0),
+            game("2026-10-15", "ada", "bot depth 4", 0.0),
+            LeaderboardGame {
+                between_players: true,
+                ..game("2026-10-16", "bob", "ada", 0.0)
+            },
+        ] {
+            Leaderboard::record(&path, &game).unwrap();
+        }
+        let leaderboard = Leaderboard::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(leaderboard.games.len(), 5);
+
+        // Even ratings expect half a point, so the first win gains half of K
+        let ratings = leaderboard.ratings(false);
+        assert_eq!(ratings[0].0, "ada");
+        assert_eq!(ratings[1].0, "bob");
+        assert!(ratings[1].1 < INITIAL_RATING);
+        assert_eq!(ratings[0].2, 4);
+        assert_eq!(leaderboard.ratings(true).len(), 2);
+        let stats = leaderboard.stats("ada").unwrap();
+        assert_eq!((stats.games, stats.wins, stats.losses), (4, 3, 1));
+        assert_eq!(stats.history.len(), 3);
+        assert_eq!(stats.history[0].0, "2026-10-14");
+        assert!((stats.history[0].1 - stats.peak_rating).abs() < 1e-9);
+        assert!(stats.peak_rating > INITIAL_RATING + K_FACTOR / 2.0);
+        assert!(leaderboard.stats("carol").is_none());
+    }
+}
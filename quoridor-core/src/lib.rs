@@ -0,0 +1,41 @@
+pub mod a_star;
+pub mod accuracy;
+pub mod all_moves;
+pub mod analysis;
+pub mod api;
+pub mod arena;
+pub mod batch_analysis;
+pub mod bot;
+pub mod broadcast;
+pub mod corridors;
+pub mod data_model;
+pub mod enclosure;
+pub mod encoding;
+pub mod engine;
+pub mod engine_log;
+pub mod engine_match;
+pub mod eval_graph;
+pub mod explorer;
+pub mod game_logic;
+pub mod game_record;
+pub mod html_report;
+pub mod ladder;
+pub mod leaderboard;
+pub mod locale;
+pub mod notation;
+pub mod opponent_model;
+pub mod personality;
+pub mod plane_images;
+pub mod playout;
+pub mod protocol;
+pub mod puzzle;
+pub mod race;
+pub mod render_board;
+pub mod rl_env;
+pub mod rules;
+pub mod square_outline_iterator;
+pub mod tablebase;
+pub mod test_suite;
+pub mod transposition;
+pub mod wall_effects;
+pub mod win_probability;
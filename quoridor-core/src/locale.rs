@@ -0,0 +1,434 @@
+use crate::{data_model::Player, game_record::ThinkTime};
+
+// Message catalogue of the text shown to players of the trainer: prompts, move
+// explanations, blunder warnings and puzzle feedback. Every message is a variant of
+// `Message` with its parameters, and every locale spells out all of them, so a
+// message can't be added without its translations. The output of the tooling
+// commands, read by developers, stays in English. Without a locale asked for, the
+// language comes from the locale of the system, as the LC_ALL, LC_MESSAGES and LANG
+// variables give it.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    English,
+    Norwegian,
+}
+
+pub const LOCALE_CODES: [&str; 2] = ["en", "nb"];
+
+impl Locale {
+    /// The locale with a code of `LOCALE_CODES`.
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "en" => Some(Locale::English),
+            "nb" => Some(Locale::Norwegian),
+            _ => None,
+        }
+    }
+
+    /// The locale of the system, English unless its language is Norwegian.
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let system_locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .find_map(|name| var(name).filter(|value| !value.is_empty()))
+            .unwrap_or_default();
+        // Such as nb_NO.UTF-8, of which only the language counts
+        let language = system_locale
+            .split(['_', '.', '@'])
+            .next()
+            .unwrap_or_default();
+        match language {
+            "nb" | "nn" | "no" => Locale::Norwegian,
+            _ => Locale::English,
+        }
+    }
+}
+
+pub enum Message<'a> {
+    Player(Player),
+    /// The player type is a command line name and isn't translated.
+    ToMove {
+        player: Player,
+        player_type: &'a str,
+    },
+    WallsLeft([usize; 2]),
+    ThinkTime(&'a ThinkTime),
+    ThinkTimes(&'a [ThinkTime; 2]),
+    InvalidMove,
+    InvalidInput,
+    IllegalMove,
+    /// A path length, None when blocked.
+    PathStays {
+        player: Player,
+        length: Option<usize>,
+    },
+    PathChanges {
+        player: Player,
+        before: Option<usize>,
+        after: Option<usize>,
+    },
+    SpendsWalls(usize),
+    GainsTempo(isize),
+    CreatesCorridor,
+    BlocksCorridor,
+    MissesWin,
+    LosesGame,
+    LostTempi(isize),
+    BetterWas(&'a str),
+    PuzzleStart {
+        id: &'a str,
+        number: usize,
+        count: usize,
+        player: Player,
+    },
+    Correct,
+    WrongPuzzleMove {
+        solution: &'a str,
+    },
+    PuzzlesSolved {
+        solved: usize,
+        count: usize,
+    },
+    /// The accept and decline commands aren't translated.
+    TakebackRequest {
+        player: Player,
+        moves: usize,
+    },
+    TakebackAccepted,
+    TakebackDeclined,
+    /// Asked at startup when the autosave file holds an unfinished game.
+    RestoreAutosave {
+        file: &'a str,
+        moves: usize,
+    },
+    /// The score and principal variation are notation and aren't translated.
+    AlphaBetaDepth {
+        depth: usize,
+        score: &'a str,
+        pv: &'a str,
+    },
+    AlphaBetaSearching,
+    /// The network value from White's point of view and its likeliest moves.
+    NetworkMoves {
+        value: f32,
+        moves: &'a str,
+    },
+    Disagree {
+        alpha_beta: &'a str,
+        network: &'a str,
+    },
+    /// The root of a network search, its value for the side to move.
+    SearchRoot {
+        value: f32,
+        simulations: usize,
+        seconds: f32,
+    },
+    SearchMove {
+        notation: &'a str,
+        visits: u32,
+        q: f32,
+        prior: f32,
+    },
+    Wins(Player),
+    Depth(usize),
+    /// The score of a watched match between two engines.
+    MatchScore {
+        game: usize,
+        games: usize,
+        names: [&'a str; 2],
+        points: [f64; 2],
+    },
+    /// The result and termination are notation and debug names and aren't translated.
+    GameOver {
+        game: usize,
+        result: &'a str,
+        termination: &'a str,
+    },
+    WatchPaused,
+    /// The eval after a move of a replay.
+    ReplayMove {
+        ply: usize,
+        eval: &'a str,
+    },
+    ReplayStart,
+}
+
+impl Message<'_> {
+    pub fn text(&self, locale: Locale) -> String {
+        match locale {
+            Locale::English => self.english(),
+            Locale::Norwegian => self.norwegian(),
+        }
+    }
+
+    fn english(&self) -> String {
+        let player = |player: &Player| Message::Player(*player).english();
+        let think_time = |think_time| Message::ThinkTime(think_time).english();
+        let length =
+            |length: &Option<usize>| length.map_or("blocked".to_string(), |l| l.to_string());
+        match self {
+            Message::Player(Player::White) => "White".to_string(),
+            Message::Player(Player::Black) => "Black".to_string(),
+            Message::ToMove {
+                player: to_move,
+                player_type,
+            } => format!("{} ({}) to move", player(to_move), player_type),
+            Message::WallsLeft([white, black]) => format!("Walls: White: {white}, Black: {black}"),
+            Message::ThinkTime(think_time) => think_time.to_string(),
+            Message::ThinkTimes([white, black]) => format!(
+                "Think time: White: {}, Black: {}",
+                think_time(white),
+                think_time(black)
+            ),
+            Message::InvalidMove => "Invalid move.".to_string(),
+            Message::InvalidInput => "Invalid input format.".to_string(),
+            Message::IllegalMove => "Illegal move".to_string(),
+            Message::PathStays {
+                player: owner,
+                length: path_length,
+            } => format!("{}'s path stays {}", player(owner), length(path_length)),
+            Message::PathChanges {
+                player: owner,
+                before,
+                after,
+            } => format!(
+                "{}'s path {} -> {}",
+                player(owner),
+                length(before),
+                length(after)
+            ),
+            Message::SpendsWalls(walls) => format!("spends {walls} wall"),
+            Message::GainsTempo(tempo) => format!("gains {tempo} tempo"),
+            Message::CreatesCorridor => "creates a corridor".to_string(),
+            Message::BlocksCorridor => "blocks a corridor".to_string(),
+            Message::MissesWin => "misses a win".to_string(),
+            Message::LosesGame => "loses the game".to_string(),
+            Message::LostTempi(1) => "lost 1 tempo".to_string(),
+            Message::LostTempi(tempi) => format!("lost {tempi} tempi"),
+            Message::BetterWas(best) => format!("better was {best}"),
+            Message::PuzzleStart {
+                id,
+                number,
+                count,
+                player: to_move,
+            } => format!(
+                "Puzzle {id} ({number}/{count}): {} to move, find the best move.",
+                player(to_move)
+            ),
+            Message::Correct => "Correct!".to_string(),
+            Message::WrongPuzzleMove { solution } => format!("Wrong, the solution is {solution}"),
+            Message::PuzzlesSolved { solved, count } => format!("Solved {solved}/{count}"),
+            Message::TakebackRequest {
+                player: requester,
+                moves: 1,
+            } => format!(
+                "{} asks to take back 1 move, accept or decline.",
+                player(requester)
+            ),
+            Message::TakebackRequest {
+                player: requester,
+                moves,
+            } => format!(
+                "{} asks to take back {moves} moves, accept or decline.",
+                player(requester)
+            ),
+            Message::TakebackAccepted => "Takeback accepted".to_string(),
+            Message::TakebackDeclined => "Takeback declined".to_string(),
+            Message::RestoreAutosave { file, moves } => {
+                format!("{file} holds an unfinished game of {moves} moves, restore it? (y/n)")
+            }
+            Message::AlphaBetaDepth { depth, score, pv } => {
+                format!("alpha-beta depth {depth}: {score} {pv}")
+            }
+            Message::AlphaBetaSearching => "alpha-beta: searching".to_string(),
+            Message::NetworkMoves { value, moves } => format!("network: {value:+.3} {moves}"),
+            Message::Disagree {
+                alpha_beta,
+                network,
+            } => format!("disagree: alpha-beta {alpha_beta}, network {network}"),
+            Message::SearchRoot {
+                value,
+                simulations,
+                seconds,
+            } => format!("value {value:+.3}, {simulations} simulations in {seconds:.1}s"),
+            Message::SearchMove {
+                notation,
+                visits,
+                q,
+                prior,
+            } => format!("{notation:<5} visits {visits:<6} q {q:+.3} prior {prior:.3}"),
+            Message::Wins(winner) => format!("{} wins", player(winner)),
+            Message::Depth(depth) => format!("depth {depth}"),
+            Message::MatchScore {
+                game,
+                games,
+                names: [first, second],
+                points: [first_points, second_points],
+            } => format!("Game {game}/{games}: {first} {first_points} - {second_points} {second}"),
+            Message::GameOver {
+                game,
+                result,
+                termination,
+            } => format!("Game {game}: {result} ({termination})"),
+            Message::WatchPaused => "Paused, Space to resume and N to step".to_string(),
+            Message::ReplayMove { ply, eval } => format!("Move {ply}: {eval}"),
+            Message::ReplayStart => "Start".to_string(),
+        }
+    }
+
+    fn norwegian(&self) -> String {
+        let player = |player: &Player| Message::Player(*player).norwegian();
+        let think_time = |think_time| Message::ThinkTime(think_time).norwegian();
+        let length =
+            |length: &Option<usize>| length.map_or("blokkert".to_string(), |l| l.to_string());
+        match self {
+            Message::Player(Player::White) => "Hvit".to_string(),
+            Message::Player(Player::Black) => "Svart".to_string(),
+            Message::ToMove {
+                player: to_move,
+                player_type,
+            } => format!("{} ({}) er i trekket", player(to_move), player_type),
+            Message::WallsLeft([white, black]) => format!("Vegger: Hvit: {white}, Svart: {black}"),
+            Message::ThinkTime(think_time) => format!(
+                "{:.1}s ({:.2}s per trekk)",
+                think_time.total.as_secs_f64(),
+                think_time.per_move().as_secs_f64()
+            ),
+            Message::ThinkTimes([white, black]) => format!(
+                "Tenketid: Hvit: {}, Svart: {}",
+                think_time(white),
+                think_time(black)
+            ),
+            Message::InvalidMove => "Ugyldig trekk.".to_string(),
+            Message::InvalidInput => "Ugyldig inndata.".to_string(),
+            Message::IllegalMove => "Ulovlig trekk".to_string(),
+            Message::PathStays {
+                player: owner,
+                length: path_length,
+            } => format!("{}s vei forblir {}", player(owner), length(path_length)),
+            Message::PathChanges {
+                player: owner,
+                before,
+                after,
+            } => format!(
+                "{}s vei {} -> {}",
+                player(owner),
+                length(before),
+                length(after)
+            ),
+            Message::SpendsWalls(walls) => format!("bruker {walls} vegg"),
+            Message::GainsTempo(tempo) => format!("vinner {tempo} tempo"),
+            Message::CreatesCorridor => "lager en korridor".to_string(),
+            Message::BlocksCorridor => "blokkerer en korridor".to_string(),
+            Message::MissesWin => "overser en gevinst".to_string(),
+            Message::LosesGame => "taper partiet".to_string(),
+            Message::LostTempi(tempi) => format!("tapte {tempi} tempo"),
+            Message::BetterWas(best) => format!("bedre var {best}"),
+            Message::PuzzleStart {
+                id,
+                number,
+                count,
+                player: to_move,
+            } => format!(
+                "Oppgave {id} ({number}/{count}): {} er i trekket, finn det beste trekket.",
+                player(to_move)
+            ),
+            Message::Correct => "Riktig!".to_string(),
+            Message::WrongPuzzleMove { solution } => format!("Feil, løsningen er {solution}"),
+            Message::PuzzlesSolved { solved, count } => format!("Løste {solved}/{count}"),
+            Message::TakebackRequest {
+                player: requester,
+                moves,
+            } => format!(
+                "{} ber om å ta tilbake {moves} trekk, accept eller decline.",
+                player(requester)
+            ),
+            Message::TakebackAccepted => "Tilbaketrekk godtatt".to_string(),
+            Message::TakebackDeclined => "Tilbaketrekk avslått".to_string(),
+            Message::RestoreAutosave { file, moves } => {
+                format!("{file} har et uferdig parti på {moves} trekk, gjenopprette det? (y/n)")
+            }
+            Message::AlphaBetaDepth { depth, score, pv } => {
+                format!("alfa-beta dybde {depth}: {score} {pv}")
+            }
+            Message::AlphaBetaSearching => "alfa-beta: søker".to_string(),
+            Message::NetworkMoves { value, moves } => format!("nettverk: {value:+.3} {moves}"),
+            Message::Disagree {
+                alpha_beta,
+                network,
+            } => format!("uenige: alfa-beta {alpha_beta}, nettverk {network}"),
+            Message::SearchRoot {
+                value,
+                simulations,
+                seconds,
+            } => format!("verdi {value:+.3}, {simulations} simuleringer på {seconds:.1}s"),
+            Message::SearchMove {
+                notation,
+                visits,
+                q,
+                prior,
+            } => format!("{notation:<5} besøk {visits:<6} q {q:+.3} prior {prior:.3}"),
+            Message::Wins(winner) => format!("{} vinner", player(winner)),
+            Message::Depth(depth) => format!("dybde {depth}"),
+            Message::MatchScore {
+                game,
+                games,
+                names: [first, second],
+                points: [first_points, second_points],
+            } => format!("Parti {game}/{games}: {first} {first_points} - {second_points} {second}"),
+            Message::GameOver {
+                game,
+                result,
+                termination,
+            } => format!("Parti {game}: {result} ({termination})"),
+            Message::WatchPaused => {
+                "Pauset, mellomrom for å fortsette og N for ett trekk".to_string()
+            }
+            Message::ReplayMove { ply, eval } => format!("Trekk {ply}: {eval}"),
+            Message::ReplayStart => "Start".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale_test() {
+        for code in LOCALE_CODES {
+            assert!(Locale::from_code(code).is_some());
+        }
+        assert_eq!(Locale::from_code("xx"), None);
+        let message = Message::PathChanges {
+            player: Player::Black,
+            before: Some(8),
+            after: None,
+        };
+        assert_eq!(message.text(Locale::English), "Black's path 8 -> blocked");
+        assert_eq!(message.text(Locale::Norwegian), "Svarts vei 8 -> blokkert");
+
+        let env = |vars: &[(&str, &str)]| {
+            Locale::from_env(|name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+        assert_eq!(env(&[]), Locale::English);
+        assert_eq!(env(&[("LANG", "nb_NO.UTF-8")]), Locale::Norwegian);
+        assert_eq!(
+            env(&[("LC_ALL", ""), ("LC_MESSAGES", "en_GB"), ("LANG", "nb_NO")]),
+            Locale::English
+        );
+        assert_eq!(
+            env(&[("LC_ALL", "no_NO"), ("LANG", "C")]),
+            Locale::Norwegian
+        );
+    }
+}
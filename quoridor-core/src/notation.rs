@@ -0,0 +1,505 @@
+use crate::{
+    data_model::{
+        Direction, Game, MovePiece, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, PiecePosition, Player,
+        PlayerMove, WALL_GRID_HEIGHT, WALL_GRID_WIDTH, WallOrientation, WallPosition,
+    },
+    game_logic::{
+        execute_move_unchecked, is_move_legal, is_move_piece_legal_with_player_at_position,
+        new_position_after_direction_unchecked, new_position_after_move_piece_unchecked,
+    },
+};
+
+/// The line without surrounding whitespace, the carriage return of Windows line endings
+/// included, and without the byte order mark Windows editors start UTF-8 files with.
+pub fn clean_line(input: &str) -> &str {
+    input.trim_start_matches('\u{feff}').trim()
+}
+
+pub fn parse_player_move(input: &str) -> Option<PlayerMove> {
+    let mut chars = input.chars();
+
+    let direction_from_char = |c: Option<char>| match c {
+        Some('u') => Some(Direction::Up),
+        Some('d') => Some(Direction::Down),
+        Some('l') => Some(Direction::Left),
+        Some('r') => Some(Direction::Right),
+        _ => None,
+    };
+
+    match chars.next() {
+        Some('m') => {
+            let direction = direction_from_char(chars.next())?;
+            let direction_on_collision = direction_from_char(chars.next()).unwrap_or(direction);
+            Some(PlayerMove::MovePiece(MovePiece {
+                direction,
+                direction_on_collision,
+            }))
+        }
+        Some('h') => match (chars.next(), chars.next()) {
+            (Some(x), Some(y)) => {
+                let x = x.to_digit(10)? as usize;
+                let y = y.to_digit(10)? as usize;
+                Some(PlayerMove::PlaceWall {
+                    orientation: WallOrientation::Horizontal,
+                    position: WallPosition { x, y },
+                })
+            }
+            _ => None,
+        },
+        Some('v') => match (chars.next(), chars.next()) {
+            (Some(x), Some(y)) => {
+                let x = x.to_digit(10)? as usize;
+                let y = y.to_digit(10)? as usize;
+                Some(PlayerMove::PlaceWall {
+                    orientation: WallOrientation::Vertical,
+                    position: WallPosition { x, y },
+                })
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Standard Quoridor notation as used by boardgamearena and quoridorstrats:
+// columns a-i from left to right, rows 1-9 counted from White's side.
+// Pawn moves name the destination square ("e2"). Walls name the square
+// south-west of the wall centre followed by the orientation ("e3h").
+
+fn square_from_notation(column: char, row: char) -> Option<(usize, usize)> {
+    let x = (column as usize).checked_sub('a' as usize)?;
+    let y = (row.to_digit(10)? as usize).checked_sub(1)?;
+    (x < PIECE_GRID_WIDTH && y < PIECE_GRID_HEIGHT).then_some((x, y))
+}
+
+fn square_to_notation(x: usize, y: usize) -> String {
+    format!("{}{}", (b'a' + x as u8) as char, y + 1)
+}
+
+/// Parses a single move in standard notation. Pawn moves need the current
+/// position to be translated into the direction based `MovePiece`.
+pub fn parse_notation_move(game: &Game, input: &str) -> Option<PlayerMove> {
+    let mut chars = input.trim().chars().map(|c| c.to_ascii_lowercase());
+    let (x, y) = square_from_notation(chars.next()?, chars.next()?)?;
+    match (chars.next(), chars.next()) {
+        (None, _) => {
+            let player = game.player;
+            let player_position = game.board.player_position(player);
+            let opponent_position = game.board.player_position(player.opponent());
+            let destination = PiecePosition::new(x, y);
+            MovePiece::iter()
+                .filter(|move_piece| {
+                    is_move_piece_legal_with_player_at_position(
+                        game,
+                        player,
+                        player_position,
+                        move_piece,
+                    )
+                })
+                // The collision direction only matters when jumping, keep the canonical form otherwise
+                .filter(|move_piece| {
+                    move_piece.direction == move_piece.direction_on_collision
+                        || new_position_after_direction_unchecked(
+                            player_position,
+                            move_piece.direction,
+                        ) == *opponent_position
+                })
+                .find(|move_piece| {
+                    new_position_after_move_piece_unchecked(
+                        player_position,
+                        move_piece,
+                        opponent_position,
+                    ) == destination
+                })
+                .map(PlayerMove::MovePiece)
+        }
+        (Some(orientation), None) => {
+            let orientation = match orientation {
+                'h' => WallOrientation::Horizontal,
+                'v' => WallOrientation::Vertical,
+                _ => return None,
+            };
+            Some(PlayerMove::PlaceWall {
+                orientation,
+                position: WallPosition { x, y },
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Formats a move in standard notation, as it would be played in `game`.
+pub fn move_to_notation(game: &Game, player_move: &PlayerMove) -> String {
+    match player_move {
+        PlayerMove::MovePiece(move_piece) => {
+            let destination = new_position_after_move_piece_unchecked(
+                game.board.player_position(game.player),
+                move_piece,
+                game.board.player_position(game.player.opponent()),
+            );
+            square_to_notation(destination.x(), destination.y())
+        }
+        PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } => format!(
+            "{}{}",
+            square_to_notation(position.x, position.y),
+            orientation.to_char()
+        ),
+    }
+}
+
+// Walls are written in one of three coordinate schemes, and tools using different ones
+// place each other's walls a row off or mirrored. The same wall in each:
+//
+//     internal  h42  orientation, then x and y of the wall centre on the grid of centres,
+//                    zero-based from the left and from White's side, as in `PlayerMove`
+//     standard  e3h  the square south-west of the centre, then the orientation
+//     bga       e4h  the square north-west of the centre, then the orientation, as on BGA
+//
+// The scheme only changes how walls are written, pawn moves keep their own notation.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WallNotation {
+    Internal,
+    #[default]
+    Standard,
+    Bga,
+}
+
+pub const WALL_NOTATION_NAMES: [&str; 3] = ["internal", "standard", "bga"];
+
+impl WallNotation {
+    /// The scheme with a name of `WALL_NOTATION_NAMES`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "internal" => Some(WallNotation::Internal),
+            "standard" => Some(WallNotation::Standard),
+            "bga" => Some(WallNotation::Bga),
+            _ => None,
+        }
+    }
+
+    /// The wall written in this scheme.
+    pub fn format(self, orientation: WallOrientation, position: &WallPosition) -> String {
+        match self {
+            WallNotation::Internal => {
+                format!("{}{}{}", orientation.to_char(), position.x, position.y)
+            }
+            WallNotation::Standard => format!(
+                "{}{}",
+                square_to_notation(position.x, position.y),
+                orientation.to_char()
+            ),
+            WallNotation::Bga => format!(
+                "{}{}",
+                square_to_notation(position.x, position.y + 1),
+                orientation.to_char()
+            ),
+        }
+    }
+
+    /// Parses a wall written in this scheme, None for anything else, pawn moves included.
+    pub fn parse(self, input: &str) -> Option<(WallOrientation, WallPosition)> {
+        let (orientation, position) = match self {
+            WallNotation::Internal => match parse_player_move(input.trim())? {
+                PlayerMove::PlaceWall {
+                    orientation,
+                    position,
+                } => (orientation, position),
+                PlayerMove::MovePiece(_) => return None,
+            },
+            WallNotation::Standard | WallNotation::Bga => {
+                let mut chars = input.trim().chars().map(|c| c.to_ascii_lowercase());
+                let (x, y) = square_from_notation(chars.next()?, chars.next()?)?;
+                let orientation = match (chars.next()?, chars.next()) {
+                    ('h', None) => WallOrientation::Horizontal,
+                    ('v', None) => WallOrientation::Vertical,
+                    _ => return None,
+                };
+                let y = match self {
+                    WallNotation::Bga => y.checked_sub(1)?,
+                    _ => y,
+                };
+                (orientation, WallPosition { x, y })
+            }
+        };
+        (position.x < WALL_GRID_WIDTH && position.y < WALL_GRID_HEIGHT)
+            .then_some((orientation, position))
+    }
+}
+
+/// Writes a wall given in one scheme in another, e.g. "e3h" from standard to bga is "e4h".
+pub fn convert_wall(input: &str, from: WallNotation, to: WallNotation) -> Option<String> {
+    let (orientation, position) = from.parse(input)?;
+    Some(to.format(orientation, &position))
+}
+
+/// Formats a move with pawn moves in standard notation and walls in the scheme.
+pub fn move_to_notation_with(game: &Game, player_move: &PlayerMove, walls: WallNotation) -> String {
+    match player_move {
+        PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } => walls.format(*orientation, position),
+        PlayerMove::MovePiece(_) => move_to_notation(game, player_move),
+    }
+}
+
+// Position strings describe a position without its history, similar to FEN:
+//
+//     <white pawn> <black pawn> <white walls left> <black walls left> <side to move> <walls>
+//     e5 e6 8 9 b e3h,d7v
+//
+// The wall list is "-" when no walls have been placed.
+
+pub fn position_to_string(game: &Game) -> String {
+    let [white, black] = &game.board.player_positions;
+    let mut walls = Vec::new();
+    for (x, column) in game.board.walls.iter().enumerate() {
+        for (y, centre) in column.iter().enumerate() {
+            for orientation in centre.orientations() {
+                walls.push(format!(
+                    "{}{}",
+                    square_to_notation(x, y),
+                    orientation.to_char()
+                ));
+            }
+        }
+    }
+    format!(
+        "{} {} {} {} {} {}",
+        square_to_notation(white.x(), white.y()),
+        square_to_notation(black.x(), black.y()),
+        game.walls_left[Player::White.as_index()],
+        game.walls_left[Player::Black.as_index()],
+        match game.player {
+            Player::White => 'w',
+            Player::Black => 'b',
+        },
+        if walls.is_empty() {
+            "-".to_string()
+        } else {
+            walls.join(",")
+        }
+    )
+}
+
+pub fn parse_position(input: &str) -> Result<Game, String> {
+    let fields: Vec<&str> = input.split_whitespace().collect();
+    let [white, black, white_walls, black_walls, side, walls] = fields[..] else {
+        return Err(format!("Expected 6 fields in position: {input}"));
+    };
+    let square = |field: &str| {
+        let mut chars = field.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(column), Some(row), None) => square_from_notation(column, row)
+                .map(|(x, y)| PiecePosition::new(x, y))
+                .ok_or_else(|| format!("Invalid square: {field}")),
+            _ => Err(format!("Invalid square: {field}")),
+        }
+    };
+    let walls_left = |field: &str| {
+        field
+            .parse()
+            .map_err(|_| format!("Invalid wall count: {field}"))
+    };
+    let mut game = Game::new();
+    game.board.player_positions = [square(white)?, square(black)?];
+    game.walls_left = [walls_left(white_walls)?, walls_left(black_walls)?];
+    game.player = match side {
+        "w" => Player::White,
+        "b" => Player::Black,
+        _ => return Err(format!("Invalid side to move: {side}")),
+    };
+    for wall in walls.split(',').filter(|wall| *wall != "-") {
+        match parse_notation_move(&game, wall) {
+            Some(PlayerMove::PlaceWall {
+                orientation,
+                position,
+            }) if position.x < WALL_GRID_WIDTH && position.y < WALL_GRID_HEIGHT => {
+                game.board.walls[position.x][position.y].set(orientation, true)
+            }
+            _ => return Err(format!("Invalid wall: {wall}")),
+        }
+    }
+    Ok(game)
+}
+
+/// Parses "startpos" or a position string, optionally followed by "moves" and
+/// a list of moves in standard notation played from that position.
+pub fn parse_position_with_moves(input: &str) -> Result<Game, String> {
+    let input = clean_line(input);
+    let (position, moves) = match input.split_once("moves") {
+        Some((position, moves)) => (position.trim(), moves),
+        None => (input, ""),
+    };
+    let mut game = match position {
+        "startpos" => Game::new(),
+        position => parse_position(position)?,
+    };
+    for move_str in moves.split_whitespace() {
+        let player = game.player;
+        let player_move = parse_notation_move(&game, move_str)
+            .filter(|player_move| is_move_legal(&game, player, player_move))
+            .ok_or_else(|| format!("Illegal move: {move_str}"))?;
+        execute_move_unchecked(&mut game, player, &player_move);
+    }
+    Ok(game)
+}
+
+fn tokens_from_json(input: &str) -> Result<Vec<String>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(input).map_err(|e| format!("Invalid JSON: {e}"))?;
+    let moves = match &value {
+        serde_json::Value::Array(moves) => moves,
+        serde_json::Value::Object(object) => object
+            .get("moves")
+            .and_then(|moves| moves.as_array())
+            .ok_or("JSON object has no \"moves\" array")?,
+        _ => return Err("Expected a JSON array or object".to_string()),
+    };
+    moves
+        .iter()
+        .map(|entry| {
+            entry
+                .as_str()
+                .or_else(|| entry.get("move").and_then(|m| m.as_str()))
+                .map(str::to_string)
+                .ok_or_else(|| format!("Unexpected move entry: {entry}"))
+        })
+        .collect()
+}
+
+fn tokens_from_text(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|token| !token.is_empty())
+        // Skip move numbers such as "12." or "12..."
+        .filter(|token| {
+            !token
+                .trim_end_matches('.')
+                .chars()
+                .all(|c| c.is_ascii_digit())
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses a full game log, either as a whitespace separated move list
+/// ("1. e2 e8 2. e3h ...") or as a JSON export, replaying it from the
+/// initial position and converting each move to the crate's representation.
+pub fn parse_game_log(input: &str) -> Result<Vec<PlayerMove>, String> {
+    let trimmed = clean_line(input);
+    let tokens = if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        tokens_from_json(trimmed)?
+    } else {
+        tokens_from_text(trimmed)
+    };
+    let mut game = Game::new();
+    let mut moves = Vec::new();
+    for (ply, token) in tokens.iter().enumerate() {
+        let player_move = parse_notation_move(&game, token)
+            .ok_or_else(|| format!("Could not parse move {}: {token}", ply + 1))?;
+        let player = game.player;
+        if !is_move_legal(&game, player, &player_move) {
+            return Err(format!("Illegal move {}: {token}", ply + 1));
+        }
+        execute_move_unchecked(&mut game, player, &player_move);
+        moves.push(player_move);
+    }
+    Ok(moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pawn_move_test() {
+        let game = Game::new();
+        let player_move = parse_notation_move(&game, "e2").unwrap();
+        assert_eq!(player_move.to_string(), "mdd");
+        assert_eq!(move_to_notation(&game, &player_move), "e2");
+    }
+
+    #[test]
+    fn wall_move_test() {
+        let game = Game::new();
+        let player_move = parse_notation_move(&game, "e3h").unwrap();
+        assert_eq!(player_move.to_string(), "h42");
+        assert_eq!(move_to_notation(&game, &player_move), "e3h");
+    }
+
+    #[test]
+    fn jump_test() {
+        let mut game = Game::new();
+        game.board.player_positions[Player::White.as_index()] = PiecePosition::new(4, 4);
+        game.board.player_positions[Player::Black.as_index()] = PiecePosition::new(4, 5);
+        let player_move = parse_notation_move(&game, "e7").unwrap();
+        assert!(matches!(
+            player_move,
+            PlayerMove::MovePiece(MovePiece {
+                direction: Direction::Down,
+                direction_on_collision: Direction::Down
+            })
+        ));
+    }
+
+    #[test]
+    fn text_log_test() {
+        let moves = parse_game_log("1. e2 e8 2. e3h d7v").unwrap();
+        let moves: Vec<_> = moves.iter().map(|m| m.to_string()).collect();
+        assert_eq!(moves, vec!["mdd", "muu", "h42", "v36"]);
+    }
+
+    #[test]
+    fn json_log_test() {
+        let moves = parse_game_log(r#"{"moves": ["e2", {"move": "e8"}]}"#).unwrap();
+        assert_eq!(moves.len(), 2);
+        assert!(parse_game_log(r#"["e2", "e3"]"#).is_err());
+    }
+
+    #[test]
+    fn position_string_test() {
+        let mut game = Game::new();
+        assert_eq!(position_to_string(&game), "e1 e9 10 10 w -");
+        for player_move in parse_game_log("e2 e3h").unwrap() {
+            let player = game.player;
+            execute_move_unchecked(&mut game, player, &player_move);
+        }
+        let position = position_to_string(&game);
+        assert_eq!(position, "e2 e9 10 9 w e3h");
+        assert_eq!(
+            position_to_string(&parse_position(&position).unwrap()),
+            position
+        );
+        assert!(parse_position("e2 e9 10 9 w i9h").is_err());
+    }
+
+    #[test]
+    fn wall_notation_test() {
+        use WallNotation::*;
+        for (from, input) in [(Internal, "h42"), (Standard, "e3h"), (Bga, "E4H")] {
+            for (to, output) in [(Internal, "h42"), (Standard, "e3h"), (Bga, "e4h")] {
+                assert_eq!(convert_wall(input, from, to).as_deref(), Some(output));
+            }
+        }
+        // The bottom row of squares is south of every wall, the top row north of them
+        assert!(Bga.parse("a1h").is_none());
+        assert!(Standard.parse("a9h").is_none());
+        assert_eq!(convert_wall("a9v", Bga, Standard).as_deref(), Some("a8v"));
+        assert!(Standard.parse("e2").is_none());
+        assert!(Internal.parse("mdd").is_none());
+        for name in WALL_NOTATION_NAMES {
+            assert!(WallNotation::from_name(name).is_some());
+        }
+
+        let game = Game::new();
+        let wall = parse_player_move("v36").unwrap();
+        assert_eq!(move_to_notation_with(&game, &wall, Bga), "d8v");
+        let pawn_move = parse_player_move("mdd").unwrap();
+        assert_eq!(move_to_notation_with(&game, &pawn_move, Internal), "e2");
+    }
+}
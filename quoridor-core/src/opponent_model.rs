@@ -0,0 +1,263 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accuracy::{Severity, analyse_game},
+    bot::EvalWeights,
+    data_model::{Game, Player, PlayerMove},
+    engine::OpeningRandomization,
+    engine_match::Phase,
+    notation::move_to_notation,
+};
+
+// Opponent models, the tendencies of a human playing the bot again and again. Every
+// finished game of a named player adds to their profile:
+//
+//     wall rate   the share of their moves that are walls
+//     openings    their first `OPENING_MOVES` moves, counted per line
+//     blunders    by phase, from a `PROFILE_DEPTH` search over their moves
+//
+// Profiles live in a JSON file next to the leaderboard, see `profiles_path`. Once a
+// player has `MIN_PROFILE_GAMES` games, the bot adapting to them plays:
+//
+//     against a repeated opening   a random move among the equally good ones for the
+//                                  first `OPENING_PLIES` plies, leaving the prepared line
+//     against many walls           valuing its walls left, to answer theirs
+//     against frequent blunders    more wall pressure, going for sharp positions
+
+pub const OPENING_MOVES: usize = 3;
+pub const PROFILE_DEPTH: usize = 1;
+pub const MIN_PROFILE_GAMES: usize = 3;
+pub const OPENING_PLIES: usize = 4;
+/// Share of moves that are walls from which an opponent plays many walls.
+pub const MANY_WALLS: f64 = 0.3;
+/// Blunders per move from which an opponent blunders often.
+pub const FREQUENT_BLUNDERS: f64 = 0.05;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpponentProfile {
+    pub games: usize,
+    pub moves: usize,
+    pub walls: usize,
+    /// Games per opening line, the moves in standard notation separated by spaces.
+    pub openings: BTreeMap<String, usize>,
+    /// Moves and blunders by `Phase::ALL`.
+    pub phase_moves: [usize; 3],
+    pub phase_blunders: [usize; 3],
+}
+
+impl OpponentProfile {
+    /// Adds the moves of `player` in a game, from its positions and moves.
+    pub fn add_game(&mut self, game_states: &[Game], player_moves: &[PlayerMove], player: Player) {
+        let report = analyse_game(game_states, player_moves, PROFILE_DEPTH);
+        let own_moves: Vec<(usize, &Game, &PlayerMove)> = game_states
+            .iter()
+            .zip(player_moves)
+            .enumerate()
+            .filter(|(_, (game, _))| game.player == player)
+            .map(|(ply, (game, player_move))| (ply, game, player_move))
+            .collect();
+        self.games += 1;
+        self.moves += own_moves.len();
+        self.walls += own_moves
+            .iter()
+            .filter(|(_, _, player_move)| matches!(player_move, PlayerMove::PlaceWall { .. }))
+            .count();
+        let opening = own_moves
+            .iter()
+            .take(OPENING_MOVES)
+            .map(|(_, game, player_move)| move_to_notation(game, player_move))
+            .collect::<Vec<_>>()
+            .join(" ");
+        *self.openings.entry(opening).or_default() += 1;
+        for review in report
+            .moves
+            .iter()
+            .filter(|review| review.game.player == player)
+        {
+            let phase = Phase::of(review.ply, &review.game) as usize;
+            self.phase_moves[phase] += 1;
+            if review.severity == Some(Severity::Blunder) {
+                self.phase_blunders[phase] += 1;
+            }
+        }
+    }
+
+    pub fn wall_rate(&self) -> f64 {
+        rate(self.walls, self.moves)
+    }
+
+    pub fn blunder_rate(&self) -> f64 {
+        rate(
+            self.phase_blunders.iter().sum(),
+            self.phase_moves.iter().sum(),
+        )
+    }
+
+    /// The most played opening line, with its games.
+    pub fn favourite_opening(&self) -> Option<(&str, usize)> {
+        self.openings
+            .iter()
+            .max_by_key(|(_, games)| **games)
+            .map(|(line, games)| (line.as_str(), *games))
+    }
+
+    /// Whether the profile holds enough games to adapt to.
+    pub fn is_known(&self) -> bool {
+        self.games >= MIN_PROFILE_GAMES
+    }
+
+    /// The bot's evaluation weights against the player.
+    pub fn eval_weights(&self) -> EvalWeights {
+        let mut weights = EvalWeights::default();
+        if !self.is_known() {
+            return weights;
+        }
+        if self.wall_rate() >= MANY_WALLS {
+            weights.walls += 20;
+        }
+        if self.blunder_rate() >= FREQUENT_BLUNDERS {
+            weights.wall_pressure += 25;
+        }
+        weights
+    }
+
+    /// The bot's opening randomization against the player, None when the player does not
+    /// repeat an opening in half of their games.
+    pub fn opening(&self) -> Option<OpeningRandomization> {
+        let (_, games) = self.favourite_opening()?;
+        (self.is_known() && 2 * games >= self.games).then_some(OpeningRandomization {
+            plies: OPENING_PLIES,
+            margin: 0,
+        })
+    }
+
+    /// A summary of the profile, e.g. for the stats command.
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "{} games, walls {:.0}% of moves, blunders {:.1}% of moves\n",
+            self.games,
+            100.0 * self.wall_rate(),
+            100.0 * self.blunder_rate()
+        );
+        if let Some((line, games)) = self.favourite_opening() {
+            text += &format!("Favourite opening: {line} ({games} games)\n");
+        }
+        for phase in Phase::ALL {
+            let index = phase as usize;
+            if self.phase_blunders[index] > 0 {
+                text += &format!(
+                    "Blunders in the {}: {} of {} moves\n",
+                    phase.name(),
+                    self.phase_blunders[index],
+                    self.phase_moves[index]
+                );
+            }
+        }
+        text
+    }
+}
+
+fn rate(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// The profiles of the players of a leaderboard, by name.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct OpponentProfiles {
+    pub profiles: BTreeMap<String, OpponentProfile>,
+}
+
+/// The profiles file of the leaderboard at `leaderboard`, e.g. leaderboard.profiles.json
+/// for leaderboard.jsonl.
+pub fn profiles_path(leaderboard: &Path) -> PathBuf {
+    leaderboard.with_extension("profiles.json")
+}
+
+impl OpponentProfiles {
+    /// Reads the profiles at `path`, none when there is no file yet.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        serde_json::from_str(&json).map_err(|e| format!("Could not parse {}: {e}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap())
+            .map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OpponentProfile> {
+        self.profiles.get(name)
+    }
+
+    /// Adds a game of `name`, playing `player`, to their profile in the file at `path`.
+    pub fn record(
+        path: &Path,
+        name: &str,
+        game_states: &[Game],
+        player_moves: &[PlayerMove],
+        player: Player,
+    ) -> Result<(), String> {
+        let mut profiles = Self::load(path)?;
+        profiles
+            .profiles
+            .entry(name.to_string())
+            .or_default()
+            .add_game(game_states, player_moves, player);
+        profiles.save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_record::GameRecord;
+
+    #[test]
+    fn opponent_profile_test() {
+        // White walks up the e file and places a wall, three times over
+        let record = GameRecord::parse("mdu;mud;mdu;mud;h00;mud;").unwrap();
+        let game_states = record.game_states();
+        let player_moves: Vec<PlayerMove> = record.player_moves().cloned().collect();
+        let mut profile = OpponentProfile::default();
+        profile.add_game(&game_states, &player_moves, Player::White);
+        assert_eq!((profile.games, profile.moves, profile.walls), (1, 3, 1));
+        assert_eq!(profile.opening(), None);
+        assert_eq!(profile.eval_weights(), EvalWeights::default());
+        for _ in 0..2 {
+            profile.add_game(&game_states, &player_moves, Player::White);
+        }
+        assert_eq!(profile.favourite_opening().map(|(_, games)| games), Some(3));
+        assert_eq!(profile.opening().map(|opening| opening.margin), Some(0));
+        // A wall in every third move
+        assert_eq!(profile.eval_weights().walls, 20);
+
+        let path = std::env::temp_dir().join("opponent_profile_test.jsonl");
+        let profiles_path = profiles_path(&path);
+        assert!(profiles_path.ends_with("opponent_profile_test.profiles.json"));
+        let _ = std::fs::remove_file(&profiles_path);
+        OpponentProfiles::record(
+            &profiles_path,
+            "ada",
+            &game_states,
+            &player_moves,
+            Player::Black,
+        )
+        .unwrap();
+        let profiles = OpponentProfiles::load(&profiles_path).unwrap();
+        assert_eq!(profiles.get("ada").map(|profile| profile.moves), Some(3));
+        std::fs::remove_file(&profiles_path).unwrap();
+    }
+}
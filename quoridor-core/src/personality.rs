@@ -0,0 +1,85 @@
+use crate::{bot::weighted_board_score, data_model::Game};
+
+// Engine personalities, playing styles for practice opponents and varied self-play
+// data. A personality is a set of evaluation weights, in half steps, and a bias in
+// which moves the search tries first, which decides between equally scored moves:
+//
+//     personality     per step   per wall left   tries first
+//     wall-spender    2          -1              walls
+//     wall-hoarder    2          1               pawn moves
+//     runner          2          2               pawn moves
+//
+// A wall-spender sees unspent walls as wasted and puts them down early. A wall-hoarder
+// only places walls gaining more than half a step, a runner only ones gaining more
+// than a full step.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Personality {
+    WallSpender,
+    WallHoarder,
+    Runner,
+}
+
+impl Personality {
+    /// The weights of a step of path difference and of a wall left.
+    pub fn weights(self) -> (isize, isize) {
+        match self {
+            Personality::WallSpender => (2, -1),
+            Personality::WallHoarder => (2, 1),
+            Personality::Runner => (2, 2),
+        }
+    }
+
+    pub fn walls_first(self) -> bool {
+        self == Personality::WallSpender
+    }
+
+    /// Scores `game` from White's point of view.
+    pub fn score(self, game: &Game) -> isize {
+        let (distance_priority, wall_priority) = self.weights();
+        weighted_board_score(game, distance_priority, wall_priority)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bot::{Evaluation, best_move_alpha_beta},
+        data_model::PlayerMove,
+        notation::parse_position,
+        transposition::TranspositionTable,
+    };
+
+    #[test]
+    fn personality_test() {
+        assert_eq!("wall-spender".parse(), Ok(Personality::WallSpender));
+        assert_eq!(Personality::WallHoarder.to_string(), "wall-hoarder");
+        // White has spent two walls more than Black, with equally long paths
+        let game = parse_position("e2 e8 8 10 w -").unwrap();
+        assert_eq!(Personality::WallSpender.score(&game), 2);
+        assert_eq!(Personality::Runner.score(&game), -4);
+
+        let first_move = |personality| {
+            let game = Game::new();
+            let search_info = best_move_alpha_beta(
+                &game,
+                game.player,
+                1,
+                Evaluation::Personality(personality),
+                None,
+                &mut TranspositionTable::new(1),
+            );
+            search_info.best_move().unwrap().clone()
+        };
+        assert!(matches!(
+            first_move(Personality::WallSpender),
+            PlayerMove::PlaceWall { .. }
+        ));
+        assert!(matches!(
+            first_move(Personality::Runner),
+            PlayerMove::MovePiece(_)
+        ));
+    }
+}
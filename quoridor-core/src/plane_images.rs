@@ -0,0 +1,162 @@
+use crate::encoding::EncodedState;
+
+// Images of the encoded input planes, to check by eye what the network is fed: one tile
+// per channel, `GRID_COLUMNS` tiles to a row in channel order, value 0 black and 1 white.
+// Tiles show a plane as it is stored, its row 0 on top, so White's first row is at the
+// top and the wall planes are drawn at their wall centres.
+//
+// The images are grayscale PNGs with uncompressed deflate blocks, a few kilobytes for
+// a position, written without an image library.
+
+/// Pixels per value of a plane.
+pub const SCALE: usize = 8;
+pub const GRID_COLUMNS: usize = 4;
+/// Pixels between tiles, drawn in mid gray.
+const SEPARATOR: usize = 2;
+const SEPARATOR_GRAY: u8 = 128;
+
+/// Grayscale pixels of the planes of `state` laid out as a grid, row-major, with the
+/// image's width and height.
+pub fn plane_grid(state: &EncodedState) -> (usize, usize, Vec<u8>) {
+    let [channels, height, width] = state.shape;
+    let columns = channels.clamp(1, GRID_COLUMNS);
+    let rows = channels.div_ceil(columns);
+    let tile_width = width * SCALE + SEPARATOR;
+    let tile_height = height * SCALE + SEPARATOR;
+    let image_width = columns * tile_width + SEPARATOR;
+    let image_height = rows * tile_height + SEPARATOR;
+    let mut pixels = vec![SEPARATOR_GRAY; image_width * image_height];
+    for channel in 0..channels {
+        let plane = state.plane(channel);
+        let left = SEPARATOR + (channel % columns) * tile_width;
+        let top = SEPARATOR + (channel / columns) * tile_height;
+        for y in 0..height * SCALE {
+            for x in 0..width * SCALE {
+                let value = plane[(y / SCALE) * width + x / SCALE];
+                pixels[(top + y) * image_width + left + x] =
+                    (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        }
+    }
+    (image_width, image_height, pixels)
+}
+
+/// A PNG of the planes of `state`, see `plane_grid`.
+pub fn planes_png(state: &EncodedState) -> Vec<u8> {
+    let (width, height, pixels) = plane_grid(state);
+    png(width, height, &pixels)
+}
+
+/// An 8 bit grayscale PNG of row-major `pixels`.
+fn png(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    assert_eq!(pixels.len(), width * height);
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::new();
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // Bit depth 8, grayscale, deflate, adaptive filters, no interlace
+    header.extend([8, 0, 0, 0, 0]);
+    write_chunk(&mut out, b"IHDR", &header);
+    // Every scanline starts with its filter, none
+    let scanlines: Vec<u8> = pixels
+        .chunks_exact(width)
+        .flat_map(|row| std::iter::once(0).chain(row.iter().copied()))
+        .collect();
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+/// A zlib stream of `data` in stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32K window, no dictionary, the check bits making it a multiple of 31
+    let mut out = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = data.chunks(u16::MAX as usize).collect();
+    if blocks.is_empty() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    for (index, block) in blocks.iter().enumerate() {
+        out.push((index + 1 == blocks.len()) as u8);
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(*block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encoding::{ENCODED_CHANNELS, encode},
+        notation::parse_position,
+    };
+
+    #[test]
+    fn planes_png_test() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let game = parse_position("e1 e9 10 9 b -").unwrap();
+        let state = encode(&game);
+        let (width, height, pixels) = plane_grid(&state);
+        assert_eq!(width, GRID_COLUMNS * (9 * SCALE + SEPARATOR) + SEPARATOR);
+        assert_eq!(height, 2 * (9 * SCALE + SEPARATOR) + SEPARATOR);
+        // White's pawn on e1, in the top row of the first tile
+        let pixel = |x: usize, y: usize| pixels[y * width + x];
+        assert_eq!(pixel(SEPARATOR + 4 * SCALE, SEPARATOR), 255);
+        assert_eq!(pixel(SEPARATOR + 3 * SCALE, SEPARATOR), 0);
+        assert_eq!(pixel(0, 0), SEPARATOR_GRAY);
+        // Black to move, the last but one tile all black
+        let tile = |channel: usize| {
+            let left = SEPARATOR + (channel % GRID_COLUMNS) * (9 * SCALE + SEPARATOR);
+            let top = SEPARATOR + (channel / GRID_COLUMNS) * (9 * SCALE + SEPARATOR);
+            pixel(left, top)
+        };
+        assert_eq!(tile(ENCODED_CHANNELS - 2), 0);
+        assert_eq!(tile(4), 255);
+        assert_eq!(tile(5), 230);
+
+        let png = planes_png(&state);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[16..20], (width as u32).to_be_bytes());
+        assert_eq!(png[20..24], (height as u32).to_be_bytes());
+        assert!(png.ends_with(&[b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+    }
+}
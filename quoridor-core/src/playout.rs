@@ -0,0 +1,161 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{
+    a_star::a_star,
+    all_moves::ALL_MOVES,
+    bot::{WHITE_LOSES_BLACK_WINS, WHITE_WINS_BLACK_LOSES, heuristic_board_score},
+    data_model::{Game, PiecePosition, Player, PlayerMove},
+    game_logic::{
+        execute_move_unchecked, is_move_legal, new_position_after_move_piece_unchecked, winner,
+    },
+    ladder::blocking_walls,
+};
+
+// Monte-Carlo playout evaluation, an alternative to the path difference at the leaves
+// of the alpha-beta search. In a playout both players walk their shortest path, the
+// side to move placing the wall next to the opponent's pawn that lengthens the
+// opponent's path the most `WALL_PROBABILITY` of the time. The score is the average
+// result, scaled so that winning every playout is worth `board_size` steps.
+
+pub const WALL_PROBABILITY: f64 = 0.2;
+
+/// Playouts are seeded the same way for every position, keeping searches reproducible.
+const PLAYOUT_SEED: u64 = 0x5eed;
+
+/// Legal pawn moves of the side to move.
+pub fn pawn_moves(game: &Game) -> impl Iterator<Item = &'static PlayerMove> {
+    ALL_MOVES.iter().filter(move |player_move| {
+        matches!(player_move, PlayerMove::MovePiece(_))
+            && is_move_legal(game, game.player, player_move)
+    })
+}
+
+/// The length of `player`'s shortest path after the side to move plays `player_move`.
+pub fn path_length_after(game: &Game, player_move: &PlayerMove, player: Player) -> Option<usize> {
+    let mut child = game.clone();
+    execute_move_unchecked(&mut child, game.player, player_move);
+    a_star(&child, player).map(|path| path.len())
+}
+
+/// The pawn move onto `next_square`, if one reaches it.
+pub fn pawn_move_to(game: &Game, next_square: &PiecePosition) -> Option<PlayerMove> {
+    let player = game.player;
+    pawn_moves(game)
+        .find(|player_move| {
+            let PlayerMove::MovePiece(move_piece) = player_move else {
+                return false;
+            };
+            let new_position = new_position_after_move_piece_unchecked(
+                game.board.player_position(player),
+                move_piece,
+                game.board.player_position(player.opponent()),
+            );
+            &new_position == next_square
+        })
+        .cloned()
+}
+
+/// The pawn move shortening the side to move's path the most.
+fn shortest_path_move(game: &Game) -> Option<PlayerMove> {
+    pawn_moves(game)
+        .min_by_key(|player_move| {
+            path_length_after(game, player_move, game.player).unwrap_or(usize::MAX)
+        })
+        .cloned()
+}
+
+/// The wall blocking the opponent's next step that lengthens their path the most, if any does.
+fn relevant_wall(game: &Game) -> Option<PlayerMove> {
+    blocking_walls(game)
+        .into_iter()
+        .max_by_key(|(_, length)| *length)
+        .map(|(player_move, _)| player_move)
+}
+
+/// Plays the game out, returning the winner or None when `max_plies` run out first.
+pub fn playout(game: &Game, rng: &mut impl Rng, max_plies: usize) -> Option<Player> {
+    let mut game = game.clone();
+    // The rest of each player's shortest path, reversed, until a wall may have changed it
+    let mut paths: [Option<Vec<PiecePosition>>; 2] = [None, None];
+    for _ in 0..max_plies {
+        if let Some(winner) = winner(&game) {
+            return Some(winner);
+        }
+        let player = game.player;
+        let wall = if game.walls_left[player.as_index()] > 0 && rng.random_bool(WALL_PROBABILITY) {
+            relevant_wall(&game)
+        } else {
+            None
+        };
+        let player_move = match wall {
+            Some(wall) => {
+                paths = [None, None];
+                wall
+            }
+            None => {
+                let path = &mut paths[player.as_index()];
+                if path.is_none() {
+                    *path = a_star(&game, player).map(|path| path.into_iter().rev().collect());
+                }
+                match path
+                    .as_mut()
+                    .and_then(|path| pawn_move_to(&game, path.last()?))
+                {
+                    Some(player_move) => {
+                        path.as_mut().unwrap().pop();
+                        player_move
+                    }
+                    // The opponent stands in the way
+                    None => {
+                        *path = None;
+                        shortest_path_move(&game)?
+                    }
+                }
+            }
+        };
+        execute_move_unchecked(&mut game, player, &player_move);
+    }
+    winner(&game)
+}
+
+/// Scores `game` from White's point of view by averaging `count` playouts.
+pub fn playout_score(game: &Game, count: usize) -> isize {
+    let score = heuristic_board_score(game);
+    if score == WHITE_WINS_BLACK_LOSES || score == WHITE_LOSES_BLACK_WINS || count == 0 {
+        return score;
+    }
+    let board_size = game.rules.board_size;
+    let max_plies = 4 * board_size * board_size;
+    let mut rng = StdRng::seed_from_u64(PLAYOUT_SEED);
+    // Two points for a White win, one for an unfinished playout
+    let white_points: usize = (0..count)
+        .map(|_| match playout(game, &mut rng, max_plies) {
+            Some(Player::White) => 2,
+            Some(Player::Black) => 0,
+            None => 1,
+        })
+        .sum();
+    (white_points as isize - count as isize) * board_size as isize / count as isize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_position;
+
+    #[test]
+    fn playout_test() {
+        // Without walls and with equally long paths, White moves first and wins every playout
+        let game = parse_position("e7 e3 0 0 w -").unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(playout(&game, &mut rng, 100), Some(Player::White));
+        assert_eq!(playout_score(&game, 4), 9);
+        // Black's wall in front of White's pawn is the relevant one
+        let game = parse_position("e8 e2 0 1 b -").unwrap();
+        assert!(matches!(
+            relevant_wall(&game),
+            Some(PlayerMove::PlaceWall { .. })
+        ));
+        assert_eq!(playout_score(&game, 8), playout_score(&game, 8));
+    }
+}
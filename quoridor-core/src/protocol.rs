@@ -0,0 +1,469 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    analysis::{AnalysisInfo, Score},
+    bot::{SearchInfo, WHITE_LOSES_BLACK_WINS, WHITE_WINS_BLACK_LOSES},
+    data_model::{Game, Player},
+    engine::{Engine, EngineMove, SearchLimits, check_playable, legal_moves},
+    game_logic::{execute_move_unchecked, is_move_legal},
+    notation::{
+        move_to_notation, parse_notation_move, parse_position_with_moves, position_to_string,
+    },
+};
+
+// Line based engine protocol modelled on UCI. Moves are in standard notation.
+//
+// To the engine:
+//     qei                                  handshake, answered by "id name ..." and "qeiok"
+//     isready                              answered by "readyok", once the engine is warmed up
+//     newgame
+//     position startpos [moves e2 e8 ...]
+//     position <position string> [moves ...]
+//     go [depth <plies>] [nodes <n>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>]
+//                                          answered by "info ..." lines and "bestmove <move>"
+//     stop
+//     quit
+//
+// From the engine:
+//     info depth 4 score cp 100 nodes 5120 time 35 hashfull 12 pv e2 e8 e3
+//     bestmove e2
+//
+// Scores are from the side to move's point of view: "cp <centipaths>", "win" or "loss".
+// hashfull is the permille of the transposition table in use. wtime and btime give the
+// time left on the clocks of White and Black, winc and binc their increments.
+// `go` is answered synchronously, an engine is free to ignore "stop". When the game is
+// over or the side to move has no legal move, it is answered by "info string <reason>"
+// and "bestmove none".
+
+#[derive(Debug, Clone)]
+pub enum ProtocolCommand {
+    Qei,
+    IsReady,
+    NewGame,
+    Position(Game),
+    Go(SearchLimits),
+    Stop,
+    Quit,
+}
+
+pub fn parse_command(line: &str) -> Result<ProtocolCommand, String> {
+    let mut tokens = line.split_whitespace();
+    match tokens.next() {
+        Some("qei") => Ok(ProtocolCommand::Qei),
+        Some("isready") => Ok(ProtocolCommand::IsReady),
+        Some("newgame") => Ok(ProtocolCommand::NewGame),
+        Some("position") => Ok(ProtocolCommand::Position(parse_position_with_moves(
+            &tokens.collect::<Vec<_>>().join(" "),
+        )?)),
+        Some("go") => {
+            let mut limits = SearchLimits::default();
+            while let Some(token) = tokens.next() {
+                let value = tokens
+                    .next()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or_else(|| format!("Expected a number after {token}"))?;
+                let time = Duration::from_millis(value);
+                let (white, black) = (Player::White.as_index(), Player::Black.as_index());
+                match token {
+                    "depth" => limits.depth = Some(value as usize),
+                    "nodes" => limits.nodes = Some(value),
+                    "movetime" => limits.movetime = Some(time),
+                    "wtime" => limits.clock.get_or_insert_default().remaining[white] = time,
+                    "btime" => limits.clock.get_or_insert_default().remaining[black] = time,
+                    "winc" => limits.clock.get_or_insert_default().increment[white] = time,
+                    "binc" => limits.clock.get_or_insert_default().increment[black] = time,
+                    _ => return Err(format!("Unknown search limit: {token}")),
+                }
+            }
+            Ok(ProtocolCommand::Go(limits))
+        }
+        Some("stop") => Ok(ProtocolCommand::Stop),
+        Some("quit") => Ok(ProtocolCommand::Quit),
+        _ => Err(format!("Unknown command: {line}")),
+    }
+}
+
+fn go_command(limits: &SearchLimits) -> String {
+    let mut command = "go".to_string();
+    if let Some(depth) = limits.depth {
+        command += &format!(" depth {depth}");
+    }
+    if let Some(nodes) = limits.nodes {
+        command += &format!(" nodes {nodes}");
+    }
+    if let Some(movetime) = limits.movetime {
+        command += &format!(" movetime {}", movetime.as_millis());
+    }
+    if let Some(clock) = limits.clock {
+        for (name, time) in [
+            ("wtime", clock.remaining[Player::White.as_index()]),
+            ("btime", clock.remaining[Player::Black.as_index()]),
+            ("winc", clock.increment[Player::White.as_index()]),
+            ("binc", clock.increment[Player::Black.as_index()]),
+        ] {
+            command += &format!(" {name} {}", time.as_millis());
+        }
+    }
+    command
+}
+
+pub fn format_info(info: &AnalysisInfo, side_to_move: Player) -> String {
+    let score = match info.score {
+        Score::Centipaths(centipaths) if side_to_move.is_maximizer(Player::White) => {
+            format!("cp {centipaths}")
+        }
+        Score::Centipaths(centipaths) => format!("cp {}", -centipaths),
+        Score::Win { winner, .. } if winner == side_to_move => "win".to_string(),
+        Score::Win { .. } => "loss".to_string(),
+    };
+    format!(
+        "info depth {} score {} nodes {} time {} hashfull {} pv {}",
+        info.depth,
+        score,
+        info.nodes,
+        info.time_ms,
+        info.hashfull,
+        info.pv.join(" ")
+    )
+}
+
+/// Parses an info line into search info with the score from White's point of view.
+pub fn parse_info(game: &Game, line: &str) -> Option<SearchInfo> {
+    let mut search_info = SearchInfo {
+        score: 0,
+        principal_variation: Vec::new(),
+        depth: 0,
+        nodes: 0,
+        hashfull: 0,
+    };
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some("info") {
+        return None;
+    }
+    let sign = if game.player.is_maximizer(Player::White) {
+        1
+    } else {
+        -1
+    };
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => search_info.depth = tokens.next()?.parse().ok()?,
+            "nodes" => search_info.nodes = tokens.next()?.parse().ok()?,
+            "hashfull" => search_info.hashfull = tokens.next()?.parse().ok()?,
+            "time" => {
+                tokens.next()?;
+            }
+            "score" => {
+                search_info.score = match tokens.next()? {
+                    "cp" => sign * tokens.next()?.parse::<isize>().ok()? / 100,
+                    "win" if sign == 1 => WHITE_WINS_BLACK_LOSES,
+                    "win" => WHITE_LOSES_BLACK_WINS,
+                    "loss" if sign == 1 => WHITE_LOSES_BLACK_WINS,
+                    "loss" => WHITE_WINS_BLACK_LOSES,
+                    _ => return None,
+                }
+            }
+            "pv" => {
+                let mut game = game.clone();
+                for move_str in tokens.by_ref() {
+                    let player = game.player;
+                    let Some(player_move) = parse_notation_move(&game, move_str)
+                        .filter(|player_move| is_move_legal(&game, player, player_move))
+                    else {
+                        break;
+                    };
+                    execute_move_unchecked(&mut game, player, &player_move);
+                    search_info.principal_variation.push(player_move);
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(search_info)
+}
+
+/// Serves `engine` over the protocol until "quit" or the end of the input.
+pub fn run_protocol(
+    engine: &mut dyn Engine,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut game = Game::new();
+    let mut warmed_up = false;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_command(&line) {
+            Ok(ProtocolCommand::Qei) => {
+                writeln!(output, "id name {}", engine.name())?;
+                writeln!(output, "qeiok")?;
+            }
+            Ok(ProtocolCommand::IsReady) => {
+                if !warmed_up {
+                    engine.warm_up(&game);
+                    warmed_up = true;
+                }
+                writeln!(output, "readyok")?;
+            }
+            Ok(ProtocolCommand::NewGame) => {
+                game = Game::new();
+                engine.new_game();
+            }
+            Ok(ProtocolCommand::Position(position)) => game = position,
+            Ok(ProtocolCommand::Go(_)) if let Err(e) = check_playable(&game) => {
+                writeln!(output, "info string {e}")?;
+                writeln!(output, "bestmove none")?;
+            }
+            Ok(ProtocolCommand::Go(limits)) => {
+                let engine_move = engine.choose_move(&game, &limits);
+                let search_result = engine_move.to_search_result(&game);
+                writeln!(output, "{}", format_info(&search_result.info, game.player))?;
+                writeln!(
+                    output,
+                    "bestmove {}",
+                    move_to_notation(&game, &engine_move.player_move)
+                )?;
+            }
+            Ok(ProtocolCommand::Stop) => engine.stop(),
+            Ok(ProtocolCommand::Quit) => break,
+            Err(e) => writeln!(output, "info string {e}")?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// An engine running as a child process, speaking the protocol over stdin and stdout.
+pub struct ExternalEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// Why the engine failed, after which it isn't asked for moves again.
+    failure: Option<String>,
+}
+
+impl ExternalEngine {
+    /// Starts the engine from a whitespace separated program and arguments.
+    pub fn spawn(command_line: &str) -> io::Result<Self> {
+        let mut parts = command_line.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty engine command"))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut engine = Self {
+            name: program.to_string(),
+            stdin: child.stdin.take().unwrap(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+            failure: None,
+        };
+        engine.send("qei")?;
+        for line in engine.read_until("qeiok")? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+        }
+        Ok(engine)
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{line}")?;
+        self.stdin.flush()
+    }
+
+    /// Reads lines up to and including the first one starting with `prefix`.
+    fn read_until(&mut self, prefix: &str) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("{} exited while waiting for {prefix}", self.name),
+                ));
+            }
+            let line = line.trim().to_string();
+            let done = line.starts_with(prefix);
+            lines.push(line);
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+
+    fn request_move(&mut self, game: &Game, limits: &SearchLimits) -> io::Result<EngineMove> {
+        let start_time = Instant::now();
+        self.send(&format!("position {}", position_to_string(game)))?;
+        self.send(&go_command(limits))?;
+        let lines = self.read_until("bestmove")?;
+        let best_move = lines.last().unwrap()["bestmove".len()..].trim();
+        let player_move = parse_notation_move(game, best_move).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} sent an invalid move: {best_move}", self.name),
+            )
+        })?;
+        Ok(EngineMove {
+            search_info: lines.iter().rev().find_map(|line| parse_info(game, line)),
+            planned_duration: limits.movetime,
+            ..EngineMove::unsearched(player_move, start_time)
+        })
+    }
+}
+
+impl Engine for ExternalEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Plays the first legal move once the engine failed, telling why on stderr, for callers
+    /// that can't forfeit. The match runner and frontends see the failure instead.
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> EngineMove {
+        let start_time = Instant::now();
+        self.try_choose_move(game, limits).unwrap_or_else(|error| {
+            eprintln!("{error}");
+            let player_move = legal_moves(game).into_iter().next().unwrap();
+            EngineMove::unsearched(player_move, start_time)
+        })
+    }
+
+    fn try_choose_move(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+    ) -> Result<EngineMove, String> {
+        if let Some(failure) = &self.failure {
+            return Err(failure.clone());
+        }
+        self.request_move(game, limits).map_err(|e| {
+            let failure = format!("External engine {} failed: {e}", self.name);
+            self.failure = Some(failure.clone());
+            failure
+        })
+    }
+
+    fn stop(&self) {
+        let _ = writeln!(&self.stdin, "stop");
+    }
+
+    fn new_game(&mut self) {
+        let _ = self.send("newgame");
+    }
+
+    /// Waits for the engine to finish starting up, loading its networks and tables. An
+    /// engine that doesn't get ready fails its first move.
+    fn warm_up(&mut self, _game: &Game) {
+        if let Err(e) = self
+            .send("isready")
+            .and_then(|_| self.read_until("readyok"))
+        {
+            let failure = format!("External engine {} failed: {e}", self.name);
+            eprintln!("{failure}");
+            self.failure = Some(failure);
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::AlphaBetaEngine;
+
+    #[test]
+    fn session_test() {
+        let input = "qei\nisready\nposition startpos moves e2 e8\ngo depth 1\nbogus\n\
+            position e9 e5 10 10 b -\ngo depth 1\nquit\ngo\n";
+        let mut output = Vec::new();
+        run_protocol(&mut AlphaBetaEngine::new(), input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "id name alpha-beta");
+        assert_eq!(lines[1..3], ["qeiok", "readyok"]);
+        assert!(lines[3].starts_with("info depth 1 score cp "));
+        assert!(lines[4].starts_with("bestmove "));
+        assert!(lines[5].starts_with("info string Unknown command"));
+        assert_eq!(
+            lines[6..],
+            ["info string The game is over, White won", "bestmove none"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_warm_up_test() {
+        // Starts up, then exits when asked whether it is ready
+        let path = std::env::temp_dir().join(format!("quitting-engine-{}", std::process::id()));
+        std::fs::write(&path, "read line\necho qeiok\nread line\n").unwrap();
+        let mut engine = ExternalEngine::spawn(&format!("sh {}", path.display())).unwrap();
+        let game = Game::new();
+        engine.warm_up(&game);
+        let error = engine
+            .try_choose_move(&game, &SearchLimits::default())
+            .unwrap_err();
+        assert!(
+            error.contains("exited while waiting for readyok"),
+            "{error}"
+        );
+        drop(engine);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn go_test() {
+        let Ok(ProtocolCommand::Go(limits)) =
+            parse_command("go nodes 5000 wtime 60000 btime 30000 binc 1000")
+        else {
+            panic!("go not parsed");
+        };
+        assert_eq!((limits.depth, limits.nodes), (None, Some(5000)));
+        let clock = limits.clock.unwrap();
+        assert_eq!(clock.remaining[1], Duration::from_secs(30));
+        assert_eq!(clock.increment, [Duration::ZERO, Duration::from_secs(1)]);
+        // Black gets a share of their 30 s plus the increment
+        assert_eq!(
+            limits.time_budget(Player::Black),
+            Some(Duration::from_millis(2500))
+        );
+        assert!(
+            matches!(parse_command(&go_command(&limits)), Ok(ProtocolCommand::Go(parsed)) if parsed == limits)
+        );
+        assert!(parse_command("go nodes").is_err());
+    }
+
+    #[test]
+    fn info_test() {
+        let mut game = Game::new();
+        game.player = Player::Black;
+        let info = parse_info(
+            &game,
+            "info depth 3 score cp 200 nodes 42 time 5 hashfull 7 pv e8 e2",
+        )
+        .unwrap();
+        assert_eq!((info.depth, info.nodes, info.score), (3, 42, -2));
+        assert_eq!(info.hashfull, 7);
+        assert_eq!(info.principal_variation.len(), 2);
+        assert_eq!(
+            parse_info(&game, "info depth 1 score win").unwrap().score,
+            WHITE_LOSES_BLACK_WINS
+        );
+        assert!(parse_info(&game, "bestmove e8").is_none());
+    }
+}
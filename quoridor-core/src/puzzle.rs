@@ -0,0 +1,110 @@
+use std::fmt::Display;
+
+use crate::{
+    bot::move_scores,
+    data_model::{Game, Player, PlayerMove},
+    game_logic::winner,
+    game_record::GameRecord,
+    notation::{move_to_notation, parse_notation_move, position_to_string},
+    test_suite::parse_test_suite,
+};
+
+// Puzzles are positions with one clearly best move, mined from recorded games.
+// They are stored in the test suite format with a single "bm" move:
+//
+//     e5 e4 7 8 b c4h,e6v; bm d4; id game-3-ply-12
+
+pub struct Puzzle {
+    pub id: String,
+    pub game: Game,
+    pub solution: PlayerMove,
+}
+
+impl Puzzle {
+    pub fn is_solution(&self, player_move: &PlayerMove) -> bool {
+        move_to_notation(&self.game, player_move) == move_to_notation(&self.game, &self.solution)
+    }
+}
+
+impl Display for Puzzle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}; bm {}; id {}",
+            position_to_string(&self.game),
+            move_to_notation(&self.game, &self.solution),
+            self.id
+        )
+    }
+}
+
+/// Returns a puzzle when the best move is at least `min_gap` steps better than the second best.
+pub fn find_puzzle(game: &Game, depth: usize, min_gap: isize, id: String) -> Option<Puzzle> {
+    if winner(game).is_some() {
+        return None;
+    }
+    let scores = move_scores(game, depth);
+    let [(best_move, best_score), (_, second_score), ..] = &scores[..] else {
+        return None;
+    };
+    let gap = if game.player.is_maximizer(Player::White) {
+        best_score.saturating_sub(*second_score)
+    } else {
+        second_score.saturating_sub(*best_score)
+    };
+    (gap >= min_gap).then(|| Puzzle {
+        id,
+        game: game.clone(),
+        solution: best_move.clone(),
+    })
+}
+
+/// Finds the puzzles among the positions of a recorded game.
+pub fn mine_puzzles(record: &GameRecord, depth: usize, min_gap: isize, id: &str) -> Vec<Puzzle> {
+    record
+        .game_states()
+        .iter()
+        .enumerate()
+        .filter_map(|(ply, game)| find_puzzle(game, depth, min_gap, format!("{id}-ply-{ply}")))
+        .collect()
+}
+
+pub fn parse_puzzles(input: &str) -> Result<Vec<Puzzle>, String> {
+    parse_test_suite(input)?
+        .into_iter()
+        .map(|test_position| {
+            let [solution] = &test_position.best_moves[..] else {
+                return Err(format!(
+                    "Puzzle {} needs exactly one bm move",
+                    test_position.id
+                ));
+            };
+            // The test suite parser has already checked that the move parses
+            let solution = parse_notation_move(&test_position.game, solution).unwrap();
+            Ok(Puzzle {
+                id: test_position.id,
+                game: test_position.game,
+                solution,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_position;
+
+    #[test]
+    fn puzzle_test() {
+        // Black wins by stepping into the goal row, anything else lets White win
+        let game = parse_position("e8 e2 0 1 b -").unwrap();
+        let puzzle = find_puzzle(&game, 2, 3, "race".to_string()).unwrap();
+        assert!(puzzle.is_solution(&parse_notation_move(&game, "e1").unwrap()));
+        let puzzles = parse_puzzles(&puzzle.to_string()).unwrap();
+        assert_eq!(puzzles.len(), 1);
+        assert_eq!(puzzles[0].to_string(), puzzle.to_string());
+        assert!(find_puzzle(&Game::new(), 1, 3, "opening".to_string()).is_none());
+        assert!(parse_puzzles("startpos; am e2").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::{
+    data_model::{Game, Player},
+    game_logic::{execute_move_unchecked, winner},
+    playout::pawn_moves,
+};
+
+// Pawn races, solved by a small search over pawn moves only: how the game ends with both
+// pawns playing their best and no new walls. Unlike path lengths this gets the jumps
+// right, a pawn standing in the other's way is jumped, or steps aside to block a jump.
+//
+// Results are in plies, as in the tablebases: odd when the side to move wins, even when
+// it loses. The search is cut off after a horizon, so races only replace the path
+// lengths of the evaluation in the late game, with no walls left to change them and the
+// nearer pawn at most `RACE_MOVES` moves from its goal.
+
+/// Moves to goal of the nearer pawn up to which races are searched.
+pub const RACE_MOVES: isize = 4;
+
+/// The plies to the end of the race of `game` if it ends within `horizon` plies.
+pub fn race_plies(game: &Game, horizon: usize) -> Option<usize> {
+    let mut game = game.clone();
+    game.walls_left = [0, 0];
+    // Deepening, as a search cut off early proves little and a short race is cheap
+    let mut memo = HashMap::new();
+    (0..=horizon).find_map(|horizon| solve(&game, horizon, &mut memo))
+}
+
+/// The race distances of the late game, replacing `distances` from White's and Black's
+/// shortest paths: the winner's moves to goal, the loser at least a move further away
+/// when it is to move and as far away otherwise, the side to move winning ties.
+pub fn race_distances(game: &Game, distances: [isize; 2]) -> Option<[isize; 2]> {
+    let nearest = *distances.iter().min().unwrap();
+    if game.walls_left != [0, 0] || nearest > RACE_MOVES {
+        return None;
+    }
+    // Pawns too far apart to meet before the race is over walk their paths
+    let [white, black] = &game.board.player_positions;
+    let apart = white.x().abs_diff(black.x()) + white.y().abs_diff(black.y());
+    if apart > 2 * nearest as usize + 1 {
+        return None;
+    }
+    // Jumps win or lose at most a move
+    let horizon = 2 * nearest as usize + 3;
+    let plies = race_plies(game, horizon)?;
+    let (winner, moves) = if plies % 2 == 1 {
+        (game.player, plies.div_ceil(2) as isize)
+    } else {
+        (game.player.opponent(), plies as isize / 2)
+    };
+    let loser = winner.opponent();
+    let mut race = distances;
+    race[winner.as_index()] = moves;
+    let behind = (loser == game.player) as isize;
+    race[loser.as_index()] = distances[loser.as_index()].max(moves + behind);
+    Some(race)
+}
+
+type StateKey = (usize, usize, Player, usize);
+
+fn solve(
+    game: &Game,
+    horizon: usize,
+    memo: &mut HashMap<StateKey, Option<usize>>,
+) -> Option<usize> {
+    if winner(game) == Some(game.player.opponent()) {
+        return Some(0);
+    }
+    if horizon == 0 {
+        return None;
+    }
+    let [white, black] = &game.board.player_positions;
+    let key = (white.index, black.index, game.player, horizon);
+    if let Some(&result) = memo.get(&key) {
+        return result;
+    }
+    let mut fastest_win = None;
+    let mut slowest_loss = Some(0);
+    for player_move in pawn_moves(game) {
+        let mut child = game.clone();
+        execute_move_unchecked(&mut child, game.player, player_move);
+        match solve(&child, horizon - 1, memo) {
+            Some(plies) if plies % 2 == 0 => {
+                fastest_win = Some(fastest_win.map_or(plies + 1, |win: usize| win.min(plies + 1)));
+            }
+            Some(plies) => slowest_loss = slowest_loss.map(|loss: usize| loss.max(plies + 1)),
+            None => slowest_loss = None,
+        }
+    }
+    // Losses within the horizon need every move lost within it
+    let result = fastest_win.or(slowest_loss);
+    memo.insert(key, result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data_model::PiecePosition, notation::parse_position, tablebase::RaceTable};
+
+    #[test]
+    fn race_test() {
+        // White jumps Black and wins in four moves, as many as its path is long
+        let game = parse_position("e4 e5 0 0 w -").unwrap();
+        assert_eq!(race_plies(&game, 9), Some(7));
+        assert_eq!(race_plies(&game, 6), None);
+        // Whatever the paths say, White to move is as far away as Black and wins
+        assert_eq!(race_distances(&game, [5, 3]), Some([4, 4]));
+        assert_eq!(race_distances(&game, [5, 5]), None);
+
+        // The races agree with the tablebase within the horizon
+        let game = parse_position("e4 e5 0 0 w d4h,e6v").unwrap();
+        let table = RaceTable::generate(&game);
+        for black in 0..81 {
+            for player in [Player::White, Player::Black] {
+                let mut game = game.clone();
+                game.board.player_positions[1] = PiecePosition::new(black % 9, black / 9);
+                game.player = player;
+                if black == 31 || winner(&game).is_some() {
+                    continue;
+                }
+                let result = table.probe(&game) as usize;
+                assert_eq!(race_plies(&game, 9), (result <= 9).then_some(result));
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use crate::data_model::{
+    Board, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, PiecePosition, Player, WALL_GRID_HEIGHT,
+    WALL_GRID_WIDTH, WallOrientation,
+};
+
+/// Width in columns of a rendered board.
+pub const BOARD_WIDTH: usize = 8 * PIECE_GRID_WIDTH;
+
+const WALL_COLOR: &str = "33";
+const WHITE_COLOR: &str = "1;97";
+const BLACK_COLOR: &str = "1;96";
+
+/// How a board is drawn, for terminals that can't show box-drawing characters or colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardStyle {
+    /// Box-drawing characters, or plain ASCII when false.
+    pub unicode: bool,
+    /// ANSI colors for the pawns and the walls.
+    pub color: bool,
+}
+
+impl Default for BoardStyle {
+    fn default() -> Self {
+        Self {
+            unicode: true,
+            color: false,
+        }
+    }
+}
+
+struct Glyphs {
+    top: &'static str,
+    side: &'static str,
+    bottom: &'static str,
+    vertical_wall: &'static str,
+    horizontal_wall: &'static str,
+}
+
+const UNICODE_GLYPHS: Glyphs = Glyphs {
+    top: "┌───┐",
+    side: "│",
+    bottom: "└───┘",
+    vertical_wall: "│",
+    horizontal_wall: "─",
+};
+
+// Walls get their own characters in ASCII, as '|' and '-' would blend with the squares
+const ASCII_GLYPHS: Glyphs = Glyphs {
+    top: "+---+",
+    side: "|",
+    bottom: "+---+",
+    vertical_wall: "#",
+    horizontal_wall: "=",
+};
+
+pub fn render_board(board: &Board) -> String {
+    render_board_with(board, BoardStyle::default())
+}
+
+pub fn render_board_with(board: &Board, style: BoardStyle) -> String {
+    let glyphs = if style.unicode {
+        &UNICODE_GLYPHS
+    } else {
+        &ASCII_GLYPHS
+    };
+    let paint = |text: &str, color: &str| {
+        if style.color && !text.trim().is_empty() {
+            format!("\x1b[{color}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    };
+    let mut output = String::new();
+    for y in 0..PIECE_GRID_HEIGHT {
+        if y > 0 {
+            output.push('\n');
+        }
+        let draw_vertical_wall = |x: usize| {
+            let wall_above = x < WALL_GRID_WIDTH
+                && y > 0
+                && board.walls[x][y - 1].has(WallOrientation::Vertical);
+            let wall_below = x < WALL_GRID_WIDTH
+                && y < WALL_GRID_HEIGHT
+                && board.walls[x][y].has(WallOrientation::Vertical);
+            if wall_below || wall_above {
+                paint(glyphs.vertical_wall, WALL_COLOR)
+            } else {
+                " ".to_string()
+            }
+        };
+        for x in 0..PIECE_GRID_WIDTH {
+            output.push_str(format!("{} {} ", glyphs.top, draw_vertical_wall(x)).as_str());
+        }
+        output.push('\n');
+        for x in 0..PIECE_GRID_WIDTH {
+            let square = PiecePosition::new(x, y);
+            let player = if *board.player_position(Player::White) == square {
+                paint("W", WHITE_COLOR)
+            } else if *board.player_position(Player::Black) == square {
+                paint("B", BLACK_COLOR)
+            } else {
+                " ".to_string()
+            };
+            output.push_str(
+                format!(
+                    "{side} {} {side} {} ",
+                    player,
+                    draw_vertical_wall(x),
+                    side = glyphs.side
+                )
+                .as_str(),
+            );
+        }
+        output.push('\n');
+        for x in 0..PIECE_GRID_WIDTH {
+            output.push_str(format!("{} {} ", glyphs.bottom, draw_vertical_wall(x)).as_str());
+        }
+        if y < WALL_GRID_HEIGHT {
+            output.push('\n');
+            for x in 0..PIECE_GRID_WIDTH {
+                let wall_right = y < WALL_GRID_WIDTH
+                    && x < WALL_GRID_HEIGHT
+                    && board.walls[x][y].has(WallOrientation::Horizontal);
+                let wall_left = y < WALL_GRID_WIDTH
+                    && x > 0
+                    && board.walls[x - 1][y].has(WallOrientation::Horizontal);
+                let vertical_wall = x < WALL_GRID_WIDTH
+                    && y < WALL_GRID_HEIGHT
+                    && board.walls[x][y].has(WallOrientation::Vertical);
+                let vertical_wall_str = if vertical_wall {
+                    paint(glyphs.vertical_wall, WALL_COLOR)
+                } else {
+                    " ".to_string()
+                };
+                let write_indices = x < WALL_GRID_WIDTH && !vertical_wall;
+                let (x_str, y_str) = if write_indices {
+                    (x.to_string(), y.to_string())
+                } else {
+                    (" ".to_string(), " ".to_string())
+                };
+                if wall_right {
+                    output.push_str(&paint(&glyphs.horizontal_wall.repeat(8), WALL_COLOR));
+                } else if wall_left {
+                    output.push_str(
+                        format!(
+                            "{}{}{}{}",
+                            paint(&glyphs.horizontal_wall.repeat(5), WALL_COLOR),
+                            x_str,
+                            vertical_wall_str,
+                            y_str,
+                        )
+                        .as_str(),
+                    );
+                } else {
+                    output.push_str(
+                        format!("     {}{}{}", x_str, vertical_wall_str, y_str,).as_str(),
+                    );
+                }
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_model::{Game, PlayerMove, WallPosition};
+    use crate::game_logic::execute_move_unchecked;
+
+    #[test]
+    fn render_board_styles_test() {
+        let mut game = Game::new();
+        for (orientation, x, y) in [
+            (WallOrientation::Horizontal, 3, 3),
+            (WallOrientation::Vertical, 5, 2),
+        ] {
+            let player = game.player;
+            execute_move_unchecked(
+                &mut game,
+                player,
+                &PlayerMove::PlaceWall {
+                    orientation,
+                    position: WallPosition { x, y },
+                },
+            );
+        }
+        let unicode = render_board(&game.board);
+        let ascii = render_board_with(
+            &game.board,
+            BoardStyle {
+                unicode: false,
+                color: false,
+            },
+        );
+        assert!(ascii.is_ascii());
+        assert!(ascii.contains("========") && ascii.contains('#'));
+        let widths =
+            |text: &str| -> Vec<usize> { text.lines().map(|l| l.chars().count()).collect() };
+        assert_eq!(widths(&ascii), widths(&unicode));
+        assert!(widths(&unicode).iter().all(|&width| width == BOARD_WIDTH));
+
+        let colored = render_board_with(
+            &game.board,
+            BoardStyle {
+                unicode: true,
+                color: true,
+            },
+        );
+        assert!(colored.contains("\x1b[33m────────\x1b[0m"));
+        assert!(colored.contains("\x1b[1;97mW\x1b[0m"));
+    }
+}
@@ -0,0 +1,246 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    all_moves::ALL_MOVES,
+    data_model::{Game, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, Player},
+    encoding::{ENCODED_CHANNELS, encode},
+    game_logic::{execute_move_unchecked, is_move_legal, winner},
+    rules::Rules,
+};
+
+// Environment adapter for external reinforcement learning frameworks,
+// following the OpenSpiel/Gymnasium conventions:
+//
+// - actions are indices into ALL_MOVES,
+// - observations are the NN input planes flattened to [C * 9 * 9],
+// - rewards are indexed by player (0 = White, 1 = Black), +1 for a win and
+//   -1 for a loss, given on the terminating step only,
+// - `terminated` means someone reached their goal row, `truncated` means the
+//   ply limit was hit without a winner.
+//
+// The same environment can be driven over a JSON lines protocol, one request
+// per line:
+//
+//     {"cmd":"spec"}
+//     {"cmd":"reset"}
+//     {"cmd":"step","action":12}
+
+pub const NUM_DISTINCT_ACTIONS: usize = ALL_MOVES.len();
+pub const OBSERVATION_SHAPE: [usize; 3] = [ENCODED_CHANNELS, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH];
+pub const DEFAULT_MAX_PLIES: usize = 200;
+
+pub struct QuoridorEnv {
+    game: Game,
+    plies: usize,
+    max_plies: usize,
+    rewards: [f32; 2],
+}
+
+/// Result of `reset` and `step`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeStep {
+    pub observation: Vec<f32>,
+    pub rewards: [f32; 2],
+    pub terminated: bool,
+    pub truncated: bool,
+    /// None once the episode is over.
+    pub current_player: Option<usize>,
+    pub legal_actions: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvSpec {
+    pub num_distinct_actions: usize,
+    pub num_players: usize,
+    pub observation_shape: [usize; 3],
+    pub max_plies: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum EnvRequest {
+    Spec,
+    Reset,
+    Step { action: usize },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum EnvResponse {
+    Spec(EnvSpec),
+    TimeStep(TimeStep),
+    Error { error: String },
+}
+
+impl Default for QuoridorEnv {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PLIES)
+    }
+}
+
+impl QuoridorEnv {
+    pub fn new(max_plies: usize) -> Self {
+        Self::with_rules(max_plies, Rules::default())
+    }
+
+    pub fn with_rules(max_plies: usize, rules: Rules) -> Self {
+        Self {
+            game: Game::with_rules(rules),
+            plies: 0,
+            max_plies,
+            rewards: [0.0; 2],
+        }
+    }
+
+    pub fn spec(&self) -> EnvSpec {
+        EnvSpec {
+            num_distinct_actions: NUM_DISTINCT_ACTIONS,
+            num_players: 2,
+            observation_shape: OBSERVATION_SHAPE,
+            max_plies: self.max_plies,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn reset(&mut self) -> TimeStep {
+        *self = Self::with_rules(self.max_plies, self.game.rules);
+        self.time_step()
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        winner(&self.game).is_some()
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        !self.is_terminated() && self.plies >= self.max_plies
+    }
+
+    pub fn current_player(&self) -> Option<Player> {
+        if self.is_terminated() || self.is_truncated() {
+            return None;
+        }
+        Some(self.game.player)
+    }
+
+    pub fn legal_actions(&self) -> Vec<usize> {
+        let Some(player) = self.current_player() else {
+            return Vec::new();
+        };
+        (0..NUM_DISTINCT_ACTIONS)
+            .filter(|&action| is_move_legal(&self.game, player, &ALL_MOVES[action]))
+            .collect()
+    }
+
+    pub fn observation_tensor(&self) -> Vec<f32> {
+        encode(&self.game).values
+    }
+
+    pub fn step(&mut self, action: usize) -> Result<TimeStep, String> {
+        let player = self.current_player().ok_or("the episode is over")?;
+        let player_move = ALL_MOVES
+            .get(action)
+            .ok_or_else(|| format!("action {action} is out of range"))?;
+        if !is_move_legal(&self.game, player, player_move) {
+            return Err(format!("action {action} ({player_move}) is illegal"));
+        }
+        execute_move_unchecked(&mut self.game, player, player_move);
+        self.plies += 1;
+        self.rewards = match winner(&self.game) {
+            Some(Player::White) => [1.0, -1.0],
+            Some(Player::Black) => [-1.0, 1.0],
+            None => [0.0, 0.0],
+        };
+        Ok(self.time_step())
+    }
+
+    fn time_step(&self) -> TimeStep {
+        TimeStep {
+            observation: self.observation_tensor(),
+            rewards: self.rewards,
+            terminated: self.is_terminated(),
+            truncated: self.is_truncated(),
+            current_player: self.current_player().map(|player| player.as_index()),
+            legal_actions: self.legal_actions(),
+        }
+    }
+
+    pub fn handle_request(&mut self, request: EnvRequest) -> EnvResponse {
+        match request {
+            EnvRequest::Spec => EnvResponse::Spec(self.spec()),
+            EnvRequest::Reset => EnvResponse::TimeStep(self.reset()),
+            EnvRequest::Step { action } => match self.step(action) {
+                Ok(time_step) => EnvResponse::TimeStep(time_step),
+                Err(error) => EnvResponse::Error { error },
+            },
+        }
+    }
+
+    /// Handles one line of the JSON lines protocol and returns the response line.
+    pub fn handle_line(&mut self, line: &str) -> String {
+        let response = match serde_json::from_str(line) {
+            Ok(request) => self.handle_request(request),
+            Err(e) => EnvResponse::Error {
+                error: e.to_string(),
+            },
+        };
+        serde_json::to_string(&response).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(move_str: &str) -> usize {
+        ALL_MOVES
+            .iter()
+            .position(|m| m.to_string() == move_str)
+            .unwrap()
+    }
+
+    #[test]
+    fn episode_test() {
+        let mut env = QuoridorEnv::default();
+        let mut time_step = env.reset();
+        assert_eq!(
+            time_step.observation.len(),
+            OBSERVATION_SHAPE.iter().product::<usize>()
+        );
+        assert_eq!(time_step.current_player, Some(0));
+        assert!(env.step(NUM_DISTINCT_ACTIONS).is_err());
+
+        // White walks straight to its goal row while Black steps left and right.
+        for ply in 0..15 {
+            assert!(!time_step.terminated);
+            let move_str = match ply % 4 {
+                1 => "mll",
+                3 => "mrr",
+                _ => "mdd",
+            };
+            time_step = env.step(action(move_str)).unwrap();
+        }
+        assert!(time_step.terminated);
+        assert_eq!(time_step.rewards, [1.0, -1.0]);
+        assert_eq!(time_step.current_player, None);
+        assert!(time_step.legal_actions.is_empty());
+        assert!(env.step(action("mll")).is_err());
+    }
+
+    #[test]
+    fn protocol_test() {
+        let mut env = QuoridorEnv::new(1);
+        let spec: EnvSpec = serde_json::from_str(&env.handle_line(r#"{"cmd":"spec"}"#)).unwrap();
+        assert_eq!(spec.num_distinct_actions, ALL_MOVES.len());
+        let reset: TimeStep = serde_json::from_str(&env.handle_line(r#"{"cmd":"reset"}"#)).unwrap();
+        let action = reset.legal_actions[0];
+        let step: TimeStep = serde_json::from_str(
+            &env.handle_line(&format!(r#"{{"cmd":"step","action":{action}}}"#)),
+        )
+        .unwrap();
+        assert!(step.truncated && !step.terminated);
+        assert!(env.handle_line("nonsense").contains("error"));
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data_model::{PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, PiecePosition, Player};
+
+/// What a pawn may do when the opponent stands on the neighbouring square.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JumpRule {
+    /// Jump over the opponent, straight or to either side.
+    #[default]
+    Diagonal,
+    /// Only jump straight over the opponent.
+    Straight,
+    /// The opponent's square can't be entered or jumped.
+    NoJumps,
+}
+
+/// The rule variant a game is played with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rules {
+    /// Squares along each side. Smaller boards use the top left corner of the 9x9 grid.
+    pub board_size: usize,
+    pub walls_per_player: usize,
+    /// Whether a wall may cut off a player's last path to their goal row.
+    pub allow_blocking: bool,
+    /// Whether a horizontal and a vertical wall may share a centre.
+    pub allow_crossing_walls: bool,
+    pub jumps: JumpRule,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            board_size: PIECE_GRID_WIDTH,
+            walls_per_player: 10,
+            allow_blocking: false,
+            allow_crossing_walls: false,
+            jumps: JumpRule::Diagonal,
+        }
+    }
+}
+
+/// Named variants selectable from the command line.
+pub const VARIANT_NAMES: [&str; 6] = [
+    "standard",
+    "blocking",
+    "straight-jumps",
+    "no-jumps",
+    "crossing-walls",
+    "small",
+];
+
+impl Rules {
+    pub fn variant(name: &str) -> Option<Self> {
+        let standard = Self::default();
+        match name {
+            "standard" => Some(standard),
+            "blocking" => Some(Self {
+                allow_blocking: true,
+                ..standard
+            }),
+            "straight-jumps" => Some(Self {
+                jumps: JumpRule::Straight,
+                ..standard
+            }),
+            "no-jumps" => Some(Self {
+                jumps: JumpRule::NoJumps,
+                ..standard
+            }),
+            "crossing-walls" => Some(Self {
+                allow_crossing_walls: true,
+                ..standard
+            }),
+            "small" => Some(Self {
+                board_size: 5,
+                walls_per_player: 3,
+                ..standard
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(3..=PIECE_GRID_WIDTH.min(PIECE_GRID_HEIGHT)).contains(&self.board_size)
+            || self.board_size.is_multiple_of(2)
+        {
+            return Err(format!(
+                "Board size must be odd and between 3 and {PIECE_GRID_WIDTH}, got {}",
+                self.board_size
+            ));
+        }
+        Ok(())
+    }
+
+    /// The row `player` has to reach to win.
+    pub fn goal_row(&self, player: Player) -> usize {
+        player.goal_row(self.board_size)
+    }
+
+    pub fn start_position(&self, player: Player) -> PiecePosition {
+        player.start_position(self.board_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_test() {
+        for name in VARIANT_NAMES {
+            assert!(Rules::variant(name).unwrap().validate().is_ok(), "{name}");
+        }
+        let rules = Rules::variant("small").unwrap();
+        assert_eq!(
+            rules.start_position(Player::Black),
+            PiecePosition::new(2, 4)
+        );
+        assert_eq!(Player::White.goal_row(5), rules.goal_row(Player::White));
+        assert!(Player::Black.is_maximizer(Player::Black));
+        assert!(!Player::Black.is_maximizer(Player::White));
+        assert!(
+            Rules {
+                board_size: 4,
+                ..rules
+            }
+            .validate()
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::win_score,
+    data_model::{Game, PiecePosition, Player, PlayerMove},
+    engine::legal_moves,
+    game_logic::{execute_move_unchecked, winner},
+    notation::position_to_string,
+    rules::Rules,
+};
+
+// Endgame tablebases for pawn races and the positions a single wall is left in: only the
+// pawns move, bar the one wall. One table per wall layout and walls left holds the exact
+// result of every pawn placement and side to move, found by retrograde analysis. The
+// table of a layout with a wall left needs the pawn race tables of the layouts the wall
+// can make, which are generated with it.
+//
+// A result is the number of plies to the end of the game with perfect play, odd when
+// the side to move wins and even when it loses. Draws, where neither side can force
+// a win, are `DRAW`. Tablebase files are JSON, keyed by the walls field of the position
+// string, followed by the walls left unless neither side has any:
+//
+//     { "tables": { "<walls>[ <white walls> <black walls>]": { "rules": {...}, "results": [...] } } }
+
+pub const DRAW: u16 = u16::MAX;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaceTable {
+    pub rules: Rules,
+    /// Indexed by `state_index`.
+    pub results: Vec<u16>,
+}
+
+/// The score of `game` with `result`, from White's point of view like the search's.
+pub fn result_score(game: &Game, result: u16) -> isize {
+    match result {
+        DRAW => 0,
+        plies if plies % 2 == 1 => win_score(game.player, plies as usize),
+        plies => win_score(game.player.opponent(), plies as usize),
+    }
+}
+
+fn state_index(board_size: usize, game: &Game) -> usize {
+    let square = |position: &PiecePosition| position.y() * board_size + position.x();
+    let [white, black] = &game.board.player_positions;
+    ((square(white) * board_size * board_size + square(black)) * 2) + game.player.as_index()
+}
+
+/// The walls field of the position string, identifying the wall layout, and the walls
+/// left when there are any.
+fn table_key(game: &Game) -> String {
+    let layout = position_to_string(game)
+        .rsplit(' ')
+        .next()
+        .unwrap()
+        .to_string();
+    match game.walls_left {
+        [0, 0] => layout,
+        [white, black] => format!("{layout} {white} {black}"),
+    }
+}
+
+impl RaceTable {
+    /// Solves all pawn races on the wall layout of `game`.
+    pub fn generate(game: &Game) -> Self {
+        let mut race = game.clone();
+        race.walls_left = [0, 0];
+        Self::generate_with(&race, &mut |_| unreachable!("pawn races place no walls"))
+    }
+
+    /// Solves all positions with the wall layout and the walls left of `game`, `placed`
+    /// giving the result of a position after a wall is placed, which is in another table.
+    fn generate_with(game: &Game, placed: &mut dyn FnMut(&Game) -> u16) -> Self {
+        let board_size = game.rules.board_size;
+        let squares = board_size * board_size;
+        let state_count = squares * squares * 2;
+        let mut results = vec![DRAW; state_count];
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); state_count];
+        // Results of the children after a wall is placed, which are known already
+        let mut placed_results: Vec<Vec<u16>> = vec![Vec::new(); state_count];
+        for white in 0..squares {
+            for black in (0..squares).filter(|&black| black != white) {
+                for player in [Player::White, Player::Black] {
+                    let mut game = game.clone();
+                    game.board.player_positions = [white, black]
+                        .map(|square| PiecePosition::new(square % board_size, square / board_size));
+                    game.player = player;
+                    let index = state_index(board_size, &game);
+                    match winner(&game) {
+                        // The side to move lost with the opponent's last move
+                        Some(winner) if winner == player.opponent() => results[index] = 0,
+                        Some(_) => {}
+                        None => {
+                            for player_move in legal_moves(&game) {
+                                let mut child = game.clone();
+                                execute_move_unchecked(&mut child, player, &player_move);
+                                match player_move {
+                                    PlayerMove::MovePiece(_) => {
+                                        children[index].push(state_index(board_size, &child))
+                                    }
+                                    PlayerMove::PlaceWall { .. } => {
+                                        placed_results[index].push(placed(&child))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // Wins in `plies` need a child lost in `plies - 1`, losses in `plies` need every
+        // child won, the slowest in `plies - 1`. A placed wall may lead to a result longer
+        // than any race here, so the solving goes on until past the longest of those
+        let longest_placed = placed_results
+            .iter()
+            .flatten()
+            .filter(|&&result| result != DRAW)
+            .max()
+            .copied()
+            .unwrap_or(0);
+        let mut last_change = 0;
+        for plies in 1.. {
+            if plies > last_change.max(longest_placed) + 2 {
+                break;
+            }
+            for index in 0..state_count {
+                if results[index] != DRAW
+                    || children[index].is_empty() && placed_results[index].is_empty()
+                {
+                    continue;
+                }
+                let child_results = children[index]
+                    .iter()
+                    .map(|&child| results[child])
+                    .chain(placed_results[index].iter().copied());
+                let solved = if plies % 2 == 1 {
+                    child_results.clone().any(|result| result == plies - 1)
+                } else {
+                    child_results
+                        .clone()
+                        .all(|result| result % 2 == 1 && result != DRAW)
+                        && child_results.max() == Some(plies - 1)
+                };
+                if solved {
+                    results[index] = plies;
+                    last_change = plies;
+                }
+            }
+        }
+        Self {
+            rules: game.rules,
+            results,
+        }
+    }
+
+    pub fn probe(&self, game: &Game) -> u16 {
+        self.results[state_index(self.rules.board_size, game)]
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Tablebase {
+    pub tables: HashMap<String, RaceTable>,
+}
+
+impl Tablebase {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let json =
+            std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+        serde_json::from_str(&json).map_err(|e| format!("Could not parse {path}: {e}"))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, serde_json::to_string(self).unwrap())
+            .map_err(|e| format!("Could not write {path}: {e}"))
+    }
+
+    /// Whether `game` is a position the tablebase can hold, one with at most a wall left.
+    pub fn covers(game: &Game) -> bool {
+        game.walls_left.iter().sum::<usize>() <= 1 && winner(game).is_none()
+    }
+
+    /// Generates the table for the wall layout and the walls left of `game`, with those
+    /// of the layouts its wall can make, returning false when it was already present.
+    pub fn add(&mut self, game: &Game) -> bool {
+        let key = table_key(game);
+        if self
+            .tables
+            .get(&key)
+            .is_some_and(|table| table.rules == game.rules)
+        {
+            return false;
+        }
+        let table = if game.walls_left == [0, 0] {
+            RaceTable::generate(game)
+        } else {
+            RaceTable::generate_with(game, &mut |child| {
+                self.add(child);
+                self.result(child).unwrap_or(DRAW)
+            })
+        };
+        self.tables.insert(key, table);
+        true
+    }
+
+    /// The exact result of `game`, when it is covered and its table is present.
+    pub fn probe(&self, game: &Game) -> Option<u16> {
+        if !Self::covers(game) {
+            return None;
+        }
+        let table = self.tables.get(&table_key(game))?;
+        (table.rules == game.rules).then(|| table.probe(game))
+    }
+
+    /// `probe`, or a loss when the side to move lost with the opponent's last move.
+    fn result(&self, game: &Game) -> Option<u16> {
+        match winner(game) {
+            Some(_) => Some(0),
+            None => self.probe(game),
+        }
+    }
+
+    /// The move keeping the best result for the side to move: the quickest win, the
+    /// slowest loss or a draw.
+    pub fn best_move(&self, game: &Game) -> Option<(PlayerMove, u16)> {
+        let result = self.probe(game)?;
+        let player = game.player;
+        legal_moves(game)
+            .into_iter()
+            .map(|player_move| {
+                let mut child = game.clone();
+                execute_move_unchecked(&mut child, player, &player_move);
+                let child_result = self.result(&child).unwrap_or(DRAW);
+                // Higher is better for the side to move
+                let preference = match child_result {
+                    DRAW => 0,
+                    plies if plies % 2 == 0 => isize::MAX - plies as isize,
+                    plies => isize::MIN + plies as isize,
+                };
+                (player_move, preference)
+            })
+            .max_by_key(|(_, preference)| *preference)
+            .map(|(player_move, _)| (player_move, result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::{
+        bot::{Evaluation, WHITE_LOSES_BLACK_WINS, WHITE_WINS_BLACK_LOSES, alpha_beta},
+        data_model::Player,
+        engine::{AlphaBetaEngine, Engine, SearchLimits},
+        notation::{move_to_notation, parse_position},
+        transposition::TranspositionTable,
+    };
+
+    #[test]
+    fn race_test() {
+        let mut tablebase = Tablebase::default();
+        let game = parse_position("e7 e3 0 0 w -").unwrap();
+        assert!(tablebase.probe(&game).is_none());
+        assert!(tablebase.add(&game));
+        assert!(!tablebase.add(&game));
+        // White needs 2 moves and Black 2, White moving first wins on its second move
+        assert_eq!(tablebase.probe(&game), Some(3));
+        let (best_move, _) = tablebase.best_move(&game).unwrap();
+        assert_eq!(move_to_notation(&game, &best_move), "e8");
+        let black_to_move = parse_position("e7 e3 0 0 b -").unwrap();
+        assert_eq!(tablebase.probe(&black_to_move), Some(3));
+        // Side by side with equally long paths, the side to move wins
+        let side_by_side = parse_position("e5 d5 0 0 b -").unwrap();
+        assert_eq!(
+            tablebase.probe(&side_by_side).map(|plies| plies % 2),
+            Some(1)
+        );
+        assert!(
+            tablebase
+                .probe(&parse_position("e7 e3 1 0 w -").unwrap())
+                .is_none()
+        );
+
+        let tablebase = Arc::new(tablebase);
+        let engine_move = AlphaBetaEngine::new()
+            .with_tablebase(tablebase.clone())
+            .choose_move(&black_to_move, &SearchLimits::default());
+        let search_info = engine_move.search_info.unwrap();
+        assert_eq!(
+            move_to_notation(&black_to_move, &engine_move.player_move),
+            "e2"
+        );
+        assert_eq!(search_info.score, win_score(Player::Black, 3));
+
+        // The search probes the positions it reaches, the children of the root here
+        let mut table = TranspositionTable::with_capacity(1024);
+        table.tablebase = Some(tablebase);
+        let (score, _) = alpha_beta(
+            &black_to_move,
+            1,
+            WHITE_LOSES_BLACK_WINS,
+            WHITE_WINS_BLACK_LOSES,
+            Player::Black,
+            Evaluation::PathDifference,
+            None,
+            None,
+            None,
+            &mut 0,
+            &mut table,
+        );
+        assert_eq!(score, win_score(Player::Black, 3));
+    }
+
+    #[test]
+    fn one_wall_test() {
+        let mut game = Game::with_rules(Rules::variant("small").unwrap());
+        game.board.player_positions = [PiecePosition::new(0, 1), PiecePosition::new(1, 2)];
+        let mut race = game.clone();
+        race.walls_left = [0, 0];
+        game.walls_left = [1, 0];
+        let mut tablebase = Tablebase::default();
+        assert!(Tablebase::covers(&game));
+        assert!(tablebase.add(&game));
+        // White's wall turns the race lost in 4 plies into a win in 7
+        assert_eq!(tablebase.probe(&game), Some(7));
+        assert!(tablebase.probe(&race).is_none());
+        assert!(tablebase.add(&race));
+        assert_eq!(tablebase.probe(&race), Some(4));
+        let (best_move, _) = tablebase.best_move(&game).unwrap();
+        assert!(matches!(best_move, PlayerMove::PlaceWall { .. }));
+
+        // As deep a search agrees
+        let (score, _) = alpha_beta(
+            &game,
+            7,
+            WHITE_LOSES_BLACK_WINS,
+            WHITE_WINS_BLACK_LOSES,
+            Player::White,
+            Evaluation::PathDifference,
+            None,
+            None,
+            None,
+            &mut 0,
+            &mut TranspositionTable::with_capacity(1 << 16),
+        );
+        assert_eq!(score, win_score(Player::White, 7));
+        game.walls_left = [1, 1];
+        assert!(!Tablebase::covers(&game));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    data_model::Game,
+    engine::{Engine, SearchLimits},
+    notation::{clean_line, move_to_notation, parse_notation_move, parse_position_with_moves},
+};
+
+// Position test suites guard against strength regressions. One position per
+// line, with semicolon separated operations after it:
+//
+//     # White walks into the goal row
+//     e8 e2 0 0 w -; bm e9; id finish
+//     startpos moves e2 e8 e3 e7; am e4h; id no-self-block
+//
+// "bm" lists moves the engine should pick, "am" moves it must avoid. The
+// position is either "startpos" or a position string, optionally followed by
+// "moves ...". Blank lines and lines starting with '#' are ignored.
+
+pub struct TestPosition {
+    pub id: String,
+    pub game: Game,
+    /// Expected best moves in standard notation.
+    pub best_moves: Vec<String>,
+    /// Moves to avoid in standard notation.
+    pub avoid_moves: Vec<String>,
+}
+
+pub struct TestOutcome {
+    pub id: String,
+    pub engine_move: String,
+    pub passed: bool,
+}
+
+fn parse_moves(game: &Game, moves: &str) -> Result<Vec<String>, String> {
+    moves
+        .split_whitespace()
+        .map(|move_str| {
+            parse_notation_move(game, move_str)
+                .map(|player_move| move_to_notation(game, &player_move))
+                .ok_or_else(|| format!("Could not parse move: {move_str}"))
+        })
+        .collect()
+}
+
+fn parse_test_position(line: &str, default_id: String) -> Result<TestPosition, String> {
+    let mut fields = line.split(';');
+    let game = parse_position_with_moves(fields.next().unwrap_or_default())?;
+    let mut test_position = TestPosition {
+        id: default_id,
+        game,
+        best_moves: Vec::new(),
+        avoid_moves: Vec::new(),
+    };
+    for field in fields.map(str::trim).filter(|field| !field.is_empty()) {
+        let (operation, operand) = field.split_once(char::is_whitespace).unwrap_or((field, ""));
+        match operation {
+            "bm" => test_position.best_moves = parse_moves(&test_position.game, operand)?,
+            "am" => test_position.avoid_moves = parse_moves(&test_position.game, operand)?,
+            "id" => test_position.id = operand.trim().trim_matches('"').to_string(),
+            _ => return Err(format!("Unknown operation: {operation}")),
+        }
+    }
+    if test_position.best_moves.is_empty() && test_position.avoid_moves.is_empty() {
+        return Err("Expected a bm or am operation".to_string());
+    }
+    Ok(test_position)
+}
+
+pub fn parse_test_suite(input: &str) -> Result<Vec<TestPosition>, String> {
+    input
+        .lines()
+        .map(clean_line)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            parse_test_position(line, format!("line {}", index + 1))
+                .map_err(|e| format!("Line {}: {e}", index + 1))
+        })
+        .collect()
+}
+
+pub fn run_test_position(
+    engine: &mut dyn Engine,
+    test_position: &TestPosition,
+    limits: &SearchLimits,
+) -> TestOutcome {
+    let game = &test_position.game;
+    let engine_move = move_to_notation(game, &engine.choose_move(game, limits).player_move);
+    let passed = (test_position.best_moves.is_empty()
+        || test_position.best_moves.contains(&engine_move))
+        && !test_position.avoid_moves.contains(&engine_move);
+    TestOutcome {
+        id: test_position.id.clone(),
+        engine_move,
+        passed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::AlphaBetaEngine;
+
+    #[test]
+    fn suite_test() {
+        let suite = parse_test_suite(
+            "# comment\n\
+             e8 e2 0 0 w -; bm e9; id finish\n\
+             \n\
+             startpos moves e2; am a1h e8; id avoid\n",
+        )
+        .unwrap();
+        assert_eq!(suite.len(), 2);
+        assert_eq!(suite[0].id, "finish");
+        assert_eq!(suite[1].avoid_moves, vec!["a1h", "e8"]);
+        let limits = SearchLimits {
+            depth: Some(1),
+            ..SearchLimits::default()
+        };
+        let outcome = run_test_position(&mut AlphaBetaEngine::new(), &suite[0], &limits);
+        assert!(outcome.passed);
+        assert_eq!(outcome.engine_move, "e9");
+        assert!(parse_test_suite("startpos; id nothing").is_err());
+        assert!(parse_test_suite("e2 e8 10 10 w -; bm e5").is_err());
+
+        // As saved by Notepad, with a byte order mark, CRLF line endings and tabs
+        let suite =
+            parse_test_suite("\u{feff}e8 e2 0 0 w -; bm\te9; id finish\r\n\r\nstartpos; am e2\r\n")
+                .unwrap();
+        assert_eq!(suite.len(), 2);
+        assert_eq!(suite[0].best_moves, vec!["e9"]);
+        assert_eq!(suite[1].avoid_moves, vec!["e2"]);
+    }
+}
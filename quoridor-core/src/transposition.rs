@@ -0,0 +1,453 @@
+use std::sync::Arc;
+
+use crate::{
+    all_moves::{ALL_MOVES, action_id},
+    data_model::{
+        Game, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, Player, PlayerMove, WALL_GRID_HEIGHT,
+        WallOrientation,
+    },
+    game_logic::new_position_after_move_piece_unchecked,
+    tablebase::Tablebase,
+};
+
+// Transposition table for the alpha-beta search. Entries are keyed by a Zobrist hash of
+// the position, see `position_key`, and hold the score of a search to some depth, which is either exact or a
+// bound from an alpha-beta cutoff, and the best move found.
+//
+// The table has a fixed number of slots, given in megabytes, and every position has
+// one slot. When two positions want the same slot the deeper search is kept, except
+// that entries left over from earlier searches are always replaced.
+//
+// The table outlives single searches: an engine keeps it between moves, so the
+// subtrees searched for the previous move are not searched again. Every search starts
+// a new generation, and probing an entry moves it to the current one. When moves are
+// taken back, the entries stored by the searches after the restored position can be
+// forgotten with `forget_after`, keeping older entries those searches merely used.
+//
+// Leaves are not stored in the table. Their static evaluations go to a separate
+// `EvalCache` instead, which only lives for one search, so sibling leaves reached
+// through transposed wall orders are evaluated once.
+//
+// The table also carries the `HistoryTable` of the search, the walls that caused
+// cutoffs by the side placing them, which the move ordering tries first. Unlike
+// positions the history carries over from one position to the next, so it is only
+// halved by a new search, not cleared.
+
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const EVAL_CACHE_SLOTS: usize = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The score is at least this.
+    Lower,
+    /// The score is at most this.
+    Upper,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    key: u64,
+    pub depth: usize,
+    /// From the point of view of the side to move.
+    pub score: isize,
+    pub bound: Bound,
+    pub best_move: Option<PlayerMove>,
+    /// Of the last search using the entry.
+    generation: u32,
+    /// Of the search storing the entry.
+    stored: u32,
+}
+
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Option<Entry>>,
+    generation: u32,
+    /// Cleared by `new_search`.
+    pub evals: EvalCache,
+    /// Halved by `new_search`.
+    pub history: HistoryTable,
+    /// Probed for the exact results of the positions it covers the search reaches.
+    pub tablebase: Option<Arc<Tablebase>>,
+}
+
+/// Static evaluations by position key, the newest evaluation keeping a slot.
+#[derive(Debug)]
+pub struct EvalCache {
+    slots: Vec<Option<(u64, isize)>>,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
+}
+
+// Zobrist keys: every pawn square, wall, walls left count and the side to move has a
+// random key, and a position's key is the xor of the keys of its features. A move
+// changes a few features, so the search updates the key of a position to its child's
+// with `key_after_move` instead of hashing the whole board again. The feature keys are
+// a fixed mix of the feature's number, the same in every run.
+
+const SQUARES: u64 = (PIECE_GRID_WIDTH * PIECE_GRID_HEIGHT) as u64;
+const PAWN_FEATURES: u64 = 0;
+const WALL_FEATURES: u64 = PAWN_FEATURES + 2 * SQUARES;
+const WALLS_LEFT_FEATURES: u64 = WALL_FEATURES + 2 * SQUARES;
+/// Room for the walls left counts of a player.
+const WALLS_LEFT_COUNTS: u64 = 1 << 16;
+const BLACK_TO_MOVE: u64 = WALLS_LEFT_FEATURES + 2 * WALLS_LEFT_COUNTS;
+
+/// The random key of a feature, splitmix64's output for it.
+fn feature_key(feature: u64) -> u64 {
+    let mut z = feature.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn pawn_key(player: Player, square: usize) -> u64 {
+    feature_key(PAWN_FEATURES + player.as_index() as u64 * SQUARES + square as u64)
+}
+
+fn wall_key(orientation: WallOrientation, x: usize, y: usize) -> u64 {
+    let centre = (x * WALL_GRID_HEIGHT + y) as u64;
+    feature_key(WALL_FEATURES + 2 * centre + orientation as u64)
+}
+
+fn walls_left_key(player: Player, walls_left: usize) -> u64 {
+    let count = walls_left as u64 % WALLS_LEFT_COUNTS;
+    feature_key(WALLS_LEFT_FEATURES + player.as_index() as u64 * WALLS_LEFT_COUNTS + count)
+}
+
+fn side_key(player: Player) -> u64 {
+    match player {
+        Player::White => 0,
+        Player::Black => feature_key(BLACK_TO_MOVE),
+    }
+}
+
+/// The Zobrist key of the position of `game`: the pawns, walls, walls left and the side
+/// to move.
+pub fn position_key(game: &Game) -> u64 {
+    let mut key = side_key(game.player);
+    for player in [Player::White, Player::Black] {
+        key ^= pawn_key(player, game.board.player_position(player).index);
+        key ^= walls_left_key(player, game.walls_left[player.as_index()]);
+    }
+    for (x, column) in game.board.walls.iter().enumerate() {
+        for (y, centre) in column.iter().enumerate() {
+            for orientation in centre.orientations() {
+                key ^= wall_key(orientation, x, y);
+            }
+        }
+    }
+    key
+}
+
+/// The key of the position after `player` makes `player_move` in `game`, whose key is
+/// `key`, as `execute_move_unchecked` would leave it.
+pub fn key_after_move(key: u64, game: &Game, player: Player, player_move: &PlayerMove) -> u64 {
+    let mut key = key ^ side_key(game.player) ^ side_key(player.opponent());
+    match player_move {
+        PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } => {
+            let walls_left = game.walls_left[player.as_index()];
+            key ^= wall_key(*orientation, position.x, position.y);
+            key ^= walls_left_key(player, walls_left) ^ walls_left_key(player, walls_left - 1);
+        }
+        PlayerMove::MovePiece(move_piece) => {
+            let from = game.board.player_position(player);
+            let to = new_position_after_move_piece_unchecked(
+                from,
+                move_piece,
+                game.board.player_position(player.opponent()),
+            );
+            key ^= pawn_key(player, from.index) ^ pawn_key(player, to.index);
+        }
+    }
+    key
+}
+
+impl TranspositionTable {
+    /// A table taking about `hash_mb` megabytes, with at least one slot.
+    pub fn new(hash_mb: usize) -> Self {
+        Self::with_capacity(Self::slots(hash_mb))
+    }
+
+    /// A table for a single search `depth` plies deep, no larger than the default one.
+    /// With fewer than 256 moves in a position, shallow searches store fewer positions
+    /// than that many per ply, so they don't zero megabytes they never use.
+    pub fn for_depth(depth: usize) -> Self {
+        let positions = 256usize.saturating_pow(depth as u32);
+        Self::with_capacity(positions.min(Self::slots(DEFAULT_HASH_MB)))
+    }
+
+    /// Slots of a table taking about `hash_mb` megabytes.
+    pub fn slots(hash_mb: usize) -> usize {
+        hash_mb * 1024 * 1024 / size_of::<Option<Entry>>()
+    }
+
+    /// A table of `slots` slots, at least one.
+    pub fn with_capacity(slots: usize) -> Self {
+        Self {
+            slots: vec![None; slots.max(1)],
+            generation: 0,
+            evals: EvalCache::new(EVAL_CACHE_SLOTS),
+            history: HistoryTable::default(),
+            tablebase: None,
+        }
+    }
+
+    /// Starts a new generation, making the entries of earlier searches replaceable.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        self.evals.clear();
+        self.history.age();
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+        self.evals.clear();
+        self.history = HistoryTable::default();
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Drops the entries stored by searches after `generation`.
+    pub fn forget_after(&mut self, generation: u32) {
+        for slot in &mut self.slots {
+            if slot.as_ref().is_some_and(|entry| entry.stored > generation) {
+                *slot = None;
+            }
+        }
+        self.evals.clear();
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Permille of the first thousand slots used by the current generation, like UCI's hashfull.
+    pub fn hashfull(&self) -> usize {
+        let sample = &self.slots[..self.slots.len().min(1000)];
+        let used = sample
+            .iter()
+            .flatten()
+            .filter(|entry| entry.generation == self.generation)
+            .count();
+        used * 1000 / sample.len()
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key % self.slots.len() as u64) as usize
+    }
+
+    /// Looks up a position, marking its entry as used by the current generation.
+    pub fn probe(&mut self, key: u64) -> Option<&Entry> {
+        let generation = self.generation;
+        let slot = self.slot(key);
+        let entry = self.slots[slot].as_mut().filter(|entry| entry.key == key)?;
+        entry.generation = generation;
+        Some(entry)
+    }
+
+    /// Stores a search result unless the slot holds a deeper result of the current generation.
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: usize,
+        score: isize,
+        bound: Bound,
+        best_move: Option<PlayerMove>,
+    ) {
+        let slot = self.slot(key);
+        if self.slots[slot]
+            .as_ref()
+            .is_some_and(|entry| entry.generation == self.generation && entry.depth > depth)
+        {
+            return;
+        }
+        self.slots[slot] = Some(Entry {
+            key,
+            depth,
+            score,
+            bound,
+            best_move,
+            generation: self.generation,
+            stored: self.generation,
+        });
+    }
+}
+
+/// Cutoffs by move, see `HistoryTable::record`.
+#[derive(Debug, Clone)]
+pub struct HistoryTable {
+    /// By `Player::as_index` and `action_id`.
+    scores: [[u64; ALL_MOVES.len()]; 2],
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self {
+            scores: [[0; ALL_MOVES.len()]; 2],
+        }
+    }
+}
+
+impl HistoryTable {
+    /// Counts a cutoff of `player_move` at `depth`, deeper cutoffs weighing more as they
+    /// save more of the search.
+    pub fn record(&mut self, player: Player, player_move: &PlayerMove, depth: usize) {
+        if let Some(id) = action_id(player_move) {
+            self.scores[player.as_index()][id] += (depth * depth) as u64;
+        }
+    }
+
+    pub fn score(&self, player: Player, player_move: &PlayerMove) -> u64 {
+        action_id(player_move).map_or(0, |id| self.scores[player.as_index()][id])
+    }
+
+    /// Halves the scores, so the cutoffs of the latest searches count most.
+    pub fn age(&mut self) {
+        for score in self.scores.iter_mut().flatten() {
+            *score /= 2;
+        }
+    }
+}
+
+impl EvalCache {
+    pub fn new(slots: usize) -> Self {
+        Self {
+            slots: vec![None; slots.max(1)],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        (key % self.slots.len() as u64) as usize
+    }
+
+    pub fn get(&self, key: u64) -> Option<isize> {
+        self.slots[self.slot(key)]
+            .filter(|(slot_key, _)| *slot_key == key)
+            .map(|(_, score)| score)
+    }
+
+    /// The cached evaluation of `key`, calling `evaluate` when there is none.
+    pub fn get_or_insert_with(&mut self, key: u64, evaluate: impl FnOnce() -> isize) -> isize {
+        self.get(key).unwrap_or_else(|| {
+            let score = evaluate();
+            let slot = self.slot(key);
+            self.slots[slot] = Some((key, score));
+            score
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        engine::legal_moves,
+        game_logic::{execute_move_unchecked, winner},
+        notation::{parse_notation_move, parse_position},
+    };
+
+    #[test]
+    fn table_test() {
+        let mut table = TranspositionTable::new(1);
+        assert!(table.capacity() > 1000);
+        assert_ne!(
+            position_key(&Game::new()),
+            position_key(&parse_position("e1 e9 10 10 b -").unwrap())
+        );
+        let key = 5;
+        table.store(key, 3, 1, Bound::Exact, None);
+        table.store(key, 2, 5, Bound::Lower, None);
+        assert_eq!(table.probe(key).unwrap().score, 1);
+
+        // A position sharing the slot replaces the entry only when searched as deep
+        let other_key = key + table.capacity() as u64;
+        table.store(other_key, 2, 7, Bound::Exact, None);
+        assert!(table.probe(other_key).is_none());
+        table.store(other_key, 3, 7, Bound::Exact, None);
+        assert!(table.probe(key).is_none());
+        assert_eq!(table.probe(other_key).unwrap().score, 7);
+
+        // Entries of earlier searches make way for any depth
+        table.new_search();
+        table.store(key, 1, 1, Bound::Exact, None);
+        assert_eq!(table.probe(key).unwrap().depth, 1);
+
+        assert_eq!(table.evals.get_or_insert_with(key, || 3), 3);
+        assert_eq!(table.evals.get_or_insert_with(key, || 4), 3);
+        table.new_search();
+        assert_eq!(table.evals.get(key), None);
+
+        let mut full = TranspositionTable::new(0);
+        assert_eq!(full.capacity(), 1);
+        assert_eq!(full.hashfull(), 0);
+        full.store(key, 1, 1, Bound::Exact, None);
+        assert_eq!(full.hashfull(), 1000);
+        full.new_search();
+        assert_eq!(full.hashfull(), 0);
+
+        // Tables for shallow searches are small, deep ones get the default size
+        assert_eq!(TranspositionTable::for_depth(1).capacity(), 256);
+        assert_eq!(
+            TranspositionTable::for_depth(40).capacity(),
+            TranspositionTable::default().capacity()
+        );
+
+        let game = parse_position("e1 e9 10 10 w -").unwrap();
+        let wall = parse_notation_move(&game, "e3h").unwrap();
+        table.history.record(Player::White, &wall, 3);
+        assert_eq!(table.history.score(Player::White, &wall), 9);
+        assert_eq!(table.history.score(Player::Black, &wall), 0);
+        table.new_search();
+        assert_eq!(table.history.score(Player::White, &wall), 4);
+
+        // Forgetting later searches keeps the entries they only probed
+        let mut table = TranspositionTable::new(1);
+        table.store(key, 2, 1, Bound::Exact, None);
+        let generation = table.generation();
+        table.new_search();
+        table.probe(key);
+        table.store(key + 1, 2, 3, Bound::Exact, None);
+        table.forget_after(generation);
+        assert_eq!(table.probe(key).unwrap().score, 1);
+        assert!(table.probe(key + 1).is_none());
+    }
+
+    #[test]
+    fn zobrist_test() {
+        // Keys kept up to date move by move match the keys of the positions
+        let mut game = Game::new();
+        let mut key = position_key(&game);
+        for ply in 0..40 {
+            let moves = legal_moves(&game);
+            let (player, player_move) = (game.player, &moves[ply * 7 % moves.len()]);
+            key = key_after_move(key, &game, player, player_move);
+            execute_move_unchecked(&mut game, player, player_move);
+            assert_eq!(key, position_key(&game));
+            if winner(&game).is_some() {
+                break;
+            }
+        }
+
+        // Transposed wall orders reach the same key
+        let walls = |first: &str, second: &str| {
+            let game = parse_position(&format!("e1 e9 10 10 w {first},{second}")).unwrap();
+            position_key(&game)
+        };
+        assert_eq!(walls("a1h", "d4v"), walls("d4v", "a1h"));
+        assert_ne!(walls("a1h", "d4v"), walls("a1v", "d4h"));
+    }
+}
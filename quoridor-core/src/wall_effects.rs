@@ -0,0 +1,202 @@
+use crate::{
+    a_star::a_star,
+    data_model::{Game, Player, PlayerMove, WallOrientation, WallPosition, Walls},
+    game_logic::{execute_move_unchecked, is_move_legal},
+};
+
+// The effect of every wall slot on both shortest paths at once, for study overlays.
+// A slot is legal when the side to move may place a wall there. For legal walls the
+// effect is how many steps longer each player's shortest path gets, the gain being
+// the opponent's increase minus the mover's: positive walls hurt the opponent more.
+
+/// A wall slot of the board and what a wall there does for the side to move.
+pub struct WallEffect {
+    pub wall: PlayerMove,
+    pub legal: bool,
+    /// Steps added to the shortest path of the side to move, None when illegal or blocked.
+    pub own_increase: Option<usize>,
+    /// Steps added to the opponent's shortest path, None when illegal or blocked.
+    pub opponent_increase: Option<usize>,
+}
+
+impl WallEffect {
+    /// Steps gained against the opponent, positive when the wall hurts them more.
+    pub fn gain(&self) -> Option<isize> {
+        Some(self.opponent_increase? as isize - self.own_increase? as isize)
+    }
+}
+
+/// The effects of every wall slot of the board, horizontal walls first, by column.
+pub fn wall_effects(game: &Game) -> Vec<WallEffect> {
+    let player = game.player;
+    let path_length = |game: &Game, player| a_star(game, player).map(|path| path.len());
+    let own_length = path_length(game, player);
+    let opponent_length = path_length(game, player.opponent());
+    let wall_grid_size = game.rules.board_size - 1;
+    let mut effects = Vec::with_capacity(2 * wall_grid_size * wall_grid_size);
+    for orientation in [WallOrientation::Horizontal, WallOrientation::Vertical] {
+        for x in 0..wall_grid_size {
+            for y in 0..wall_grid_size {
+                let wall = PlayerMove::PlaceWall {
+                    orientation,
+                    position: WallPosition { x, y },
+                };
+                let legal = is_move_legal(game, player, &wall);
+                let (own_increase, opponent_increase) = if legal {
+                    let mut child = game.clone();
+                    execute_move_unchecked(&mut child, player, &wall);
+                    let increase = |before: Option<usize>, after: Option<usize>| {
+                        Some(after?.saturating_sub(before?))
+                    };
+                    (
+                        increase(own_length, path_length(&child, player)),
+                        increase(opponent_length, path_length(&child, player.opponent())),
+                    )
+                } else {
+                    (None, None)
+                };
+                effects.push(WallEffect {
+                    wall,
+                    legal,
+                    own_increase,
+                    opponent_increase,
+                });
+            }
+        }
+    }
+    effects
+}
+
+/// How far from a wall, in wall grid centres, the opponent's counter walls are tried by
+/// `countered_walls`. Walls further away do not combine with it.
+pub const COUNTER_WALL_REACH: usize = 2;
+
+/// The legal walls lengthening the path of the side to move by more than `margin` steps
+/// beyond the opponent's, as a wall grid. The search skips them, see `alpha_beta`.
+pub fn self_harming_walls(effects: &[WallEffect], margin: isize) -> Walls {
+    wall_grid(effects, |effect| {
+        effect.gain().is_some_and(|gain| gain < -margin)
+    })
+}
+
+/// The gaining walls the opponent can answer with a wall next to them, gaining back at
+/// least as much or walling the side to move in, as a wall grid. A two ply check the
+/// search orders its moves by, see `alpha_beta`.
+pub fn countered_walls(game: &Game, effects: &[WallEffect]) -> Walls {
+    wall_grid(effects, |effect| is_countered(game, effect))
+}
+
+fn wall_grid(effects: &[WallEffect], include: impl Fn(&WallEffect) -> bool) -> Walls {
+    let mut walls = Walls::default();
+    for effect in effects {
+        if let PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } = &effect.wall
+            && include(effect)
+        {
+            walls[position.x][position.y].set(*orientation, true);
+        }
+    }
+    walls
+}
+
+fn is_countered(game: &Game, effect: &WallEffect) -> bool {
+    let (Some(gain), PlayerMove::PlaceWall { position, .. }) =
+        (effect.gain().filter(|&gain| gain > 0), &effect.wall)
+    else {
+        return false;
+    };
+    let mut child = game.clone();
+    execute_move_unchecked(&mut child, game.player, &effect.wall);
+    let wall_grid_size = game.rules.board_size - 1;
+    let reach = |centre: usize| {
+        centre.saturating_sub(COUNTER_WALL_REACH)
+            ..(centre + COUNTER_WALL_REACH + 1).min(wall_grid_size)
+    };
+    for orientation in [WallOrientation::Horizontal, WallOrientation::Vertical] {
+        for x in reach(position.x) {
+            for y in reach(position.y) {
+                let counter = PlayerMove::PlaceWall {
+                    orientation,
+                    position: WallPosition { x, y },
+                };
+                if !is_move_legal(&child, game.player.opponent(), &counter) {
+                    continue;
+                }
+                // Only counter walls the wall made better count, the others the opponent
+                // could have placed anyway
+                let countered = match (
+                    counter_gain(&child, game.player, &counter),
+                    counter_gain(game, game.player, &counter),
+                ) {
+                    (None, without) => without.is_some(),
+                    (Some(with), without) => {
+                        with >= gain && without.is_some_and(|without| with > without)
+                    }
+                };
+                if countered {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Steps the opponent of `player` gains with the wall `counter`, None when it walls
+/// `player` in.
+fn counter_gain(game: &Game, player: Player, counter: &PlayerMove) -> Option<isize> {
+    let path_length = |game: &Game, player| a_star(game, player).map(|path| path.len());
+    let (own_length, opponent_length) = (
+        path_length(game, player)?,
+        path_length(game, player.opponent())?,
+    );
+    let mut answered = game.clone();
+    execute_move_unchecked(&mut answered, player.opponent(), counter);
+    let own_after = path_length(&answered, player)?;
+    let opponent_after = path_length(&answered, player.opponent()).unwrap_or(opponent_length);
+    Some(
+        own_after.saturating_sub(own_length) as isize
+            - opponent_after.saturating_sub(opponent_length) as isize,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_position;
+
+    #[test]
+    fn wall_effects_test() {
+        let game = parse_position("e2 e8 10 10 w d8h").unwrap();
+        let effects = wall_effects(&game);
+        assert_eq!(effects.len(), 2 * 8 * 8);
+        let effect = |notation: &str| {
+            effects
+                .iter()
+                .find(|effect| effect.wall.to_string() == notation)
+                .unwrap()
+        };
+        // Overlapping the wall on the board
+        assert!(!effect("h47").legal);
+        assert_eq!(effect("h47").gain(), None);
+        // Across Black's last step only
+        assert_eq!(effect("h30").own_increase, Some(0));
+        assert_eq!(effect("h30").gain(), Some(1));
+        // Across White's way around the wall on the board
+        assert_eq!(effect("h57").gain(), Some(-1));
+        assert_eq!(effect("h00").gain(), Some(0));
+
+        let self_harming = self_harming_walls(&effects, 0);
+        assert!(self_harming[5][7].horizontal);
+        assert!(!self_harming[3][0].horizontal);
+        assert!(!self_harming_walls(&effects, 1)[5][7].horizontal);
+        // Across both players' paths, White then walks around it by the f file, where
+        // Black has a wall gaining a step it did not have before
+        let countered = countered_walls(&game, &effects);
+        assert!(countered[3][3].horizontal);
+        assert!(!countered[3][0].horizontal);
+        assert!(!countered[0][0].horizontal);
+    }
+}
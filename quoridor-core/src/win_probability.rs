@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::{decided, heuristic_board_score},
+    data_model::{Game, Player},
+    game_logic::winner,
+    game_record::GameRecord,
+};
+
+// Logistic model turning engine scores into the probability that White wins:
+//
+//     P(White wins) = 1 / (1 + exp(-(bias + path_weight * paths + wall_weight * walls + to_move_weight * to_move)))
+//
+// `paths` is the engine score (Black's shortest path minus White's), `walls` is
+// White's walls left minus Black's and `to_move` is 1 with White to move, -1 otherwise.
+// The weights are fitted from finished games with `fit`.
+
+/// Inputs of the model for one position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Features {
+    pub path_differential: f64,
+    pub wall_differential: f64,
+    pub to_move: f64,
+}
+
+impl Features {
+    /// Features of `game` with `score` being a non-decisive engine score for it.
+    pub fn new(game: &Game, score: isize) -> Self {
+        Self {
+            path_differential: score as f64,
+            wall_differential: game.walls_left[Player::White.as_index()] as f64
+                - game.walls_left[Player::Black.as_index()] as f64,
+            to_move: if game.player.is_maximizer(Player::White) {
+                1.0
+            } else {
+                -1.0
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WinProbabilityModel {
+    pub bias: f64,
+    pub path_weight: f64,
+    pub wall_weight: f64,
+    pub to_move_weight: f64,
+}
+
+impl Default for WinProbabilityModel {
+    /// Rough weights for when no fitted model is available.
+    fn default() -> Self {
+        Self {
+            bias: 0.0,
+            path_weight: 0.5,
+            wall_weight: 0.2,
+            to_move_weight: 0.25,
+        }
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl WinProbabilityModel {
+    pub fn probability(&self, features: &Features) -> f64 {
+        sigmoid(
+            self.bias
+                + self.path_weight * features.path_differential
+                + self.wall_weight * features.wall_differential
+                + self.to_move_weight * features.to_move,
+        )
+    }
+
+    /// The probability that White wins `game`, given an engine score for it.
+    pub fn white_win_probability(&self, game: &Game, score: isize) -> f64 {
+        match decided(score) {
+            Some((Player::White, _)) => 1.0,
+            Some((Player::Black, _)) => 0.0,
+            None => self.probability(&Features::new(game, score)),
+        }
+    }
+
+    /// The probability that White wins `game`, judged by the position without searching.
+    pub fn position_probability(&self, game: &Game) -> f64 {
+        self.white_win_probability(game, heuristic_board_score(game))
+    }
+
+    /// Fits the model with gradient descent on the log loss, each sample being the
+    /// features of a position and 1 when White went on to win, 0 otherwise.
+    pub fn fit(samples: &[(Features, f64)], epochs: usize, learning_rate: f64) -> Self {
+        let mut model = Self {
+            bias: 0.0,
+            path_weight: 0.0,
+            wall_weight: 0.0,
+            to_move_weight: 0.0,
+        };
+        if samples.is_empty() {
+            return model;
+        }
+        let step = learning_rate / samples.len() as f64;
+        for _ in 0..epochs {
+            let mut gradient = [0.0; 4];
+            for (features, outcome) in samples {
+                let error = model.probability(features) - outcome;
+                gradient[0] += error;
+                gradient[1] += error * features.path_differential;
+                gradient[2] += error * features.wall_differential;
+                gradient[3] += error * features.to_move;
+            }
+            model.bias -= step * gradient[0];
+            model.path_weight -= step * gradient[1];
+            model.wall_weight -= step * gradient[2];
+            model.to_move_weight -= step * gradient[3];
+        }
+        model
+    }
+}
+
+/// Training samples from every undecided position of a game that ended with a winner.
+pub fn samples_from_record(record: &GameRecord) -> Vec<(Features, f64)> {
+    let game_states = record.game_states();
+    let outcome = match game_states.last().and_then(winner) {
+        Some(Player::White) => 1.0,
+        Some(Player::Black) => 0.0,
+        None => return Vec::new(),
+    };
+    game_states
+        .iter()
+        .filter(|game| winner(game).is_none())
+        .map(|game| (Features::new(game, heuristic_board_score(game)), outcome))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::win_score;
+
+    #[test]
+    fn fit_test() {
+        let features = |path_differential| Features {
+            path_differential,
+            wall_differential: 0.0,
+            to_move: 1.0,
+        };
+        // White wins whenever it is ahead in the race, and half the even games
+        let samples: Vec<_> = (-4..=4)
+            .flat_map(|paths| {
+                let outcome = if paths > 0 { 1.0 } else { 0.0 };
+                let even_outcome = if paths == 0 { 1.0 } else { outcome };
+                [
+                    (features(paths as f64), outcome),
+                    (features(paths as f64), even_outcome),
+                ]
+            })
+            .collect();
+        let model = WinProbabilityModel::fit(&samples, 2000, 0.5);
+        assert!(model.path_weight > 0.0);
+        assert!((model.probability(&features(0.0)) - 0.5).abs() < 0.05);
+        assert!(model.probability(&features(3.0)) > 0.9);
+        let game = Game::new();
+        assert_eq!(
+            model.white_win_probability(&game, win_score(Player::White, 7)),
+            1.0
+        );
+        assert!(samples_from_record(&GameRecord::default()).is_empty());
+    }
+}
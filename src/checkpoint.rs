@@ -0,0 +1,222 @@
+// Model checkpoints. A checkpoint `<name>` in a run directory is the network weights,
+// `<name>.mpk`, next to a manifest, `<name>.json`, recording how it was produced:
+//
+//     {
+//       "name": "iter-0003",
+//       "parent": "iter-0002",
+//       "git_revision": "4779775...",
+//       "created_unix": 1760000000,
+//       "training_config": { ... },
+//       "shards": ["self-play-0003.qshd"],
+//       "evaluations": [{ "opponent": "iter-0002", "wins": 30, "losses": 18, "draws": 2 }]
+//     }
+//
+// Following the parents gives the whole lineage of a network.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::nn_bot::QuoridorNet;
+
+/// Result of evaluation games of the checkpoint against an opponent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvaluationResult {
+    pub opponent: String,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointManifest {
+    pub name: String,
+    /// The checkpoint training started from, None for freshly initialised networks.
+    pub parent: Option<String>,
+    /// None when not built from a git checkout.
+    pub git_revision: Option<String>,
+    pub created_unix: u64,
+    /// Whatever configuration the producer of the checkpoint used.
+    #[serde(default)]
+    pub training_config: serde_json::Value,
+    /// Training shards the network was trained on.
+    #[serde(default)]
+    pub shards: Vec<String>,
+    #[serde(default)]
+    pub evaluations: Vec<EvaluationResult>,
+}
+
+impl CheckpointManifest {
+    /// A manifest for a checkpoint created now, from the current git revision.
+    pub fn new(name: &str, parent: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+            git_revision: git_revision(),
+            created_unix: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
+            training_config: serde_json::Value::Null,
+            shards: Vec::new(),
+            evaluations: Vec::new(),
+        }
+    }
+}
+
+/// The revision of the checkout the binary runs in, marked dirty with local changes.
+pub fn git_revision() -> Option<String> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let revision = git(&["rev-parse", "HEAD"])?;
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    Some(if dirty {
+        format!("{revision}-dirty")
+    } else {
+        revision
+    })
+}
+
+fn manifest_path(run_dir: &Path, name: &str) -> PathBuf {
+    run_dir.join(format!("{name}.json"))
+}
+
+fn weights_path(run_dir: &Path, name: &str) -> PathBuf {
+    run_dir.join(name)
+}
+
+pub fn save_manifest(run_dir: &Path, manifest: &CheckpointManifest) -> Result<(), String> {
+    let path = manifest_path(run_dir, &manifest.name);
+    std::fs::write(&path, serde_json::to_string_pretty(manifest).unwrap())
+        .map_err(|e| format!("Could not write {}: {e}", path.display()))
+}
+
+pub fn load_manifest(run_dir: &Path, name: &str) -> Result<CheckpointManifest, String> {
+    let path = manifest_path(run_dir, name);
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    serde_json::from_str(&json).map_err(|e| format!("Could not parse {}: {e}", path.display()))
+}
+
+/// Writes the weights and the manifest of a checkpoint, creating the run directory.
+pub fn save_checkpoint(
+    run_dir: &Path,
+    network: &QuoridorNet,
+    manifest: &CheckpointManifest,
+) -> Result<(), String> {
+    std::fs::create_dir_all(run_dir)
+        .map_err(|e| format!("Could not create {}: {e}", run_dir.display()))?;
+    network.save(&weights_path(run_dir, &manifest.name))?;
+    save_manifest(run_dir, manifest)
+}
+
+pub fn load_checkpoint(
+    run_dir: &Path,
+    name: &str,
+) -> Result<(QuoridorNet, CheckpointManifest), String> {
+    let manifest = load_manifest(run_dir, name)?;
+    let network = QuoridorNet::load(&weights_path(run_dir, name))?;
+    Ok((network, manifest))
+}
+
+/// The manifests of all checkpoints in the run directory, oldest first.
+pub fn list_checkpoints(run_dir: &Path) -> Result<Vec<CheckpointManifest>, String> {
+    let entries = std::fs::read_dir(run_dir)
+        .map_err(|e| format!("Could not read {}: {e}", run_dir.display()))?;
+    let mut manifests = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+            && let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+            // Other JSON files of the run, like training.json, have no weights
+            && run_dir.join(format!("{name}.mpk")).exists()
+        {
+            manifests.push(load_manifest(run_dir, name)?);
+        }
+    }
+    manifests.sort_by(|a, b| {
+        a.created_unix
+            .cmp(&b.created_unix)
+            .then(a.name.cmp(&b.name))
+    });
+    Ok(manifests)
+}
+
+/// The checkpoint followed by its parent, grandparent and so on.
+pub fn lineage(run_dir: &Path, name: &str) -> Result<Vec<CheckpointManifest>, String> {
+    let mut lineage = vec![load_manifest(run_dir, name)?];
+    while let Some(parent) = lineage.last().unwrap().parent.clone() {
+        if lineage.iter().any(|manifest| manifest.name == parent) {
+            return Err(format!("Checkpoint {parent} is its own ancestor"));
+        }
+        lineage.push(load_manifest(run_dir, &parent)?);
+    }
+    Ok(lineage)
+}
+
+/// Records evaluation games played by a saved checkpoint.
+pub fn add_evaluation(run_dir: &Path, name: &str, result: EvaluationResult) -> Result<(), String> {
+    let mut manifest = load_manifest(run_dir, name)?;
+    manifest.evaluations.push(result);
+    save_manifest(run_dir, &manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lineage_test() {
+        let run_dir = std::env::temp_dir().join(format!("checkpoint-test-{}", std::process::id()));
+        let network = QuoridorNet::new();
+        let mut first = CheckpointManifest::new("iter-0000", None);
+        first.training_config = serde_json::json!({ "learning_rate": 0.001 });
+        save_checkpoint(&run_dir, &network, &first).unwrap();
+        let mut second = CheckpointManifest::new("iter-0001", Some("iter-0000"));
+        second.created_unix = first.created_unix + 1;
+        second.shards.push("self-play-0001.qshd".to_string());
+        save_checkpoint(&run_dir, &network, &second).unwrap();
+        add_evaluation(
+            &run_dir,
+            "iter-0001",
+            EvaluationResult {
+                opponent: "iter-0000".to_string(),
+                wins: 3,
+                losses: 1,
+                draws: 0,
+            },
+        )
+        .unwrap();
+
+        let (_, manifest) = load_checkpoint(&run_dir, "iter-0001").unwrap();
+        assert_eq!(manifest.evaluations[0].wins, 3);
+        let names = |manifests: Vec<CheckpointManifest>| -> Vec<String> {
+            manifests
+                .into_iter()
+                .map(|manifest| manifest.name)
+                .collect()
+        };
+        assert_eq!(
+            names(lineage(&run_dir, "iter-0001").unwrap()),
+            ["iter-0001", "iter-0000"]
+        );
+        assert_eq!(
+            names(list_checkpoints(&run_dir).unwrap()),
+            ["iter-0000", "iter-0001"]
+        );
+        assert_eq!(
+            lineage(&run_dir, "iter-0000").unwrap()[0].training_config["learning_rate"],
+            0.001
+        );
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+}
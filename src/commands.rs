@@ -1,56 +1,255 @@
-use std::{collections::HashMap};
+use std::{cell::RefCell, collections::HashMap, io::Write};
 
 use clap::Parser;
 
-use crate::{
-    bot::{best_move_alpha_beta, best_move_alpha_beta_iterative_deepening},
-    data_model::{Direction, Game, MovePiece, Player, PlayerMove, WallOrientation, WallPosition},
-    game_logic::{execute_move_unchecked, is_move_legal},
-    nn_bot::{self, QuoridorNet}
+use quoridor_core::{
+    accuracy::{DECISIVE_LOSS, GameReport, KEY_MOMENTS, analyse_game},
+    analysis::line_to_notation,
+    arena::play_match_with,
+    batch_analysis::{BatchGame, analyse_games, annotate, write_summary},
+    bot::{Blunder, MoveExplanation, analyze, check_blunder, decided, explain},
+    broadcast::Broadcast,
+    data_model::{Game, Player, PlayerMove},
+    engine::{AlphaBetaEngine, Clock, Engine, EngineMove, GameEvent, SearchLimits, check_playable},
+    engine_match::engine_match,
+    eval_graph::{annotate_evals, evals, sparkline},
+    explorer::OpeningExplorer,
+    game_record::{GameRecord, MoveAnnotation, RecordedMove, ThinkTime},
+    game_logic::{execute_move_unchecked, is_move_legal, winner},
+    html_report::html_report,
+    leaderboard::{Leaderboard, LeaderboardGame, today},
+    locale::{LOCALE_CODES, Locale, Message},
+    notation::{self, WALL_NOTATION_NAMES, WallNotation, convert_wall, parse_player_move},
+    opponent_model::{OpponentProfiles, profiles_path},
+    puzzle::{Puzzle, mine_puzzles, parse_puzzles},
+    render_board::{BoardStyle, render_board_with},
+    tablebase::Tablebase,
+    rules::{Rules, VARIANT_NAMES},
+    test_suite::{parse_test_suite, run_test_position},
+    win_probability::{WinProbabilityModel, samples_from_record},
 };
 
-use std::{fmt::Display, time::Duration};
+use std::time::{Duration, Instant};
+
+use crate::shutdown::Shutdown;
+
+/// Search depth of the blunder check, shallow enough to run after every move.
+const BLUNDER_CHECK_DEPTH: usize = 2;
+
+pub const DEFAULT_LEADERBOARD: &str = "leaderboard.jsonl";
+pub const DEFAULT_AUTOSAVE: &str = "autosave.txt";
 
 #[derive(clap_derive::Subcommand, Debug)]
 pub enum AuxCommand {
     Reset,
     BotMove {
-        #[arg(short, long, group = "time_control")]
-        depth: Option<usize>,
+        #[command(flatten)]
+        limits: LimitArgs,
 
-        #[arg(short, long, group = "time_control")]
-        seconds: Option<u64>,
+        #[arg(long)]
+        json: bool,
     },
     PlayBotMove {
-        #[arg(short, long, group = "time_control")]
-        depth: Option<usize>,
-
-        #[arg(short, long, group = "time_control")]
-        seconds: Option<u64>,
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    PlayEngineMove {
+        #[command(flatten)]
+        limits: LimitArgs,
     },
-    PlayNNMove {
-        #[arg(default_value_t = 0.0)]
-        temperature: f32,
+    /// Suggests a move and explains it.
+    Hint {
+        #[command(flatten)]
+        limits: LimitArgs,
     },
+    /// Takes back moves, in games with a clock by asking the opponent first.
     Undo {
         #[arg(default_value_t = 1)]
         moves: usize,
     },
+    /// Accepts the opponent's takeback request.
+    Accept,
+    /// Declines the opponent's takeback request.
+    Decline,
+    /// Plays moves taken back by undo again.
+    Redo {
+        #[arg(default_value_t = 1)]
+        moves: usize,
+    },
     Eval {
         #[arg()]
         move_to_evaluate: Option<String>,
 
-        #[arg(short, long, group = "time_control")]
-        depth: Option<usize>,
+        #[command(flatten)]
+        limits: LimitArgs,
+
+        #[arg(long)]
+        json: bool,
+    },
+    /// Shows the best moves of the position, each with its score and principal variation.
+    Analyze {
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
 
-        #[arg(short, long, group = "time_control")]
-        seconds: Option<u64>,
+        /// How many moves to show.
+        #[arg(long, default_value_t = 3)]
+        multipv: usize,
     },
     Export,
     Import {
         #[arg()]
         moves_string: String,
     },
+    ImportLog {
+        #[arg()]
+        file: std::path::PathBuf,
+    },
+    History,
+    /// Writes a wall in every coordinate scheme, to check where a wall from another tool goes.
+    ConvertWall {
+        #[arg()]
+        wall: String,
+
+        /// The scheme the wall is written in, the session's when not given.
+        #[arg(long, value_parser = parse_wall_notation)]
+        from: Option<WallNotation>,
+    },
+    Match {
+        #[arg(default_value_t = 2)]
+        games: usize,
+
+        #[command(flatten)]
+        limits: LimitArgs,
+
+        #[arg(long, default_value_t = 200)]
+        max_plies: usize,
+
+        /// Appends the game records, one per line, to this file after every game.
+        #[arg(long)]
+        save: Option<std::path::PathBuf>,
+
+        /// Appends every move with its eval to this stream file as the games are played,
+        /// for other tools to tail, see broadcast.rs for the format.
+        #[arg(long)]
+        broadcast: Option<std::path::PathBuf>,
+
+        /// Writes the broadcast stream to stdout instead.
+        #[arg(long)]
+        follow: bool,
+    },
+    TestSuite {
+        #[arg()]
+        file: std::path::PathBuf,
+
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
+    /// Searches game records, one per line, for puzzles and writes them to `output`.
+    MinePuzzles {
+        #[arg()]
+        records: std::path::PathBuf,
+
+        #[arg()]
+        output: std::path::PathBuf,
+
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+
+        /// How many steps better than the second best move the solution has to be.
+        #[arg(long, default_value_t = 3)]
+        min_gap: isize,
+    },
+    /// Plays through the puzzles in a file, grading each played move.
+    Puzzles {
+        #[arg()]
+        file: std::path::PathBuf,
+    },
+    /// Re-analyses every move of the game so far and reports each player's accuracy and
+    /// the key moments of the game.
+    Report {
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+
+        /// How many key moments to show.
+        #[arg(short, long, default_value_t = KEY_MOMENTS)]
+        key_moments: usize,
+    },
+    /// Ranks every move of the game so far among the engine's choices and shows how often
+    /// each player matched its top moves, overall and by phase.
+    EngineMatch {
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+    },
+    /// Re-analyses the game so far and writes it with the analysis to `output` as a
+    /// self-contained HTML page, with an eval graph, the move list and diagrams.
+    ExportHtml {
+        #[arg()]
+        output: std::path::PathBuf,
+
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+    },
+    /// Evaluates every move of the game so far, storing the evals in the record, and
+    /// shows them as a sparkline.
+    Evals {
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+    },
+    /// Shows the moves played from the current position in a game database, loading the
+    /// database from a directory of game record files, one record per line, when given.
+    Explore {
+        #[arg()]
+        directory: Option<std::path::PathBuf>,
+
+        /// How many plies of each game to include.
+        #[arg(long, default_value_t = 30)]
+        max_plies: usize,
+    },
+    /// Generates endgame tablebases for the wall layout of the current position, or of
+    /// every position in game records, one per line, where at most a wall is left.
+    /// New tables are added to `output` when it exists.
+    GenerateTablebase {
+        #[arg()]
+        output: std::path::PathBuf,
+
+        #[arg(long)]
+        records: Option<std::path::PathBuf>,
+    },
+    /// Fits the win probability model to game records, one per line, and writes it to `output`.
+    FitWinModel {
+        #[arg()]
+        records: std::path::PathBuf,
+
+        #[arg()]
+        output: std::path::PathBuf,
+
+        #[arg(long, default_value_t = 2000)]
+        epochs: usize,
+    },
+    /// Analyses every game record in a directory, one game per worker thread, writing
+    /// annotated copies of the files and a summary CSV to `output`.
+    AnalyzeBatch {
+        #[arg()]
+        directory: std::path::PathBuf,
+
+        /// Defaults to an `analysis` directory inside `directory`.
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+
+        /// Defaults to the number of cores.
+        #[arg(long)]
+        workers: Option<usize>,
+    },
+    /// Shows the ratings of the players and engine configurations on the leaderboard.
+    Leaderboard,
+    /// Shows a player's results and rating over time, by default the player's own.
+    Stats {
+        #[arg()]
+        name: Option<String>,
+    },
 }
 const AUX_COMMAND_NAME: &str = "";
 
@@ -66,90 +265,523 @@ pub enum Command {
     AuxCommand(AuxCommand),
 }
 
+/// The names of a human playing an engine on the leaderboard.
+pub struct LeaderboardPlayer {
+    pub name: String,
+    /// The engine configuration, e.g. "bot depth 4".
+    pub engine: String,
+}
+
+/// How engines answer takeback requests in games with a clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
+pub enum TakebackPolicy {
+    Accept,
+    Decline,
+}
+
+/// Progress through a set of puzzles.
+pub struct PuzzleRun {
+    puzzles: Vec<Puzzle>,
+    current: usize,
+    solved: usize,
+}
+
 pub struct Session {
     pub game_states: Vec<Game>,
-    pub neural_networks: HashMap<Player, QuoridorNet>,
-    pub moves: Vec<PlayerMove>,
+    pub engines: HashMap<Player, Box<dyn Engine>>,
+    pub record: GameRecord,
+    pub rules: Rules,
+    /// While puzzles are loaded, played moves are graded instead of played.
+    pub puzzles: Option<PuzzleRun>,
+    /// Explanation of the last hint or played move.
+    pub explanation: Option<MoveExplanation>,
+    /// Human moves at least this many steps worse than the engine's choice are flagged.
+    pub blunder_threshold: Option<isize>,
+    /// The last move, when it was flagged as a blunder.
+    pub blunder: Option<Blunder>,
+    /// Turns engine scores into win probabilities.
+    pub win_model: WinProbabilityModel,
+    /// Game database statistics loaded by the explore command.
+    pub explorer: Option<OpeningExplorer>,
+    /// Answers bot moves, hints and evaluations, keeping its search cache between them.
+    pub bot: AlphaBetaEngine,
+    /// The clocks at the start of the game, engines playing on the time left when given.
+    pub time_control: Option<Clock>,
+    /// Language of the prompts, explanations and puzzle feedback.
+    pub locale: Locale,
+    /// The leaderboard file, see leaderboard.rs.
+    pub leaderboard: std::path::PathBuf,
+    /// When given, the player's finished games against the engine are recorded on the leaderboard.
+    pub leaderboard_player: Option<LeaderboardPlayer>,
+    /// The last stats screen shown, updated after every recorded game.
+    pub stats: Option<String>,
+    /// When the current position was reached, timing the human moves.
+    position_reached: Instant,
+    /// Moves taken back by undo, the next one to redo last.
+    undone: Vec<RecordedMove>,
+    /// How engines answer takeback requests.
+    pub takeback_policy: TakebackPolicy,
+    /// The moves of a takeback waiting for the opponent's answer, dropped when a move is played.
+    takeback_request: Option<usize>,
+    /// When given, the game is written to this recovery file after every move and takeback,
+    /// and the file removed once the game is over.
+    pub autosave: Option<std::path::PathBuf>,
+    /// How walls are read at the prompt and written in the move history.
+    pub wall_notation: WallNotation,
+    /// How boards are drawn, plain ASCII for terminals without box-drawing characters.
+    pub board_style: BoardStyle,
 }
 impl Session {
-    pub(crate) fn new(neural_networks: HashMap<Player, QuoridorNet>) -> Self {
+    pub fn new(engines: HashMap<Player, Box<dyn Engine>>, rules: Rules) -> Self {
         Self {
-            game_states: vec![Game::new()],
-            neural_networks: neural_networks,
-            moves: Vec::new(),
+            game_states: vec![Game::with_rules(rules)],
+            engines,
+            record: GameRecord::default(),
+            rules,
+            puzzles: None,
+            explanation: None,
+            blunder_threshold: None,
+            blunder: None,
+            win_model: WinProbabilityModel::default(),
+            explorer: None,
+            bot: AlphaBetaEngine::new(),
+            time_control: None,
+            locale: Locale::default(),
+            leaderboard: DEFAULT_LEADERBOARD.into(),
+            leaderboard_player: None,
+            stats: None,
+            position_reached: Instant::now(),
+            undone: Vec::new(),
+            takeback_policy: TakebackPolicy::Accept,
+            takeback_request: None,
+            autosave: None,
+            wall_notation: WallNotation::default(),
+            board_style: BoardStyle::default(),
+        }
+    }
+
+    /// The clocks in the current position: the time control's time, plus the increments
+    /// and minus the think time of the moves played so far.
+    pub fn clock(&self) -> Option<Clock> {
+        let mut clock = self.time_control?;
+        for (index, think_time) in self.think_times().into_iter().enumerate() {
+            let increments = clock.increment[index] * think_time.moves as u32;
+            clock.remaining[index] =
+                (clock.remaining[index] + increments).saturating_sub(think_time.total);
+        }
+        Some(clock)
+    }
+
+    /// The think time of each player in the game so far, by `Player::as_index`.
+    pub fn think_times(&self) -> [ThinkTime; 2] {
+        self.record.think_times()
+    }
+
+    /// Lets the engine of the opponent of the side to move search ahead while the human
+    /// to move thinks, see `Engine::ponder`.
+    pub fn ponder(&mut self) {
+        if self.puzzles.is_some() {
+            return;
+        }
+        let game = self.game_states.last().unwrap();
+        if let Some(engine) = self.engines.get_mut(&game.player.opponent()) {
+            engine.ponder(game);
+        }
+    }
+
+    /// Goes back to the initial position, keeping the engines but not their search caches.
+    fn reset(&mut self) {
+        self.bot.new_game();
+        for engine in self.engines.values_mut() {
+            engine.new_game();
+        }
+        self.game_states = vec![Game::with_rules(self.rules)];
+        self.record = GameRecord::default();
+        self.explanation = None;
+        self.blunder = None;
+        self.position_reached = Instant::now();
+        self.undone.clear();
+        self.takeback_request = None;
+        self.write_autosave();
+    }
+
+    /// The probability that White wins the current position, from the engine score of the
+    /// last move when it has one.
+    pub fn white_win_probability(&self) -> f64 {
+        let game = self.game_states.last().unwrap();
+        match self.record.moves.last().and_then(|last| last.annotation.eval) {
+            Some(score) => self.win_model.white_win_probability(game, score),
+            None => self.win_model.position_probability(game),
+        }
+    }
+
+    /// The database continuations from the current position, one per line.
+    pub fn explorer_text(&self) -> Option<String> {
+        let explorer = self.explorer.as_ref()?;
+        let game = self.game_states.last().unwrap();
+        let continuations = explorer.continuations(game);
+        if continuations.is_empty() {
+            return Some("No games in the database reach this position".to_string());
+        }
+        let mut text = format!(
+            "{:<6}{:>7}{:>7}{:>7}{:>7}",
+            "Move", "Games", "White", "Black", "Score"
+        );
+        for (notation, stats) in continuations {
+            text += &format!(
+                "\n{:<6}{:>7}{:>7}{:>7}{:>6.0}%",
+                notation,
+                stats.games,
+                stats.white_wins,
+                stats.black_wins,
+                100.0 * stats.score(game.player)
+            );
+        }
+        Some(text)
+    }
+
+    fn show_puzzle(&mut self) {
+        let Some(run) = &self.puzzles else {
+            return;
+        };
+        let puzzle = &run.puzzles[run.current];
+        let message = Message::PuzzleStart {
+            id: &puzzle.id,
+            number: run.current + 1,
+            count: run.puzzles.len(),
+            player: puzzle.game.player,
+        };
+        println!("{}", message.text(self.locale));
+        self.game_states = vec![puzzle.game.clone()];
+        self.record = GameRecord::default();
+        self.explanation = None;
+    }
+
+    fn grade_puzzle_move(&mut self, player_move: &PlayerMove) {
+        let Some(run) = &mut self.puzzles else {
+            return;
+        };
+        let puzzle = &run.puzzles[run.current];
+        if puzzle.is_solution(player_move) {
+            println!("{}", Message::Correct.text(self.locale));
+            run.solved += 1;
+        } else {
+            let solution = notation::move_to_notation(&puzzle.game, &puzzle.solution);
+            let message = Message::WrongPuzzleMove {
+                solution: &solution,
+            };
+            println!("{}", message.text(self.locale));
+        }
+        run.current += 1;
+        if run.current < run.puzzles.len() {
+            self.show_puzzle();
+        } else {
+            let message = Message::PuzzlesSolved {
+                solved: run.solved,
+                count: run.puzzles.len(),
+            };
+            println!("{}", message.text(self.locale));
+            self.puzzles = None;
+            self.reset();
+        }
+    }
+
+    fn play_move(&mut self, player_move: PlayerMove, annotation: MoveAnnotation) {
+        self.explanation = Some(explain(self.game_states.last().unwrap(), &player_move));
+        self.blunder = None;
+        self.takeback_request = None;
+        // Playing the next undone move keeps the rest to redo, any other move drops them
+        match self.undone.last() {
+            Some(undone) if undone.player_move.to_string() == player_move.to_string() => {
+                self.undone.pop();
+            }
+            _ => self.undone.clear(),
+        }
+        let mut next_game_state = self.game_states.last().unwrap().clone();
+        let player = next_game_state.player;
+        execute_move_unchecked(&mut next_game_state, player, &player_move);
+        self.game_states.push(next_game_state);
+        self.record.moves.push(RecordedMove {
+            player_move,
+            annotation,
+        });
+        self.notify_engines(false);
+        self.position_reached = Instant::now();
+        if let Err(error) = self.record_result() {
+            println!("{error}");
+        }
+        self.write_autosave();
+    }
+
+    /// Writes the game to the autosave file, removing the file when there is no unfinished
+    /// game to recover. Puzzles aren't saved.
+    fn write_autosave(&self) {
+        let Some(path) = &self.autosave else {
+            return;
+        };
+        if self.puzzles.is_some() {
+            return;
+        }
+        let finished = winner(self.game_states.last().unwrap()).is_some();
+        let result = if finished || self.record.moves.is_empty() {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        } else {
+            let mut record = self.record.clone();
+            record.set_think_time_tags();
+            // Written aside first, so a crash while writing leaves the previous save
+            let temporary_path = path.with_extension("tmp");
+            std::fs::write(&temporary_path, record.to_string())
+                .and_then(|_| std::fs::rename(&temporary_path, path))
+        };
+        if let Err(e) = result {
+            println!("Could not autosave to {}: {e}", path.display());
+        }
+    }
+
+    /// Records the game on the leaderboard when the last move won it, showing the player's
+    /// new stats.
+    fn record_result(&mut self) -> Result<(), String> {
+        let [.., previous, current] = &self.game_states[..] else {
+            return Ok(());
+        };
+        let (Some(player), Some(winner), None) =
+            (&self.leaderboard_player, winner(current), winner(previous))
+        else {
+            return Ok(());
+        };
+        let game = LeaderboardGame {
+            date: today(),
+            player: player.name.clone(),
+            engine: player.engine.clone(),
+            score: if self.engines.contains_key(&winner) {
+                0.0
+            } else {
+                1.0
+            },
+            between_players: false,
+        };
+        Leaderboard::record(&self.leaderboard, &game)?;
+        let name = player.name.clone();
+        if let Some(human) = [Player::White, Player::Black]
+            .into_iter()
+            .find(|player| !self.engines.contains_key(player))
+        {
+            let player_moves: Vec<PlayerMove> = self.record.player_moves().cloned().collect();
+            OpponentProfiles::record(
+                &profiles_path(&self.leaderboard),
+                &name,
+                &self.game_states,
+                &player_moves,
+                human,
+            )?;
+        }
+        self.show_stats(&name)
+    }
+
+    fn show_stats(&mut self, name: &str) -> Result<(), String> {
+        let stats = Leaderboard::load(&self.leaderboard)?
+            .stats(name)
+            .ok_or_else(|| format!("{name} has no games on the leaderboard"))?;
+        let mut text = stats.text(name);
+        if let Some(profile) = OpponentProfiles::load(&profiles_path(&self.leaderboard))?.get(name) {
+            text += &profile.text();
+        }
+        print!("{text}");
+        self.stats = Some(text);
+        Ok(())
+    }
+
+    fn take_back(&mut self, moves: usize) {
+        let moves = moves.min(self.game_states.len() - 1);
+        for _ in 0..moves {
+            self.game_states.pop();
+            self.undone.extend(self.record.moves.pop());
+        }
+        // The clocks follow, summed from the think times of the moves left
+        if moves > 0 {
+            self.notify_engines(true);
+            self.position_reached = Instant::now();
+            self.write_autosave();
+        }
+        self.explanation = None;
+    }
+
+    /// Asks the opponent of the player requesting to take back moves, an engine answering at
+    /// once by the takeback policy. Against an engine the human asks, otherwise the player who
+    /// moved last.
+    fn request_takeback(&mut self, moves: usize) -> Result<(), String> {
+        let last_mover = self.game_states.last().unwrap().player.opponent();
+        let player = match (
+            self.engines.contains_key(&Player::White),
+            self.engines.contains_key(&Player::Black),
+        ) {
+            (true, false) => Player::Black,
+            (false, true) => Player::White,
+            _ => last_mover,
+        };
+        if self.engines.contains_key(&player.opponent()) {
+            if self.takeback_policy == TakebackPolicy::Decline {
+                return Err(Message::TakebackDeclined.text(self.locale));
+            }
+            self.take_back(moves);
+            println!("{}", Message::TakebackAccepted.text(self.locale));
+            return Ok(());
+        }
+        println!(
+            "{}",
+            Message::TakebackRequest { player, moves }.text(self.locale)
+        );
+        self.takeback_request = Some(moves);
+        Ok(())
+    }
+
+    /// Tells the bot and the engines that the current position was reached by a move, or
+    /// by taking moves back, see `GameEvent`.
+    fn notify_engines(&mut self, undone: bool) {
+        let game = self.game_states.last().unwrap();
+        let ply = self.game_states.len() - 1;
+        let event = if undone {
+            GameEvent::Undone { game, ply }
+        } else {
+            GameEvent::MovePlayed { game, ply }
+        };
+        self.bot.game_event(&event);
+        for engine in self.engines.values_mut() {
+            engine.game_event(&event);
         }
     }
 }
 
-pub fn execute_command(session: &mut Session, command: Command) {
+/// Executes a command in the session. Moves are checked to be legal first, and commands
+/// that can't be carried out return why.
+pub fn execute_command(session: &mut Session, command: Command) -> Result<(), String> {
     let current_game_state = session.game_states.last().unwrap();
     let player = current_game_state.player;
+    if let Command::AuxCommand(
+        AuxCommand::BotMove { .. }
+        | AuxCommand::PlayBotMove { .. }
+        | AuxCommand::PlayEngineMove { .. }
+        | AuxCommand::Hint { .. }
+        | AuxCommand::Analyze { .. }
+        | AuxCommand::Eval {
+            move_to_evaluate: None,
+            ..
+        },
+    ) = &command
+    {
+        check_playable(current_game_state)?;
+    }
+    if let Command::PlayMove(player_move) = &command
+        && !is_move_legal(current_game_state, player, player_move)
+    {
+        return Err(format!("Illegal move {player_move}"));
+    }
     match command {
+        Command::PlayMove(player_move) if session.puzzles.is_some() => {
+            session.grade_puzzle_move(&player_move);
+        }
         Command::PlayMove(player_move) => {
-            let mut next_game_state = current_game_state.clone();
-            execute_move_unchecked(&mut next_game_state, player, &player_move);
-            session.game_states.push(next_game_state);
-            session.moves.push(player_move);
+            let blunder = session.blunder_threshold.and_then(|threshold| {
+                check_blunder(current_game_state, &player_move, BLUNDER_CHECK_DEPTH, threshold)
+            });
+            if let Some(blunder) = &blunder {
+                println!("{}", blunder.text(session.locale));
+            }
+            let annotation = MoveAnnotation {
+                time: Some(session.position_reached.elapsed()),
+                ..MoveAnnotation::default()
+            };
+            session.play_move(player_move, annotation);
+            session.blunder = blunder;
         }
         Command::AuxCommand(aux_command) => match aux_command {
-            AuxCommand::Reset => {*session = Session::new(HashMap::new())},
-            AuxCommand::BotMove { depth, seconds } => {
-                let bot_move = get_bot_move(
-                    current_game_state,
-                    player,
-                    depth,
-                    seconds.map(Duration::from_secs),
-                );
-                println!("{bot_move}");
+            AuxCommand::Reset => {
+                session.puzzles = None;
+                session.reset();
             }
-            AuxCommand::PlayBotMove { depth, seconds } => {
-                let bot_move = get_bot_move(
-                    current_game_state,
-                    player,
-                    depth,
-                    seconds.map(Duration::from_secs),
-                );
+            AuxCommand::BotMove { limits, json } => {
+                let bot_move = get_bot_move(&mut session.bot, current_game_state, &limits);
+                if json {
+                    print_search_result(current_game_state, &bot_move, &session.win_model);
+                } else {
+                    println!("{bot_move}");
+                }
+            }
+            AuxCommand::PlayBotMove { limits } => {
+                let bot_move = get_bot_move(&mut session.bot, current_game_state, &limits);
                 println!("{bot_move}");
-                let mut next_game_state = current_game_state.clone();
-                execute_move_unchecked(&mut next_game_state, player, &bot_move.player_move);
-                session.game_states.push(next_game_state);
-                session.moves.push(bot_move.player_move);
+                let annotation = bot_move.annotation();
+                session.play_move(bot_move.player_move, annotation);
             }
-            AuxCommand::PlayNNMove {temperature} =>
-            {
-                let nn_move = nn_bot::get_move(&current_game_state, session.neural_networks.get(&player).unwrap(), player, temperature);
-                
-                let mut next_game_state = current_game_state.clone();
-                execute_move_unchecked(&mut next_game_state, player, &nn_move);
-                session.game_states.push(next_game_state);
-
+            AuxCommand::PlayEngineMove { limits } => {
+                let limits = SearchLimits {
+                    clock: session.clock(),
+                    ..limits.search_limits()
+                };
+                let engine_move = match session.engines.get_mut(&player) {
+                    Some(engine) => engine.try_choose_move(current_game_state, &limits)?,
+                    None => session.bot.choose_move(current_game_state, &limits),
+                };
+                println!("{engine_move}");
+                let annotation = engine_move.annotation();
+                session.play_move(engine_move.player_move, annotation);
+            }
+            AuxCommand::Hint { limits } => {
+                let bot_move = get_bot_move(&mut session.bot, current_game_state, &limits);
+                let explanation = explain(current_game_state, &bot_move.player_move);
+                println!("{}", explanation.text(session.locale));
+                session.explanation = Some(explanation);
             }
-            AuxCommand::Undo { moves } => {
+            AuxCommand::Undo { moves } if session.time_control.is_some() => {
+                session.request_takeback(moves)?;
+            }
+            AuxCommand::Undo { moves } => session.take_back(moves),
+            AuxCommand::Accept => {
+                let moves = session
+                    .takeback_request
+                    .take()
+                    .ok_or("No takeback was requested")?;
+                session.take_back(moves);
+                println!("{}", Message::TakebackAccepted.text(session.locale));
+            }
+            AuxCommand::Decline => {
+                session
+                    .takeback_request
+                    .take()
+                    .ok_or("No takeback was requested")?;
+                println!("{}", Message::TakebackDeclined.text(session.locale));
+            }
+            AuxCommand::Redo { moves } => {
                 for _ in 0..moves {
-                    if session.game_states.len() == 1 {
+                    let Some(recorded) = session.undone.last().cloned() else {
                         break;
-                    }
-                    session.game_states.pop();
-                    session.moves.pop();
+                    };
+                    session.play_move(recorded.player_move, recorded.annotation);
                 }
             }
             AuxCommand::Eval {
                 move_to_evaluate,
-                depth,
-                seconds,
+                limits,
+                json,
             } => {
                 if let Some(move_str) = move_to_evaluate {
                     if let Some(player_move) = parse_player_move(&move_str) {
                         if is_move_legal(current_game_state, player, &player_move) {
                             let mut child_game_state = current_game_state.clone();
                             execute_move_unchecked(&mut child_game_state, player, &player_move);
-                            let score = get_bot_move(
-                                &child_game_state,
-                                player,
-                                depth,
-                                seconds.map(Duration::from_secs),
-                            );
-                            println!("{}", score);
+                            let score = get_bot_move(&mut session.bot, &child_game_state, &limits);
+                            if json {
+                                print_search_result(&child_game_state, &score, &session.win_model);
+                            } else {
+                                println!(
+                                    "{} ({})",
+                                    score,
+                                    win_percentage(&child_game_state, &score, &session.win_model)
+                                );
+                            }
                         } else {
                             println!("Invalid move");
                         }
@@ -157,40 +789,579 @@ pub fn execute_command(session: &mut Session, command: Command) {
                         println!("Could not parse move: {}", move_str);
                     }
                 } else {
-                    let score = get_bot_move(
-                        current_game_state,
-                        player,
-                        depth,
-                        seconds.map(Duration::from_secs),
-                    );
-                    println!("Best move evaluates to {}", score);
+                    let score = get_bot_move(&mut session.bot, current_game_state, &limits);
+                    if json {
+                        print_search_result(current_game_state, &score, &session.win_model);
+                    } else {
+                        println!(
+                            "Best move evaluates to {} ({})",
+                            score,
+                            win_percentage(current_game_state, &score, &session.win_model)
+                        );
+                    }
                 }
             }
             AuxCommand::Export => {
-                for m in &session.moves {
-                    print!("{m};");
-                }
-                println!();
+                let mut record = session.record.clone();
+                record.set_think_time_tags();
+                println!("{record}");
             }
             AuxCommand::Import { moves_string } => {
-                if let Some(moves) = moves_string
-                    .trim_matches(';')
-                    .split(';')
-                    .map(parse_player_move)
-                    .collect::<Option<Vec<_>>>()
+                if let Some(record) = GameRecord::parse(&moves_string) {
+                    load_record(session, record);
+                }
+            }
+            AuxCommand::ImportLog { file } => match std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|log| notation::parse_game_log(&log))
+            {
+                Ok(moves) => load_record(
+                    session,
+                    GameRecord {
+                        tags: Vec::new(),
+                        moves: moves
+                            .into_iter()
+                            .map(|player_move| RecordedMove {
+                                player_move,
+                                annotation: MoveAnnotation::default(),
+                            })
+                            .collect(),
+                    },
+                ),
+                Err(e) => println!("Could not import {}: {}", file.display(), e),
+            },
+            AuxCommand::History => {
+                for (ply, (game, recorded_move)) in session
+                    .game_states
+                    .iter()
+                    .zip(&session.record.moves)
+                    .enumerate()
                 {
-                    *session = Session::new(HashMap::new());
-                    for player_move in moves {
-                        let mut next_game_state = session.game_states.last().unwrap().clone();
-                        let player = next_game_state.player;
-                        execute_move_unchecked(&mut next_game_state, player, &player_move);
-                        session.game_states.push(next_game_state);
-                        session.moves.push(player_move);
+                    print!(
+                        "{:>3}. {} {}",
+                        ply + 1,
+                        game.player.to_string(),
+                        notation::move_to_notation_with(
+                            game,
+                            &recorded_move.player_move,
+                            session.wall_notation
+                        )
+                    );
+                    if !recorded_move.annotation.is_empty() {
+                        print!(" {{{}}}", recorded_move.annotation);
+                    }
+                    println!();
+                }
+            }
+            AuxCommand::ConvertWall { wall, from } => {
+                let from = from.unwrap_or(session.wall_notation);
+                let converted: Vec<String> = WALL_NOTATION_NAMES
+                    .iter()
+                    .filter_map(|&name| {
+                        let to = WallNotation::from_name(name)?;
+                        Some(format!("{name} {}", convert_wall(&wall, from, to)?))
+                    })
+                    .collect();
+                if converted.is_empty() {
+                    return Err(format!("Could not parse wall: {wall}"));
+                }
+                println!("{}", converted.join(", "));
+            }
+            AuxCommand::Match {
+                games,
+                limits,
+                max_plies,
+                save,
+                broadcast,
+                follow,
+            } => {
+                let output: Option<Box<dyn Write>> = match (&broadcast, follow) {
+                    (_, true) => Some(Box::new(std::io::stdout())),
+                    (Some(path), false) => Some(Box::new(
+                        std::fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(path)
+                            .map_err(|e| format!("Could not open {}: {e}", path.display()))?,
+                    )),
+                    (None, false) => None,
+                };
+                // Written to by both the move and the game callbacks
+                let broadcast = RefCell::new(output.map(Broadcast::new));
+                let (Some(mut white), Some(mut black)) = (
+                    session.engines.remove(&Player::White),
+                    session.engines.remove(&Player::Black),
+                ) else {
+                    return Err("A match needs engines for both players.".to_string());
+                };
+                let names = [white.name(), black.name()];
+                // Ctrl-C ends the match after the game being played
+                let shutdown = Shutdown::catch()?;
+                let players = |first_is_white| {
+                    if first_is_white {
+                        [names[0].as_str(), names[1].as_str()]
+                    } else {
+                        [names[1].as_str(), names[0].as_str()]
+                    }
+                };
+                let broadcast_error = |e: std::io::Error| println!("Could not broadcast: {e}");
+                let score = play_match_with(
+                    white.as_mut(),
+                    black.as_mut(),
+                    games,
+                    &session.rules,
+                    &limits.search_limits(),
+                    max_plies,
+                    |game_index, first_is_white, played_move| {
+                        if let Some(broadcast) = broadcast.borrow_mut().as_mut() {
+                            broadcast
+                                .move_played(game_index, players(first_is_white), played_move)
+                                .unwrap_or_else(broadcast_error);
+                        }
+                    },
+                    |game_index, first_is_white, finished_game| {
+                        if let Some(broadcast) = broadcast.borrow_mut().as_mut() {
+                            broadcast
+                                .game_finished(game_index, finished_game)
+                                .unwrap_or_else(broadcast_error);
+                        }
+                        let [white_name, black_name] = players(first_is_white);
+                        let result = finished_game.outcome.result();
+                        println!(
+                            "Game {}: {} - {} {} ({:?}, {} plies)",
+                            game_index + 1,
+                            white_name,
+                            black_name,
+                            result,
+                            finished_game.termination,
+                            finished_game.record.moves.len()
+                        );
+                        let mut record = finished_game.record.clone();
+                        record.set_tag("white", white_name);
+                        record.set_tag("black", black_name);
+                        record.set_tag("result", result);
+                        record.set_think_time_tags();
+                        if let Some(save) = &save {
+                            let appended = std::fs::OpenOptions::new()
+                                .create(true)
+                                .append(true)
+                                .open(save)
+                                .and_then(|mut file| writeln!(file, "{record}"));
+                            if let Err(e) = appended {
+                                println!("Could not write {}: {}", save.display(), e);
+                            }
+                        }
+                        !shutdown.requested()
+                    },
+                );
+                if shutdown.requested() {
+                    println!("Interrupted after {} of {} games", score.games(), games);
+                }
+                println!(
+                    "{} vs {}: +{} -{} ={} ({}/{})",
+                    names[0],
+                    names[1],
+                    score.wins,
+                    score.losses,
+                    score.draws,
+                    score.points(),
+                    score.games()
+                );
+                for (name, think_time) in names.iter().zip(score.think_times) {
+                    println!("{name} thought for {think_time}");
+                }
+                session.engines.insert(Player::White, white);
+                session.engines.insert(Player::Black, black);
+            }
+            AuxCommand::TestSuite { file, limits } => match std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|suite| parse_test_suite(&suite))
+            {
+                Ok(suite) => {
+                    let limits = limits.search_limits();
+                    let mut engine = AlphaBetaEngine::new();
+                    let mut passed = 0;
+                    for test_position in &suite {
+                        let outcome = run_test_position(&mut engine, test_position, &limits);
+                        println!(
+                            "{} {}: played {}",
+                            if outcome.passed { "PASS" } else { "FAIL" },
+                            outcome.id,
+                            outcome.engine_move
+                        );
+                        passed += outcome.passed as usize;
+                    }
+                    println!("Passed {}/{}", passed, suite.len());
+                }
+                Err(e) => println!("Could not load {}: {}", file.display(), e),
+            },
+            AuxCommand::MinePuzzles {
+                records,
+                output,
+                depth,
+                min_gap,
+            } => {
+                let records = std::fs::read_to_string(&records)
+                    .map_err(|e| format!("Could not read {}: {}", records.display(), e))?;
+                let mut puzzles = Vec::new();
+                for (index, line) in records.lines().enumerate() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match GameRecord::parse(line) {
+                        Some(record) => puzzles.extend(mine_puzzles(
+                            &record,
+                            depth,
+                            min_gap,
+                            &format!("game-{}", index + 1),
+                        )),
+                        None => println!("Skipping line {}: not a game record", index + 1),
+                    }
+                }
+                let text: String = puzzles.iter().map(|puzzle| format!("{puzzle}\n")).collect();
+                match std::fs::write(&output, text) {
+                    Ok(()) => println!("Wrote {} puzzles to {}", puzzles.len(), output.display()),
+                    Err(e) => println!("Could not write {}: {}", output.display(), e),
+                }
+            }
+            AuxCommand::Puzzles { file } => match std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|puzzles| parse_puzzles(&puzzles))
+            {
+                Ok(puzzles) if puzzles.is_empty() => println!("No puzzles in {}", file.display()),
+                Ok(puzzles) => {
+                    session.puzzles = Some(PuzzleRun {
+                        puzzles,
+                        current: 0,
+                        solved: 0,
+                    });
+                    session.show_puzzle();
+                }
+                Err(e) => println!("Could not load {}: {}", file.display(), e),
+            },
+            AuxCommand::Report { depth, key_moments } => {
+                let report =
+                    analyse_game(&session.game_states, session.record.player_moves(), depth);
+                for review in &report.moves {
+                    if review.severity.is_some() {
+                        println!(
+                            "{:>3}. {} {}",
+                            review.ply + 1,
+                            review.game.player.to_string(),
+                            review.comparison
+                        );
+                    }
+                }
+                println!("{report}");
+                println!("{}", report.key_moments_text(key_moments));
+                if let Some(turning_point) = report.turning_point() {
+                    let board = render_board_with(&turning_point.game.board, session.board_style);
+                    println!("{board}");
+                }
+            }
+            AuxCommand::EngineMatch { depth } => {
+                let report =
+                    analyse_game(&session.game_states, session.record.player_moves(), depth);
+                println!("{}", engine_match(&report));
+            }
+            AuxCommand::ExportHtml { output, depth } => {
+                annotate_evals(&mut session.record, &session.game_states, depth);
+                let report =
+                    analyse_game(&session.game_states, session.record.player_moves(), depth);
+                let html = html_report(&session.record, &session.game_states, &report);
+                std::fs::write(&output, html)
+                    .map_err(|e| format!("Could not write {}: {e}", output.display()))?;
+                println!("Wrote the analysed game to {}", output.display());
+            }
+            AuxCommand::Analyze { depth, multipv } => {
+                let lines = analyze(current_game_state, player, depth, multipv);
+                for (rank, line) in lines.iter().enumerate() {
+                    let score = match decided(line.score) {
+                        Some((winner, plies)) => format!("{} wins in {plies}", winner.to_string()),
+                        None => format!("{:+}", line.score),
+                    };
+                    let probability = session
+                        .win_model
+                        .white_win_probability(current_game_state, line.score);
+                    println!(
+                        "{}. {} ({:.0}% White) {}",
+                        rank + 1,
+                        score,
+                        100.0 * probability,
+                        line_to_notation(current_game_state, &line.principal_variation).join(" ")
+                    );
+                }
+            }
+            AuxCommand::Evals { depth } => {
+                let evaluated = annotate_evals(&mut session.record, &session.game_states, depth);
+                println!("Evaluated {evaluated} moves");
+                println!("{}", sparkline(&evals(&session.record), DECISIVE_LOSS));
+            }
+            AuxCommand::Explore {
+                directory,
+                max_plies,
+            } => {
+                if let Some(directory) = directory {
+                    match load_explorer(&directory, max_plies) {
+                        Ok(explorer) => {
+                            println!(
+                                "Loaded {} games from {}",
+                                explorer.games,
+                                directory.display()
+                            );
+                            session.explorer = Some(explorer);
+                        }
+                        Err(e) => println!("Could not load {}: {}", directory.display(), e),
                     }
                 }
+                match session.explorer_text() {
+                    Some(text) => println!("{text}"),
+                    None => println!("No game database loaded, pass a directory of game records."),
+                }
+            }
+            AuxCommand::AnalyzeBatch {
+                directory,
+                output,
+                depth,
+                workers,
+            } => {
+                let games = load_batch_games(&directory)
+                    .map_err(|e| format!("Could not read {}: {}", directory.display(), e))?;
+                let workers = workers.unwrap_or_else(|| {
+                    std::thread::available_parallelism().map_or(1, |workers| workers.get())
+                });
+                println!(
+                    "Analysing {} games with {} workers at depth {}",
+                    games.len(),
+                    workers,
+                    depth
+                );
+                let reports = analyse_games(&games, depth, workers, |game, report| {
+                    let [white, black] = &report.players;
+                    println!(
+                        "{}:{}: average loss {:.2} for White, {:.2} for Black",
+                        game.file,
+                        game.line,
+                        white.average_loss(),
+                        black.average_loss()
+                    );
+                });
+                let output = output.unwrap_or_else(|| directory.join("analysis"));
+                match write_batch_analysis(&games, &reports, depth, &output) {
+                    Ok(()) => println!(
+                        "Wrote the analysis of {} games to {}",
+                        games.len(),
+                        output.display()
+                    ),
+                    Err(e) => println!("Could not write {}: {}", output.display(), e),
+                }
+            }
+            AuxCommand::Leaderboard => {
+                print!("{}", Leaderboard::load(&session.leaderboard)?.table());
+            }
+            AuxCommand::Stats { name } => {
+                let name = name
+                    .or_else(|| {
+                        let player = session.leaderboard_player.as_ref()?;
+                        Some(player.name.clone())
+                    })
+                    .ok_or("Give a player name, or start with --name")?;
+                session.show_stats(&name)?;
+            }
+            AuxCommand::GenerateTablebase { output, records } => {
+                let output = output.to_string_lossy();
+                let mut tablebase = if std::path::Path::new(output.as_ref()).exists() {
+                    Tablebase::load(&output)?
+                } else {
+                    Tablebase::default()
+                };
+                let positions = match records {
+                    Some(records) => std::fs::read_to_string(&records)
+                        .map_err(|e| format!("Could not read {}: {}", records.display(), e))?
+                        .lines()
+                        .filter_map(GameRecord::parse)
+                        .flat_map(|record| record.game_states())
+                        .collect(),
+                    None => vec![current_game_state.clone()],
+                };
+                let added = positions
+                    .iter()
+                    .filter(|game| Tablebase::covers(game))
+                    .filter(|game| tablebase.add(game))
+                    .count();
+                match tablebase.save(&output) {
+                    Ok(()) => println!(
+                        "Added {} tables, {} has {} tables",
+                        added,
+                        output,
+                        tablebase.tables.len()
+                    ),
+                    Err(e) => println!("{e}"),
+                }
+            }
+            AuxCommand::FitWinModel {
+                records,
+                output,
+                epochs,
+            } => {
+                let records = std::fs::read_to_string(&records)
+                    .map_err(|e| format!("Could not read {}: {}", records.display(), e))?;
+                let samples: Vec<_> = records
+                    .lines()
+                    .filter_map(GameRecord::parse)
+                    .flat_map(|record| samples_from_record(&record))
+                    .collect();
+                if samples.is_empty() {
+                    return Err("No finished games to fit the model to.".to_string());
+                }
+                let model = WinProbabilityModel::fit(&samples, epochs, 0.1);
+                let json = serde_json::to_string_pretty(&model).unwrap();
+                match std::fs::write(&output, &json) {
+                    Ok(()) => println!(
+                        "Fitted {} positions, wrote {}: {}",
+                        samples.len(),
+                        output.display(),
+                        json
+                    ),
+                    Err(e) => println!("Could not write {}: {}", output.display(), e),
+                }
+                session.win_model = model;
             }
         },
     }
+    Ok(())
+}
+
+/// Reads every file in `directory` as game records, one per line.
+fn load_explorer(
+    directory: &std::path::Path,
+    max_plies: usize,
+) -> Result<OpeningExplorer, String> {
+    let mut explorer = OpeningExplorer::default();
+    let mut paths = std::fs::read_dir(directory)
+        .map_err(|e| e.to_string())?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    paths.sort();
+    for path in paths.iter().filter(|path| path.is_file()) {
+        match std::fs::read_to_string(path) {
+            Ok(records) => {
+                let skipped = explorer.add_records(&records, max_plies);
+                if skipped > 0 {
+                    println!("Skipped {} lines of {}", skipped, path.display());
+                }
+            }
+            Err(e) => println!("Skipping {}: {}", path.display(), e),
+        }
+    }
+    Ok(explorer)
+}
+
+fn load_batch_games(directory: &std::path::Path) -> Result<Vec<BatchGame>, String> {
+    let mut paths = std::fs::read_dir(directory)
+        .map_err(|e| e.to_string())?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    paths.sort();
+    let mut games = Vec::new();
+    for path in paths.iter().filter(|path| path.is_file()) {
+        let records = match std::fs::read_to_string(path) {
+            Ok(records) => records,
+            Err(e) => {
+                println!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let file = path
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().to_string());
+        let mut skipped = 0;
+        for (index, line) in records.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match GameRecord::parse(line) {
+                Some(record) => games.push(BatchGame {
+                    file: file.clone(),
+                    line: index + 1,
+                    record,
+                }),
+                None => skipped += 1,
+            }
+        }
+        if skipped > 0 {
+            println!("Skipped {} lines of {}", skipped, path.display());
+        }
+    }
+    Ok(games)
+}
+
+fn write_batch_analysis(
+    games: &[BatchGame],
+    reports: &[GameReport],
+    depth: usize,
+    output: &std::path::Path,
+) -> Result<(), String> {
+    std::fs::create_dir_all(output).map_err(|e| e.to_string())?;
+    let mut files: Vec<(&str, String)> = Vec::new();
+    for (game, report) in games.iter().zip(reports) {
+        let annotated = annotate(&game.record, report, depth);
+        match files.last_mut() {
+            Some((file, contents)) if *file == game.file => {
+                *contents += &format!("{annotated}\n");
+            }
+            _ => files.push((&game.file, format!("{annotated}\n"))),
+        }
+    }
+    for (file, contents) in files {
+        std::fs::write(output.join(file), contents).map_err(|e| e.to_string())?;
+    }
+    let mut summary =
+        std::fs::File::create(output.join("summary.csv")).map_err(|e| e.to_string())?;
+    write_summary(games, reports, &mut summary).map_err(|e| e.to_string())
+}
+
+fn load_record(session: &mut Session, record: GameRecord) {
+    session.reset();
+    for (key, value) in &record.tags {
+        println!("{key}: {value}");
+    }
+    for recorded_move in record.moves {
+        session.play_move(recorded_move.player_move, recorded_move.annotation);
+    }
+    session.record.tags = record.tags;
+    session.write_autosave();
+}
+
+/// Offers to restore the unfinished game left in the session's autosave file, reading the
+/// answer from `next_line`. Returns whether the game was restored.
+pub fn offer_autosave(
+    session: &mut Session,
+    mut next_line: impl FnMut() -> Option<String>,
+) -> bool {
+    let Some(path) = &session.autosave else {
+        return false;
+    };
+    let Some(record) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| GameRecord::parse(text.trim()))
+        .filter(|record| !record.moves.is_empty())
+    else {
+        return false;
+    };
+    let message = Message::RestoreAutosave {
+        file: &path.display().to_string(),
+        moves: record.moves.len(),
+    };
+    println!("{}", message.text(session.locale));
+    if !next_line().is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y")) {
+        return false;
+    }
+    load_record(session, record);
+    true
 }
 
 pub enum ParseCommandResult {
@@ -199,8 +1370,15 @@ pub enum ParseCommandResult {
     InvalidInput,
 }
 
-pub fn parse_command(input: &str) -> ParseCommandResult {
-    match parse_player_move(input) {
+/// Parses a move or a command, walls written in the scheme or as in `PlayerMove`.
+pub fn parse_command(input: &str, walls: WallNotation) -> ParseCommandResult {
+    let wall = walls
+        .parse(input)
+        .map(|(orientation, position)| PlayerMove::PlaceWall {
+            orientation,
+            position,
+        });
+    match wall.or_else(|| parse_player_move(input)) {
         Some(player_move) => ParseCommandResult::Command(Command::PlayMove(player_move)),
         None => {
             match AuxCommandParserHelper::try_parse_from(
@@ -213,120 +1391,240 @@ pub fn parse_command(input: &str) -> ParseCommandResult {
     }
 }
 
-pub fn get_legal_command(game: &Game, player: Player) -> Command {
-    use std::io::{self, Write};
-
+/// Reads lines from `next_line` until one is a legal move or a command.
+pub fn read_legal_command(
+    game: &Game,
+    player: Player,
+    locale: Locale,
+    walls: WallNotation,
+    mut next_line: impl FnMut() -> Option<String>,
+) -> Option<Command> {
     loop {
         print!("> ");
-        io::stdout().flush().unwrap();
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let input = input.trim();
+        std::io::stdout().flush().unwrap();
+        let input = next_line()?;
+        let input = notation::clean_line(&input);
 
-        match parse_command(input) {
+        match parse_command(input, walls) {
             ParseCommandResult::Command(Command::PlayMove(player_move))
                 if !is_move_legal(game, player, &player_move) =>
             {
-                println!("Invalid move.")
+                println!("{}", Message::InvalidMove.text(locale))
             }
-            ParseCommandResult::Command(command) => break command,
+            ParseCommandResult::Command(command) => break Some(command),
             ParseCommandResult::HelpText(help_text) => println!("{}", help_text),
-            ParseCommandResult::InvalidInput => println!("Invalid input format."),
+            ParseCommandResult::InvalidInput => println!("{}", Message::InvalidInput.text(locale)),
         }
     }
 }
-pub fn parse_player_move(input: &str) -> Option<PlayerMove> {
-    let mut chars = input.chars();
-
-    let direction_from_char = |c: Option<char>| match c {
-        Some('u') => Some(Direction::Up),
-        Some('d') => Some(Direction::Down),
-        Some('l') => Some(Direction::Left),
-        Some('r') => Some(Direction::Right),
-        _ => None,
-    };
 
-    match chars.next() {
-        Some('m') => {
-            let direction = direction_from_char(chars.next())?;
-            let direction_on_collision = direction_from_char(chars.next()).unwrap_or(direction);
-            Some(PlayerMove::MovePiece(MovePiece {
-                direction,
-                direction_on_collision,
-            }))
-        }
-        Some('h') => match (chars.next(), chars.next()) {
-            (Some(x), Some(y)) => {
-                let x = x.to_digit(10)? as usize;
-                let y = y.to_digit(10)? as usize;
-                Some(PlayerMove::PlaceWall {
-                    orientation: WallOrientation::Horizontal,
-                    position: WallPosition { x, y },
-                })
-            }
-            _ => None,
-        },
-        Some('v') => match (chars.next(), chars.next()) {
-            (Some(x), Some(y)) => {
-                let x = x.to_digit(10)? as usize;
-                let y = y.to_digit(10)? as usize;
-                Some(PlayerMove::PlaceWall {
-                    orientation: WallOrientation::Vertical,
-                    position: WallPosition { x, y },
-                })
-            }
-            _ => None,
-        },
-        _ => None,
-    }
+/// Parses a wall coordinate scheme name for the `--wall-notation` flags.
+pub fn parse_wall_notation(name: &str) -> Result<WallNotation, String> {
+    WallNotation::from_name(name).ok_or_else(|| {
+        format!(
+            "Unknown wall notation {name}, expected one of: {}",
+            WALL_NOTATION_NAMES.join(", ")
+        )
+    })
 }
 
-pub struct BotMove {
-    player_move: PlayerMove,
-    score: isize,
-    depth: usize,
-    planned_duration: Option<Duration>,
-    actual_duration: Duration,
+/// Parses a rule variant name for the `--rules` flags.
+pub fn parse_rules(name: &str) -> Result<Rules, String> {
+    Rules::variant(name).ok_or_else(|| {
+        format!(
+            "Unknown rule variant {name}, expected one of: {}",
+            VARIANT_NAMES.join(", ")
+        )
+    })
+}
+
+/// Parses a locale code for the `--locale` flags.
+pub fn parse_locale(code: &str) -> Result<Locale, String> {
+    Locale::from_code(code).ok_or_else(|| {
+        format!(
+            "Unknown locale {code}, expected one of: {}",
+            LOCALE_CODES.join(", ")
+        )
+    })
 }
 
-impl Display for BotMove {
+/// Search limits of the commands running an engine.
+#[derive(clap_derive::Args, Debug, Clone, Copy, Default)]
+pub struct LimitArgs {
+    #[arg(short, long, group = "time_control")]
+    pub depth: Option<usize>,
+
+    #[arg(short, long, group = "time_control")]
+    pub seconds: Option<u64>,
+
+    /// Milliseconds per move, the bot deepening its search until they are up and playing the
+    /// best move of the last depth it searched in full.
+    #[arg(long, group = "time_control")]
+    pub movetime: Option<u64>,
+
+    /// Positions the bot searches, or simulations of tree searches.
+    #[arg(short, long)]
+    pub nodes: Option<u64>,
+}
+
+impl std::fmt::Display for LimitArgs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.player_move)?;
-        write!(f, " score:{}", self.score)?;
-        write!(f, " depth:{}", self.depth)?;
-        write!(f, " {:?}", self.actual_duration)?;
-        if let Some(d) = self.planned_duration {
-            write!(f, "({:?})", d)?;
+        let mut limits = Vec::new();
+        if let Some(depth) = self.depth {
+            limits.push(format!("depth {depth}"));
         }
-        Ok(())
+        if let Some(seconds) = self.seconds {
+            limits.push(format!("{seconds}s"));
+        }
+        if let Some(movetime) = self.movetime {
+            limits.push(format!("{movetime}ms"));
+        }
+        if let Some(nodes) = self.nodes {
+            limits.push(format!("nodes {nodes}"));
+        }
+        write!(f, "{}", limits.join(" "))
     }
 }
 
-fn get_bot_move(
-    game: &Game,
-    player: Player,
-    depth: Option<usize>,
-    duration: Option<Duration>,
-) -> BotMove {
-    let start_time = std::time::Instant::now();
-    let (score, best_move, depth, planned_duration) = match (depth, duration) {
-        (Some(depth), _) => {
-            let (score, best_move) = best_move_alpha_beta(game, player, depth);
-            (score, best_move, depth, None)
-        }
-        (_, duration) => {
-            let duration = duration.unwrap_or(Duration::from_secs(3));
-            let (score, best_move, depth) =
-                best_move_alpha_beta_iterative_deepening(game, player, duration);
-            (score, best_move, depth, Some(duration))
+impl LimitArgs {
+    /// These limits, or a search to `depth` when they set neither a depth nor a move time.
+    pub fn or_depth(self, depth: usize) -> Self {
+        match self {
+            LimitArgs { depth: None, seconds: None, movetime: None, .. } => LimitArgs { depth: Some(depth), ..self },
+            limits => limits,
         }
-    };
-    let elapsed = start_time.elapsed();
-    BotMove {
-        player_move: best_move.unwrap(),
-        score,
-        depth,
-        planned_duration,
-        actual_duration: elapsed,
+    }
+
+    pub fn search_limits(&self) -> SearchLimits {
+        SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            movetime: self.seconds.map(Duration::from_secs).or(self.movetime.map(Duration::from_millis)),
+            clock: None,
+        }
+    }
+}
+
+/// The clocks of a game with `seconds` per player and `increment` seconds per move, for
+/// the `--clock` flags.
+pub fn time_control(seconds: u64, increment: u64) -> Clock {
+    Clock {
+        remaining: [Duration::from_secs(seconds); 2],
+        increment: [Duration::from_secs(increment); 2],
+    }
+}
+
+/// Loads a win probability model written by `fit-win-model`, for the `--win-model` flags.
+pub fn load_win_model(path: &str) -> Result<WinProbabilityModel, String> {
+    let json = std::fs::read_to_string(path).map_err(|e| format!("Could not read {path}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("Could not parse {path}: {e}"))
+}
+
+fn white_win_probability(game: &Game, bot_move: &EngineMove, model: &WinProbabilityModel) -> f64 {
+    match &bot_move.search_info {
+        Some(search_info) => model.white_win_probability(game, search_info.score),
+        None => model.position_probability(game),
+    }
+}
+
+fn win_percentage(game: &Game, bot_move: &EngineMove, model: &WinProbabilityModel) -> String {
+    format!(
+        "White wins {:.0}%",
+        100.0 * white_win_probability(game, bot_move, model)
+    )
+}
+
+fn print_search_result(game: &Game, bot_move: &EngineMove, model: &WinProbabilityModel) {
+    let mut search_result = bot_move.to_search_result(game);
+    search_result.info.win_probability = Some(white_win_probability(game, bot_move, model));
+    println!("{}", serde_json::to_string(&search_result).unwrap());
+}
+
+/// Searches the position with the session's alpha-beta bot, whatever engines the session has.
+pub fn get_bot_move(bot: &mut AlphaBetaEngine, game: &Game, limits: &LimitArgs) -> EngineMove {
+    bot.choose_move(game, &limits.search_limits())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execute_command_test() {
+        let mut session = Session::new(HashMap::new(), Rules::default());
+        let illegal = parse_player_move("muu").unwrap();
+        assert!(execute_command(&mut session, Command::PlayMove(illegal)).is_err());
+        assert_eq!(session.game_states.len(), 1);
+        assert!(session.record.moves.is_empty());
+
+        let legal = parse_player_move("mdd").unwrap();
+        assert!(execute_command(&mut session, Command::PlayMove(legal)).is_ok());
+        assert_eq!(session.game_states.len(), 2);
+
+        // Walls are read in the scheme, or as in `PlayerMove`
+        let wall = |input, walls| match parse_command(input, walls) {
+            ParseCommandResult::Command(Command::PlayMove(player_move)) => player_move.to_string(),
+            _ => panic!("{input} should parse as a wall"),
+        };
+        assert_eq!(wall("e4h", WallNotation::Standard), "h43");
+        assert_eq!(wall("e4h", WallNotation::Bga), "h42");
+        assert_eq!(wall("h42", WallNotation::Bga), "h42");
+    }
+
+    #[test]
+    fn takeback_test() {
+        let aux = |command| Command::AuxCommand(command);
+        let mut session = Session::new(HashMap::new(), Rules::default());
+        session.time_control = Some(time_control(60, 0));
+        let pawn_move = parse_player_move("mdd").unwrap();
+        execute_command(&mut session, Command::PlayMove(pawn_move.clone())).unwrap();
+
+        // Between humans the takeback waits for the opponent
+        execute_command(&mut session, aux(AuxCommand::Undo { moves: 1 })).unwrap();
+        assert_eq!(session.game_states.len(), 2);
+        execute_command(&mut session, aux(AuxCommand::Decline)).unwrap();
+        assert!(execute_command(&mut session, aux(AuxCommand::Accept)).is_err());
+        execute_command(&mut session, aux(AuxCommand::Undo { moves: 1 })).unwrap();
+        execute_command(&mut session, aux(AuxCommand::Accept)).unwrap();
+        assert_eq!(session.game_states.len(), 1);
+        assert_eq!(session.clock(), session.time_control);
+
+        // An engine answers by the policy
+        let mut engines: HashMap<Player, Box<dyn Engine>> = HashMap::new();
+        engines.insert(Player::Black, Box::new(quoridor_core::engine::GreedyEngine));
+        let mut session = Session::new(engines, Rules::default());
+        session.time_control = Some(time_control(60, 0));
+        session.takeback_policy = TakebackPolicy::Decline;
+        execute_command(&mut session, Command::PlayMove(pawn_move)).unwrap();
+        assert!(execute_command(&mut session, aux(AuxCommand::Undo { moves: 1 })).is_err());
+        session.takeback_policy = TakebackPolicy::Accept;
+        execute_command(&mut session, aux(AuxCommand::Undo { moves: 1 })).unwrap();
+        assert_eq!(session.game_states.len(), 1);
+    }
+
+    #[test]
+    fn autosave_test() {
+        let path = std::env::temp_dir().join(format!("autosave-test-{}.txt", std::process::id()));
+        let mut session = Session::new(HashMap::new(), Rules::default());
+        session.autosave = Some(path.clone());
+        for notation in ["mdd", "muu"] {
+            let player_move = parse_player_move(notation).unwrap();
+            execute_command(&mut session, Command::PlayMove(player_move)).unwrap();
+        }
+        let saved = GameRecord::parse(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.moves.len(), 2);
+
+        // A new session restores the game when the answer is yes
+        let mut restored = Session::new(HashMap::new(), Rules::default());
+        restored.autosave = Some(path.clone());
+        assert!(!offer_autosave(&mut restored, || Some("n\n".to_string())));
+        assert!(!offer_autosave(&mut restored, || None));
+        assert_eq!(restored.game_states.len(), 1);
+        assert!(offer_autosave(&mut restored, || Some("y\n".to_string())));
+        assert_eq!(restored.game_states.len(), 3);
+
+        // Starting over leaves nothing to recover
+        execute_command(&mut restored, Command::AuxCommand(AuxCommand::Reset)).unwrap();
+        assert!(!path.exists());
     }
 }
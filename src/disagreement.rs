@@ -0,0 +1,152 @@
+// Mining of positions where the network and the alpha-beta search disagree. The most
+// likely legal move of the policy is compared with the best move of a fixed depth
+// search, the positions where it loses at least a threshold of steps being kept.
+//
+// The dataset is written as a position file readable by nn-eval, every position
+// preceded by comment lines with the comparison and a diagram of the board:
+//
+//     # games.records:3, Black to move
+//     # network d4h (62%, e8 at 3%) loses 2 tempi against e8
+//     # ┌───┐   ┌───┐ ...
+//     e3 e7 8 9 b d6h,e2v
+
+use std::io::Write;
+
+use quoridor_core::bot::{Blunder, check_blunder};
+use quoridor_core::game_logic::winner;
+use quoridor_core::notation::{parse_notation_move, position_to_string};
+use quoridor_core::render_board::render_board;
+
+use crate::nn_bot::{QuoridorNet, policy_moves, predict_games};
+use crate::nn_eval::InputPosition;
+
+const BATCH_SIZE: usize = 64;
+
+/// A position where the network's move scores clearly worse than the search's.
+pub struct Disagreement<'a> {
+    pub position: &'a InputPosition,
+    /// The network's move compared with the best move of the search.
+    pub comparison: Blunder,
+    /// Policy probabilities of the network's and of the best move.
+    pub network_probability: f32,
+    pub best_probability: f32,
+}
+
+impl Disagreement<'_> {
+    fn write(&self, output: &mut impl Write) -> std::io::Result<()> {
+        let game = &self.position.game;
+        writeln!(
+            output,
+            "# {}:{}, {} to move",
+            self.position.file,
+            self.position.line,
+            game.player.to_string()
+        )?;
+        let loss = match &self.comparison {
+            comparison if comparison.loss == 0 => "is as good".to_string(),
+            comparison if comparison.misses_win => "misses a win".to_string(),
+            comparison if comparison.loses_game => "loses the game".to_string(),
+            Blunder { loss: 1, .. } => "loses 1 tempo".to_string(),
+            comparison => format!("loses {} tempi", comparison.loss),
+        };
+        writeln!(
+            output,
+            "# network {} ({:.0}%, {} at {:.0}%) {} against {}",
+            self.comparison.played,
+            100.0 * self.network_probability,
+            self.comparison.best,
+            100.0 * self.best_probability,
+            loss,
+            self.comparison.best
+        )?;
+        for line in render_board(&game.board).lines() {
+            writeln!(output, "# {line}")?;
+        }
+        writeln!(output, "{}", position_to_string(game))?;
+        writeln!(output)
+    }
+}
+
+/// The positions where the network's most likely move loses at least `min_loss` steps
+/// against the best move of a `depth` ply search, in input order.
+pub fn find_disagreements<'a>(
+    network: &QuoridorNet,
+    positions: &'a [InputPosition],
+    depth: usize,
+    min_loss: isize,
+) -> Vec<Disagreement<'a>> {
+    let positions: Vec<&InputPosition> = positions
+        .iter()
+        .filter(|position| winner(&position.game).is_none())
+        .collect();
+    let mut disagreements = Vec::new();
+    for batch in positions.chunks(BATCH_SIZE) {
+        let games: Vec<_> = batch.iter().map(|position| &position.game).collect();
+        for (position, prediction) in batch.iter().zip(predict_games(network, &games)) {
+            let game = &position.game;
+            let moves = policy_moves(game, &prediction.policy_logits);
+            let Some((notation, network_probability)) = moves.first() else {
+                continue;
+            };
+            let network_move = parse_notation_move(game, notation)
+                .expect("Policy moves are legal moves in notation");
+            let Some(comparison) = check_blunder(game, &network_move, depth, min_loss) else {
+                continue;
+            };
+            let best_probability = moves
+                .iter()
+                .find(|(notation, _)| *notation == comparison.best)
+                .map_or(0.0, |(_, probability)| *probability);
+            disagreements.push(Disagreement {
+                position,
+                comparison,
+                network_probability: *network_probability,
+                best_probability,
+            });
+        }
+    }
+    disagreements
+}
+
+/// Writes the disagreements as a position file with the comparisons and diagrams in comments.
+pub fn write_disagreements(
+    disagreements: &[Disagreement],
+    output: &mut impl Write,
+) -> std::io::Result<()> {
+    disagreements
+        .iter()
+        .try_for_each(|disagreement| disagreement.write(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn_eval::read_positions;
+
+    #[test]
+    fn disagreements_test() {
+        let dir = std::env::temp_dir().join(format!("disagreement-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // White wins with e9, the comparison is kept whatever the network plays with no minimum loss
+        std::fs::write(dir.join("a.positions"), "e8 e2 10 10 w -\n").unwrap();
+        let positions = read_positions(&dir).unwrap();
+        let disagreements = find_disagreements(&QuoridorNet::new(), &positions, 1, 0);
+        assert_eq!(disagreements.len(), 1);
+        let comparison = &disagreements[0].comparison;
+        assert_eq!(comparison.best, "e9");
+        assert_eq!(comparison.loss > 0, comparison.played != "e9");
+
+        // The dataset reads back as the positions it was mined from
+        let mut dataset = Vec::new();
+        write_disagreements(&disagreements, &mut dataset).unwrap();
+        std::fs::write(dir.join("a.positions"), &dataset).unwrap();
+        let mined = read_positions(&dir).unwrap();
+        assert_eq!(mined.len(), 1);
+        assert_eq!(
+            position_to_string(&mined[0].game),
+            position_to_string(&positions[0].game)
+        );
+        assert!(String::from_utf8(dataset).unwrap().contains("against e9"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,5 @@
-use crate::data_model::{
-    Game, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, Player, WALL_GRID_WIDTH, WallOrientation,
-};
+use quoridor_core::data_model::{Game, Player, PlayerMove, WallCentre, WallOrientation};
+use quoridor_core::wall_effects::WallEffect;
 use ggez::graphics::{self, PxScale, TextFragment, Transform};
 use ggez::mint::{Point2, Vector2};
 use ggez::{Context, GameResult};
@@ -12,6 +11,12 @@ enum Color {
     Wall,
     Background,
     Text,
+    /// Wall slots hurting the opponent more, the side to move more, or neither.
+    WallGain,
+    WallLoss,
+    WallEven,
+    IllegalWall,
+    Cursor,
 }
 
 impl Color {
@@ -23,23 +28,56 @@ impl Color {
             Color::PieceSquare => graphics::Color::from_rgb(240, 217, 181),
             Color::Background => graphics::Color::from_rgb(181, 136, 99),
             Color::Text => graphics::Color::from_rgb(255, 255, 255),
+            Color::WallGain => graphics::Color::from_rgb(46, 160, 67),
+            Color::WallLoss => graphics::Color::from_rgb(207, 34, 46),
+            Color::WallEven => graphics::Color::from_rgb(240, 240, 240),
+            Color::IllegalWall => graphics::Color::from_rgb(60, 60, 60),
+            Color::Cursor => graphics::Color::from_rgb(66, 133, 244),
         }
     }
 }
 
-pub fn draw(game: &Game, ctx: &mut Context) -> GameResult {
+/// The keyboard cursor, on a square or, when placing walls, on the centre of a wall slot.
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor {
+    pub x: usize,
+    pub y: usize,
+    pub wall: Option<WallOrientation>,
+}
+
+/// What is drawn on top of the board.
+pub struct Overlays<'a> {
+    /// Shown in the eval bar to the right of the board.
+    pub white_win_probability: f64,
+    /// Text shown next to the mouse position.
+    pub tooltip: Option<(&'a str, [f32; 2])>,
+    /// A message shown at the top.
+    pub toast: Option<&'a str>,
+    /// A text panel shown at the bottom.
+    pub panel: Option<&'a str>,
+    /// Wall slots shaded by legality and by their effect on the paths.
+    pub wall_effects: Option<&'a [WallEffect]>,
+    pub cursor: Option<Cursor>,
+}
+
+pub fn draw(game: &Game, overlays: &Overlays, ctx: &mut Context) -> GameResult {
     let window_size = ctx.gfx.window().inner_size();
-    let total_board_size = u32::min(window_size.width, window_size.height) as f32;
+    let eval_bar_width = u32::min(window_size.width, window_size.height) as f32 / 40.0;
+    let total_board_size = f32::min(
+        window_size.width as f32 - 2.0 * eval_bar_width,
+        window_size.height as f32,
+    );
     const PIECE_SQUARE_SIZE_TO_WALL_WIDTH_RATIO: f32 = 5.0;
+    let board_size = game.rules.board_size;
     let wall_thickness = total_board_size
-        / (PIECE_GRID_WIDTH as f32 * PIECE_SQUARE_SIZE_TO_WALL_WIDTH_RATIO
-            + WALL_GRID_WIDTH as f32);
+        / (board_size as f32 * PIECE_SQUARE_SIZE_TO_WALL_WIDTH_RATIO
+            + (board_size - 1) as f32);
     let piece_square_size = PIECE_SQUARE_SIZE_TO_WALL_WIDTH_RATIO * wall_thickness;
     let wall_length = 2.0 * piece_square_size + wall_thickness;
     let piece_radius = piece_square_size / 3.0;
     let mut canvas = graphics::Canvas::from_frame(ctx, Color::Background.to_ggez_color());
-    for x in 0..PIECE_GRID_WIDTH {
-        for y in 0..PIECE_GRID_HEIGHT {
+    for x in 0..board_size {
+        for y in 0..board_size {
             let screen_x = x as f32 * (piece_square_size + wall_thickness);
             let screen_y = y as f32 * (piece_square_size + wall_thickness);
             let rect =
@@ -81,10 +119,10 @@ pub fn draw(game: &Game, ctx: &mut Context) -> GameResult {
         );
     }
     for (x, col) in game.board.walls.iter().enumerate() {
-        for (y, wall) in col.iter().enumerate() {
+        for (y, centre) in col.iter().enumerate() {
             let screen_x = x as f32 * (piece_square_size + wall_thickness) + piece_square_size;
             let screen_y = y as f32 * (piece_square_size + wall_thickness) + piece_square_size;
-            if let Some(wall) = wall {
+            for wall in centre.orientations() {
                 let rect = match wall {
                     WallOrientation::Horizontal => graphics::Rect::new(
                         screen_x - piece_square_size,
@@ -108,7 +146,8 @@ pub fn draw(game: &Game, ctx: &mut Context) -> GameResult {
                     )?,
                     graphics::DrawParam::default(),
                 );
-            } else {
+            }
+            if *centre == WallCentre::default() {
                 canvas.draw(
                     &graphics::Text::new(TextFragment {
                         text: format!("{x}{y}"),
@@ -132,5 +171,158 @@ pub fn draw(game: &Game, ctx: &mut Context) -> GameResult {
             }
         }
     }
+    for effect in overlays.wall_effects.unwrap_or_default() {
+        let PlayerMove::PlaceWall {
+            orientation,
+            position,
+        } = &effect.wall
+        else {
+            continue;
+        };
+        let screen_x = position.x as f32 * (piece_square_size + wall_thickness) + piece_square_size;
+        let screen_y = position.y as f32 * (piece_square_size + wall_thickness) + piece_square_size;
+        // Half a wall long around its centre, so that neighbouring slots don't overlap
+        let rect = match orientation {
+            WallOrientation::Horizontal => graphics::Rect::new(
+                screen_x + (wall_thickness - piece_square_size) / 2.0,
+                screen_y,
+                piece_square_size,
+                wall_thickness,
+            ),
+            WallOrientation::Vertical => graphics::Rect::new(
+                screen_x,
+                screen_y + (wall_thickness - piece_square_size) / 2.0,
+                wall_thickness,
+                piece_square_size,
+            ),
+        };
+        let (color, strength) = match effect.gain() {
+            None if effect.legal => (Color::WallEven, 1),
+            None => (Color::IllegalWall, 1),
+            Some(gain) if gain > 0 => (Color::WallGain, gain),
+            Some(gain) if gain < 0 => (Color::WallLoss, -gain),
+            Some(_) => (Color::WallEven, 1),
+        };
+        let mut color = color.to_ggez_color();
+        color.a = f32::min(0.3 + 0.2 * strength as f32, 1.0);
+        canvas.draw(
+            &graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::fill(), rect, color)?,
+            graphics::DrawParam::default(),
+        );
+    }
+    if let Some(cursor) = overlays.cursor {
+        let screen_x = cursor.x as f32 * (piece_square_size + wall_thickness);
+        let screen_y = cursor.y as f32 * (piece_square_size + wall_thickness);
+        let rect = match cursor.wall {
+            None => graphics::Rect::new(screen_x, screen_y, piece_square_size, piece_square_size),
+            Some(WallOrientation::Horizontal) => graphics::Rect::new(
+                screen_x,
+                screen_y + piece_square_size,
+                wall_length,
+                wall_thickness,
+            ),
+            Some(WallOrientation::Vertical) => graphics::Rect::new(
+                screen_x + piece_square_size,
+                screen_y,
+                wall_thickness,
+                wall_length,
+            ),
+        };
+        canvas.draw(
+            &graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::stroke(wall_thickness / 2.0),
+                rect,
+                Color::Cursor.to_ggez_color(),
+            )?,
+            graphics::DrawParam::default(),
+        );
+    }
+    // White starts at the top, so White's share of the bar is at the top too
+    let eval_bar_x = total_board_size + eval_bar_width / 2.0;
+    for (color, height) in [
+        (Color::PlayerB, total_board_size),
+        (
+            Color::PlayerA,
+            overlays.white_win_probability as f32 * total_board_size,
+        ),
+    ] {
+        canvas.draw(
+            &graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::fill(),
+                graphics::Rect::new(eval_bar_x, 0.0, eval_bar_width, height),
+                color.to_ggez_color(),
+            )?,
+            graphics::DrawParam::default(),
+        );
+    }
+    if let Some(text) = overlays.panel {
+        draw_text_box(
+            &mut canvas,
+            ctx,
+            text,
+            [wall_thickness, total_board_size],
+            wall_thickness,
+            total_board_size,
+        )?;
+    }
+    if let Some((text, [mouse_x, mouse_y])) = overlays.tooltip {
+        draw_text_box(
+            &mut canvas,
+            ctx,
+            text,
+            [mouse_x + wall_thickness, mouse_y + wall_thickness],
+            wall_thickness,
+            total_board_size,
+        )?;
+    }
+    if let Some(text) = overlays.toast {
+        draw_text_box(
+            &mut canvas,
+            ctx,
+            text,
+            [wall_thickness, wall_thickness],
+            wall_thickness,
+            total_board_size,
+        )?;
+    }
     canvas.finish(ctx)
 }
+
+/// Draws `text` on a background box with its top left corner at `[x, y]`, moved inside
+/// the board if needed.
+fn draw_text_box(
+    canvas: &mut graphics::Canvas,
+    ctx: &mut Context,
+    text: &str,
+    [x, y]: [f32; 2],
+    wall_thickness: f32,
+    total_board_size: f32,
+) -> GameResult {
+    let text = graphics::Text::new(TextFragment {
+        text: text.to_string(),
+        color: Some(Color::Text.to_ggez_color()),
+        font: Some("LiberationMono-Regular".into()),
+        scale: Some(PxScale::from(wall_thickness * 1.5)),
+    });
+    let text_size = text.measure(ctx)?;
+    let x = f32::min(x, total_board_size - text_size.x - wall_thickness).max(0.0);
+    let y = f32::min(y, total_board_size - text_size.y - wall_thickness).max(0.0);
+    canvas.draw(
+        &graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(
+                x - wall_thickness / 2.0,
+                y - wall_thickness / 2.0,
+                text_size.x + wall_thickness,
+                text_size.y + wall_thickness,
+            ),
+            Color::Wall.to_ggez_color(),
+        )?,
+        graphics::DrawParam::default(),
+    );
+    canvas.draw(&text, graphics::DrawParam::default().dest([x, y]));
+    Ok(())
+}
@@ -0,0 +1,162 @@
+// Evaluation arena. A candidate checkpoint plays the best checkpoint so far with
+// colours alternating, and is promoted when it scores at least `PROMOTION_SCORE`.
+// The score is added to the candidate's manifest.
+//
+// Every game is written to `arena/<candidate>-vs-<best>.records` in the run directory,
+// one game record per line, tagged with the checkpoint playing each colour and the
+// result:
+//
+//     [white=iter-0004,black=iter-0003,result=0-1]e2;e8;...
+//
+// so lost games of a promoted checkpoint can be replayed with `import` in the GUI.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use quoridor_core::arena::play_match;
+use quoridor_core::engine::SearchLimits;
+use quoridor_core::rules::Rules;
+
+use crate::checkpoint::{EvaluationResult, add_evaluation, load_checkpoint};
+use crate::nn_bot::NeuralNetEngine;
+
+/// Share of the points a candidate needs to replace the best checkpoint.
+pub const PROMOTION_SCORE: f64 = 0.55;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationConfig {
+    /// Games per evaluation, none to promote every checkpoint unseen.
+    pub games: usize,
+    pub max_plies: usize,
+    /// Lower than in self-play, so the networks play closer to their best moves.
+    pub temperature: f32,
+}
+
+impl Default for EvaluationConfig {
+    fn default() -> Self {
+        Self {
+            games: 20,
+            max_plies: 200,
+            temperature: 0.25,
+        }
+    }
+}
+
+/// Points scored by the candidate as a share of the games, a draw counting half.
+pub fn score(result: &EvaluationResult) -> f64 {
+    let games = result.wins + result.losses + result.draws;
+    if games == 0 {
+        return 0.0;
+    }
+    (result.wins as f64 + result.draws as f64 / 2.0) / games as f64
+}
+
+pub fn is_promoted(result: &EvaluationResult) -> bool {
+    score(result) >= PROMOTION_SCORE
+}
+
+pub fn records_path(run_dir: &Path, candidate: &str, best: &str) -> PathBuf {
+    run_dir
+        .join("arena")
+        .join(format!("{candidate}-vs-{best}.records"))
+}
+
+/// Plays the evaluation games of `candidate` against `best` with `rules`, writing
+/// their records and adding the result to the manifest of `candidate`.
+pub fn evaluate(
+    run_dir: &Path,
+    candidate: &str,
+    best: &str,
+    rules: &Rules,
+    config: &EvaluationConfig,
+) -> Result<EvaluationResult, String> {
+    let mut candidate_engine =
+        NeuralNetEngine::new(load_checkpoint(run_dir, candidate)?.0, config.temperature);
+    let mut best_engine =
+        NeuralNetEngine::new(load_checkpoint(run_dir, best)?.0, config.temperature);
+    let mut records = String::new();
+    let score = play_match(
+        &mut candidate_engine,
+        &mut best_engine,
+        config.games,
+        rules,
+        &SearchLimits::default(),
+        config.max_plies,
+        |_, candidate_is_white, finished_game| {
+            let (white, black) = if candidate_is_white {
+                (candidate, best)
+            } else {
+                (best, candidate)
+            };
+            let mut record = finished_game.record.clone();
+            record.set_tag("white", white);
+            record.set_tag("black", black);
+            record.set_tag("result", finished_game.outcome.result());
+            records += &format!("{record}\n");
+            true
+        },
+    );
+
+    let path = records_path(run_dir, candidate, best);
+    std::fs::create_dir_all(path.parent().unwrap())
+        .and_then(|_| std::fs::write(&path, records))
+        .map_err(|e| format!("Could not write {}: {e}", path.display()))?;
+    let result = EvaluationResult {
+        opponent: best.to_string(),
+        wins: score.wins,
+        losses: score.losses,
+        draws: score.draws,
+    };
+    add_evaluation(run_dir, candidate, result.clone())?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::{CheckpointManifest, load_manifest, save_checkpoint};
+    use crate::nn_bot::QuoridorNet;
+    use quoridor_core::game_record::GameRecord;
+
+    #[test]
+    fn arena_records_test() {
+        let run_dir = std::env::temp_dir().join(format!("evaluation-test-{}", std::process::id()));
+        for (name, parent) in [("iter-0000", None), ("iter-0001", Some("iter-0000"))] {
+            let manifest = CheckpointManifest::new(name, parent);
+            save_checkpoint(&run_dir, &QuoridorNet::new(), &manifest).unwrap();
+        }
+        let config = EvaluationConfig {
+            games: 2,
+            max_plies: 4,
+            ..EvaluationConfig::default()
+        };
+        let result = evaluate(
+            &run_dir,
+            "iter-0001",
+            "iter-0000",
+            &Rules::default(),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(result.draws, 2);
+        assert!(!is_promoted(&result));
+        assert_eq!(
+            load_manifest(&run_dir, "iter-0001").unwrap().evaluations,
+            [result]
+        );
+
+        let records =
+            std::fs::read_to_string(records_path(&run_dir, "iter-0001", "iter-0000")).unwrap();
+        let records: Vec<_> = records
+            .lines()
+            .map(|line| GameRecord::parse(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].tag("white"), Some("iter-0001"));
+        assert_eq!(records[1].tag("white"), Some("iter-0000"));
+        assert_eq!(records[1].tag("result"), Some("1/2-1/2"));
+        assert_eq!(records[1].moves.len(), 4);
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+}
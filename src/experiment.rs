@@ -0,0 +1,426 @@
+// Experiments, training runs of one base configuration with some settings swept. A spec
+// file holds the base `TrainingConfig`, missing fields at their defaults, and the values
+// of each swept setting by its path in the configuration:
+//
+//     {
+//       "base": {"iterations": 20, "evaluation": {"games": 10}},
+//       "sweep": {"learning_rate": [0.001, 0.0003], "self_play.simulations": [0, 100]}
+//     }
+//
+// Every combination of the swept values is a run, in run-01, run-02, ... of the
+// experiment directory, the settings taken in name order, the first varying slowest.
+// The spec is copied to the directory as `SPEC_FILE`, so its runs can be compared, and
+// the experiment launched again, without it.
+//
+// All runs are started before any is trained, then trained one after the other, or in
+// up to `processes` child processes of `train --resume` at a time, each logging to
+// train.log in its run directory. Launching an experiment again resumes the runs that
+// are not finished, so a crash or Ctrl-C costs no more than it does a single run.
+//
+// The comparison table has a row per run with its swept values, its latest losses, the
+// best checkpoint with its Elo in the run (see train_status.rs) and the last gating.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::checkpoint::list_checkpoints;
+use crate::shutdown::Shutdown;
+use crate::train_status::{elo_trend, last_gating};
+use crate::training::{Trainer, TrainingConfig, has_run, read_metrics, read_state};
+
+pub const SPEC_FILE: &str = "experiment.json";
+const LOG_FILE: &str = "train.log";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentSpec {
+    /// Fields of the base configuration, the others at their defaults.
+    #[serde(default)]
+    pub base: Value,
+    /// Values of each swept setting, by its path in the configuration with fields
+    /// separated by dots.
+    #[serde(default)]
+    pub sweep: BTreeMap<String, Vec<Value>>,
+}
+
+/// A run of an experiment.
+#[derive(Debug, Clone)]
+pub struct ExperimentRun {
+    pub name: String,
+    /// The swept settings and their values in the run.
+    pub settings: Vec<(String, Value)>,
+    pub config: TrainingConfig,
+}
+
+impl ExperimentRun {
+    /// The settings as "learning_rate=0.001 self_play.simulations=100".
+    pub fn label(&self) -> String {
+        self.settings
+            .iter()
+            .map(|(path, value)| format!("{path}={value}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl ExperimentSpec {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        serde_json::from_str(&json).map_err(|e| format!("Could not parse {}: {e}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, serde_json::to_string_pretty(self).unwrap())
+            .map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    /// The runs of every combination of the swept values.
+    pub fn runs(&self) -> Result<Vec<ExperimentRun>, String> {
+        let mut base = serde_json::to_value(TrainingConfig::default()).unwrap();
+        if !self.base.is_null() {
+            merge(&mut base, &self.base, "")?;
+        }
+        let mut combinations: Vec<Vec<(String, Value)>> = vec![Vec::new()];
+        for (path, values) in &self.sweep {
+            if values.is_empty() {
+                return Err(format!("No values to sweep {path} over"));
+            }
+            combinations = combinations
+                .into_iter()
+                .flat_map(|settings| {
+                    values.iter().map(move |value| {
+                        let mut settings = settings.clone();
+                        settings.push((path.clone(), value.clone()));
+                        settings
+                    })
+                })
+                .collect();
+        }
+        combinations
+            .into_iter()
+            .enumerate()
+            .map(|(index, settings)| {
+                let mut config = base.clone();
+                for (path, value) in &settings {
+                    let field = path
+                        .split('.')
+                        .try_fold(&mut config, |object, field| object.get_mut(field))
+                        .ok_or_else(|| format!("Unknown setting {path}"))?;
+                    *field = value.clone();
+                }
+                let config = serde_json::from_value(config)
+                    .map_err(|e| format!("Invalid setting in {settings:?}: {e}"))?;
+                Ok(ExperimentRun {
+                    name: format!("run-{:02}", index + 1),
+                    settings,
+                    config,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Writes the fields of `from` over those of `into`, objects field by field.
+fn merge(into: &mut Value, from: &Value, path: &str) -> Result<(), String> {
+    match (into, from) {
+        (Value::Object(into), Value::Object(from)) => {
+            for (field, value) in from {
+                let path = if path.is_empty() {
+                    field.clone()
+                } else {
+                    format!("{path}.{field}")
+                };
+                let into = into
+                    .get_mut(field)
+                    .ok_or_else(|| format!("Unknown setting {path}"))?;
+                merge(into, value, &path)?;
+            }
+            Ok(())
+        }
+        (into, from) => {
+            *into = from.clone();
+            Ok(())
+        }
+    }
+}
+
+/// Starts the runs of the experiment in `dir` that are not started yet and trains them
+/// all to the end, `processes` at a time.
+pub fn run_experiment(
+    dir: &Path,
+    spec: Option<&Path>,
+    processes: usize,
+    shutdown: &Shutdown,
+) -> Result<(), String> {
+    let spec_path = dir.join(SPEC_FILE);
+    let spec = match spec {
+        Some(path) => {
+            let spec = ExperimentSpec::load(path)?;
+            if spec_path.exists() && ExperimentSpec::load(&spec_path)? != spec {
+                return Err(format!(
+                    "{} holds another experiment, launch it without a spec to resume it",
+                    dir.display()
+                ));
+            }
+            spec
+        }
+        None => ExperimentSpec::load(&spec_path)?,
+    };
+    let runs = spec.runs()?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("Could not create {}: {e}", dir.display()))?;
+    spec.save(&spec_path)?;
+    for run in &runs {
+        let run_dir = dir.join(&run.name);
+        if !has_run(&run_dir) {
+            println!("Starting {}: {}", run.name, run.label());
+            Trainer::start(&run_dir, run.config.clone())?;
+        }
+    }
+    let run_dirs: Vec<PathBuf> = runs.iter().map(|run| dir.join(&run.name)).collect();
+    if processes <= 1 {
+        for (run, run_dir) in runs.iter().zip(&run_dirs) {
+            if shutdown.requested() {
+                break;
+            }
+            println!("Training {}: {}", run.name, run.label());
+            Trainer::resume(run_dir)?.train(shutdown)?;
+        }
+        return Ok(());
+    }
+    train_in_processes(&run_dirs, processes, shutdown)
+}
+
+/// Trains the runs in child processes, at most `processes` at a time. Interrupts reach
+/// the children as well, which stop after their current iteration.
+fn train_in_processes(
+    run_dirs: &[PathBuf],
+    processes: usize,
+    shutdown: &Shutdown,
+) -> Result<(), String> {
+    let executable =
+        std::env::current_exe().map_err(|e| format!("Could not find the executable: {e}"))?;
+    let queue = Mutex::new(run_dirs.iter());
+    let errors = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..processes.min(run_dirs.len()) {
+            scope.spawn(|| {
+                loop {
+                    let Some(run_dir) = queue.lock().unwrap().next() else {
+                        break;
+                    };
+                    if shutdown.requested() {
+                        break;
+                    }
+                    if let Err(error) = train_process(&executable, run_dir) {
+                        errors.lock().unwrap().push(error);
+                    }
+                }
+            });
+        }
+    });
+    let errors = errors.into_inner().unwrap();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+fn train_process(executable: &Path, run_dir: &Path) -> Result<(), String> {
+    let log_path = run_dir.join(LOG_FILE);
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(|e| format!("Could not open {}: {e}", log_path.display()))?;
+    let log_error = log
+        .try_clone()
+        .map_err(|e| format!("Could not open {}: {e}", log_path.display()))?;
+    println!(
+        "Training {} in a new process, logging to {}",
+        run_dir.display(),
+        log_path.display()
+    );
+    let status = Command::new(executable)
+        .arg("train")
+        .arg("--resume")
+        .arg(run_dir)
+        .stdin(Stdio::null())
+        .stdout(log)
+        .stderr(log_error)
+        .status()
+        .map_err(|e| format!("Could not start training {}: {e}", run_dir.display()))?;
+    if !status.success() {
+        return Err(format!(
+            "Training {} failed with {status}, see {}",
+            run_dir.display(),
+            log_path.display()
+        ));
+    }
+    println!("Finished {}", run_dir.display());
+    Ok(())
+}
+
+/// The comparison table of the runs of the experiment in `dir`, a row per run.
+pub fn experiment_summary(dir: &Path) -> Result<String, String> {
+    let spec = ExperimentSpec::load(&dir.join(SPEC_FILE))?;
+    let mut rows = vec![[
+        "Run".to_string(),
+        "Settings".to_string(),
+        "Iterations".to_string(),
+        "Losses".to_string(),
+        "Best".to_string(),
+        "Last gating".to_string(),
+    ]];
+    for run in spec.runs()? {
+        let run_dir = dir.join(&run.name);
+        if !has_run(&run_dir) {
+            rows.push([
+                run.name.clone(),
+                run.label(),
+                "not started".to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+            continue;
+        }
+        let (config, counters) = read_state(&run_dir)?;
+        let losses = match read_metrics(&run_dir)?.last() {
+            Some(latest) => format!(
+                "policy {:.4}, value {:.4}",
+                latest.policy_loss, latest.value_loss
+            ),
+            None => "none".to_string(),
+        };
+        let manifests = list_checkpoints(&run_dir)?;
+        let best = match elo_trend(&manifests)
+            .iter()
+            .find(|(name, _)| *name == counters.best)
+        {
+            Some((name, rating)) => format!("{name} {rating:+.0}"),
+            None => counters.best.clone(),
+        };
+        rows.push([
+            run.name.clone(),
+            run.label(),
+            format!("{} of {}", counters.iteration, config.iterations),
+            losses,
+            best,
+            last_gating(&manifests),
+        ]);
+    }
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap())
+        .collect();
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            cells.join("  ").trim_end().to_string() + "\n"
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::UnvisitedValue;
+
+    #[test]
+    fn experiment_test() {
+        let spec: ExperimentSpec = serde_json::from_str(
+            r#"{
+                "base": {"iterations": 0, "network": "feature-mlp", "evaluation": {"games": 0}},
+                "sweep": {"learning_rate": [0.001, 0.0003], "self_play.simulations": [0, 100, 200]}
+            }"#,
+        )
+        .unwrap();
+        let runs = spec.runs().unwrap();
+        assert_eq!(runs.len(), 6);
+        assert_eq!(runs[5].name, "run-06");
+        assert_eq!(
+            runs[1].label(),
+            "learning_rate=0.001 self_play.simulations=100"
+        );
+        assert_eq!(runs[4].config.learning_rate, 0.0003);
+        assert_eq!(runs[4].config.self_play.simulations, 100);
+        // The base replaces fields of nested settings one by one
+        assert_eq!(runs[0].config.evaluation.games, 0);
+        assert_eq!(
+            runs[0].config.evaluation.max_plies,
+            TrainingConfig::default().evaluation.max_plies
+        );
+        // The search settings of self-play, an unset c_puct_base among them
+        let puct_spec: ExperimentSpec = serde_json::from_str(
+            r#"{"sweep": {
+                "self_play.puct.unvisited_value": ["draw", "parent"],
+                "self_play.puct.c_puct_base": [null, 19652]
+            }}"#,
+        )
+        .unwrap();
+        let puct_runs = puct_spec.runs().unwrap();
+        assert_eq!(puct_runs.len(), 4);
+        assert_eq!(puct_runs[0].config.self_play.puct.c_puct_base, None);
+        assert_eq!(
+            puct_runs[3].config.self_play.puct.c_puct_base,
+            Some(19652.0)
+        );
+        assert_eq!(
+            puct_runs[3].config.self_play.puct.unvisited_value,
+            UnvisitedValue::Parent
+        );
+        let unknown = |json: &str| {
+            serde_json::from_str::<ExperimentSpec>(json)
+                .unwrap()
+                .runs()
+                .unwrap_err()
+        };
+        assert_eq!(
+            unknown(r#"{"sweep": {"self_play.simulatons": [1]}}"#),
+            "Unknown setting self_play.simulatons"
+        );
+        assert_eq!(
+            unknown(r#"{"base": {"learning_rat": 1}}"#),
+            "Unknown setting learning_rat"
+        );
+
+        // Runs without iterations are started and compared at once
+        let dir = std::env::temp_dir().join(format!("experiment-test-{}", std::process::id()));
+        let spec_path =
+            std::env::temp_dir().join(format!("experiment-test-{}.json", std::process::id()));
+        let spec = ExperimentSpec {
+            sweep: BTreeMap::from([(
+                "learning_rate".to_string(),
+                spec.sweep["learning_rate"].clone(),
+            )]),
+            ..spec
+        };
+        spec.save(&spec_path).unwrap();
+        run_experiment(&dir, Some(&spec_path), 1, &Shutdown::default()).unwrap();
+        let summary = experiment_summary(&dir).unwrap();
+        assert_eq!(summary.lines().count(), 3);
+        assert!(
+            summary
+                .lines()
+                .nth(2)
+                .unwrap()
+                .starts_with("run-02  learning_rate=0.0003  0 of 0")
+        );
+        // Launching it again resumes it, another spec is refused
+        run_experiment(&dir, None, 1, &Shutdown::default()).unwrap();
+        std::fs::write(&spec_path, r#"{"sweep": {"learning_rate": [0.01]}}"#).unwrap();
+        assert!(run_experiment(&dir, Some(&spec_path), 1, &Shutdown::default()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_file(&spec_path).unwrap();
+    }
+}
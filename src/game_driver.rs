@@ -0,0 +1,220 @@
+// The game loop shared by the CLI and the GUI. The driver owns the session and decides
+// whose turn it is: engines of the session play their moves, clocks and game over are
+// the session's, and humans are asked for moves and commands through the frontend. A
+// frontend only shows the game and reads input, so a feature of the game loop is written
+// once for both.
+
+use quoridor_core::data_model::Player;
+use quoridor_core::engine::check_playable;
+use quoridor_core::locale::Message;
+
+use crate::commands::{
+    AuxCommand, Command, LimitArgs, Session, execute_command, offer_autosave, read_legal_command,
+};
+use crate::player_type::PlayerType;
+
+/// What a frontend shows of the game and where it reads the humans' input from.
+pub trait Frontend {
+    /// Shows the position before the side to move is asked for a command, with the
+    /// status lines of who is to move, their walls and the think times.
+    fn show_turn(&mut self, session: &Session, status: &[String]);
+
+    /// The next line of input from the humans, a move or a command, None once the input
+    /// is closed.
+    fn next_line(&mut self) -> Option<String>;
+
+    /// Shows the session after a command.
+    fn show_update(&mut self, _session: &Session) {}
+}
+
+pub struct GameDriver<F: Frontend> {
+    pub session: Session,
+    pub frontend: F,
+    player_types: [PlayerType; 2],
+    limits: LimitArgs,
+    /// Turns after which the driver stops, None to play on.
+    pub max_turns: Option<usize>,
+    /// Whether the driver stops when an engine is to move in a finished game or without
+    /// legal moves, rather than waiting for a command such as an undo or a reset.
+    pub stop_when_unplayable: bool,
+}
+
+impl<F: Frontend> GameDriver<F> {
+    /// A driver playing the engines of `session` with `limits`, `player_types` giving
+    /// White's and Black's player.
+    pub fn new(
+        session: Session,
+        frontend: F,
+        player_types: [PlayerType; 2],
+        limits: LimitArgs,
+    ) -> Self {
+        Self {
+            session,
+            frontend,
+            player_types,
+            limits,
+            max_turns: None,
+            stop_when_unplayable: false,
+        }
+    }
+
+    /// Offers to restore the game saved to the recovery file `autosave`, from then on
+    /// saving the game there after every move.
+    pub fn offer_autosave(&mut self, autosave: Option<std::path::PathBuf>) {
+        self.session.autosave = autosave;
+        if offer_autosave(&mut self.session, || self.frontend.next_line()) {
+            self.frontend.show_update(&self.session);
+        }
+    }
+
+    /// Plays until `max_turns`, until the input is closed or, with `stop_when_unplayable`,
+    /// until an engine can't move.
+    pub fn run(&mut self) {
+        for turn in 0.. {
+            if self.max_turns.is_some_and(|max_turns| turn >= max_turns) {
+                break;
+            }
+            let game = self.session.game_states.last().unwrap();
+            let player = game.player;
+            let status = self.status(player);
+            self.frontend.show_turn(&self.session, &status);
+
+            let engine_to_move =
+                self.session.engines.contains_key(&player) && self.session.puzzles.is_none();
+            let command = match check_playable(game) {
+                Ok(()) if engine_to_move => Some(Command::AuxCommand(AuxCommand::PlayEngineMove {
+                    limits: self.limits,
+                })),
+                Err(error) if engine_to_move => {
+                    println!("{error}");
+                    if self.stop_when_unplayable {
+                        break;
+                    }
+                    self.read_command(player)
+                }
+                _ => {
+                    self.session.ponder();
+                    self.read_command(player)
+                }
+            };
+            let Some(command) = command else {
+                break;
+            };
+            if let Err(error) = execute_command(&mut self.session, command) {
+                println!("{error}");
+            }
+            self.frontend.show_update(&self.session);
+        }
+    }
+
+    fn status(&self, player: Player) -> Vec<String> {
+        let game = self.session.game_states.last().unwrap();
+        let think_times = self.session.think_times();
+        let player_type = self.player_types[player.as_index()].to_string();
+        [
+            Message::ToMove {
+                player,
+                player_type: &player_type,
+            },
+            Message::WallsLeft(game.walls_left),
+            Message::ThinkTimes(&think_times),
+        ]
+        .iter()
+        .map(|message| message.text(self.session.locale))
+        .collect()
+    }
+
+    fn read_command(&mut self, player: Player) -> Option<Command> {
+        let session = &self.session;
+        read_legal_command(
+            session.game_states.last().unwrap(),
+            player,
+            session.locale,
+            session.wall_notation,
+            || self.frontend.next_line(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, VecDeque};
+
+    use quoridor_core::engine::{Engine, GreedyEngine};
+    use quoridor_core::game_logic::winner;
+    use quoridor_core::rules::Rules;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct ScriptedFrontend {
+        lines: VecDeque<String>,
+        turns: usize,
+        updates: usize,
+    }
+
+    impl Frontend for ScriptedFrontend {
+        fn show_turn(&mut self, _session: &Session, status: &[String]) {
+            assert_eq!(status.len(), 3);
+            self.turns += 1;
+        }
+
+        fn next_line(&mut self) -> Option<String> {
+            self.lines.pop_front()
+        }
+
+        fn show_update(&mut self, _session: &Session) {
+            self.updates += 1;
+        }
+    }
+
+    fn greedy(players: &[Player]) -> HashMap<Player, Box<dyn Engine>> {
+        players
+            .iter()
+            .map(|&player| (player, Box::new(GreedyEngine) as Box<dyn Engine>))
+            .collect()
+    }
+
+    #[test]
+    fn driver_test() {
+        // A human against an engine, the illegal move asked again
+        let frontend = ScriptedFrontend {
+            lines: ["muu", "mdd", "mdd"].map(String::from).into(),
+            ..ScriptedFrontend::default()
+        };
+        let session = Session::new(greedy(&[Player::Black]), Rules::default());
+        let player_types = [PlayerType::Human, PlayerType::Greedy];
+        let mut driver = GameDriver::new(session, frontend, player_types, LimitArgs::default());
+        driver.max_turns = Some(3);
+        driver.run();
+        assert_eq!(driver.session.game_states.len(), 4);
+        assert_eq!(driver.frontend.turns, 3);
+        assert_eq!(driver.frontend.updates, 3);
+        assert!(driver.frontend.lines.is_empty());
+
+        // The game ends with the input
+        let frontend = ScriptedFrontend {
+            lines: ["mdd".to_string()].into(),
+            ..ScriptedFrontend::default()
+        };
+        let session = Session::new(greedy(&[Player::Black]), Rules::default());
+        let player_types = [PlayerType::Human, PlayerType::Greedy];
+        let mut driver = GameDriver::new(session, frontend, player_types, LimitArgs::default());
+        driver.run();
+        assert_eq!(driver.session.game_states.len(), 3);
+        assert_eq!(driver.frontend.turns, 3);
+
+        // Engines against each other play until the game is over
+        let session = Session::new(greedy(&[Player::White, Player::Black]), Rules::default());
+        let player_types = [PlayerType::Greedy, PlayerType::Greedy];
+        let mut driver = GameDriver::new(
+            session,
+            ScriptedFrontend::default(),
+            player_types,
+            LimitArgs::default(),
+        );
+        driver.stop_when_unplayable = true;
+        driver.run();
+        assert!(winner(driver.session.game_states.last().unwrap()).is_some());
+    }
+}
@@ -1,93 +1,276 @@
 use std::collections::HashMap;
 
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 use burn::backend::NdArray ;
 
 
-use crate::commands::{Command, Session, execute_command, get_legal_command};
-use crate::data_model::{Player};
-use crate::player_type::{PlayerType};
-use crate::nn_bot::{QuoridorNet};
+use crate::commands::{
+    Command, LimitArgs, ParseCommandResult, Session, TakebackPolicy, execute_command,
+    time_control,
+};
+use crate::game_driver::{Frontend, GameDriver};
+use crate::player_type::{EngineOptions, PlayerOptions, PlayerType, engine_description};
+use crate::terminal::Terminal;
+use quoridor_core::engine::Engine;
+use quoridor_core::locale::Locale;
+use quoridor_core::notation::{self, WallNotation};
+use quoridor_core::rules::Rules;
+use quoridor_core::win_probability::WinProbabilityModel;
+use quoridor_core::data_model::{Game, Player};
+use quoridor_core::render_board;
 
 
-pub mod all_moves;
 pub mod nn_bot;
-pub mod a_star;
-pub mod bot;
 pub mod commands;
-pub mod data_model;
-pub mod game_logic;
+pub mod game_driver;
+pub mod mcts;
 pub mod player_type;
-pub mod render_board;
-pub mod square_outline_iterator;
+pub mod shutdown;
+pub mod terminal;
+
+/// Depth of the engines' searches without other limits.
+const DEFAULT_DEPTH: usize = 4;
 
 #[derive(clap_derive::Parser, Debug)]
+// An external player's command is the shared one or in the player's own options
+#[command(group(ArgGroup::new("white_engine").args(["engine_command", "white_options"]).multiple(true)))]
+#[command(group(ArgGroup::new("black_engine").args(["engine_command", "black_options"]).multiple(true)))]
 struct Args {
-    #[clap(short, long, default_value_t = 4)]
-    depth: usize,
+    // Search limits of the engines, depth `DEFAULT_DEPTH` unless a depth or move time is given
+    #[command(flatten)]
+    limits: LimitArgs,
+
+    /// Seconds on each player's clock, engines sharing out the time they have left over their moves.
+    #[clap(long)]
+    clock: Option<u64>,
+
+    /// Seconds added to a player's clock after each of their moves.
+    #[clap(long, default_value_t = 0, requires = "clock")]
+    increment: u64,
+
+    /// How engines answer takeback requests, which undo makes in games with a clock.
+    #[clap(long, value_enum, default_value_t = TakebackPolicy::Accept)]
+    takebacks: TakebackPolicy,
+
+    #[command(flatten)]
+    engine_options: EngineOptions,
+
+    #[clap(long, default_value = "standard", value_parser = commands::parse_rules)]
+    rules: Rules,
 
-    #[clap(short, long, default_value_t = 0.0)]
-    temperature: f32,
+    /// Language of the prompts, move explanations and puzzle feedback, one of en and nb, by
+    /// default the language of the system locale.
+    #[clap(long, value_parser = commands::parse_locale)]
+    locale: Option<Locale>,
 
-    #[clap(short='a', long, default_value_t = PlayerType::Human)]
+    #[clap(short='a', long, default_value_t = PlayerType::Human, requires_if("external", "white_engine"))]
     player_a: PlayerType,
 
-    #[clap(short='b', long, default_value_t = PlayerType::Bot)]
+    #[clap(short='b', long, default_value_t = PlayerType::Bot, requires_if("external", "black_engine"))]
     player_b: PlayerType,
 
+    /// Engine options and search limits of White's engine, replacing the shared ones, e.g. "-d 6".
+    #[clap(long, value_parser = player_type::parse_player_options, allow_hyphen_values = true)]
+    white_options: Option<PlayerOptions>,
+
+    /// Engine options and search limits of Black's engine, replacing the shared ones,
+    /// e.g. "--network black.mpk --simulations 400".
+    #[clap(long, value_parser = player_type::parse_player_options, allow_hyphen_values = true)]
+    black_options: Option<PlayerOptions>,
+
+    /// Your name on the leaderboard, recording your finished games against an engine.
+    #[clap(long)]
+    name: Option<String>,
+
+    /// The leaderboard file, shown with the leaderboard and stats commands.
+    #[clap(long, default_value = commands::DEFAULT_LEADERBOARD)]
+    leaderboard: std::path::PathBuf,
+
     #[clap(short, long)]
     end_after_moves: Option<usize>,
+
+    /// Flag human moves that are at least this many steps worse than the engine's choice.
+    #[clap(long)]
+    blunder_check: Option<isize>,
+
+    /// Win probability model written by the fit-win-model command.
+    #[clap(long, value_parser = commands::load_win_model)]
+    win_model: Option<WinProbabilityModel>,
+
+    /// Play this many games between the two players instead of an interactive game.
+    #[clap(short, long)]
+    games: Option<usize>,
+
+    /// With --games, append the game records to this file.
+    #[clap(long)]
+    save_games: Option<std::path::PathBuf>,
+
+    /// With --games, append every move with its eval to this stream file as it is played.
+    #[clap(long)]
+    broadcast: Option<std::path::PathBuf>,
+
+    /// With --games, write the broadcast stream to stdout.
+    #[clap(long)]
+    follow: bool,
+
+    /// Execute this command, as typed at the prompt, instead of an interactive game, for
+    /// example `--run "analyze-batch games"`.
+    #[clap(long)]
+    run: Option<String>,
+
+    /// How walls are typed and shown in the move history: internal (h42), standard (e3h) or
+    /// bga (e4h), all three naming the same wall.
+    #[clap(long, value_parser = commands::parse_wall_notation, default_value = "standard")]
+    wall_notation: WallNotation,
+
+    /// Draws the board in plain ASCII without colors, for terminals that show garbled boards.
+    #[clap(long)]
+    plain: bool,
+
+    /// Save the game to this recovery file after every move, offering to restore it on startup.
+    /// Without a file, autosave.txt.
+    #[clap(long, num_args = 0..=1, default_missing_value = commands::DEFAULT_AUTOSAVE)]
+    autosave: Option<std::path::PathBuf>,
+
+    /// Print the action ids of the network and their moves, then exit.
+    #[clap(long)]
+    dump_action_space: bool,
 }
 
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if let Err(error) = nn_bot::validate_action_space() {
+        eprintln!("Invalid action space: {error}");
+        std::process::exit(1);
+    }
+    if args.dump_action_space {
+        print!("{}", nn_bot::action_space_table());
+        return;
+    }
+    let side_options = [&mut args.white_options, &mut args.black_options].into_iter().flatten();
+    for options in std::iter::once(&mut args.engine_options).chain(side_options.map(|options| &mut options.engine_options)) {
+        if let Err(error) = options.load_opponent(&args.leaderboard, args.name.as_deref()) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    }
 
     let device = <NdArray as burn::prelude::Backend>::Device::default();
 
-    let mut neural_networks: HashMap<Player, QuoridorNet> = HashMap::new();
+    let mut engines: HashMap<Player, Box<dyn Engine>> = HashMap::new();
+    for (player, player_type, options) in [(Player::White, args.player_a, &args.white_options), (Player::Black, args.player_b, &args.black_options)] {
+        let engine = match options {
+            Some(options) => options.engine(player_type),
+            None => player_type.engine(&args.engine_options),
+        };
+        let engine = engine.unwrap_or_else(|error| {
+            eprintln!("{error}");
+            std::process::exit(1);
+        });
+        if let Some(engine) = engine {
+            engines.insert(player, engine);
+        }
+    }
+    if let Err(error) = args.engine_options.log_engines(&mut engines) {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
+    player_type::warm_up_engines(&mut engines, &Game::with_rules(args.rules));
+    // Only games of a human against one engine are rated
+    let engine_player = match (args.player_a, args.player_b) {
+        (PlayerType::Human, PlayerType::Human) => None,
+        (PlayerType::Human, player_type) => Some((player_type, &args.black_options)),
+        (player_type, PlayerType::Human) => Some((player_type, &args.white_options)),
+        _ => None,
+    };
+    let leaderboard_player = args.name.clone().zip(engine_player).map(|(name, (player_type, options))| {
+        commands::LeaderboardPlayer { name, engine: engine_description(player_type, options.as_ref(), args.limits.or_depth(DEFAULT_DEPTH)) }
+    });
+
+    let mut session = Session::new(engines, args.rules);
+    session.bot = args.engine_options.alpha_beta_engine();
+    session.blunder_threshold = args.blunder_check;
+    session.win_model = args.win_model.unwrap_or_default();
+    session.time_control = args.clock.map(|clock| time_control(clock, args.increment));
+    session.locale = args.locale.unwrap_or_else(Locale::detect);
+    session.takeback_policy = args.takebacks;
+    session.leaderboard = args.leaderboard.clone();
+    session.leaderboard_player = leaderboard_player;
+    session.wall_notation = args.wall_notation;
+    let terminal = if args.plain { Terminal::detect().plain() } else { Terminal::detect() };
+    session.board_style = terminal.board_style();
+    let limits = args.limits.or_depth(DEFAULT_DEPTH);
 
-    if args.player_a == PlayerType::NeuralNet
-    {
-        neural_networks.insert(Player::White, QuoridorNet::new());
+    if let Some(games) = args.games {
+        let result = execute_command(
+            &mut session,
+            Command::AuxCommand(commands::AuxCommand::Match {
+                games,
+                limits,
+                max_plies: 200,
+                save: args.save_games,
+                broadcast: args.broadcast,
+                follow: args.follow,
+            }),
+        );
+        if let Err(error) = result {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
     }
-    if args.player_b == PlayerType::NeuralNet
-    {
-        neural_networks.insert(Player::Black, QuoridorNet::new());
+
+    if let Some(command) = args.run {
+        match commands::parse_command(&command, args.wall_notation) {
+            ParseCommandResult::Command(command) => {
+                if let Err(error) = execute_command(&mut session, command) {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
+            ParseCommandResult::HelpText(text) => {
+                eprintln!("{text}");
+                std::process::exit(2);
+            }
+            ParseCommandResult::InvalidInput => {
+                eprintln!("Invalid command: {command}");
+                std::process::exit(2);
+            }
+        }
+        return;
     }
 
-    let player_type = |p: Player| match p {
-        Player::White => args.player_a,
-        Player::Black => args.player_b,
-    };
-    let mut session = Session::new(neural_networks);
-
-    for move_number in 0.. {
-        let current_game_state = session.game_states.last().unwrap();
-        let player = current_game_state.player;
-        if let Some(end_after_moves) = args.end_after_moves
-            && move_number >= end_after_moves
-        {
-            break;
+    let mut driver = GameDriver::new(session, CliFrontend { terminal }, [args.player_a, args.player_b], limits);
+    driver.max_turns = args.end_after_moves;
+    driver.stop_when_unplayable = true;
+    driver.offer_autosave(args.autosave.clone());
+    driver.run();
+}
+
+/// Draws the board in the terminal before every turn and reads the humans' input from standard input.
+struct CliFrontend {
+    terminal: Terminal,
+}
+
+impl Frontend for CliFrontend {
+    fn show_turn(&mut self, session: &Session, status: &[String]) {
+        let game = session.game_states.last().unwrap();
+        if self.terminal.fits(render_board::BOARD_WIDTH) {
+            println!("{}", render_board::render_board_with(&game.board, session.board_style));
+        } else {
+            println!("{}", notation::position_to_string(game));
         }
-        println!("{}", render_board::render_board(&current_game_state.board));
-        println!(
-            "{} ({}) to move. Walls: White: {}, Black: {}",
-            player.to_string(),
-            player_type(player),
-            current_game_state.walls_left[Player::White.as_index()],
-            current_game_state.walls_left[Player::Black.as_index()]
-        );
+        let line = status.join(". ");
+        // Each part on a line of its own rather than wrapped mid-word by a narrow terminal
+        if self.terminal.fits(line.chars().count()) { println!("{line}") } else { println!("{}", status.join("\n")) }
+    }
 
-        let command = match player_type(player) {
-            PlayerType::Human => get_legal_command(current_game_state, player),
-            PlayerType::NeuralNet => {
-                Command::AuxCommand(commands::AuxCommand::PlayNNMove {temperature: args.temperature})
-            },
-            PlayerType::Bot => Command::AuxCommand(commands::AuxCommand::PlayBotMove {
-                depth: Some(args.depth),
-                seconds: None,
-            }),
-        };
-        execute_command(&mut session, command);
+    fn next_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        // Nothing read at the end of the input
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line),
+        }
     }
 }
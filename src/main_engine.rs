@@ -0,0 +1,22 @@
+use clap::Parser;
+use quoridor_core::engine::AlphaBetaEngine;
+use quoridor_core::protocol::run_protocol;
+use quoridor_core::transposition::DEFAULT_HASH_MB;
+
+#[derive(clap_derive::Parser, Debug)]
+struct Args {
+    /// Size of the transposition table in megabytes.
+    #[clap(long, default_value_t = DEFAULT_HASH_MB)]
+    hash_mb: usize,
+}
+
+/// Runs the alpha-beta bot over the text engine protocol on stdin and stdout,
+/// so other programs can use it as an external engine.
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    run_protocol(
+        &mut AlphaBetaEngine::new().with_hash_mb(args.hash_mb),
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+    )
+}
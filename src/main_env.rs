@@ -0,0 +1,48 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+use clap::Parser;
+
+use quoridor_core::all_moves::validate_all_moves;
+use quoridor_core::rl_env::{DEFAULT_MAX_PLIES, QuoridorEnv};
+
+#[derive(clap_derive::Parser, Debug)]
+struct Args {
+    #[clap(short, long, default_value = "127.0.0.1:50052")]
+    address: std::net::SocketAddr,
+
+    /// Episodes are truncated after this many plies.
+    #[clap(long, default_value_t = DEFAULT_MAX_PLIES)]
+    max_plies: usize,
+}
+
+/// Serves one environment per connection over the JSON lines protocol.
+fn serve_connection(stream: TcpStream, max_plies: usize) -> std::io::Result<()> {
+    let mut env = QuoridorEnv::new(max_plies);
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", env.handle_line(&line))?;
+    }
+    Ok(())
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    validate_all_moves().map_err(std::io::Error::other)?;
+    let listener = TcpListener::bind(args.address)?;
+    println!("Quoridor environment listening on {}", args.address);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let max_plies = args.max_plies;
+        std::thread::spawn(move || {
+            if let Err(e) = serve_connection(stream, max_plies) {
+                eprintln!("Connection closed: {e}");
+            }
+        });
+    }
+    Ok(())
+}
@@ -1,68 +1,242 @@
-use crate::commands::{Command, Session, execute_command, get_legal_command};
-use crate::data_model::{Game, Player};
-use crate::player_type::PlayerType;
-use crate::nn_bot::{QuoridorNet};
-use clap::Parser;
+use crate::commands::{Command, LimitArgs, Session, TakebackPolicy, execute_command, time_control};
+use crate::game_driver::{Frontend, GameDriver};
+use crate::draw::Cursor;
+use crate::sounds::{Sound, Sounds};
+use crate::mcts::{MctsConfig, MctsEngine, RootStats};
+use crate::nn_bot::{QuoridorNet, policy_moves, predict_games};
+use crate::player_type::{EngineOptions, PlayerOptions, PlayerType, engine_description};
+use quoridor_core::analysis::{Score, SearchResult};
+use quoridor_core::engine::{AlphaBetaEngine, Clock, Engine, MAX_ADAPTIVE_DEPTH, SearchLimits};
+use quoridor_core::accuracy::{DECISIVE_LOSS, KEY_MOMENTS, analyse_game};
+use quoridor_core::eval_graph::{annotate_evals, evals, sparkline};
+use quoridor_core::arena::{Outcome, PlayedMove, play_match_with};
+use quoridor_core::game_record::{GameRecord, MoveAnnotation};
+use quoridor_core::game_logic::{execute_move_unchecked, winner};
+use quoridor_core::locale::{Locale, Message};
+use quoridor_core::notation::WallNotation;
+use quoridor_core::rules::Rules;
+use quoridor_core::wall_effects::{WallEffect, wall_effects};
+use quoridor_core::win_probability::WinProbabilityModel;
+use quoridor_core::data_model::{Game, PiecePosition, Player, PlayerMove, WallOrientation, WallPosition};
+use quoridor_core::game_logic::is_move_legal;
+use quoridor_core::playout::pawn_move_to;
+use clap::{ArgGroup, Parser};
 use ggez::conf::WindowMode;
 use ggez::event::{self, EventHandler};
+use ggez::input::keyboard::{KeyCode, KeyInput};
 use ggez::{Context, ContextBuilder, GameResult};
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::sync::mpsc::{Receiver, channel};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::time::{Duration, Instant};
 use burn::backend::NdArray;
 
 
-pub mod all_moves;
-pub mod a_star;
-pub mod bot;
 pub mod nn_bot;
 pub mod commands;
-pub mod data_model;
+pub mod game_driver;
+pub mod mcts;
 pub mod draw;
-pub mod game_logic;
 pub mod player_type;
-pub mod render_board;
-pub mod square_outline_iterator;
+pub mod sounds;
+pub mod shutdown;
 
 #[derive(clap_derive::Parser, Debug)]
+// An external player's command is the shared one or in the player's own options
+#[command(group(ArgGroup::new("white_engine").args(["engine_command", "white_options"]).multiple(true)))]
+#[command(group(ArgGroup::new("black_engine").args(["engine_command", "black_options"]).multiple(true)))]
 struct Args {
-    #[arg(short, long, group = "time_control")]
-    depth: Option<usize>,
+    #[command(flatten)]
+    limits: LimitArgs,
 
-    #[arg(short, long, group = "time_control")]
-    seconds: Option<u64>,
+    /// Seconds on each player's clock, engines sharing out the time they have left over their moves.
+    #[clap(long)]
+    clock: Option<u64>,
+
+    /// Seconds added to a player's clock after each of their moves.
+    #[clap(long, default_value_t = 0, requires = "clock")]
+    increment: u64,
+
+    /// How engines answer takeback requests, which undo makes in games with a clock.
+    #[clap(long, value_enum, default_value_t = TakebackPolicy::Accept)]
+    takebacks: TakebackPolicy,
 
-    #[clap(short, long, default_value_t = 0.0)]
-    temperature: f32,
+    #[command(flatten)]
+    engine_options: EngineOptions,
 
-    #[clap(short='a', long, default_value_t = PlayerType::Human)]
+    #[clap(long, default_value = "standard", value_parser = commands::parse_rules)]
+    rules: Rules,
+
+    /// Language of the prompts, move explanations, puzzle feedback and panels, one of en and nb,
+    /// by default the language of the system locale.
+    #[clap(long, value_parser = commands::parse_locale)]
+    locale: Option<Locale>,
+
+    #[clap(short='a', long, default_value_t = PlayerType::Human, requires_if("external", "white_engine"))]
     player_a: PlayerType,
 
-    #[clap(short='b', long, default_value_t = PlayerType::Bot)]
+    #[clap(short='b', long, default_value_t = PlayerType::Bot, requires_if("external", "black_engine"))]
     player_b: PlayerType,
 
+    /// Engine options and search limits of White's engine, replacing the shared ones, e.g. "-d 6".
+    #[clap(long, value_parser = player_type::parse_player_options, allow_hyphen_values = true)]
+    white_options: Option<PlayerOptions>,
+
+    /// Engine options and search limits of Black's engine, replacing the shared ones,
+    /// e.g. "--network black.mpk --simulations 400".
+    #[clap(long, value_parser = player_type::parse_player_options, allow_hyphen_values = true)]
+    black_options: Option<PlayerOptions>,
+
+    /// Your name on the leaderboard, recording your finished games against an engine. Press L
+    /// to show your stats.
+    #[clap(long)]
+    name: Option<String>,
+
+    /// The leaderboard file, shown with the leaderboard and stats commands.
+    #[clap(long, default_value = commands::DEFAULT_LEADERBOARD)]
+    leaderboard: std::path::PathBuf,
+
     #[clap(short, long)]
     end_after_moves: Option<usize>,
 
+    /// Flag human moves that are at least this many steps worse than the engine's choice.
+    #[clap(long)]
+    blunder_check: Option<isize>,
+
+    /// Win probability model written by the fit-win-model command.
+    #[clap(long, value_parser = commands::load_win_model)]
+    win_model: Option<WinProbabilityModel>,
+
     #[clap(short, long, default_value_t = 1000)]
     window_size: usize,
 
     #[clap(long)]
     skip_initial_moves: bool,
+
+    /// Show a live tree search analysis of the position in the panel, with the --network weights.
+    /// Press C to compare the network's evaluation with the alpha-beta bot's next to it.
+    /// Press W, with or without analysis, to shade the wall slots by their effect on both paths.
+    /// Moves are typed in the terminal or played with the keyboard: the arrow keys move a cursor,
+    /// Tab switches between the pawn and the wall orientations, Enter plays and U and R undo and redo.
+    #[clap(long)]
+    analysis: bool,
+
+    /// Start without the sounds of moves, low time and the end of the game. Press M to toggle them.
+    #[clap(long)]
+    mute: bool,
+
+    /// Step through the game in a game record file instead of playing, with R and U, and the eval
+    /// after every move, searched --depth plies deep or 2, as a graph in the panel. The key moments
+    /// are marked with * under the graph, press K to jump to the next one.
+    #[clap(long)]
+    replay: Option<std::path::PathBuf>,
+
+    /// Watch the engines of --player-a and --player-b play a match of this many games instead of
+    /// playing, swapping colours every game, e.g. two checkpoints given with --white-options and
+    /// --black-options. The panel shows the match score, the eval of every move as a graph and the
+    /// last eval of each engine. Press Space to pause and resume and N to play one move while paused.
+    #[clap(long, conflicts_with = "replay")]
+    watch: Option<usize>,
+
+    /// Moves after which a watched game is drawn.
+    #[clap(long, default_value_t = 200, requires = "watch")]
+    max_plies: usize,
+
+    /// How walls are typed in the terminal and shown in the move history: internal (h42),
+    /// standard (e3h) or bga (e4h), all three naming the same wall.
+    #[clap(long, value_parser = commands::parse_wall_notation, default_value = "standard")]
+    wall_notation: WallNotation,
+
+    /// Save the game to this recovery file after every move, offering to restore it in the
+    /// terminal on startup. Without a file, autosave.txt.
+    #[clap(long, num_args = 0..=1, default_missing_value = commands::DEFAULT_AUTOSAVE)]
+    autosave: Option<std::path::PathBuf>,
+}
+
+/// The analysis of a replayed game shown in the panel.
+struct Replay {
+    /// The eval after every move.
+    evals: Vec<Option<isize>>,
+    /// The plies of the key moments, in order.
+    key_moments: Vec<usize>,
+    key_moments_text: String,
+}
+
+/// The first game record in the file, with the eval after every move, and its analysis.
+fn load_replay(path: &std::path::Path, rules: &Rules, depth: usize) -> Result<(GameRecord, Replay), String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    let line = contents.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    let mut record = GameRecord::parse(line).ok_or_else(|| format!("Could not parse the game record in {}", path.display()))?;
+    let game_states = record.game_states_with_rules(rules);
+    annotate_evals(&mut record, &game_states, depth);
+    let report = analyse_game(&game_states, record.player_moves(), depth);
+    let replay = Replay {
+        evals: evals(&record),
+        key_moments: report.key_moments(KEY_MOMENTS).iter().map(|review| review.ply).collect(),
+        key_moments_text: report.key_moments_text(KEY_MOMENTS),
+    };
+    Ok((record, replay))
 }
 
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let (replay_record, replay) = args.replay.as_deref().map(|path| {
+        load_replay(path, &args.rules, args.limits.depth.unwrap_or(2)).unwrap_or_else(|error| {
+            eprintln!("{error}");
+            std::process::exit(1);
+        })
+    }).unzip();
 
-    let mut neural_networks: HashMap<Player, QuoridorNet> = HashMap::new();
+    let side_options = [&mut args.white_options, &mut args.black_options].into_iter().flatten();
+    for options in std::iter::once(&mut args.engine_options).chain(side_options.map(|options| &mut options.engine_options)) {
+        if let Err(error) = options.load_opponent(&args.leaderboard, args.name.as_deref()) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    }
 
-    if args.player_a == PlayerType::NeuralNet
-    {
-        neural_networks.insert(Player::White, QuoridorNet::new());
+    let mut engines: HashMap<Player, Box<dyn Engine>> = HashMap::new();
+    let players = [(Player::White, args.player_a, &args.white_options), (Player::Black, args.player_b, &args.black_options)];
+    // Replays are stepped through by hand
+    for (player, player_type, options) in players.into_iter().filter(|_| replay.is_none()) {
+        let engine = match options {
+            Some(options) => options.engine(player_type),
+            None => player_type.engine(&args.engine_options),
+        };
+        let engine = engine.unwrap_or_else(|error| {
+            eprintln!("{error}");
+            std::process::exit(1);
+        });
+        if let Some(engine) = engine {
+            engines.insert(player, engine);
+        }
     }
-    if args.player_b == PlayerType::NeuralNet
-    {
-        neural_networks.insert(Player::Black, QuoridorNet::new());
+    if let Err(error) = args.engine_options.log_engines(&mut engines) {
+        eprintln!("{error}");
+        std::process::exit(1);
     }
+    player_type::warm_up_engines(&mut engines, &Game::with_rules(args.rules));
+    // Player a's engine plays first, taking White in the first game
+    let watched = args.watch.map(|games| match (engines.remove(&Player::White), engines.remove(&Player::Black)) {
+        (Some(first), Some(second)) => (games, first, second),
+        _ => {
+            eprintln!("Watching a match needs engines for both players.");
+            std::process::exit(1);
+        }
+    });
+    // Only games of a human against one engine are rated
+    let engine_player = match (args.player_a, args.player_b) {
+        _ if replay.is_some() => None,
+        (PlayerType::Human, PlayerType::Human) => None,
+        (PlayerType::Human, player_type) => Some((player_type, &args.black_options)),
+        (player_type, PlayerType::Human) => Some((player_type, &args.white_options)),
+        _ => None,
+    };
+    let leaderboard_player = args.name.clone().zip(engine_player).map(|(name, (player_type, options))| {
+        commands::LeaderboardPlayer { name, engine: engine_description(player_type, options.as_ref(), args.limits) }
+    });
 
     let (ctx, event_loop) = ContextBuilder::new("quoridor-bot", "Torstein Tenstad")
         .window_mode(
@@ -72,61 +246,701 @@ fn main() {
         )
         .build()
         .unwrap();
-    let (tx, rx) = channel::<Game>();
+    let (tx, rx) = channel::<GuiUpdate>();
+    // Moves and commands come from both the terminal and the keyboard controls of the window
+    let (input_tx, input_rx) = channel::<String>();
+    let terminal_tx = input_tx.clone();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lines().map_while(Result::ok) {
+            if terminal_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let analysis_network = args.analysis.then(|| args.engine_options.network()).transpose().unwrap_or_else(|error| {
+        eprintln!("{error}");
+        std::process::exit(1);
+    });
+    let locale = args.locale.unwrap_or_else(Locale::detect);
+    let watch = watched.map(|(games, first, second)| {
+        let win_model = args.win_model.unwrap_or_default();
+        Watch::start([first, second], games, args.rules, args.limits.search_limits(), args.max_plies, win_model, locale, tx.clone())
+    });
     let gui_state = GuiState {
         rx,
-        current_state: Game::new(),
+        current_state: Game::with_rules(args.rules),
+        explanation: None,
+        toast: None,
+        white_win_probability: 0.5,
+        explorer: None,
+        stats: None,
+        show_stats: false,
+        replay,
+        watch,
+        analysis: analysis_network.clone().map(|network| Analysis::start(network, Game::with_rules(args.rules))),
+        analysis_network,
+        engine_options: args.engine_options.clone(),
+        comparison: None,
+        wall_effects: None,
+        input: input_tx,
+        cursor: None,
+        undo_moves: if engines.len() == 1 { 2 } else { 1 },
+        ply: 0,
+        clock: None,
+        low_time_warned: [false; 2],
+        sounds: Sounds::new(args.mute),
+        locale,
+        mouse_position: [0.0, 0.0],
     };
 
-    std::thread::spawn(move || {
-        let player_type = |p: Player| match p {
-            Player::White => args.player_a,
-            Player::Black => args.player_b,
-        };
-        let mut session = Session::new(neural_networks);
-        loop {
-            let current_game_state = session.game_states.last().unwrap();
-            let player = current_game_state.player;
-            println!(
-                "{} ({}) to move. Walls: White: {}, Black: {}",
-                player.to_string(),
-                player_type(player),
-                current_game_state.walls_left[Player::White.as_index()],
-                current_game_state.walls_left[Player::Black.as_index()]
-            );
-            let command = match player_type(player) {
-                PlayerType::Human => get_legal_command(current_game_state, player),
-                PlayerType::NeuralNet => {
-                    Command::AuxCommand(commands::AuxCommand::PlayNNMove {temperature: args.temperature})
-                },
-                PlayerType::Bot => Command::AuxCommand(commands::AuxCommand::PlayBotMove {
-                    depth: args.depth,
-                    seconds: args.seconds,
-                }),
-            };
-            execute_command(&mut session, command);
-            tx.send(session.game_states.last().unwrap().clone())
-                .unwrap();
-        }
-    });
+    // A watched match plays without a session, the window only pausing and stepping it
+    if gui_state.watch.is_none() {
+        std::thread::spawn(move || {
+            let mut session = Session::new(engines, args.rules);
+            session.bot = args.engine_options.alpha_beta_engine();
+            session.blunder_threshold = args.blunder_check;
+            session.win_model = args.win_model.unwrap_or_default();
+            session.time_control = args.clock.map(|clock| time_control(clock, args.increment));
+            session.locale = locale;
+            session.takeback_policy = args.takebacks;
+            session.leaderboard = args.leaderboard.clone();
+            session.leaderboard_player = leaderboard_player;
+            session.wall_notation = args.wall_notation;
+            let frontend = GuiFrontend { updates: tx, input: input_rx };
+            let mut driver = GameDriver::new(session, frontend, [args.player_a, args.player_b], args.limits);
+            if let Some(record) = replay_record {
+                // From the start of the game, the moves left to redo
+                let moves = record.moves.len();
+                for command in [commands::AuxCommand::Import { moves_string: record.to_string() }, commands::AuxCommand::Undo { moves }] {
+                    if let Err(error) = execute_command(&mut driver.session, Command::AuxCommand(command)) {
+                        println!("{error}");
+                    }
+                }
+            } else {
+                driver.offer_autosave(args.autosave.clone());
+            }
+            driver.run();
+        });
+    }
 
     event::run(ctx, event_loop, gui_state);
 }
 
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+/// Time left on the clock of the side to move at which the low time warning sounds.
+const LOW_TIME: Duration = Duration::from_secs(10);
+const ANALYSIS_INTERVAL: Duration = Duration::from_millis(250);
+const ANALYSIS_TIME: Duration = Duration::from_secs(600);
+/// Policy moves shown in the comparison.
+const COMPARISON_MOVES: usize = 3;
+
+/// Sent from the session thread after every command.
+struct GuiUpdate {
+    game: Game,
+    explanation: Option<String>,
+    toast: Option<String>,
+    white_win_probability: f64,
+    explorer: Option<String>,
+    stats: Option<String>,
+    /// Moves played in the game so far.
+    ply: usize,
+    clock: Option<Clock>,
+    /// The panel text of a watched match.
+    watch: Option<String>,
+}
+
+impl GuiUpdate {
+    fn new(session: &Session) -> Self {
+        Self {
+            game: session.game_states.last().unwrap().clone(),
+            explanation: session.explanation.as_ref().map(|explanation| explanation.text(session.locale)),
+            toast: session.blunder.as_ref().map(|blunder| blunder.text(session.locale)),
+            white_win_probability: session.white_win_probability(),
+            explorer: session.explorer_text(),
+            stats: session.stats.clone(),
+            ply: session.game_states.len() - 1,
+            clock: session.clock(),
+            watch: None,
+        }
+    }
+}
+
+/// Prints the turns to the terminal, sends the window the session after every command and
+/// reads the moves typed in the terminal or made with the window's controls.
+struct GuiFrontend {
+    updates: Sender<GuiUpdate>,
+    input: Receiver<String>,
+}
+
+impl Frontend for GuiFrontend {
+    fn show_turn(&mut self, _session: &Session, status: &[String]) {
+        println!("{}", status.join(". "));
+    }
+
+    fn next_line(&mut self) -> Option<String> {
+        self.input.recv().ok()
+    }
+
+    fn show_update(&mut self, session: &Session) {
+        // Once the window is gone the next read ends the game
+        let _ = self.updates.send(GuiUpdate::new(session));
+    }
+}
+
 struct GuiState {
-    rx: Receiver<Game>,
+    rx: Receiver<GuiUpdate>,
     current_state: Game,
+    /// Explanation of the last hint or move, shown as a tooltip.
+    explanation: Option<String>,
+    /// A message shown for a while after it arrives.
+    toast: Option<(String, Instant)>,
+    /// Shown in the eval bar.
+    white_win_probability: f64,
+    /// Opening explorer continuations, shown in a panel.
+    explorer: Option<String>,
+    /// The player's leaderboard stats, shown in the panel while toggled on with L.
+    stats: Option<String>,
+    show_stats: bool,
+    /// Set with --replay, the evals and key moments of the replayed game.
+    replay: Option<Replay>,
+    /// Set with --watch, the match played in the background.
+    watch: Option<Watch>,
+    /// Set with --analysis, the network analysing every position shown.
+    analysis_network: Option<QuoridorNet>,
+    /// Analysis of the current position, shown in the panel.
+    analysis: Option<Analysis>,
+    /// For the alpha-beta bot of the comparison.
+    engine_options: EngineOptions,
+    /// Toggled with C in analysis mode, the classical and network evaluations of the current position.
+    comparison: Option<Comparison>,
+    /// Toggled with W, the effect of every wall slot for the side to move.
+    wall_effects: Option<Vec<WallEffect>>,
+    /// Sends the moves and commands of the keyboard controls to the session.
+    input: Sender<String>,
+    /// Shown once a cursor key is pressed.
+    cursor: Option<Cursor>,
+    /// Moves per undo or redo key press, a move of each side against an engine.
+    undo_moves: usize,
+    ply: usize,
+    /// The clocks in the current position, with when it was reached.
+    clock: Option<(Clock, Instant)>,
+    /// By `Player::as_index`, so the low time warning sounds once per player.
+    low_time_warned: [bool; 2],
+    sounds: Sounds,
+    locale: Locale,
+    mouse_position: [f32; 2],
+}
+
+/// A tree search of one position running in the background, streaming its root statistics.
+struct Analysis {
+    stop: Arc<AtomicBool>,
+    rx: Receiver<RootStats>,
+    text: Option<String>,
+}
+
+impl Analysis {
+    fn start(network: QuoridorNet, game: Game) -> Self {
+        let mut engine = MctsEngine::new(network, MctsConfig::default());
+        let stop = engine.stop_flag();
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let limits = SearchLimits { movetime: Some(ANALYSIS_TIME), ..SearchLimits::default() };
+            engine.analyse_streaming(&game, &limits, ANALYSIS_INTERVAL, |stats| tx.send(stats).is_ok());
+        });
+        Self { stop, rx, text: None }
+    }
+}
+
+impl Drop for Analysis {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A match between two engines playing in the background, sending the position and the panel
+/// text after every move.
+struct Watch {
+    pause: Arc<WatchPause>,
+    text: Option<String>,
+}
+
+impl Watch {
+    #[allow(clippy::too_many_arguments)]
+    fn start(engines: [Box<dyn Engine>; 2], games: usize, rules: Rules, limits: SearchLimits, max_plies: usize, win_model: WinProbabilityModel, locale: Locale, tx: Sender<GuiUpdate>) -> Self {
+        let pause = Arc::new(WatchPause::default());
+        let match_pause = pause.clone();
+        std::thread::spawn(move || {
+            let [mut first, mut second] = engines;
+            // Written to by both the move and the game callbacks
+            let view = RefCell::new(MatchView::new([first.name(), second.name()], games, rules, locale));
+            play_match_with(
+                first.as_mut(),
+                second.as_mut(),
+                games,
+                &rules,
+                &limits,
+                max_plies,
+                |game_index, first_is_white, played_move| {
+                    let update = view.borrow_mut().move_played(game_index, first_is_white, played_move, &win_model);
+                    if tx.send(update).is_ok() {
+                        match_pause.wait();
+                    }
+                },
+                |game_index, _, finished_game| {
+                    let mut view = view.borrow_mut();
+                    view.game_finished(&finished_game.outcome);
+                    let result = finished_game.outcome.result();
+                    let termination = format!("{:?}", finished_game.termination);
+                    let toast = Message::GameOver { game: game_index + 1, result, termination: &termination }.text(locale);
+                    tx.send(view.update(Some(toast), &win_model)).is_ok()
+                },
+            );
+        });
+        Self { pause, text: None }
+    }
+}
+
+/// Shared by the window and a watched match, which waits after every move while paused.
+#[derive(Default)]
+struct WatchPause {
+    /// Whether paused, and the moves to play before waiting again.
+    state: Mutex<(bool, usize)>,
+    changed: Condvar,
+}
+
+impl WatchPause {
+    fn toggle(&self) {
+        let mut state = self.state.lock().unwrap();
+        let paused = !state.0;
+        *state = (paused, 0);
+        self.changed.notify_all();
+    }
+
+    /// Plays one more move while paused.
+    fn step(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.0 {
+            state.1 += 1;
+            self.changed.notify_all();
+        }
+    }
+
+    fn paused(&self) -> bool {
+        self.state.lock().unwrap().0
+    }
+
+    /// Blocks while paused, until resumed or stepped.
+    fn wait(&self) {
+        let mut state = self.changed.wait_while(self.state.lock().unwrap(), |(paused, steps)| *paused && *steps == 0).unwrap();
+        state.1 = state.1.saturating_sub(1);
+    }
+}
+
+/// A watched match as it is played, for the panel.
+struct MatchView {
+    /// Of the first and the second engine.
+    names: [String; 2],
+    points: [f64; 2],
+    games: usize,
+    game_index: usize,
+    first_is_white: bool,
+    /// After the last move.
+    game: Game,
+    ply: usize,
+    /// The eval after every move of the game, from White's point of view.
+    evals: Vec<Option<isize>>,
+    /// By `Player::as_index`, the last move of each engine.
+    last_moves: [Option<MoveAnnotation>; 2],
+    locale: Locale,
+}
+
+impl MatchView {
+    fn new(names: [String; 2], games: usize, rules: Rules, locale: Locale) -> Self {
+        Self { names, points: [0.0; 2], games, game_index: 0, first_is_white: true, game: Game::with_rules(rules), ply: 0, evals: Vec::new(), last_moves: [None, None], locale }
+    }
+
+    fn move_played(&mut self, game_index: usize, first_is_white: bool, played_move: &PlayedMove, win_model: &WinProbabilityModel) -> GuiUpdate {
+        if played_move.ply == 1 {
+            self.game_index = game_index;
+            self.first_is_white = first_is_white;
+            self.evals.clear();
+            self.last_moves = [None, None];
+        }
+        let player = played_move.game.player;
+        self.game = played_move.game.clone();
+        execute_move_unchecked(&mut self.game, player, &played_move.recorded_move.player_move);
+        self.ply = played_move.ply;
+        self.evals.push(played_move.recorded_move.annotation.eval);
+        self.last_moves[player.as_index()] = Some(played_move.recorded_move.annotation.clone());
+        self.update(None, win_model)
+    }
+
+    fn game_finished(&mut self, outcome: &Outcome) {
+        let first = if self.first_is_white { Player::White } else { Player::Black };
+        match outcome {
+            Outcome::Win(winner) if *winner == first => self.points[0] += 1.0,
+            Outcome::Win(_) => self.points[1] += 1.0,
+            Outcome::Draw => self.points = self.points.map(|points| points + 0.5),
+        }
+    }
+
+    fn update(&self, toast: Option<String>, win_model: &WinProbabilityModel) -> GuiUpdate {
+        let eval = self.evals.last().copied().flatten();
+        GuiUpdate {
+            game: self.game.clone(),
+            explanation: None,
+            toast,
+            white_win_probability: eval.map_or(0.5, |eval| win_model.white_win_probability(&self.game, eval)),
+            explorer: None,
+            stats: None,
+            ply: self.ply,
+            clock: None,
+            watch: Some(self.text()),
+        }
+    }
+
+    /// The match score, the eval graph of the game and the last eval of each engine.
+    fn text(&self) -> String {
+        let [first, second] = &self.names;
+        let colours = if self.first_is_white { [first, second] } else { [second, first] };
+        let score = Message::MatchScore { game: self.game_index + 1, games: self.games, names: [first.as_str(), second.as_str()], points: self.points };
+        let mut text = format!("{}\n", score.text(self.locale));
+        text += &format!("{}\n", sparkline(&self.evals, DECISIVE_LOSS));
+        for (player, name) in [Player::White, Player::Black].into_iter().zip(colours) {
+            let eval = match &self.last_moves[player.as_index()] {
+                Some(MoveAnnotation { eval: Some(eval), depth, .. }) => {
+                    let depth = depth.map_or(String::new(), |depth| format!(" {}", Message::Depth(depth).text(self.locale)));
+                    format!("{}{depth}", score_text(&Score::from_engine_score(*eval), self.locale))
+                }
+                _ => "-".to_string(),
+            };
+            text += &format!("{} {name}: {eval}\n", Message::Player(player).text(self.locale));
+        }
+        text
+    }
+}
+
+/// The alpha-beta analysis of one position deepening in the background, next to the
+/// network's value and policy, so positions where they disagree stand out.
+struct Comparison {
+    stop: Arc<AtomicBool>,
+    engine_stop: Arc<AtomicBool>,
+    rx: Receiver<SearchResult>,
+    alpha_beta: Option<SearchResult>,
+    /// From White's point of view, like the alpha-beta score.
+    network_value: f32,
+    /// The most likely moves of the network policy with their probabilities.
+    network_moves: Vec<(String, f32)>,
+}
+
+impl Comparison {
+    fn start(engine: AlphaBetaEngine, network: &QuoridorNet, game: Game) -> Self {
+        let prediction = &predict_games(network, &[&game])[0];
+        let network_value = if game.player == Player::White { prediction.value } else { -prediction.value };
+        let mut network_moves = policy_moves(&game, &prediction.policy_logits);
+        network_moves.truncate(COMPARISON_MOVES);
+        let stop = Arc::new(AtomicBool::new(false));
+        let engine_stop = engine.stop_flag();
+        let (tx, rx) = channel();
+        if winner(&game).is_none() {
+            let stop = stop.clone();
+            let mut engine = engine;
+            std::thread::spawn(move || {
+                // One search per depth, the transposition table saving most of the repeated work
+                for depth in 1..=MAX_ADAPTIVE_DEPTH {
+                    let limits = SearchLimits { depth: Some(depth), movetime: Some(ANALYSIS_TIME), ..SearchLimits::default() };
+                    let result = engine.analyse(&game, &limits);
+                    if stop.load(Ordering::Relaxed) || tx.send(result).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        Self { stop, engine_stop, rx, alpha_beta: None, network_value, network_moves }
+    }
+
+    fn text(&self, locale: Locale) -> String {
+        let mut text = match &self.alpha_beta {
+            Some(result) => {
+                let score = score_text(&result.info.score, locale);
+                let pv = result.info.pv.join(" ");
+                Message::AlphaBetaDepth { depth: result.info.depth, score: &score, pv: &pv }.text(locale)
+            }
+            None => Message::AlphaBetaSearching.text(locale),
+        };
+        text += "\n";
+        let moves: Vec<String> = self
+            .network_moves
+            .iter()
+            .map(|(notation, probability)| format!("{notation} {:.0}%", 100.0 * probability))
+            .collect();
+        let moves = moves.join(", ");
+        text += &format!("{}\n", Message::NetworkMoves { value: self.network_value, moves: &moves }.text(locale));
+        let alpha_beta_move = self.alpha_beta.as_ref().and_then(|result| result.best_move_notation.as_deref());
+        let network_move = self.network_moves.first().map(|(notation, _)| notation.as_str());
+        if let (Some(alpha_beta_move), Some(network_move)) = (alpha_beta_move, network_move)
+            && alpha_beta_move != network_move
+        {
+            text += &format!("{}\n", Message::Disagree { alpha_beta: alpha_beta_move, network: network_move }.text(locale));
+        }
+        text
+    }
+}
+
+impl Drop for Comparison {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.engine_stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// An alpha-beta score from White's point of view, in path steps.
+fn score_text(score: &Score, locale: Locale) -> String {
+    match score {
+        Score::Centipaths(centipaths) => format!("{:+.2}", *centipaths as f64 / 100.0),
+        Score::Win { winner, .. } => Message::Wins(*winner).text(locale),
+    }
+}
+
+impl GuiState {
+    /// The cursor, starting on the pawn of the side to move.
+    fn cursor(&self) -> Cursor {
+        self.cursor.unwrap_or_else(|| {
+            let pawn = self.current_state.board.player_position(self.current_state.player);
+            Cursor { x: pawn.x(), y: pawn.y(), wall: None }
+        })
+    }
+
+    /// Keeps the cursor on the board, walls having one row and column less than squares.
+    fn clamp_cursor(&self, cursor: Cursor) -> Cursor {
+        let board_size = self.current_state.rules.board_size;
+        let size = if cursor.wall.is_some() { board_size - 1 } else { board_size };
+        Cursor { x: cursor.x.min(size - 1), y: cursor.y.min(size - 1), ..cursor }
+    }
+
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let cursor = self.cursor();
+        let moved = Cursor {
+            x: cursor.x.saturating_add_signed(dx),
+            y: cursor.y.saturating_add_signed(dy),
+            ..cursor
+        };
+        self.cursor = Some(self.clamp_cursor(moved));
+    }
+
+    /// Plays the pawn move onto the cursor's square or the wall at the cursor.
+    fn play_cursor_move(&mut self) {
+        let Some(cursor) = self.cursor else {
+            return;
+        };
+        let game = &self.current_state;
+        let player_move = match cursor.wall {
+            None => pawn_move_to(game, &PiecePosition::new(cursor.x, cursor.y)),
+            Some(orientation) => Some(PlayerMove::PlaceWall { orientation, position: WallPosition { x: cursor.x, y: cursor.y } })
+                .filter(|player_move| is_move_legal(game, game.player, player_move)),
+        };
+        match player_move {
+            Some(player_move) => self.send_input(player_move.to_string()),
+            None => self.toast = Some((Message::IllegalMove.text(self.locale), Instant::now())),
+        }
+    }
+
+    fn send_input(&self, input: String) {
+        println!("{input}");
+        // The session thread only stops with the window
+        let _ = self.input.send(input);
+    }
+
+    fn start_comparison(&self, network: &QuoridorNet) -> Comparison {
+        Comparison::start(self.engine_options.alpha_beta_engine(), network, self.current_state.clone())
+    }
+
+    /// The eval graph of a replay, marking the key moments with * and the move that led to the
+    /// position shown with ^.
+    fn replay_text(&self, replay: &Replay) -> String {
+        let marks: String = (0..replay.evals.len())
+            .map(|ply| match ply {
+                _ if ply + 1 == self.ply => '^',
+                _ if replay.key_moments.contains(&ply) => '*',
+                _ => ' ',
+            })
+            .collect();
+        let position = match self.ply.checked_sub(1).and_then(|ply| replay.evals.get(ply)) {
+            Some(eval) => {
+                let eval = eval.map_or("-".to_string(), |eval| eval.to_string());
+                Message::ReplayMove { ply: self.ply, eval: &eval }.text(self.locale)
+            }
+            None => Message::ReplayStart.text(self.locale),
+        };
+        format!("{}\n{}\n{position}\n{}", sparkline(&replay.evals, DECISIVE_LOSS), marks.trim_end(), replay.key_moments_text)
+    }
+
+    /// Steps a replay to the position of the next key moment, before its move, wrapping around.
+    fn next_key_moment(&self, replay: &Replay) {
+        let next = replay.key_moments.iter().find(|&&ply| ply > self.ply).or(replay.key_moments.first());
+        match next {
+            Some(&ply) if ply > self.ply => self.send_input(format!("redo {}", ply - self.ply)),
+            Some(&ply) if ply < self.ply => self.send_input(format!("undo {}", self.ply - ply)),
+            _ => {}
+        }
+    }
 }
 
 impl EventHandler for GuiState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult {
-        if let Ok(game) = self.rx.try_recv() {
-            self.current_state = game;
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        if let Ok(update) = self.rx.try_recv() {
+            // Only moves sound, not taking them back or other commands
+            if update.ply == self.ply + 1 {
+                let sound = if winner(&update.game).is_some() {
+                    Sound::GameEnd
+                } else if update.game.walls_left != self.current_state.walls_left {
+                    Sound::WallPlacement
+                } else {
+                    Sound::PawnMove
+                };
+                self.sounds.play(ctx, sound)?;
+            }
+            self.ply = update.ply;
+            self.clock = update.clock.map(|clock| (clock, Instant::now()));
+            self.current_state = update.game;
+            self.explanation = update.explanation;
+            self.white_win_probability = update.white_win_probability;
+            self.explorer = update.explorer;
+            self.stats = update.stats;
+            if let (Some(watch), Some(text)) = (&mut self.watch, update.watch) {
+                watch.text = Some(text);
+            }
+            if let Some(network) = &self.analysis_network {
+                self.analysis = Some(Analysis::start(network.clone(), self.current_state.clone()));
+                if self.comparison.is_some() {
+                    self.comparison = Some(self.start_comparison(network));
+                }
+            }
+            if self.wall_effects.is_some() {
+                self.wall_effects = Some(wall_effects(&self.current_state));
+            }
+            if let Some(toast) = update.toast {
+                self.toast = Some((toast, Instant::now()));
+            }
+        }
+        if let Some(analysis) = &mut self.analysis {
+            while let Ok(stats) = analysis.rx.try_recv() {
+                analysis.text = Some(stats.text(self.locale));
+            }
+        }
+        if let Some(comparison) = &mut self.comparison {
+            while let Ok(result) = comparison.rx.try_recv() {
+                comparison.alpha_beta = Some(result);
+            }
+        }
+        if let Some((clock, reached)) = &self.clock
+            && winner(&self.current_state).is_none()
+        {
+            let player = self.current_state.player.as_index();
+            if clock.remaining[player].saturating_sub(reached.elapsed()) < LOW_TIME && !self.low_time_warned[player] {
+                self.low_time_warned[player] = true;
+                self.sounds.play(ctx, Sound::LowTime)?;
+            }
+        }
+        if self
+            .toast
+            .as_ref()
+            .is_some_and(|(_, shown_at)| shown_at.elapsed() > TOAST_DURATION)
+        {
+            self.toast = None;
+        }
+        Ok(())
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) -> GameResult {
+        self.mouse_position = [x, y];
+        Ok(())
+    }
+
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, _repeated: bool) -> GameResult {
+        match input.keycode {
+            Some(KeyCode::Escape) => ctx.request_quit(),
+            Some(KeyCode::C) => {
+                self.comparison = match (&self.comparison, &self.analysis_network) {
+                    (None, Some(network)) => Some(self.start_comparison(network)),
+                    _ => None,
+                };
+            }
+            Some(KeyCode::W) => {
+                self.wall_effects = match self.wall_effects {
+                    None => Some(wall_effects(&self.current_state)),
+                    Some(_) => None,
+                };
+            }
+            Some(KeyCode::Up) => self.move_cursor(0, -1),
+            Some(KeyCode::Down) => self.move_cursor(0, 1),
+            Some(KeyCode::Left) => self.move_cursor(-1, 0),
+            Some(KeyCode::Right) => self.move_cursor(1, 0),
+            Some(KeyCode::Tab) => {
+                let mut cursor = self.cursor();
+                cursor.wall = match cursor.wall {
+                    None => Some(WallOrientation::Horizontal),
+                    Some(WallOrientation::Horizontal) => Some(WallOrientation::Vertical),
+                    Some(WallOrientation::Vertical) => None,
+                };
+                self.cursor = Some(self.clamp_cursor(cursor));
+            }
+            Some(KeyCode::Return | KeyCode::NumpadEnter) => self.play_cursor_move(),
+            Some(KeyCode::M) => self.sounds.muted = !self.sounds.muted,
+            Some(KeyCode::L) => {
+                self.show_stats = !self.show_stats;
+                if self.show_stats {
+                    self.send_input("stats".to_string());
+                }
+            }
+            Some(KeyCode::K) => {
+                if let Some(replay) = &self.replay {
+                    self.next_key_moment(replay);
+                }
+            }
+            Some(KeyCode::Space) => {
+                if let Some(watch) = &self.watch {
+                    watch.pause.toggle();
+                }
+            }
+            Some(KeyCode::N) => {
+                if let Some(watch) = &self.watch {
+                    watch.pause.step();
+                }
+            }
+            Some(KeyCode::U) => self.send_input(format!("undo {}", self.undo_moves)),
+            Some(KeyCode::R) => self.send_input(format!("redo {}", self.undo_moves)),
+            _ => {}
         }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        draw::draw(&self.current_state, ctx)
+        let analysis = self.analysis.as_ref().and_then(|analysis| analysis.text.as_deref());
+        let comparison = self.comparison.as_ref().map(|comparison| comparison.text(self.locale));
+        let stats = self.stats.as_deref().filter(|_| self.show_stats);
+        let replay = self.replay.as_ref().map(|replay| self.replay_text(replay));
+        let watch = self.watch.as_ref().and_then(|watch| {
+            let paused = if watch.pause.paused() { format!("{}\n", Message::WatchPaused.text(self.locale)) } else { String::new() };
+            watch.text.as_ref().map(|text| format!("{text}{paused}"))
+        });
+        let panel: Vec<&str> = [watch.as_deref(), replay.as_deref(), stats, self.explorer.as_deref(), comparison.as_deref(), analysis].into_iter().flatten().collect();
+        let panel = panel.join("\n");
+        let overlays = draw::Overlays {
+            white_win_probability: self.white_win_probability,
+            tooltip: self.explanation.as_deref().map(|text| (text, self.mouse_position)),
+            toast: self.toast.as_ref().map(|(text, _)| text.as_str()),
+            panel: (!panel.is_empty()).then_some(panel.as_str()),
+            wall_effects: self.wall_effects.as_deref(),
+            cursor: self.cursor,
+        };
+        draw::draw(&self.current_state, &overlays, ctx)
     }
 }
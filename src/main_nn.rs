@@ -1,9 +1,536 @@
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use quoridor_core::data_model::Player;
+use quoridor_core::encoding::{CHANNEL_NAMES, encode};
+use quoridor_core::notation::parse_position;
+use quoridor_core::plane_images::{GRID_COLUMNS, planes_png};
+use quoridor_core::rules::Rules;
+
+use crate::checkpoint::{CheckpointManifest, lineage, list_checkpoints, load_checkpoint, save_checkpoint};
+use crate::disagreement::{find_disagreements, write_disagreements};
+use crate::experiment::{experiment_summary, run_experiment};
+use crate::evaluation::{EvaluationConfig, evaluate, is_promoted, records_path, score};
+use crate::nn_bot::{ACTIONS, NetworkKind, QuoridorNet, validate_action_space};
+use crate::nn_eval::{read_positions, write_evaluations};
+use crate::record_shards::convert_records;
+use crate::self_play::{Opponent, SelfPlayConfig, TargetConfig, network_player, play_training_game, training_samples};
+use crate::shutdown::Shutdown;
+use crate::train_status::train_status;
+use crate::training::{Trainer, TrainingConfig, default_curriculum};
+use crate::training_shard::create_shard;
+
+pub mod checkpoint;
+pub mod disagreement;
+pub mod evaluation;
+pub mod experiment;
+pub mod mcts;
 pub mod nn_bot;
-pub mod data_model;
-pub mod all_moves;
-pub mod game_logic;
-pub mod a_star;
+pub mod nn_eval;
+pub mod record_shards;
+pub mod replay_buffer;
+pub mod self_play;
+pub mod shutdown;
+pub mod train_status;
+pub mod training;
+pub mod training_shard;
+
+#[derive(clap_derive::Parser, Debug)]
+struct Args {
+    #[command(subcommand)]
+    command: NnCommand,
+}
+
+/// Tree search of the network in self-play and the training targets taken from it.
+#[derive(clap_derive::Args, Debug)]
+struct SearchOptions {
+    /// Tree search simulations per move of the network, 0 to sample its policy directly.
+    #[clap(long, default_value_t = 0)]
+    simulations: usize,
+
+    /// Temperature of the visit counts in the policy targets, lower sharpens them towards the most visited move.
+    #[clap(long, default_value_t = 1.0)]
+    visit_temperature: f32,
+
+    /// Weight of the move played, as a one-hot, against the visit counts in the policy targets.
+    #[clap(long, default_value_t = 0.0)]
+    played_move_weight: f32,
+
+    /// Weight of the searched root value against the game result in the value targets.
+    #[clap(long, default_value_t = 0.0)]
+    root_value_weight: f32,
+}
+
+impl SearchOptions {
+    fn self_play_config(&self, config: SelfPlayConfig) -> SelfPlayConfig {
+        let targets = TargetConfig {
+            visit_temperature: self.visit_temperature,
+            played_move_weight: self.played_move_weight,
+            root_value_weight: self.root_value_weight,
+        };
+        SelfPlayConfig { simulations: self.simulations, targets, ..config }
+    }
+}
+
+#[derive(clap_derive::Subcommand, Debug)]
+enum NnCommand {
+    /// Generates self-play training data, mixing in games against the alpha-beta bot.
+    SelfPlay {
+        /// Number of games to generate.
+        #[clap(short, long, default_value_t = 100)]
+        games: usize,
+
+        /// Training shard the samples are written to.
+        #[clap(short, long, default_value = "self-play.qshd")]
+        output: PathBuf,
+
+        /// Run directory and name of the checkpoint playing, a fresh network when omitted.
+        #[clap(long, requires = "checkpoint")]
+        run_dir: Option<PathBuf>,
+
+        #[clap(long, requires = "run_dir")]
+        checkpoint: Option<String>,
+
+        /// Share of the games played against the alpha-beta bot instead of the network itself.
+        #[clap(long, default_value_t = 0.25)]
+        opponent_fraction: f64,
+
+        #[clap(long, default_value_t = 1)]
+        min_depth: usize,
+
+        #[clap(long, default_value_t = 3)]
+        max_depth: usize,
+
+        #[clap(long, default_value_t = 200)]
+        max_plies: usize,
+
+        #[clap(short, long, default_value_t = 1.0)]
+        temperature: f32,
+
+        #[command(flatten)]
+        search: SearchOptions,
+    },
+    /// Saves a freshly initialised network as the first checkpoint of a run.
+    Init {
+        run_dir: PathBuf,
+
+        #[clap(long, default_value = "iter-0000")]
+        name: String,
+
+        #[clap(long, value_enum, default_value_t = NetworkKind::Conv)]
+        network: NetworkKind,
+    },
+    /// Trains a network by self-play, starting a new run or resuming one after a crash. Ctrl-C stops after the
+    /// current iteration, saving a checkpoint to resume from.
+    Train {
+        /// Directory of a new run.
+        #[clap(long, required_unless_present = "resume", conflicts_with = "resume")]
+        run_dir: Option<PathBuf>,
+
+        /// Continues the run in this directory from its latest checkpoint, with its own configuration.
+        #[clap(long)]
+        resume: Option<PathBuf>,
+
+        /// Iterations after which training stops, 100 for new runs. Extends resumed runs.
+        #[clap(long)]
+        iterations: Option<usize>,
+
+        /// Network of a new run: the conv tower, or the tiny MLP over handcrafted features for fast CPU play.
+        #[clap(long, value_enum, default_value_t = NetworkKind::Conv)]
+        network: NetworkKind,
+
+        #[clap(long, default_value_t = 50)]
+        games_per_iteration: usize,
+
+        #[clap(long, default_value_t = 200)]
+        steps_per_iteration: usize,
+
+        #[clap(long, default_value_t = 128)]
+        batch_size: usize,
+
+        #[clap(long, default_value_t = 50_000)]
+        replay_size: usize,
+
+        /// Merges samples of the same position in the replay buffer, averaging their targets.
+        #[clap(long)]
+        deduplicate_replay: bool,
+
+        #[clap(long, default_value_t = 1e-3)]
+        learning_rate: f64,
+
+        /// Iterations between checkpoints.
+        #[clap(long, default_value_t = 1)]
+        checkpoint_every: usize,
+
+        #[clap(long, default_value_t = 0.25)]
+        opponent_fraction: f64,
+
+        #[clap(short, long, default_value_t = 1.0)]
+        temperature: f32,
+
+        /// Games a new checkpoint plays against the best one to replace it, 0 to always replace it.
+        #[clap(long, default_value_t = 20)]
+        evaluation_games: usize,
+
+        #[command(flatten)]
+        search: SearchOptions,
+
+        /// Starts with this many iterations on a 5x5 and then on a 7x7 board, with fewer walls, before the full board.
+        #[clap(long)]
+        curriculum: Option<usize>,
+
+        /// Share of the self-play games kept out of training to measure validation losses on, 0 for none.
+        #[clap(long, default_value_t = 0.1)]
+        validation_fraction: f64,
+
+        /// Iterations between measurements of the validation losses.
+        #[clap(long, default_value_t = 1)]
+        validate_every: usize,
+
+        /// Warn when the gap between the validation and training losses grows by this much over its smallest.
+        #[clap(long, default_value_t = 0.1)]
+        overfit_warning: f32,
+    },
+    /// Trains a run for every combination of the swept settings of an experiment and compares them in a table,
+    /// resuming the unfinished runs when launched again.
+    Experiment {
+        /// Directory of the experiment, holding its spec and a directory per run.
+        dir: PathBuf,
+
+        /// JSON spec of a new experiment: the base training config and the values of the swept settings,
+        /// e.g. {"base": {"iterations": 20}, "sweep": {"learning_rate": [0.001, 0.0003]}}.
+        #[clap(long)]
+        spec: Option<PathBuf>,
+
+        /// Runs trained at a time, each in a process of its own logging to train.log in its directory.
+        #[clap(long, default_value_t = 1)]
+        processes: usize,
+
+        /// Only print the comparison table of the runs.
+        #[clap(long, conflicts_with_all = ["spec", "processes"])]
+        summary: bool,
+    },
+    /// Summarises a training run: progress, latest losses, last gating result, Elo trend, replay buffer and disk usage.
+    TrainStatus {
+        run_dir: PathBuf,
+    },
+    /// Plays a checkpoint against another in the evaluation arena and writes the games as records.
+    Evaluate {
+        run_dir: PathBuf,
+
+        candidate: String,
+
+        best: String,
+
+        #[clap(short, long, default_value_t = 20)]
+        games: usize,
+
+        #[clap(long, default_value_t = 200)]
+        max_plies: usize,
+
+        #[clap(short, long, default_value_t = 0.25)]
+        temperature: f32,
+    },
+    /// Evaluates positions with a checkpoint, writing the value and the most likely moves of each to CSV.
+    NnEval {
+        /// File, or directory of files, with a position string or a game record per line.
+        input: PathBuf,
+
+        #[clap(long)]
+        run_dir: PathBuf,
+
+        #[clap(long)]
+        checkpoint: String,
+
+        #[clap(short, long, default_value = "nn-eval.csv")]
+        output: PathBuf,
+
+        /// Moves of the policy written per position.
+        #[clap(short = 'k', long, default_value_t = 3)]
+        top_k: usize,
+
+        #[clap(long, default_value_t = 64)]
+        batch_size: usize,
+    },
+    /// Collects the positions where the policy's move of a checkpoint loses against the alpha-beta search's best move.
+    Disagreements {
+        /// File, or directory of files, with a position string or a game record per line.
+        input: PathBuf,
+
+        #[clap(long)]
+        run_dir: PathBuf,
+
+        #[clap(long)]
+        checkpoint: String,
+
+        /// Position file the disagreements are written to, with diagrams in comments.
+        #[clap(short, long, default_value = "disagreements.positions")]
+        output: PathBuf,
+
+        /// Depth of the alpha-beta search.
+        #[clap(short, long, default_value_t = 2)]
+        depth: usize,
+
+        /// Steps the network's move must lose to be collected.
+        #[clap(long, default_value_t = 2)]
+        min_loss: isize,
+    },
+    /// Converts game records to a training shard with the current encoder, so encoder changes don't need new self-play.
+    ConvertRecords {
+        /// File, or directory of files, with a game record per line.
+        input: PathBuf,
+
+        /// Training shard the samples are written to.
+        #[clap(short, long, default_value = "records.qshd")]
+        output: PathBuf,
+
+        /// The network the samples are encoded for.
+        #[clap(long, value_enum, default_value_t = NetworkKind::Conv)]
+        network: NetworkKind,
+    },
+    /// Writes the input planes of a position as a PNG, one grayscale tile per channel, to check the
+    /// encoding by eye.
+    Planes {
+        /// Position string, e.g. "e1 e9 10 10 w d4h".
+        position: String,
+
+        #[clap(short, long, default_value = "planes.png")]
+        output: PathBuf,
+    },
+    /// Lists the checkpoints of a run, or shows how one checkpoint was produced.
+    Checkpoints {
+        run_dir: PathBuf,
+
+        /// Show this checkpoint and its ancestors.
+        name: Option<String>,
+    },
+}
+
+fn print_manifest(manifest: &CheckpointManifest) {
+    println!(
+        "{} (parent {}, revision {}, created {})",
+        manifest.name,
+        manifest.parent.as_deref().unwrap_or("none"),
+        manifest.git_revision.as_deref().unwrap_or("unknown"),
+        manifest.created_unix
+    );
+    if !manifest.training_config.is_null() {
+        println!("  config: {}", manifest.training_config);
+    }
+    if !manifest.shards.is_empty() {
+        println!("  shards: {}", manifest.shards.join(", "));
+    }
+    for evaluation in &manifest.evaluations {
+        println!(
+            "  against {}: +{} -{} ={}",
+            evaluation.opponent, evaluation.wins, evaluation.losses, evaluation.draws
+        );
+    }
+}
 
-fn main() {
+fn self_play(
+    network: QuoridorNet,
+    output: &Path,
+    games: usize,
+    config: &SelfPlayConfig,
+    temperature: f32,
+) -> std::io::Result<()> {
+    let new_network = || network_player(&network, temperature, config);
+    let [channels, height, width] = network.kind().input_shape();
+    let mut writer = create_shard(output, channels, height, width, ACTIONS)?;
+    let mut rng = rand::rng();
+    let mut samples = 0;
+    // Ctrl-C stops after the game being played, keeping the samples so far
+    let shutdown = Shutdown::catch().map_err(std::io::Error::other)?;
+    for game_index in 0..games {
+        if shutdown.requested() {
+            println!("Interrupted after {game_index} games");
+            break;
+        }
+        let opponent = config.pick_opponent(&mut rng);
+        let network_color = if game_index % 2 == 0 { Player::White } else { Player::Black };
+        let training_game = play_training_game(&new_network, opponent, network_color, &Rules::default(), config);
+        for sample in training_samples(&training_game, network.kind(), &config.targets) {
+            writer.write(&sample)?;
+            samples += 1;
+        }
+        let opponent = match opponent {
+            Opponent::Network => "itself".to_string(),
+            Opponent::AlphaBeta { depth } => format!("alpha-beta depth {depth}"),
+        };
+        println!(
+            "Game {}: network as {} against {}, {:?} after {} plies",
+            game_index + 1,
+            network_color.to_string(),
+            opponent,
+            training_game.finished_game.outcome,
+            training_game.finished_game.record.moves.len()
+        );
+    }
+    writer.finish()?;
+    println!("Wrote {} samples to {}", samples, output.display());
+    Ok(())
+}
 
-}
\ No newline at end of file
+fn main() -> Result<(), String> {
+    let args = Args::parse();
+    validate_action_space()?;
+    match args.command {
+        NnCommand::SelfPlay {
+            games,
+            output,
+            run_dir,
+            checkpoint,
+            opponent_fraction,
+            min_depth,
+            max_depth,
+            max_plies,
+            temperature,
+            search,
+        } => {
+            let network = match (run_dir, checkpoint) {
+                (Some(run_dir), Some(checkpoint)) => load_checkpoint(&run_dir, &checkpoint)?.0,
+                _ => QuoridorNet::new(),
+            };
+            let config = search.self_play_config(SelfPlayConfig { opponent_fraction, min_depth, max_depth, max_plies, ..SelfPlayConfig::default() });
+            self_play(network, &output, games, &config, temperature).map_err(|e| e.to_string())
+        }
+        NnCommand::Train {
+            run_dir,
+            resume,
+            iterations,
+            network,
+            games_per_iteration,
+            steps_per_iteration,
+            batch_size,
+            replay_size,
+            deduplicate_replay,
+            learning_rate,
+            checkpoint_every,
+            opponent_fraction,
+            temperature,
+            evaluation_games,
+            curriculum,
+            validation_fraction,
+            validate_every,
+            overfit_warning,
+            search,
+        } => {
+            let mut trainer = match resume {
+                Some(run_dir) => {
+                    let mut trainer = Trainer::resume(&run_dir)?;
+                    if let Some(iterations) = iterations {
+                        trainer.set_iterations(iterations);
+                    }
+                    println!("Resuming {} after iteration {}", trainer.counters().checkpoint, trainer.counters().iteration);
+                    trainer
+                }
+                None => {
+                    let config = TrainingConfig {
+                        iterations: iterations.unwrap_or(TrainingConfig::default().iterations),
+                        network,
+                        games_per_iteration,
+                        steps_per_iteration,
+                        batch_size,
+                        replay_size,
+                        deduplicate_replay,
+                        learning_rate,
+                        checkpoint_every,
+                        temperature,
+                        self_play: search.self_play_config(SelfPlayConfig { opponent_fraction, ..SelfPlayConfig::default() }),
+                        evaluation: EvaluationConfig { games: evaluation_games, ..EvaluationConfig::default() },
+                        curriculum: curriculum.map(default_curriculum).unwrap_or_default(),
+                        validation_fraction,
+                        validate_every,
+                        overfit_warning,
+                    };
+                    Trainer::start(&run_dir.unwrap(), config)?
+                }
+            };
+            trainer.train(&Shutdown::catch()?)
+        }
+        NnCommand::Experiment { dir, spec, processes, summary } => {
+            if !summary {
+                run_experiment(&dir, spec.as_deref(), processes, &Shutdown::catch()?)?;
+            }
+            print!("{}", experiment_summary(&dir)?);
+            Ok(())
+        }
+        NnCommand::TrainStatus { run_dir } => {
+            print!("{}", train_status(&run_dir)?);
+            Ok(())
+        }
+        NnCommand::Evaluate { run_dir, candidate, best, games, max_plies, temperature } => {
+            let config = EvaluationConfig { games, max_plies, temperature };
+            let result = evaluate(&run_dir, &candidate, &best, &Rules::default(), &config)?;
+            println!(
+                "{} against {}: +{} -{} ={} ({:.0}%, {})",
+                candidate,
+                best,
+                result.wins,
+                result.losses,
+                result.draws,
+                100.0 * score(&result),
+                if is_promoted(&result) { "promoted" } else { "not promoted" }
+            );
+            println!("Wrote the games to {}", records_path(&run_dir, &candidate, &best).display());
+            Ok(())
+        }
+        NnCommand::Init { run_dir, name, network } => {
+            let manifest = CheckpointManifest::new(&name, None);
+            save_checkpoint(&run_dir, &QuoridorNet::with_kind(network), &manifest)?;
+            print_manifest(&manifest);
+            Ok(())
+        }
+        NnCommand::NnEval { input, run_dir, checkpoint, output, top_k, batch_size } => {
+            let network = load_checkpoint(&run_dir, &checkpoint)?.0;
+            let positions = read_positions(&input)?;
+            std::fs::File::create(&output)
+                .map(std::io::BufWriter::new)
+                .and_then(|mut writer| write_evaluations(&network, &positions, top_k, batch_size, &mut writer))
+                .map_err(|e| format!("Could not write {}: {e}", output.display()))?;
+            println!("Wrote {} positions to {}", positions.len(), output.display());
+            Ok(())
+        }
+        NnCommand::Disagreements { input, run_dir, checkpoint, output, depth, min_loss } => {
+            let network = load_checkpoint(&run_dir, &checkpoint)?.0;
+            let positions = read_positions(&input)?;
+            let disagreements = find_disagreements(&network, &positions, depth, min_loss);
+            std::fs::File::create(&output)
+                .map(std::io::BufWriter::new)
+                .and_then(|mut writer| write_disagreements(&disagreements, &mut writer))
+                .map_err(|e| format!("Could not write {}: {e}", output.display()))?;
+            println!("Wrote {} of {} positions to {}", disagreements.len(), positions.len(), output.display());
+            Ok(())
+        }
+        NnCommand::ConvertRecords { input, output, network } => {
+            let summary = convert_records(&input, &output, network, &TargetConfig::default())?;
+            println!(
+                "Wrote {} samples of {} games in {} files to {}",
+                summary.samples,
+                summary.games,
+                summary.files,
+                output.display()
+            );
+            Ok(())
+        }
+        NnCommand::Planes { position, output } => {
+            let state = encode(&parse_position(&position)?);
+            std::fs::write(&output, planes_png(&state)).map_err(|e| format!("Could not write {}: {e}", output.display()))?;
+            println!("Wrote the planes of {} to {}, {} tiles to a row:", position, output.display(), GRID_COLUMNS);
+            for (channel, name) in CHANNEL_NAMES.iter().enumerate() {
+                println!("  {channel}: {name}");
+            }
+            Ok(())
+        }
+        NnCommand::Checkpoints { run_dir, name } => {
+            let manifests = match name {
+                Some(name) => lineage(&run_dir, &name)?,
+                None => list_checkpoints(&run_dir)?,
+            };
+            manifests.iter().for_each(print_manifest);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use clap::Parser;
+#[cfg(unix)]
+use tokio::signal::unix::{Signal, SignalKind, signal};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, transport::Server};
+
+use quoridor_core::api::{
+    AlphaBetaEngine, Clock, Engine, Game, Leaderboard, SearchLimits, check_playable,
+    execute_move_unchecked, is_move_legal, legal_moves, parse_player_move,
+};
+
+use crate::matchmaking::{Difficulty, Joined, LadderMatch, Matchmaking};
+use crate::mcts::{MctsConfig, MctsEngine, RootStats};
+use crate::nn_bot::{QuoridorNet, predict_games, validate_action_space};
+use crate::proto::quoridor_engine_server::{QuoridorEngine, QuoridorEngineServer};
+use crate::proto::ratings::Rating;
+use crate::proto::root_statistics::MoveStatistics;
+use crate::proto::{
+    AnalyseRequest, AnalysisResult, JoinRequest, LeaderboardRequest, MatchState, MoveList,
+    PlayRequest, Position, Ratings, RootStatistics, StreamAnalysisRequest, WatchRequest,
+};
+
+pub mod commands;
+pub mod matchmaking;
+pub mod mcts;
+pub mod nn_bot;
+pub mod shutdown;
+
+pub mod proto {
+    tonic::include_proto!("quoridor.engine");
+}
+
+#[derive(clap_derive::Parser, Debug)]
+struct Args {
+    #[clap(short, long, default_value = "127.0.0.1:50051")]
+    address: std::net::SocketAddr,
+
+    /// Network weights for streamed analyses, a fresh network when omitted.
+    #[clap(long)]
+    network: Option<PathBuf>,
+
+    /// Bot difficulties of the ladder as <name>=<depth>, replacing the defaults when given.
+    #[clap(long = "bot", value_parser = matchmaking::parse_difficulty, default_values = ["easy=1", "medium=3", "hard=5"])]
+    bots: Vec<Difficulty>,
+
+    /// Finished ladder games are recorded here.
+    #[clap(long, default_value = commands::DEFAULT_LEADERBOARD)]
+    leaderboard: PathBuf,
+}
+
+const DEFAULT_STREAM_INTERVAL: Duration = Duration::from_millis(100);
+/// Root statistics messages waiting for a slow client before the search waits too.
+const STREAM_BUFFER: usize = 16;
+
+struct EngineService {
+    /// Every request gets a fresh engine, so concurrent searches don't share state.
+    new_engine: fn() -> Box<dyn Engine>,
+    /// Cloned for every streamed analysis.
+    network: Mutex<QuoridorNet>,
+    /// Shared with the searches of the ladder's bots.
+    ladder: Arc<Mutex<Matchmaking>>,
+    leaderboard: PathBuf,
+}
+
+#[allow(clippy::result_large_err)]
+fn game_from_position(position: &Position) -> Result<Game, Status> {
+    let mut game = Game::new();
+    for move_str in &position.moves {
+        let player_move = parse_player_move(move_str)
+            .ok_or_else(|| Status::invalid_argument(format!("Could not parse move: {move_str}")))?;
+        let player = game.player;
+        if !is_move_legal(&game, player, &player_move) {
+            return Err(Status::invalid_argument(format!(
+                "Illegal move: {move_str}"
+            )));
+        }
+        execute_move_unchecked(&mut game, player, &player_move);
+    }
+    Ok(game)
+}
+
+fn search_limits(limits: Option<crate::proto::SearchLimits>) -> SearchLimits {
+    let limits = limits.unwrap_or_default();
+    SearchLimits {
+        depth: limits.depth.map(|d| d as usize),
+        nodes: limits.nodes,
+        movetime: limits.movetime_ms.map(Duration::from_millis),
+        clock: limits.clock.map(|clock| Clock {
+            remaining: [clock.white_time_ms, clock.black_time_ms].map(Duration::from_millis),
+            increment: [clock.white_increment_ms, clock.black_increment_ms]
+                .map(Duration::from_millis),
+        }),
+    }
+}
+
+fn root_statistics(stats: &RootStats) -> RootStatistics {
+    RootStatistics {
+        moves: stats
+            .moves
+            .iter()
+            .map(|move_stats| MoveStatistics {
+                player_move: move_stats.player_move.to_string(),
+                visits: move_stats.visits,
+                q: move_stats.q,
+                prior: move_stats.prior,
+            })
+            .collect(),
+        value: stats.value,
+        simulations: stats.simulations as u64,
+        time_used_ms: stats.elapsed.as_millis() as u64,
+    }
+}
+
+fn match_state(match_id: u64, ladder_match: &LadderMatch) -> MatchState {
+    let [white, black] = ladder_match.players.clone();
+    MatchState {
+        match_id,
+        white,
+        black,
+        moves: ladder_match.moves.clone(),
+        result: ladder_match.result().to_string(),
+        secret: String::new(),
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn ladder_match_state(ladder: &Mutex<Matchmaking>, match_id: u64) -> Result<MatchState, Status> {
+    let ladder = ladder.lock().unwrap();
+    let ladder_match = ladder.get(match_id).map_err(Status::not_found)?;
+    Ok(match_state(match_id, ladder_match))
+}
+
+/// Plays the bot's moves of a ladder match for as long as it is to move, off the async executor,
+/// unless a loop started earlier still plays them.
+fn play_bot_moves(ladder: Arc<Mutex<Matchmaking>>, match_id: u64) {
+    if !ladder.lock().unwrap().claim_bot(match_id) {
+        return;
+    }
+    tokio::task::spawn_blocking(move || {
+        let mut engine = AlphaBetaEngine::new();
+        loop {
+            let Some((game, depth, ply)) = ladder.lock().unwrap().bot_to_move(match_id) else {
+                break;
+            };
+            let limits = SearchLimits {
+                depth: Some(depth),
+                ..SearchLimits::default()
+            };
+            let bot_move = engine.choose_move(&game, &limits);
+            if let Err(error) =
+                ladder
+                    .lock()
+                    .unwrap()
+                    .play_bot_move(match_id, ply, bot_move.player_move)
+            {
+                eprintln!("Match {match_id}: {error}");
+            }
+        }
+    });
+}
+
+#[tonic::async_trait]
+impl QuoridorEngine for EngineService {
+    async fn analyse(
+        &self,
+        request: Request<AnalyseRequest>,
+    ) -> Result<Response<AnalysisResult>, Status> {
+        let request = request.into_inner();
+        let game = game_from_position(&request.position.unwrap_or_default())?;
+        check_playable(&game).map_err(Status::failed_precondition)?;
+        let limits = search_limits(request.limits);
+        // The search is CPU bound, keep it off the async executor.
+        let search_game = game.clone();
+        let mut engine = (self.new_engine)();
+        let bot_move =
+            tokio::task::spawn_blocking(move || engine.choose_move(&search_game, &limits))
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?;
+        let search_info = bot_move.search_info(&game);
+        let search_result = bot_move.to_search_result(&game);
+        Ok(Response::new(AnalysisResult {
+            best_move: bot_move.player_move.to_string(),
+            score: search_info.score as i64,
+            depth: search_info.depth as u32,
+            time_used_ms: bot_move.actual_duration.as_millis() as u64,
+            nodes: search_info.nodes,
+            pv: search_result.info.pv.clone(),
+            search_result_json: serde_json::to_string(&search_result)
+                .map_err(|e| Status::internal(e.to_string()))?,
+        }))
+    }
+
+    type StreamAnalysisStream = ReceiverStream<Result<RootStatistics, Status>>;
+
+    async fn stream_analysis(
+        &self,
+        request: Request<StreamAnalysisRequest>,
+    ) -> Result<Response<Self::StreamAnalysisStream>, Status> {
+        let request = request.into_inner();
+        let game = game_from_position(&request.position.unwrap_or_default())?;
+        check_playable(&game).map_err(Status::failed_precondition)?;
+        let limits = search_limits(request.limits);
+        let interval = request
+            .interval_ms
+            .map_or(DEFAULT_STREAM_INTERVAL, Duration::from_millis);
+        let mut engine =
+            MctsEngine::new(self.network.lock().unwrap().clone(), MctsConfig::default());
+        let (tx, rx) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+        // Like analyse, off the async executor. The search stops when the client hangs up.
+        tokio::task::spawn_blocking(move || {
+            engine.analyse_streaming(&game, &limits, interval, |stats| {
+                tx.blocking_send(Ok(root_statistics(&stats))).is_ok()
+            });
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn legal_moves(&self, request: Request<Position>) -> Result<Response<MoveList>, Status> {
+        let game = game_from_position(request.get_ref())?;
+        let moves = legal_moves(&game)
+            .iter()
+            .map(|player_move| player_move.to_string())
+            .collect();
+        Ok(Response::new(MoveList { moves }))
+    }
+
+    async fn join_queue(
+        &self,
+        request: Request<JoinRequest>,
+    ) -> Result<Response<MatchState>, Status> {
+        let request = request.into_inner();
+        let joined = self
+            .ladder
+            .lock()
+            .unwrap()
+            .join(&request.name, request.difficulty.as_deref());
+        let (match_id, _, secret) = match joined.map_err(Status::invalid_argument)? {
+            Joined::Paired(pairing) => pairing,
+            Joined::Waiting(receiver) => receiver
+                .await
+                .map_err(|_| Status::aborted("Replaced by a newer join of the same name"))?,
+        };
+        play_bot_moves(self.ladder.clone(), match_id);
+        Ok(Response::new(MatchState {
+            secret,
+            ..ladder_match_state(&self.ladder, match_id)?
+        }))
+    }
+
+    async fn play_move(
+        &self,
+        request: Request<PlayRequest>,
+    ) -> Result<Response<MatchState>, Status> {
+        let request = request.into_inner();
+        let played = self.ladder.lock().unwrap().play(
+            request.match_id,
+            &request.name,
+            &request.secret,
+            &request.player_move,
+        );
+        played.map_err(Status::failed_precondition)?;
+        play_bot_moves(self.ladder.clone(), request.match_id);
+        Ok(Response::new(ladder_match_state(
+            &self.ladder,
+            request.match_id,
+        )?))
+    }
+
+    async fn watch_match(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<MatchState>, Status> {
+        let request = request.into_inner();
+        let watched = self.ladder.lock().unwrap().watch(request.match_id);
+        let mut updates = watched.map_err(Status::not_found)?;
+        updates
+            .wait_for(|&(moves, over)| moves > request.after_moves as usize || over)
+            .await
+            .map_err(|_| Status::not_found(format!("Match {} was abandoned", request.match_id)))?;
+        Ok(Response::new(ladder_match_state(
+            &self.ladder,
+            request.match_id,
+        )?))
+    }
+
+    async fn leaderboard(
+        &self,
+        _request: Request<LeaderboardRequest>,
+    ) -> Result<Response<Ratings>, Status> {
+        let leaderboard = Leaderboard::load(&self.leaderboard).map_err(Status::internal)?;
+        let ratings = |engines| {
+            leaderboard
+                .ratings(engines)
+                .into_iter()
+                .map(|(name, rating, games)| Rating {
+                    name,
+                    rating,
+                    games: games as u32,
+                })
+                .collect()
+        };
+        Ok(Response::new(Ratings {
+            players: ratings(false),
+            engines: ratings(true),
+        }))
+    }
+}
+
+/// The interrupts the server stops on: SIGINT, and SIGTERM where there are Unix signals.
+struct Interrupts {
+    #[cfg(unix)]
+    terminate: Signal,
+}
+
+impl Interrupts {
+    fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            #[cfg(unix)]
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    #[cfg(unix)]
+    async fn recv(&mut self) -> std::io::Result<()> {
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = self.terminate.recv() => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn recv(&mut self) -> std::io::Result<()> {
+        tokio::signal::ctrl_c().await
+    }
+}
+
+/// Resolves on the first interrupt, so the server stops taking requests and finishes the ones in
+/// flight, and exits at once on a second one, as streams being watched may never finish.
+async fn shutdown_signal(mut interrupts: Interrupts) {
+    if let Err(e) = interrupts.recv().await {
+        // Serving on, as resolving would shut the server down at once
+        eprintln!("Could not listen for interrupts: {e}");
+        return std::future::pending().await;
+    }
+    println!("Shutting down after the requests in flight, interrupt again to stop at once");
+    tokio::spawn(async move {
+        if interrupts.recv().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    validate_action_space()?;
+    let interrupts = Interrupts::new().map_err(|e| format!("Could not catch SIGTERM: {e}"))?;
+    let network = match &args.network {
+        Some(path) => QuoridorNet::load(path)?,
+        None => QuoridorNet::new(),
+    };
+    // The first forward pass sets up the backend, before any analysis waits for it
+    let start = std::time::Instant::now();
+    predict_games(&network, &[&Game::new()]);
+    println!("Warmed up the network in {:.1?}", start.elapsed());
+    println!("Quoridor engine listening on {}", args.address);
+    Server::builder()
+        .add_service(QuoridorEngineServer::new(EngineService {
+            new_engine: || Box::new(AlphaBetaEngine::new()),
+            network: Mutex::new(network),
+            ladder: Arc::new(Mutex::new(Matchmaking::new(
+                args.bots,
+                args.leaderboard.clone(),
+            ))),
+            leaderboard: args.leaderboard,
+        }))
+        .serve_with_shutdown(args.address, shutdown_signal(interrupts))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{oneshot, watch};
+
+use quoridor_core::api::{
+    Game, Leaderboard, LeaderboardGame, Player, PlayerMove, execute_move_unchecked, is_move_legal,
+    parse_player_move, today, winner,
+};
+
+// Matchmaking of the server's club ladder. Clients join a queue by name and are paired
+// with the next client waiting, the first of the two playing White, or at once with a
+// bot of the difficulty they ask for, taking White and Black in turn. Moves are played
+// by name with the secret the client got on joining, and every client waits for the
+// moves of its opponent, bot or not, by watching the match. One loop at a time plays the
+// moves of a bot, each for the ply it searched, so a move of a stale search is turned
+// down rather than played in another position. Finished games are recorded
+// on the leaderboard, games against a bot with the bot as "bot <difficulty>", and are
+// dropped a minute later, once the clients had the final position. Matches without a
+// move for an hour are dropped as abandoned.
+
+/// How long a finished match is kept for its clients to see the final position.
+const FINISHED_MATCH_KEPT: Duration = Duration::from_secs(60);
+/// How long a match may go without a move before it is dropped as abandoned.
+const IDLE_MATCH_KEPT: Duration = Duration::from_secs(60 * 60);
+
+/// A bot opponent of the ladder, the alpha-beta bot searching to a fixed depth.
+#[derive(Debug, Clone)]
+pub struct Difficulty {
+    pub name: String,
+    pub depth: usize,
+}
+
+/// Parses a difficulty given as `<name>=<depth>`, e.g. "hard=5".
+pub fn parse_difficulty(input: &str) -> Result<Difficulty, String> {
+    let (name, depth) = input
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <name>=<depth>: {input}"))?;
+    let depth = depth
+        .parse()
+        .map_err(|_| format!("Invalid depth: {depth}"))?;
+    Ok(Difficulty {
+        name: name.to_string(),
+        depth,
+    })
+}
+
+pub struct LadderMatch {
+    /// White's and Black's names, a bot's being "bot <difficulty>".
+    pub players: [String; 2],
+    /// The side a bot plays, with its search depth.
+    pub bot: Option<(Player, usize)>,
+    pub game: Game,
+    pub moves: Vec<String>,
+    /// The number of moves played, and whether the game is over.
+    updates: watch::Sender<(usize, bool)>,
+    /// Given to White's and Black's clients on joining, to play their moves with.
+    secrets: [String; 2],
+    /// When the last move was played, or the match started.
+    last_move: Instant,
+    /// Whether a loop plays the bot's moves, so that only one does.
+    bot_playing: bool,
+}
+
+impl LadderMatch {
+    fn new(players: [String; 2], bot: Option<(Player, usize)>) -> Self {
+        Self {
+            players,
+            bot,
+            game: Game::new(),
+            moves: Vec::new(),
+            updates: watch::channel((0, false)).0,
+            secrets: [new_secret(), new_secret()],
+            last_move: Instant::now(),
+            bot_playing: false,
+        }
+    }
+
+    /// Plays a legal move for the side to move, recording the game on the leaderboard when
+    /// it ends. A game that can't be recorded is still over, so the failure is only reported.
+    fn play(&mut self, id: u64, player_move: PlayerMove, leaderboard: &Path) {
+        let player = self.game.player;
+        execute_move_unchecked(&mut self.game, player, &player_move);
+        self.moves.push(player_move.to_string());
+        self.last_move = Instant::now();
+        let finished = winner(&self.game);
+        self.updates
+            .send_replace((self.moves.len(), finished.is_some()));
+        let Some(winner) = finished else {
+            return;
+        };
+        // Recorded from the human's side against a bot, otherwise from White's
+        let (player, opponent) = match self.bot {
+            Some((bot, _)) => (bot.opponent(), bot),
+            None => (Player::White, Player::Black),
+        };
+        let game = LeaderboardGame {
+            date: today(),
+            player: self.players[player.as_index()].clone(),
+            engine: self.players[opponent.as_index()].clone(),
+            score: if winner == player { 1.0 } else { 0.0 },
+            between_players: self.bot.is_none(),
+        };
+        if let Err(error) = Leaderboard::record(leaderboard, &game) {
+            eprintln!("Match {id}: {error}");
+        }
+    }
+
+    /// The result in the usual "1-0" notation, empty while the game is on.
+    pub fn result(&self) -> &'static str {
+        match winner(&self.game) {
+            Some(Player::White) => "1-0",
+            Some(Player::Black) => "0-1",
+            None => "",
+        }
+    }
+}
+
+/// The match a client joined, the side it plays and the secret to play it with.
+pub type Pairing = (u64, Player, String);
+
+fn new_secret() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+pub enum Joined {
+    Paired(Pairing),
+    /// Waiting in the queue for the next client.
+    Waiting(oneshot::Receiver<Pairing>),
+}
+
+pub struct Matchmaking {
+    difficulties: Vec<Difficulty>,
+    leaderboard: PathBuf,
+    waiting: Option<(String, oneshot::Sender<Pairing>)>,
+    pub matches: HashMap<u64, LadderMatch>,
+    next_id: u64,
+    bot_games: usize,
+}
+
+impl Matchmaking {
+    pub fn new(difficulties: Vec<Difficulty>, leaderboard: PathBuf) -> Self {
+        Self {
+            difficulties,
+            leaderboard,
+            waiting: None,
+            matches: HashMap::new(),
+            next_id: 1,
+            bot_games: 0,
+        }
+    }
+
+    fn start_match(&mut self, players: [String; 2], bot: Option<(Player, usize)>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.matches.insert(id, LadderMatch::new(players, bot));
+        id
+    }
+
+    /// The pairing of `side` in match `id`, with its secret.
+    fn pairing(&self, id: u64, side: Player) -> Pairing {
+        (id, side, self.matches[&id].secrets[side.as_index()].clone())
+    }
+
+    /// Drops the matches finished or abandoned long enough before `now`.
+    fn expire(&mut self, now: Instant) {
+        self.matches.retain(|_, ladder_match| {
+            let kept = match winner(&ladder_match.game) {
+                Some(_) => FINISHED_MATCH_KEPT,
+                None => IDLE_MATCH_KEPT,
+            };
+            now.duration_since(ladder_match.last_move) < kept
+        });
+    }
+
+    /// Pairs the player with a bot of the difficulty, or with the client waiting in the
+    /// queue, queueing them when nobody is.
+    pub fn join(&mut self, name: &str, difficulty: Option<&str>) -> Result<Joined, String> {
+        self.expire(Instant::now());
+        if let Some(difficulty) = difficulty {
+            let Difficulty {
+                name: difficulty,
+                depth,
+            } = self
+                .difficulties
+                .iter()
+                .find(|known| known.name == difficulty)
+                .cloned()
+                .ok_or_else(|| format!("Unknown difficulty: {difficulty}"))?;
+            let side = if self.bot_games.is_multiple_of(2) {
+                Player::White
+            } else {
+                Player::Black
+            };
+            self.bot_games += 1;
+            let bot_name = format!("bot {difficulty}");
+            let players = match side {
+                Player::White => [name.to_string(), bot_name],
+                Player::Black => [bot_name, name.to_string()],
+            };
+            let id = self.start_match(players, Some((side.opponent(), depth)));
+            return Ok(Joined::Paired(self.pairing(id, side)));
+        }
+        // A client joining again replaces its place in the queue
+        match self.waiting.take() {
+            Some((opponent, sender)) if opponent != name => {
+                let id = self.start_match([opponent, name.to_string()], None);
+                if sender.send(self.pairing(id, Player::White)).is_ok() {
+                    return Ok(Joined::Paired(self.pairing(id, Player::Black)));
+                }
+                // The waiting client hung up
+                self.matches.remove(&id);
+            }
+            _ => {}
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.waiting = Some((name.to_string(), sender));
+        Ok(Joined::Waiting(receiver))
+    }
+
+    pub fn get(&self, id: u64) -> Result<&LadderMatch, String> {
+        self.matches
+            .get(&id)
+            .ok_or_else(|| format!("No match {id}"))
+    }
+
+    /// Plays a move of the named player, who has to be the side to move and hold its secret.
+    pub fn play(
+        &mut self,
+        id: u64,
+        name: &str,
+        secret: &str,
+        move_str: &str,
+    ) -> Result<(), String> {
+        let ladder_match = self.get(id)?;
+        let player = ladder_match.game.player;
+        if winner(&ladder_match.game).is_some() {
+            return Err(format!("Match {id} is over"));
+        }
+        if ladder_match.players[player.as_index()] != name
+            || matches!(ladder_match.bot, Some((bot, _)) if bot == player)
+        {
+            return Err(format!("It is not {name}'s turn"));
+        }
+        if ladder_match.secrets[player.as_index()] != secret {
+            return Err(format!("Wrong secret for {name} in match {id}"));
+        }
+        let player_move = parse_player_move(move_str)
+            .ok_or_else(|| format!("Could not parse move: {move_str}"))?;
+        if !is_move_legal(&ladder_match.game, player, &player_move) {
+            return Err(format!("Illegal move: {move_str}"));
+        }
+        let leaderboard = &self.leaderboard;
+        self.matches
+            .get_mut(&id)
+            .unwrap()
+            .play(id, player_move, leaderboard);
+        Ok(())
+    }
+
+    /// Takes on playing the bot's moves of the match, false when another loop already does
+    /// or the match has no bot.
+    pub fn claim_bot(&mut self, id: u64) -> bool {
+        match self.matches.get_mut(&id) {
+            Some(ladder_match) if ladder_match.bot.is_some() && !ladder_match.bot_playing => {
+                ladder_match.bot_playing = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The position, search depth and ply of the bot when it is to move in the match.
+    /// Otherwise the loop playing the bot is done, and the next move claims the bot again.
+    pub fn bot_to_move(&mut self, id: u64) -> Option<(Game, usize, usize)> {
+        let ladder_match = self.matches.get_mut(&id)?;
+        match ladder_match.bot {
+            Some((bot, depth))
+                if bot == ladder_match.game.player && winner(&ladder_match.game).is_none() =>
+            {
+                Some((ladder_match.game.clone(), depth, ladder_match.moves.len()))
+            }
+            _ => {
+                ladder_match.bot_playing = false;
+                None
+            }
+        }
+    }
+
+    /// Plays the bot's move searched at `ply`, unless the match has moved on since.
+    pub fn play_bot_move(
+        &mut self,
+        id: u64,
+        ply: usize,
+        player_move: PlayerMove,
+    ) -> Result<(), String> {
+        let leaderboard = &self.leaderboard;
+        let ladder_match = self
+            .matches
+            .get_mut(&id)
+            .ok_or_else(|| format!("No match {id}"))?;
+        let player = ladder_match.game.player;
+        let bot_to_move = matches!(ladder_match.bot, Some((bot, _)) if bot == player)
+            && winner(&ladder_match.game).is_none();
+        if !bot_to_move
+            || ladder_match.moves.len() != ply
+            || !is_move_legal(&ladder_match.game, player, &player_move)
+        {
+            return Err(format!(
+                "Stale bot move {player_move} for ply {ply} of match {id}"
+            ));
+        }
+        ladder_match.play(id, player_move, leaderboard);
+        Ok(())
+    }
+
+    /// Follows the number of moves played in the match, and whether it is over.
+    pub fn watch(&self, id: u64) -> Result<watch::Receiver<(usize, bool)>, String> {
+        Ok(self.get(id)?.updates.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quoridor_core::api::{legal_moves, parse_position};
+
+    #[test]
+    fn matchmaking_test() {
+        let leaderboard =
+            std::env::temp_dir().join(format!("matchmaking-test-{}", std::process::id()));
+        let difficulties = vec![parse_difficulty("easy=1").unwrap()];
+        let mut matchmaking = Matchmaking::new(difficulties, leaderboard.clone());
+
+        let Joined::Waiting(mut ada) = matchmaking.join("ada", None).unwrap() else {
+            panic!("ada should wait for an opponent");
+        };
+        let Joined::Paired((id, Player::Black, bob)) = matchmaking.join("bob", None).unwrap()
+        else {
+            panic!("bob should be paired with ada");
+        };
+        let (ada_id, Player::White, ada) = ada.try_recv().unwrap() else {
+            panic!("ada should play White");
+        };
+        assert_eq!(ada_id, id);
+        assert!(matchmaking.play(id, "bob", &bob, "mdd").is_err());
+        assert!(matchmaking.play(id, "ada", &ada, "muu").is_err());
+        // Only ada's client knows her secret
+        assert!(matchmaking.play(id, "ada", &bob, "mdd").is_err());
+        matchmaking.play(id, "ada", &ada, "mdd").unwrap();
+        assert_eq!(*matchmaking.watch(id).unwrap().borrow(), (1, false));
+
+        // A game won by White is recorded between the players
+        let game = parse_position("e8 a2 10 10 w -").unwrap();
+        let winning_move = legal_moves(&game)
+            .into_iter()
+            .find(|player_move| {
+                let mut child = game.clone();
+                execute_move_unchecked(&mut child, Player::White, player_move);
+                winner(&child).is_some()
+            })
+            .unwrap();
+        matchmaking.matches.get_mut(&id).unwrap().game = game;
+        matchmaking
+            .play(id, "ada", &ada, &winning_move.to_string())
+            .unwrap();
+        assert_eq!(matchmaking.get(id).unwrap().result(), "1-0");
+        let recorded = Leaderboard::load(&leaderboard).unwrap();
+        std::fs::remove_file(&leaderboard).unwrap();
+        assert_eq!(recorded.games[0].player, "ada");
+        assert_eq!(recorded.games[0].engine, "bob");
+        assert!(recorded.games[0].between_players);
+
+        // Bots play at once, White first
+        assert!(matchmaking.join("ada", Some("hard")).is_err());
+        let Joined::Paired((bot_id, Player::White, ada)) =
+            matchmaking.join("ada", Some("easy")).unwrap()
+        else {
+            panic!("ada should be paired with the bot");
+        };
+        assert!(!matchmaking.claim_bot(id));
+        assert!(matchmaking.claim_bot(bot_id));
+        assert!(!matchmaking.claim_bot(bot_id));
+        assert!(matchmaking.bot_to_move(bot_id).is_none());
+        assert!(matchmaking.claim_bot(bot_id));
+        matchmaking.play(bot_id, "ada", &ada, "mdd").unwrap();
+        let (game, depth, ply) = matchmaking.bot_to_move(bot_id).unwrap();
+        assert_eq!((depth, ply), (1, 1));
+        assert_eq!(matchmaking.get(bot_id).unwrap().players[1], "bot easy");
+        // Only the move searched for the current ply is played
+        let bot_move = legal_moves(&game)[0].clone();
+        assert!(
+            matchmaking
+                .play_bot_move(bot_id, 0, bot_move.clone())
+                .is_err()
+        );
+        matchmaking
+            .play_bot_move(bot_id, ply, bot_move.clone())
+            .unwrap();
+        assert!(matchmaking.play_bot_move(bot_id, ply, bot_move).is_err());
+        assert!(matchmaking.bot_to_move(bot_id).is_none());
+
+        // The finished match goes after a minute, the abandoned one after an hour
+        let now = Instant::now();
+        matchmaking.expire(now + Duration::from_secs(30));
+        assert_eq!(matchmaking.matches.len(), 2);
+        matchmaking.expire(now + FINISHED_MATCH_KEPT);
+        assert!(matchmaking.get(id).is_err());
+        assert!(matchmaking.get(bot_id).is_ok());
+        matchmaking.expire(now + IDLE_MATCH_KEPT);
+        assert!(matchmaking.matches.is_empty());
+    }
+}
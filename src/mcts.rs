@@ -0,0 +1,736 @@
+// Monte Carlo tree search guided by the network (PUCT), as in AlphaZero. Every
+// simulation walks from the root to a leaf, picking the child maximising
+//
+//     Q + c_puct * P * sqrt(N_parent) / (1 + N)
+//
+// then evaluates the leaf with the network, expands it with the policy as priors and
+// backs the value up the path, flipping its sign every ply. Q and the values are from
+// the point of view of the player choosing the move, in [-1, 1]. Dirichlet noise on
+// the root priors can be mixed in to make self-play explore.
+//
+// Children not visited yet have no Q of their own, see `UnvisitedValue`. Counting them
+// as draws, the default, sends an untrained network to a new move of the hundreds of
+// walls whenever the moves tried look any worse than even. The first play urgency (FPU)
+// counts them at the parent's value less a reduction growing with the priors of the
+// children visited, so a search keeps to the moves it has tried once those hold most
+// of the policy. With `c_puct_base` the exploration grows with the parent's visits,
+// as in AlphaZero:
+//
+//     c_puct + ln((N_parent + c_puct_base + 1) / c_puct_base)
+//
+// An analysis can stream the root statistics while searching: every `interval` the
+// visit counts, Q values and priors of the root moves are sent to the caller, the
+// way the alpha-beta analysis reports a line per finished depth.
+//
+// With `early_stop` the search ends as soon as the most visited root move leads the
+// runner-up by more visits than the simulations left, which can no longer change the
+// move played. Under a move time the simulations left are estimated from the rate so
+// far, and the time saved stays on the clock. Sampling by visits depends on the counts
+// of every move, so the rule only applies at temperature zero.
+//
+// With a `contempt` the searching player takes itself for the stronger one: a line
+// coming back to a position earlier in it, pawns shuffling back and forth, ends there
+// as a draw worth the contempt less than even to the player at the root, and as much
+// more to its opponent. The search then prefers lines making progress, so a game against
+// a weaker opponent does not fizzle out into a draw by the ply limit.
+
+use std::fmt::Display;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+use rand::rngs::StdRng;
+use rand_distr::Gamma;
+use serde::{Deserialize, Serialize};
+
+use quoridor_core::all_moves::ALL_MOVES;
+use quoridor_core::data_model::{Game, PlayerMove};
+use quoridor_core::engine::{Engine, EngineMove, SearchLimits};
+use quoridor_core::game_logic::{execute_move_unchecked, is_move_legal, winner};
+use quoridor_core::locale::{Locale, Message};
+use quoridor_core::notation::move_to_notation;
+use quoridor_core::transposition::position_key;
+
+use crate::nn_bot::{ACTIONS, QuoridorNet, predict_games};
+
+pub const DEFAULT_SIMULATIONS: usize = 400;
+/// Moves shown by the text of the root statistics.
+const DISPLAYED_MOVES: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct MctsConfig {
+    /// Simulations per search when no move time is given.
+    pub simulations: usize,
+    pub puct: PuctConfig,
+    pub dirichlet_alpha: f32,
+    /// Weight of the noise in the root priors.
+    pub dirichlet_epsilon: f32,
+    pub root_noise: bool,
+    /// 0 plays the most visited move, higher samples moves by visits^(1/temperature).
+    pub temperature: f32,
+    /// End the search once the most visited root move is decided, see the top.
+    pub early_stop: bool,
+    /// How much worse than even a repeated position is for the player at the root,
+    /// 0 to evaluate repetitions like any other position.
+    pub contempt: f32,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            simulations: DEFAULT_SIMULATIONS,
+            puct: PuctConfig::default(),
+            dirichlet_alpha: 0.3,
+            dirichlet_epsilon: 0.25,
+            root_noise: false,
+            temperature: 0.0,
+            early_stop: false,
+            contempt: 0.0,
+        }
+    }
+}
+
+/// How the search picks the child to visit, see the top of the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PuctConfig {
+    pub c_puct: f32,
+    /// Makes c_puct grow with the parent's visits, by ln 2 at about this many, None to
+    /// keep it constant.
+    pub c_puct_base: Option<f32>,
+    pub unvisited_value: UnvisitedValue,
+    /// Taken off the parent's value for the children not visited yet, times the square
+    /// root of the priors of those visited, with `UnvisitedValue::Parent`.
+    pub fpu_reduction: f32,
+}
+
+impl Default for PuctConfig {
+    fn default() -> Self {
+        Self {
+            c_puct: 1.5,
+            c_puct_base: None,
+            unvisited_value: UnvisitedValue::Draw,
+            fpu_reduction: 0.25,
+        }
+    }
+}
+
+impl PuctConfig {
+    /// The exploration constant at a node with `visits`.
+    pub fn c_puct(&self, visits: u32) -> f32 {
+        let growth = self
+            .c_puct_base
+            .map_or(0.0, |base| ((visits as f32 + base + 1.0) / base).ln());
+        self.c_puct + growth
+    }
+}
+
+/// The Q of the children a search has not visited yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnvisitedValue {
+    Draw,
+    /// The parent's value less the `fpu_reduction`.
+    Parent,
+    /// Visiting every move tried again before a new one, unless it loses.
+    Loss,
+}
+
+/// Search statistics of one root move.
+#[derive(Debug, Clone)]
+pub struct MoveStats {
+    pub player_move: PlayerMove,
+    pub notation: String,
+    pub visits: u32,
+    /// Mean value of the move for the side to move.
+    pub q: f32,
+    pub prior: f32,
+}
+
+/// Snapshot of the root of a running or finished search.
+#[derive(Debug, Clone)]
+pub struct RootStats {
+    /// Most visited first.
+    pub moves: Vec<MoveStats>,
+    /// Mean value of the root for the side to move.
+    pub value: f32,
+    pub simulations: usize,
+    pub elapsed: Duration,
+}
+
+impl RootStats {
+    /// The root value and the most visited moves, a line each.
+    pub fn text(&self, locale: Locale) -> String {
+        let root = Message::SearchRoot {
+            value: self.value,
+            simulations: self.simulations,
+            seconds: self.elapsed.as_secs_f32(),
+        };
+        let moves = self
+            .moves
+            .iter()
+            .take(DISPLAYED_MOVES)
+            .map(|stats| Message::SearchMove {
+                notation: &stats.notation,
+                visits: stats.visits,
+                q: stats.q,
+                prior: stats.prior,
+            });
+        std::iter::once(root)
+            .chain(moves)
+            .map(|message| message.text(locale) + "\n")
+            .collect()
+    }
+}
+
+impl Display for RootStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text(Locale::English))
+    }
+}
+
+struct Node {
+    game: Game,
+    /// The Zobrist key of the position, to spot repetitions.
+    key: u64,
+    /// The move leading here, None at the root.
+    player_move: Option<PlayerMove>,
+    prior: f32,
+    visits: u32,
+    /// From the point of view of the player who made `player_move`.
+    value_sum: f32,
+    /// Indices of the children in the tree's arena, appended together on expansion.
+    children: Range<usize>,
+    expanded: bool,
+}
+
+impl Node {
+    fn new(game: Game, player_move: Option<PlayerMove>, prior: f32) -> Self {
+        Self {
+            key: position_key(&game),
+            game,
+            player_move,
+            prior,
+            visits: 0,
+            value_sum: 0.0,
+            children: 0..0,
+            expanded: false,
+        }
+    }
+
+    fn q(&self) -> f32 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.value_sum / self.visits as f32
+        }
+    }
+}
+
+struct Tree<'a> {
+    nodes: Vec<Node>,
+    network: &'a QuoridorNet,
+    config: &'a MctsConfig,
+}
+
+impl Tree<'_> {
+    /// Expands a leaf, returning its value for the side to move.
+    fn expand(&mut self, index: usize) -> f32 {
+        let game = &self.nodes[index].game;
+        if winner(game).is_some() {
+            // The player who moved here reached the goal
+            return -1.0;
+        }
+        if self.nodes[index].expanded {
+            return 0.0;
+        }
+        let game = game.clone();
+        let prediction = &predict_games(self.network, &[&game])[0];
+        // The children are appended to the arena as a contiguous range, holding their
+        // logit as prior until the softmax over the legal moves below
+        let first_child = self.nodes.len();
+        for (player_move, logit) in ALL_MOVES[..ACTIONS].iter().zip(prediction.policy_logits) {
+            if !is_move_legal(&game, game.player, player_move) {
+                continue;
+            }
+            let mut child_game = game.clone();
+            execute_move_unchecked(&mut child_game, game.player, player_move);
+            // Pawn moves written differently can reach the same square, the first one is kept
+            let destination = child_game.board.player_position(game.player);
+            let is_duplicate = matches!(player_move, PlayerMove::MovePiece(_))
+                && self.nodes[first_child..].iter().any(|child| {
+                    matches!(child.player_move, Some(PlayerMove::MovePiece(_)))
+                        && child.game.board.player_position(game.player) == destination
+                });
+            if !is_duplicate {
+                self.nodes
+                    .push(Node::new(child_game, Some(player_move.clone()), logit));
+            }
+        }
+        let children = first_child..self.nodes.len();
+        let max_logit = self.nodes[children.clone()]
+            .iter()
+            .map(|child| child.prior)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let exp_sum: f32 = self.nodes[children.clone()]
+            .iter()
+            .map(|child| (child.prior - max_logit).exp())
+            .sum();
+        for child in &mut self.nodes[children.clone()] {
+            child.prior = (child.prior - max_logit).exp() / exp_sum;
+        }
+        self.nodes[index].children = children;
+        self.nodes[index].expanded = true;
+        prediction.value
+    }
+
+    fn select_child(&self, index: usize) -> usize {
+        let parent = &self.nodes[index];
+        let puct = &self.config.puct;
+        let sqrt_visits = (parent.visits as f32).sqrt();
+        let c_puct = puct.c_puct(parent.visits);
+        let unvisited_q = match puct.unvisited_value {
+            UnvisitedValue::Draw => 0.0,
+            UnvisitedValue::Loss => -1.0,
+            UnvisitedValue::Parent => {
+                let visited_prior: f32 = parent
+                    .children
+                    .clone()
+                    .map(|child| &self.nodes[child])
+                    .filter(|child| child.visits > 0)
+                    .map(|child| child.prior)
+                    .sum();
+                // The parent holds values for the player who moved into it
+                -parent.q() - puct.fpu_reduction * visited_prior.sqrt()
+            }
+        };
+        let score = |child: usize| {
+            let node = &self.nodes[child];
+            let q = if node.visits == 0 {
+                unvisited_q
+            } else {
+                node.q()
+            };
+            q + c_puct * node.prior * sqrt_visits / (1.0 + node.visits as f32)
+        };
+        parent
+            .children
+            .clone()
+            .max_by(|&a, &b| score(a).total_cmp(&score(b)))
+            .unwrap()
+    }
+
+    fn simulate(&mut self) {
+        let mut path = vec![0];
+        let mut index = 0;
+        while !self.nodes[index].children.is_empty() {
+            index = self.select_child(index);
+            path.push(index);
+        }
+        // Each node stores values for the player who moved into it
+        let mut value = -self.leaf_value(&path);
+        for &index in path.iter().rev() {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.value_sum += value;
+            value = -value;
+        }
+    }
+
+    /// The value of the leaf at the end of `path` for its side to move, see `expand`, a
+    /// position repeated on the path counting as a draw judged with the contempt.
+    fn leaf_value(&mut self, path: &[usize]) -> f32 {
+        let (&index, ancestors) = path.split_last().unwrap();
+        let contempt = self.config.contempt;
+        let key = self.nodes[index].key;
+        if contempt != 0.0
+            && ancestors
+                .iter()
+                .any(|&ancestor| self.nodes[ancestor].key == key)
+        {
+            return if self.nodes[index].game.player == self.nodes[0].game.player {
+                -contempt
+            } else {
+                contempt
+            };
+        }
+        self.expand(index)
+    }
+
+    fn add_root_noise(&mut self, rng: &mut StdRng) {
+        let Ok(gamma) = Gamma::new(self.config.dirichlet_alpha.max(1e-3), 1.0) else {
+            return;
+        };
+        let children = self.nodes[0].children.clone();
+        let noise: Vec<f32> = children.clone().map(|_| gamma.sample(rng)).collect();
+        let noise_sum: f32 = noise.iter().sum();
+        if noise_sum <= 0.0 {
+            return;
+        }
+        let epsilon = self.config.dirichlet_epsilon;
+        for (child, noise) in children.zip(noise) {
+            let node = &mut self.nodes[child];
+            node.prior = (1.0 - epsilon) * node.prior + epsilon * noise / noise_sum;
+        }
+    }
+
+    /// Whether the most visited root move keeps the lead whatever `remaining` more
+    /// simulations visit.
+    fn is_decided(&self, remaining: usize) -> bool {
+        let (mut first, mut second) = (0, 0);
+        for child in self.nodes[0].children.clone() {
+            let visits = self.nodes[child].visits;
+            if visits > first {
+                (first, second) = (visits, first);
+            } else if visits > second {
+                second = visits;
+            }
+        }
+        (first - second) as usize > remaining
+    }
+
+    fn root_stats(&self, start_time: Instant) -> RootStats {
+        let root = &self.nodes[0];
+        let mut moves: Vec<MoveStats> = root
+            .children
+            .clone()
+            .map(|child| {
+                let node = &self.nodes[child];
+                let player_move = node.player_move.clone().unwrap();
+                MoveStats {
+                    notation: move_to_notation(&root.game, &player_move),
+                    player_move,
+                    visits: node.visits,
+                    q: node.q(),
+                    prior: node.prior,
+                }
+            })
+            .collect();
+        moves.sort_by(|a, b| b.visits.cmp(&a.visits).then(b.prior.total_cmp(&a.prior)));
+        RootStats {
+            moves,
+            value: -root.q(),
+            simulations: root.visits as usize,
+            elapsed: start_time.elapsed(),
+        }
+    }
+}
+
+/// Plays the moves the network guided tree search visits most.
+pub struct MctsEngine {
+    network: QuoridorNet,
+    config: MctsConfig,
+    stop: Arc<AtomicBool>,
+    rng: StdRng,
+}
+
+impl MctsEngine {
+    pub fn new(network: QuoridorNet, config: MctsConfig) -> Self {
+        Self {
+            network,
+            config,
+            stop: Arc::new(AtomicBool::new(false)),
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// Seeds the generator of the root noise and of move sampling.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// A handle that stops the running search from another thread.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Searches `game`, calling `send` with the root statistics every `interval` and
+    /// once more at the end. The search runs for the move time or the share of the clock
+    /// and the node limit when given, for the configured number of simulations otherwise,
+    /// and ends early when stopped or when `send` returns false, like a channel whose
+    /// receiver is gone.
+    pub fn analyse_streaming(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+        interval: Duration,
+        mut send: impl FnMut(RootStats) -> bool,
+    ) -> RootStats {
+        self.search(game, limits, Some((interval, &mut send)))
+    }
+
+    fn search(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+        mut report: Option<(Duration, &mut dyn FnMut(RootStats) -> bool)>,
+    ) -> RootStats {
+        let start_time = Instant::now();
+        self.stop.store(false, Ordering::Relaxed);
+        let mut tree = Tree {
+            nodes: vec![Node::new(game.clone(), None, 1.0)],
+            network: &self.network,
+            config: &self.config,
+        };
+        tree.simulate();
+        if self.config.root_noise {
+            tree.add_root_noise(&mut self.rng);
+        }
+        let movetime = limits.time_budget(game.player);
+        let max_simulations = match (limits.nodes, movetime) {
+            (Some(nodes), _) => nodes as usize,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => self.config.simulations,
+        };
+        let early_stop = self.config.early_stop && self.config.temperature <= 0.0;
+        let mut last_report = start_time;
+        let mut simulations = 1;
+        loop {
+            let decided = early_stop
+                && tree.is_decided(remaining_simulations(
+                    simulations,
+                    max_simulations,
+                    movetime,
+                    start_time,
+                ));
+            let done = decided
+                || simulations >= max_simulations
+                || movetime.is_some_and(|movetime| start_time.elapsed() >= movetime);
+            if done || self.stop.load(Ordering::Relaxed) || tree.nodes[0].children.is_empty() {
+                break;
+            }
+            tree.simulate();
+            simulations += 1;
+            if let Some((interval, send)) = report.as_mut()
+                && last_report.elapsed() >= *interval
+            {
+                last_report = Instant::now();
+                if !send(tree.root_stats(start_time)) {
+                    break;
+                }
+            }
+        }
+        let stats = tree.root_stats(start_time);
+        if let Some((_, send)) = report {
+            send(stats.clone());
+        }
+        stats
+    }
+
+    fn pick_move(&mut self, stats: &RootStats) -> usize {
+        if self.config.temperature <= 0.0 {
+            return 0;
+        }
+        let weights = stats
+            .moves
+            .iter()
+            .map(|stats| (stats.visits as f64).powf(1.0 / self.config.temperature as f64));
+        match WeightedIndex::new(weights) {
+            Ok(distribution) => distribution.sample(&mut self.rng),
+            Err(_) => 0,
+        }
+    }
+}
+
+/// The simulations a search can still run, within `max_simulations` and, at the rate
+/// of the `simulations` so far, the move time.
+fn remaining_simulations(
+    simulations: usize,
+    max_simulations: usize,
+    movetime: Option<Duration>,
+    start_time: Instant,
+) -> usize {
+    let remaining = max_simulations.saturating_sub(simulations);
+    let Some(movetime) = movetime else {
+        return remaining;
+    };
+    let elapsed = start_time.elapsed();
+    let left = movetime.saturating_sub(elapsed).as_secs_f64();
+    let rate = simulations as f64 / elapsed.as_secs_f64().max(1e-6);
+    remaining.min((left * rate).ceil() as usize)
+}
+
+impl Engine for MctsEngine {
+    fn name(&self) -> String {
+        "mcts".to_string()
+    }
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> EngineMove {
+        let start_time = Instant::now();
+        let stats = self.search(game, limits, None);
+        let chosen = &stats.moves[self.pick_move(&stats)];
+        EngineMove {
+            policy_prior: Some(chosen.visits as f32 / stats.simulations.max(1) as f32),
+            root_visits: Some(
+                stats
+                    .moves
+                    .iter()
+                    .map(|stats| (stats.player_move.clone(), stats.visits))
+                    .collect(),
+            ),
+            root_value: Some(stats.value),
+            planned_duration: limits.time_budget(game.player),
+            ..EngineMove::unsearched(chosen.player_move.clone(), start_time)
+        }
+    }
+
+    fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// A first forward pass, which sets up the backend and is much slower than the rest.
+    fn warm_up(&mut self, game: &Game) {
+        predict_games(&self.network, &[game]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quoridor_core::notation::{parse_notation_move, parse_position};
+
+    #[test]
+    fn streaming_test() {
+        let config = MctsConfig {
+            simulations: 30,
+            root_noise: true,
+            ..MctsConfig::default()
+        };
+        let mut engine = MctsEngine::new(QuoridorNet::new(), config);
+        let game = Game::new();
+        let mut streamed = Vec::new();
+        let stats =
+            engine.analyse_streaming(&game, &SearchLimits::default(), Duration::ZERO, |stats| {
+                streamed.push(stats);
+                true
+            });
+        assert_eq!(stats.simulations, 30);
+        assert_eq!(streamed.last().unwrap().simulations, 30);
+        assert!(streamed.len() > 2);
+        let visits: u32 = stats.moves.iter().map(|stats| stats.visits).sum();
+        assert_eq!(visits, 29);
+        assert!(stats.moves.windows(2).all(|w| w[0].visits >= w[1].visits));
+
+        // A dropped receiver stops the search
+        let stats =
+            engine.analyse_streaming(&game, &SearchLimits::default(), Duration::ZERO, |_| false);
+        assert_eq!(stats.simulations, 2);
+
+        // The winning step is found whatever the untrained network thinks
+        let game = parse_position("e8 a9 0 0 w -").unwrap();
+        let engine_move = engine.choose_move(&game, &SearchLimits::default());
+        assert_eq!(move_to_notation(&game, &engine_move.player_move), "e9");
+    }
+
+    #[test]
+    fn unvisited_value_test() {
+        let network = QuoridorNet::new();
+        let visited_moves = |unvisited_value| {
+            let config = MctsConfig {
+                puct: PuctConfig {
+                    unvisited_value,
+                    ..PuctConfig::default()
+                },
+                ..MctsConfig::default()
+            };
+            let mut tree = Tree {
+                nodes: vec![Node::new(Game::new(), None, 1.0)],
+                network: &network,
+                config: &config,
+            };
+            for _ in 0..20 {
+                tree.simulate();
+            }
+            let root = &tree.nodes[0];
+            root.children
+                .clone()
+                .filter(|&child| tree.nodes[child].visits > 0)
+                .count()
+        };
+        // Untried moves counted as losses wait for the tried one to lose
+        assert_eq!(visited_moves(UnvisitedValue::Loss), 1);
+
+        let puct = PuctConfig::default();
+        assert_eq!(puct.c_puct(1000), puct.c_puct);
+        let growing = PuctConfig {
+            c_puct_base: Some(100.0),
+            ..puct
+        };
+        assert!((growing.c_puct(99) - growing.c_puct - 2f32.ln()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn contempt_test() {
+        // The pawns step forward and back and forward again
+        let mut game_states = vec![Game::new()];
+        for notation in ["e2", "e8", "e1", "e9", "e2"] {
+            let mut game = game_states.last().unwrap().clone();
+            let player = game.player;
+            let player_move = parse_notation_move(&game, notation).unwrap();
+            execute_move_unchecked(&mut game, player, &player_move);
+            game_states.push(game);
+        }
+        let network = QuoridorNet::new();
+        let config = MctsConfig {
+            contempt: 0.3,
+            ..MctsConfig::default()
+        };
+        let mut tree = Tree {
+            nodes: game_states
+                .into_iter()
+                .map(|game| Node::new(game, None, 1.0))
+                .collect(),
+            network: &network,
+            config: &config,
+        };
+        // White to move in the start position again, the draw White at the root avoids
+        assert_eq!(tree.leaf_value(&[0, 1, 2, 3, 4]), -0.3);
+        // Black to move after e2 again, to whom the draw is worth as much more
+        assert_eq!(tree.leaf_value(&[0, 1, 2, 3, 4, 5]), 0.3);
+        assert!(!tree.nodes[1].expanded);
+        tree.leaf_value(&[0, 1]);
+        assert!(tree.nodes[1].expanded);
+    }
+
+    #[test]
+    fn early_stop_test() {
+        // The winning step draws every visit, so the rest of the budget is not needed
+        let game = parse_position("e8 a9 0 0 w -").unwrap();
+        let config = MctsConfig {
+            simulations: 200,
+            ..MctsConfig::default()
+        };
+        let mut engine = MctsEngine::new(QuoridorNet::new(), config.clone());
+        let stats = engine.search(&game, &SearchLimits::default(), None);
+        assert_eq!(stats.simulations, 200);
+
+        let early_stop = MctsConfig {
+            early_stop: true,
+            ..config.clone()
+        };
+        let mut engine = MctsEngine::new(QuoridorNet::new(), early_stop.clone());
+        let stats = engine.search(&game, &SearchLimits::default(), None);
+        assert!(stats.simulations < 200);
+        assert_eq!(stats.moves[0].notation, "e9");
+        assert!(
+            stats.moves[0].visits as usize
+                > stats.moves[1].visits as usize + 200 - stats.simulations
+        );
+
+        // Sampling by visits needs all of them
+        let sampling = MctsConfig {
+            temperature: 1.0,
+            ..early_stop
+        };
+        let mut engine = MctsEngine::new(QuoridorNet::new(), sampling);
+        let stats = engine.search(&game, &SearchLimits::default(), None);
+        assert_eq!(stats.simulations, 200);
+    }
+}
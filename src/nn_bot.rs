@@ -12,47 +12,73 @@
 // You can split this into modules later; kept single-file for clarity.
 
 use burn::backend::NdArray;
-use rand::{prelude::*, rng};
+use rand::prelude::*;
+use rand::rngs::StdRng;
 use burn;
 use burn::nn::{self, Initializer, Relu};
 use burn::tensor::{backend::Backend, Tensor};
 use burn::module::Module;
+use burn::record::{FullPrecisionSettings, NamedMpkFileRecorder, Recorder};
+use std::collections::HashMap;
+use std::path::Path;
 use burn::nn::conv::{Conv2d, Conv2dConfig};
 
-use crate::data_model::{Game, Player, PlayerMove, WallOrientation, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, WALL_GRID_HEIGHT, WALL_GRID_WIDTH};
-use crate::all_moves::ALL_MOVES;
-use crate::game_logic::is_move_legal;
+use quoridor_core::data_model::{Game, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH, Player, PlayerMove};
+use quoridor_core::all_moves::{ALL_MOVES, PAWN_MOVES, is_playable, validate_all_moves};
+pub use quoridor_core::encoding::{ENCODED_CHANNELS, EncodedState, FEATURE_COUNT, encode, encode_features, encode_features_into, encode_into};
+use quoridor_core::engine::{Engine, EngineMove, SearchLimits};
+use quoridor_core::game_logic::is_move_legal;
+use quoridor_core::notation::move_to_notation;
 
 
 // ===== 0) Domain adapter =====
 // Glue layer between YOUR existing rules/state and this scaffold.
 
-/// A compact action id in [0, 138). 0..10 pawn moves, 10..138 walls, for example.
+/// A compact action id in [0, ACTIONS), the index of the move in ALL_MOVES.
 pub type ActionId = u16; // keep it small
 
-/// Encoded input planes for the NN. Shape: C x 9 x 9 flattened to row-major.
-#[derive(Clone)]
-pub struct EncodedState {
-    pub planes: Vec<Vec<Vec<f32>>>, // length = C*9*9
-    pub c: usize,         // channels
-}
-
 /// Mask of legal actions aligned with the fixed action space.
 #[derive(Clone)]
 pub struct ActionMask(pub [bool; ACTIONS]);
 
 pub const ACTIONS: usize = 138; // adjust if you use a different scheme
 
+/// Checks the network's action space, the first ACTIONS moves of ALL_MOVES, against the canonical one.
+pub fn validate_action_space() -> Result<(), String> {
+    validate_all_moves()?;
+    if ACTIONS > ALL_MOVES.len() {
+        return Err(format!("The network has {} actions, ALL_MOVES only {}", ACTIONS, ALL_MOVES.len()));
+    }
+    if ACTIONS < PAWN_MOVES {
+        return Err(format!("The network has {} actions, fewer than the {} pawn moves", ACTIONS, PAWN_MOVES));
+    }
+    Ok(())
+}
+
+/// Lists every action id with its move, whether the network has a policy output for it
+/// and whether it can ever be legal.
+pub fn action_space_table() -> String {
+    let mut table = "id\tmove\tnetwork\tplayable\n".to_string();
+    for (id, player_move) in ALL_MOVES.iter().enumerate() {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        table += &format!("{}\t{}\t{}\t{}\n", id, player_move, yes_no(id < ACTIONS), yes_no(is_playable(player_move)));
+    }
+    let playable = ALL_MOVES.iter().filter(|player_move| is_playable(player_move)).count();
+    let covered = ALL_MOVES[..ACTIONS].iter().filter(|player_move| is_playable(player_move)).count();
+    table += &format!("# the network covers {} of {} playable moves\n", covered, playable);
+    table
+}
+
 
 fn action_from_id(action_id: ActionId) -> PlayerMove {
     return ALL_MOVES.get(action_id as usize).unwrap().clone();
 }
 
-pub fn get_move(game: &Game, network: &QuoridorNet, player: Player, temperature: f32) -> PlayerMove
+/// Samples a move from the network policy, returning it with its probability.
+/// A temperature of zero or less plays the most likely move instead.
+pub fn get_move(game: &Game, network: &QuoridorNet, player: Player, temperature: f32, rng: &mut impl Rng) -> (PlayerMove, f32)
 {
-    let mut rng = rng();
-
-    let prediction = predict_batch(network, &[encode(game)]);
+    let prediction = predict_games(network, &[game]);
 
     let legal_moves: Vec<(usize, &f32)> = prediction.first().unwrap().policy_logits.iter().enumerate()
         .filter(|(id, _)|{is_move_legal(game, player, &action_from_id(*id as u16))}).collect();
@@ -62,62 +88,61 @@ pub fn get_move(game: &Game, network: &QuoridorNet, player: Player, temperature:
     let max_logit = legal_moves.iter().map(|&(_, l)| l.clone()).fold(f32::NEG_INFINITY, f32::max);
     let exp_logits: Vec<f32> = legal_moves
         .iter()
-        .map(|&(_, logit)| ((logit - max_logit) / temperature).exp())
+        .map(|&(_, logit)| ((logit - max_logit) / temperature.max(f32::MIN_POSITIVE)).exp())
         .collect();
 
         // Normalize into probabilities
     let sum_exp: f32 = exp_logits.iter().sum();
     let probs: Vec<f32> = exp_logits.iter().map(|x| x / sum_exp).collect();
 
-    // Sample from distribution
-    let dist = rand::distr::weighted::WeightedIndex::new(&probs).unwrap();
-    let choice = dist.sample(&mut rng);
+    // Sample from distribution, or take the first most likely move at zero temperature
+    let choice = if temperature <= 0.0 {
+        legal_moves.iter().position(|&(_, &logit)| logit == max_logit).unwrap()
+    } else {
+        let dist = rand::distr::weighted::WeightedIndex::new(&probs).unwrap();
+        dist.sample(rng)
+    };
 
     // Extract the most likely move from the output
-    action_from_id( legal_moves[choice].0 as u16)
+    (action_from_id( legal_moves[choice].0 as u16), probs[choice])
 }
 
-fn encode(game: &Game) -> EncodedState {
-    // shape: [channels, 9, 9]
-    let mut channels = vec![vec![vec![0.0; PIECE_GRID_WIDTH]; PIECE_GRID_HEIGHT]; 8];
-
-    // player pawns
-    for p in [Player::White, Player::Black] {
-        let pos = game.board.player_position(p);
-        channels[p.as_index()][pos.y()][pos.x()] = 1.0;
-    }
-
-    // walls (just fill in as 1.0 where a wall is placed)
-    for x in 0..WALL_GRID_WIDTH {
-        for y in 0..WALL_GRID_HEIGHT {
-            if let Some(o) = game.board.walls[x][y] {
-                match o {
-                    WallOrientation::Horizontal =>
-                        channels[2][y][x] = 1.0,
-                    WallOrientation::Vertical =>
-                        channels[3][y][x] = 1.0,
-                }
-            }
-        }
+/// Plays moves sampled from the network policy.
+pub struct NeuralNetEngine {
+    pub network: QuoridorNet,
+    pub temperature: f32,
+    rng: StdRng,
+}
+
+impl NeuralNetEngine {
+    pub fn new(network: QuoridorNet, temperature: f32) -> Self {
+        Self { network, temperature, rng: StdRng::from_os_rng() }
     }
 
-    // walls left (normalized by 10)
-    for x in 0..PIECE_GRID_WIDTH {
-        for y in 0..PIECE_GRID_HEIGHT {
-            channels[4][y][x] = game.walls_left[0] as f32 / 10.0;
-            channels[5][y][x] = game.walls_left[1] as f32 / 10.0;
-        }
+    /// Samples moves from a seeded generator, so the same network plays the same games.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self { rng: StdRng::seed_from_u64(seed), ..self }
+    }
+}
+
+impl Engine for NeuralNetEngine {
+    fn name(&self) -> String {
+        "neural network".to_string()
     }
 
-    // player-to-move plane
-    let current = game.player.as_index();
-    for x in 0..PIECE_GRID_WIDTH {
-        for y in 0..PIECE_GRID_HEIGHT {
-            channels[6][y][x] = if current == 0 { 1.0 } else { 0.0 };
+    fn choose_move(&mut self, game: &Game, _limits: &SearchLimits) -> EngineMove {
+        let start_time = std::time::Instant::now();
+        let (player_move, prior) = get_move(game, &self.network, game.player, self.temperature, &mut self.rng);
+        EngineMove {
+            policy_prior: Some(prior),
+            ..EngineMove::unsearched(player_move, start_time)
         }
     }
 
-    EncodedState { planes: channels, c: 8 }
+    /// A first forward pass, which sets up the backend and is much slower than the rest.
+    fn warm_up(&mut self, game: &Game) {
+        predict_games(&self.network, &[game]);
+    }
 }
 
 // ===== 1) Policy-Value Network interface =====
@@ -530,21 +555,79 @@ pub trait PolicyValueNet: Send + 'static {
 
 /// Burn network
 
+/// The input encoding together with the network architecture reading it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap_derive::ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NetworkKind {
+    /// Convolutions over the board planes.
+    #[default]
+    Conv,
+    /// A tiny MLP over handcrafted features, for fast play on a CPU.
+    FeatureMlp,
+}
+
+impl NetworkKind {
+    pub fn encode(self, game: &Game) -> EncodedState {
+        match self {
+            NetworkKind::Conv => encode(game),
+            NetworkKind::FeatureMlp => encode_features(game),
+        }
+    }
+
+    /// Writes the encoded position to `out`, of length `input_len`.
+    pub fn encode_into(self, game: &Game, out: &mut [f32]) {
+        match self {
+            NetworkKind::Conv => encode_into(game, out),
+            NetworkKind::FeatureMlp => encode_features_into(game, out),
+        }
+    }
+
+    /// Values of an encoded position.
+    pub fn input_len(self) -> usize {
+        self.input_shape().iter().product()
+    }
+
+    /// Channels, height and width of the encoded states, as in training shards.
+    pub fn input_shape(self) -> [usize; 3] {
+        match self {
+            NetworkKind::Conv => [ENCODED_CHANNELS, PIECE_GRID_HEIGHT, PIECE_GRID_WIDTH],
+            NetworkKind::FeatureMlp => [1, 1, FEATURE_COUNT],
+        }
+    }
+}
+
 /// Quoridor AlphaZero-style network.
+#[derive(Clone)]
 pub struct QuoridorNet
 {
     device: <NdArray as burn::prelude::Backend>::Device,
-    network_model: NetworkModel
+    network_model: NetworkModel<NdArray>
 }
 
-#[derive(Module, Debug, Clone)]
-pub struct NetworkModel
+#[derive(Module, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum NetworkModel<B: Backend> {
+    Conv(ConvModel<B>),
+    FeatureMlp(FeatureModel<B>),
+}
+
+#[derive(Module, Debug)]
+pub struct ConvModel<B: Backend>
 {
-    conv1: Conv2d<NdArray>,
-    conv2: Conv2d<NdArray>,
-    fc_policy: nn::Linear<NdArray>,
-    fc_value1: nn::Linear<NdArray>,
-    fc_value2: nn::Linear<NdArray>,
+    conv1: Conv2d<B>,
+    conv2: Conv2d<B>,
+    fc_policy: nn::Linear<B>,
+    fc_value1: nn::Linear<B>,
+    fc_value2: nn::Linear<B>,
+}
+
+#[derive(Module, Debug)]
+pub struct FeatureModel<B: Backend>
+{
+    fc1: nn::Linear<B>,
+    fc2: nn::Linear<B>,
+    fc_policy: nn::Linear<B>,
+    fc_value: nn::Linear<B>,
 }
 
 #[derive(Clone, Debug)]
@@ -555,42 +638,140 @@ pub struct NeuralNetOutput<B: Backend> {
 
 impl QuoridorNet {
     pub fn new() -> Self {
+        Self::with_kind(NetworkKind::default())
+    }
+
+    pub fn with_kind(kind: NetworkKind) -> Self {
         let device = <NdArray as burn::prelude::Backend>::Device::default();
+        let network_model = NetworkModel::new(kind, &device);
+        Self { device, network_model }
+    }
+
+    /// Wraps a model trained elsewhere, for example the inner model of an autodiff one.
+    pub fn from_model(network_model: NetworkModel<NdArray>) -> Self {
+        Self { device: Default::default(), network_model }
+    }
+
+    pub fn kind(&self) -> NetworkKind {
+        self.network_model.kind()
+    }
+
+    /// The position encoded as the input of this network.
+    pub fn encode(&self, game: &Game) -> EncodedState {
+        self.kind().encode(game)
+    }
+
+    /// Writes the weights to `path`, which gets the `.mpk` extension.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        save_model(&self.network_model, path)
+    }
+
+    /// Reads weights written by `save`, of either kind of network.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let device = Default::default();
+        let network_model = load_model(path, &device)?;
+        Ok(Self { device, network_model })
+    }
+}
+
+fn recorder() -> NamedMpkFileRecorder<FullPrecisionSettings> {
+    NamedMpkFileRecorder::new()
+}
+
+/// Writes the weights of a model on any backend, so they load into any other.
+pub fn save_model<B: Backend>(network_model: &NetworkModel<B>, path: &Path) -> Result<(), String> {
+    network_model.clone()
+        .save_file(path, &recorder())
+        .map_err(|e| format!("Could not save {}: {e}", path.display()))
+}
+
+/// Reads weights written by `save_model`, building the model of the kind they were saved from.
+pub fn load_model<B: Backend>(path: &Path, device: &B::Device) -> Result<NetworkModel<B>, String> {
+    let path = path.with_extension("mpk");
+    match Recorder::<B>::load::<NetworkModelRecord<B>>(&recorder(), path.clone(), device) {
+        Ok(record) => {
+            let kind = match &record {
+                NetworkModelRecord::Conv(_) => NetworkKind::Conv,
+                NetworkModelRecord::FeatureMlp(_) => NetworkKind::FeatureMlp,
+            };
+            Ok(NetworkModel::new(kind, device).load_record(record))
+        }
+        // Weights saved before there were kinds of networks are those of the conv model
+        Err(_) => ConvModel::new(device)
+            .load_file(&path, &recorder(), device)
+            .map(NetworkModel::Conv)
+            .map_err(|e| format!("Could not load {}: {e}", path.display())),
+    }
+}
 
-        let conv_cfg = Conv2dConfig::new([7, 64], [3, 3])
-            .with_initializer(Initializer::KaimingUniform { gain: 1.0, fan_out_only: false }); // in_channels=7, out=64
+impl<B: Backend> NetworkModel<B> {
+    pub fn new(kind: NetworkKind, device: &B::Device) -> Self {
+        match kind {
+            NetworkKind::Conv => NetworkModel::Conv(ConvModel::new(device)),
+            NetworkKind::FeatureMlp => NetworkModel::FeatureMlp(FeatureModel::new(device)),
+        }
+    }
 
-        let conv1 = conv_cfg.init(&device);
+    pub fn kind(&self) -> NetworkKind {
+        match self {
+            NetworkModel::Conv(_) => NetworkKind::Conv,
+            NetworkModel::FeatureMlp(_) => NetworkKind::FeatureMlp,
+        }
+    }
+
+    /// Runs a batch of states encoded for this kind of network.
+    pub fn forward_batch(&self, batch: &[EncodedState], device: &B::Device) -> NeuralNetOutput<B> {
+        self.forward_flat(flatten_batch(batch), batch.len(), device)
+    }
+
+    /// Runs `batch_size` states encoded for this kind of network one after the other in `flat`,
+    /// which becomes the input tensor without a copy.
+    pub fn forward_flat(&self, flat: Vec<f32>, batch_size: usize, device: &B::Device) -> NeuralNetOutput<B> {
+        let [channels, height, width] = self.kind().input_shape();
+        match self {
+            NetworkModel::Conv(model) => model.forward(Tensor::<B, 4>::from_data(
+                burn::tensor::TensorData::new(flat, [batch_size, channels, height, width]),
+                device,
+            )),
+            NetworkModel::FeatureMlp(model) => model.forward(Tensor::<B, 2>::from_data(
+                burn::tensor::TensorData::new(flat, [batch_size, FEATURE_COUNT]),
+                device,
+            )),
+        }
+    }
+}
+
+impl<B: Backend> ConvModel<B>
+{
+    pub fn new(device: &B::Device) -> Self {
+        let conv_cfg = Conv2dConfig::new([ENCODED_CHANNELS, 64], [3, 3])
+            .with_initializer(Initializer::KaimingUniform { gain: 1.0, fan_out_only: false });
+
+        let conv1 = conv_cfg.init(device);
 
         let conv_cfg2 = Conv2dConfig::new([64, 64], [3, 3])
           .with_initializer(Initializer::KaimingUniform { gain: 1.0, fan_out_only: false });
-        let conv2 = conv_cfg2.init(&device);
+        let conv2 = conv_cfg2.init(device);
 
         // Flatten feature map (approx 64 * 5 * 5 after two 3x3 conv on 9x9 input, no padding)
         let fc_policy = nn::LinearConfig::new(64 * 5 * 5, 138)
             .with_initializer(Initializer::KaimingUniform { gain: 1.0, fan_out_only: false })
-            .init(&device);
+            .init(device);
 
         let fc_value1 = nn::LinearConfig::new(64 * 5 * 5, 64)
             .with_initializer(Initializer::KaimingUniform { gain: 1.0, fan_out_only: false })
-            .init(&device);
+            .init(device);
 
         let fc_value2 = nn::LinearConfig::new(64, 1)
             .with_initializer(Initializer::XavierNormal { gain: (1.0) })
-            .init(&device);
+            .init(device);
 
-        Self {
-            device,
-            network_model: NetworkModel { conv1, conv2, fc_policy, fc_value1, fc_value2 }
-        }
+        Self { conv1, conv2, fc_policy, fc_value1, fc_value2 }
     }
-}
 
-impl NetworkModel
-{
-    pub fn forward(&self, x: Tensor<NdArray, 4>) -> NeuralNetOutput<NdArray> {
+    pub fn forward(&self, x: Tensor<B, 4>) -> NeuralNetOutput<B> {
         let relu = Relu::new();
-        // x: [batch, 7, 9, 9]
+        // x: [batch, C, 9, 9]
         let x = self.conv1.forward(x);
         let x = relu.forward(x);
         let x = self.conv2.forward(x);
@@ -612,40 +793,60 @@ impl NetworkModel
 }
 
 
-pub fn encode_batch_to_tensor<B: Backend>(
-    batch: &[EncodedState],
-    device: &B::Device,
-) -> Tensor<B, 4> {
-    let batch_size = batch.len();
-    let c = batch[0].c; // assume all states have the same channel count
+impl<B: Backend> FeatureModel<B>
+{
+    pub fn new(device: &B::Device) -> Self {
+        let linear = |inputs, outputs| nn::LinearConfig::new(inputs, outputs)
+            .with_initializer(Initializer::KaimingUniform { gain: 1.0, fan_out_only: false })
+            .init(device);
+        // The features are sparse, so a narrow first layer is cheap to evaluate
+        Self {
+            fc1: linear(FEATURE_COUNT, 32),
+            fc2: linear(32, 32),
+            fc_policy: linear(32, ACTIONS),
+            fc_value: nn::LinearConfig::new(32, 1)
+                .with_initializer(Initializer::XavierNormal { gain: 1.0 })
+                .init(device),
+        }
+    }
+
+    pub fn forward(&self, x: Tensor<B, 2>) -> NeuralNetOutput<B> {
+        let relu = Relu::new();
+        // x: [batch, FEATURE_COUNT]
+        let x = relu.forward(self.fc1.forward(x));
+        let x = relu.forward(self.fc2.forward(x));
+        let policy = self.fc_policy.forward(x.clone());
+        let value = self.fc_value.forward(x).tanh();
+        NeuralNetOutput { policy, value }
+    }
+}
 
-    // Flatten into a single Vec<f32>: [batch, c, 9, 9]
-    let mut flat: Vec<f32> = Vec::with_capacity(batch_size * c * 9 * 9);
 
+/// The states one after the other, [batch, C, H, W] in row-major order.
+pub fn flatten_batch(batch: &[EncodedState]) -> Vec<f32> {
+    let mut flat = Vec::with_capacity(batch.iter().map(|state| state.values.len()).sum());
     for state in batch {
-        assert_eq!(state.planes.len(), c);
-        for chan in 0..c {
-            assert_eq!(state.planes[chan].len(), 9);
-            for row in 0..9 {
-                assert_eq!(state.planes[chan][row].len(), 9);
-                flat.extend_from_slice(&state.planes[chan][row]);
-            }
-        }
+        assert_eq!(state.shape, batch[0].shape, "States of a batch have the same shape");
+        flat.extend_from_slice(&state.values);
     }
-
-    // Build tensor with shape [batch, c, 9, 9]
-    Tensor::<B, 4>::from_data(
-        burn::tensor::TensorData::new(flat, [batch_size, c, 9, 9]),
-        device,
-    )
+    flat
 }
 
-fn predict_batch(network: &QuoridorNet, batch: &[EncodedState]) -> Vec<NetOut> {
-// Convert batch &[EncodedState] → Tensor<B,4> of shape [batch, 7, 9, 9]
-    let input = encode_batch_to_tensor::<NdArray>(batch, &network.device);
+pub fn predict_batch(network: &QuoridorNet, batch: &[EncodedState]) -> Vec<NetOut> {
+    network_outputs(network.network_model.forward_batch(batch, &network.device))
+}
 
-    let out = network.network_model.forward(input);
+/// Like `predict_batch`, encoding the positions straight into the input tensor.
+pub fn predict_games(network: &QuoridorNet, games: &[&Game]) -> Vec<NetOut> {
+    let kind = network.kind();
+    let mut flat = vec![0.0; games.len() * kind.input_len()];
+    for (game, slot) in games.iter().zip(flat.chunks_exact_mut(kind.input_len())) {
+        kind.encode_into(game, slot);
+    }
+    network_outputs(network.network_model.forward_flat(flat, games.len(), &network.device))
+}
 
+fn network_outputs(out: NeuralNetOutput<NdArray>) -> Vec<NetOut> {
     // Map NetOut<B> → your NetOut type (convert tensor to Vec<f32>)
     let values: Vec<f32> = out.value.into_data().to_vec().unwrap();
 
@@ -657,4 +858,85 @@ fn predict_batch(network: &QuoridorNet, batch: &[EncodedState]) -> Vec<NetOut> {
         .collect()
 }
 
+/// The legal moves by the policy, most likely first, normalised over the legal moves.
+/// Pawn moves reaching the same square are one move.
+pub fn policy_moves(game: &Game, policy_logits: &[f32; ACTIONS]) -> Vec<(String, f32)> {
+    let legal: Vec<(String, f32)> = ALL_MOVES
+        .iter()
+        .take(ACTIONS)
+        .zip(policy_logits)
+        .filter(|(player_move, _)| is_move_legal(game, game.player, player_move))
+        .map(|(player_move, &logit)| (move_to_notation(game, player_move), logit))
+        .collect();
+    let max_logit = legal
+        .iter()
+        .map(|&(_, logit)| logit)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let total: f32 = legal
+        .iter()
+        .map(|(_, logit)| (logit - max_logit).exp())
+        .sum();
+    let mut probabilities = HashMap::<String, f32>::new();
+    for (notation, logit) in legal {
+        *probabilities.entry(notation).or_default() += (logit - max_logit).exp() / total;
+    }
+    let mut moves: Vec<_> = probabilities.into_iter().collect();
+    moves.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    moves
+}
+
+// The imports are in the tests, as the benchmarks include this file without running its
+// tests and would find them unused
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn seeded_play_test() {
+        use super::*;
+        use quoridor_core::arena::play_game;
+        use quoridor_core::rules::Rules;
+
+        let network = QuoridorNet::new();
+        let play = |temperature: f32| {
+            let mut white = NeuralNetEngine::new(network.clone(), temperature).with_seed(3);
+            let mut black = NeuralNetEngine::new(network.clone(), temperature).with_seed(4);
+            let finished_game = play_game(&mut white, &mut black, &Rules::default(), &SearchLimits::default(), 6);
+            finished_game.record.player_moves().map(|player_move| player_move.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(play(1.0), play(1.0));
+        let greedy = play(0.0);
+        assert_eq!(greedy.len(), 6);
+        assert_eq!(greedy, play(0.0));
+    }
 
+    #[test]
+    fn action_space_test() {
+        use super::*;
+
+        assert_eq!(validate_action_space(), Ok(()));
+        let table = action_space_table();
+        assert_eq!(table.lines().nth(1), Some("0\tmuu\tyes\tyes"));
+        assert_eq!(table.lines().count(), ALL_MOVES.len() + 2);
+    }
+
+    #[test]
+    fn network_kind_test() {
+        use super::*;
+
+        let path = std::env::temp_dir().join(format!("network-kind-test-{}", std::process::id()));
+        let game = Game::new();
+        for kind in [NetworkKind::Conv, NetworkKind::FeatureMlp] {
+            let network = QuoridorNet::with_kind(kind);
+            network.save(&path).unwrap();
+            let loaded = QuoridorNet::load(&path).unwrap();
+            assert_eq!(loaded.kind(), kind);
+            let value = |network: &QuoridorNet| predict_batch(network, &[network.encode(&game)])[0].value;
+            assert_eq!(value(&loaded), value(&network));
+            // Encoding straight into the batch gives the same predictions
+            let other = quoridor_core::notation::parse_position("e3 e7 9 10 b d6h").unwrap();
+            let predictions = predict_games(&network, &[&game, &other]);
+            assert_eq!(predictions[0].value, value(&network));
+            assert_eq!(predictions[0].policy_logits, predict_batch(&network, &[network.encode(&game)])[0].policy_logits);
+        }
+        std::fs::remove_file(path.with_extension("mpk")).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+// Batch evaluation of positions with the network, for comparing its value head with
+// other evaluations. Input files hold one position per line, either a position string
+// (see notation.rs) or a game record, which stands for every position of the game.
+// Empty lines and lines starting with '#' are skipped.
+//
+// Every position becomes a CSV row with the value for the side to move and the most
+// likely legal moves of the policy, with their probabilities among the legal moves:
+//
+//     file,line,position,player,value,move1,probability1,move2,probability2,...
+//     games.records,3,"e3 e7 8 9 w d6h,e2v",white,0.4121,e4,0.3100,d3,0.1254,...
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use quoridor_core::data_model::Game;
+use quoridor_core::game_record::GameRecord;
+use quoridor_core::notation::{clean_line, parse_position, position_to_string};
+
+use crate::nn_bot::{QuoridorNet, policy_moves, predict_games};
+
+/// A position read from an input file, with where it came from.
+pub struct InputPosition {
+    pub file: String,
+    /// 1 based.
+    pub line: usize,
+    pub game: Game,
+}
+
+/// The input files of `path`: the file itself, or the files in the directory sorted by name.
+pub fn input_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let entries =
+        std::fs::read_dir(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The positions of a file or of all files in a directory.
+pub fn read_positions(path: &Path) -> Result<Vec<InputPosition>, String> {
+    let mut positions = Vec::new();
+    for file in input_files(path)? {
+        let contents = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Could not read {}: {e}", file.display()))?;
+        let name = file
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().to_string());
+        for (index, line) in contents.lines().enumerate() {
+            let line = clean_line(line);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let games = match parse_position(line) {
+                Ok(game) => vec![game],
+                Err(_) => GameRecord::parse(line)
+                    .ok_or_else(|| {
+                        format!(
+                            "{}:{}: not a position or game record",
+                            file.display(),
+                            index + 1
+                        )
+                    })?
+                    .game_states(),
+            };
+            positions.extend(games.into_iter().map(|game| InputPosition {
+                file: name.clone(),
+                line: index + 1,
+                game,
+            }));
+        }
+    }
+    Ok(positions)
+}
+
+/// Evaluates the positions in batches of `batch_size`, writing a CSV row per position
+/// with the `top_k` most likely moves.
+pub fn write_evaluations(
+    network: &QuoridorNet,
+    positions: &[InputPosition],
+    top_k: usize,
+    batch_size: usize,
+    output: &mut impl Write,
+) -> std::io::Result<()> {
+    let mut header = "file,line,position,player,value".to_string();
+    for k in 1..=top_k {
+        header += &format!(",move{k},probability{k}");
+    }
+    writeln!(output, "{header}")?;
+    for batch in positions.chunks(batch_size.max(1)) {
+        let games: Vec<_> = batch.iter().map(|position| &position.game).collect();
+        for (position, prediction) in batch.iter().zip(predict_games(network, &games)) {
+            let mut row = format!(
+                "{},{},\"{}\",{},{:.4}",
+                position.file,
+                position.line,
+                position_to_string(&position.game),
+                position.game.player.to_string().to_lowercase(),
+                prediction.value
+            );
+            let moves = policy_moves(&position.game, &prediction.policy_logits);
+            for k in 0..top_k {
+                row += &match moves.get(k) {
+                    Some((notation, probability)) => format!(",{notation},{probability:.4}"),
+                    None => ",,".to_string(),
+                };
+            }
+            writeln!(output, "{row}")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluations_csv_test() {
+        let dir = std::env::temp_dir().join(format!("nn-eval-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.positions"), "# comment\ne1 e9 10 10 w -\n").unwrap();
+        std::fs::write(dir.join("b.records"), "mdd;muu;\n").unwrap();
+        let positions = read_positions(&dir).unwrap();
+        assert_eq!(positions.len(), 4);
+        assert_eq!(
+            (positions[0].file.as_str(), positions[0].line),
+            ("a.positions", 2)
+        );
+        assert_eq!(positions[3].file, "b.records");
+
+        let mut csv = Vec::new();
+        write_evaluations(&QuoridorNet::new(), &positions, 2, 3, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            rows[0],
+            "file,line,position,player,value,move1,probability1,move2,probability2"
+        );
+        assert_eq!(rows.len(), 5);
+        assert!(rows[1].starts_with("a.positions,2,\"e1 e9 10 10 w -\",white,"));
+
+        std::fs::write(dir.join("c.positions"), "not a position\n").unwrap();
+        assert!(read_positions(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,334 @@
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use quoridor_core::bot::{DEFAULT_ASPIRATION_WINDOW, EvalWeights, Evaluation};
+use quoridor_core::data_model::{Game, Player};
+use quoridor_core::engine::{AlphaBetaEngine, Engine, GreedyEngine, OpeningRandomization, OwnLimitsEngine, RandomEngine};
+use quoridor_core::engine_log::{EngineLog, LoggingEngine};
+use quoridor_core::opponent_model::{OpponentProfile, OpponentProfiles, profiles_path};
+use quoridor_core::personality::Personality;
+use quoridor_core::protocol::ExternalEngine;
+use quoridor_core::tablebase::Tablebase;
+use quoridor_core::transposition::DEFAULT_HASH_MB;
+
+use crate::commands::LimitArgs;
+use crate::mcts::{DEFAULT_SIMULATIONS, MctsConfig, MctsEngine};
+use crate::nn_bot::{NeuralNetEngine, QuoridorNet};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, clap_derive::ValueEnum)]
 pub enum PlayerType {
     Human,
     Bot,
-    NeuralNet
+    NeuralNet,
+    Mcts,
+    External,
+    Random,
+    Greedy,
+}
+
+/// Settings shared by the engines the player types create.
+#[derive(clap_derive::Args, Debug, Clone)]
+pub struct EngineOptions {
+    #[clap(short, long, default_value_t = 0.0)]
+    pub temperature: f32,
+
+    /// Play the `neural-net` and `mcts` players at temperature zero without root noise, seeding
+    /// what randomness is left, like the bot's opening moves, with --seed or 0, so identical
+    /// players play identical games.
+    #[clap(long)]
+    pub deterministic: bool,
+
+    /// Command starting the engine used for `external` players, e.g. "./other-engine --threads 2".
+    #[clap(long)]
+    pub engine_command: Option<String>,
+
+    /// Seed for the random player, the bot's opening moves and deterministic network players, a fresh seed is used for every run when omitted.
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// Endgame tablebase written by the generate-tablebase command, used by the bot.
+    #[clap(long, value_parser = load_tablebase)]
+    pub tablebase: Option<Arc<Tablebase>>,
+
+    /// Score the bot's leaf positions by averaging this many playouts instead of the path difference.
+    #[clap(long)]
+    pub playouts: Option<usize>,
+
+    /// Playing style of the bot: wall-spender, wall-hoarder or runner.
+    #[clap(long, conflicts_with = "playouts")]
+    pub personality: Option<Personality>,
+
+    /// Weights of the bot's evaluation terms in hundredths of a step, for tuning:
+    /// distance,walls,wall_pressure,tempo, by default 100,0,50,100.
+    #[clap(long, conflicts_with_all = ["playouts", "personality"])]
+    pub eval_weights: Option<EvalWeights>,
+
+    /// Size of the bot's transposition tables in megabytes, shared out between its threads.
+    #[clap(long, default_value_t = DEFAULT_HASH_MB)]
+    pub hash_mb: usize,
+
+    /// Let the bot pick its depth for every move from the number of moves in the position,
+    /// searching deeper as the walls run out. The move time still caps the search.
+    #[clap(long)]
+    pub adaptive_depth: bool,
+
+    /// Let the bot skip walls lengthening its own path by more than this many steps beyond
+    /// the opponent's without searching them.
+    #[clap(long)]
+    pub wall_pruning: Option<isize>,
+
+    /// Steps either side of the score of the last depth the bot searches the next depth
+    /// within before widening the window, 0 to search full windows.
+    #[clap(long, default_value_t = DEFAULT_ASPIRATION_WINDOW)]
+    pub aspiration_window: isize,
+
+    /// Threads the bot searches with, splitting the moves of the root between them, each with a
+    /// transposition table of its share of --hash-mb.
+    #[clap(long, default_value_t = 1)]
+    pub threads: usize,
+
+    /// Let the bot search the reply it expects while you think, starting its next search from
+    /// the transposition table it filled.
+    #[clap(long)]
+    pub ponder: bool,
+
+    /// Let the bot play a random move among the near best ones for this many plies of every game,
+    /// seeded with --seed, or 0 with --deterministic.
+    #[clap(long, default_value_t = 0)]
+    pub opening_plies: usize,
+
+    /// Steps a random opening move of the bot may score worse than the best move.
+    #[clap(long, default_value_t = 1)]
+    pub opening_margin: isize,
+
+    /// Let the bot adapt its evaluation and opening moves to the player named with --name,
+    /// from the profile of their earlier games kept next to the leaderboard.
+    #[clap(long)]
+    pub adapt: bool,
+
+    /// The profile the bot adapts to, loaded by `load_opponent`.
+    #[clap(skip)]
+    pub opponent: Option<OpponentProfile>,
+
+    /// Network weights of `neural-net` and `mcts` players, a fresh network when omitted.
+    #[clap(long)]
+    pub network: Option<PathBuf>,
+
+    /// Tree search simulations per move of `mcts` players without a time control.
+    #[clap(long, default_value_t = DEFAULT_SIMULATIONS)]
+    pub simulations: usize,
+
+    /// End the searches of `mcts` players once no move can overtake the most visited one
+    /// in the simulations or time left, keeping the time saved on the clock.
+    #[clap(long)]
+    pub early_stop: bool,
+
+    /// Let `mcts` players, as the stronger side, value lines repeating a position at this much
+    /// less than even, between 0 and 1, so they avoid shuffling towards a draw.
+    #[clap(long, default_value_t = 0.0)]
+    pub contempt: f32,
+
+    /// Append a JSON line per engine search to this file, with the position, the move chosen,
+    /// the score, depth, nodes and time.
+    #[clap(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// Size in megabytes at which the log file is moved to <log file>.1 and a new one started.
+    #[clap(long, default_value_t = 10)]
+    pub log_size_mb: u64,
+}
+
+fn load_tablebase(path: &str) -> Result<Arc<Tablebase>, String> {
+    Tablebase::load(path).map(Arc::new)
+}
+
+impl EngineOptions {
+    /// The alpha-beta bot with these options.
+    pub fn alpha_beta_engine(&self) -> AlphaBetaEngine {
+        let mut engine = AlphaBetaEngine::new().with_hash_mb(self.hash_mb);
+        if let Some(tablebase) = &self.tablebase {
+            engine = engine.with_tablebase(tablebase.clone());
+        }
+        if let Some(playouts) = self.playouts {
+            engine = engine.with_evaluation(Evaluation::Playouts(playouts));
+        }
+        if let Some(personality) = self.personality {
+            engine = engine.with_evaluation(Evaluation::Personality(personality));
+        }
+        if let Some(weights) = self.eval_weights {
+            engine = engine.with_evaluation(Evaluation::Weights(weights));
+        }
+        // Options given explicitly win over the opponent's profile
+        let profile = self.opponent.as_ref().filter(|profile| profile.is_known());
+        if let Some(profile) = profile
+            && self.playouts.is_none()
+            && self.personality.is_none()
+            && self.eval_weights.is_none()
+        {
+            engine = engine.with_evaluation(Evaluation::Weights(profile.eval_weights()));
+        }
+        if self.adaptive_depth {
+            engine = engine.with_adaptive_depth();
+        }
+        if let Some(margin) = self.wall_pruning {
+            engine = engine.with_wall_pruning(margin);
+        }
+        engine = engine.with_aspiration_window(self.aspiration_window).with_threads(self.threads);
+        if self.ponder {
+            engine = engine.with_pondering();
+        }
+        let opening = match self.opening_plies {
+            0 => profile.and_then(OpponentProfile::opening),
+            plies => Some(OpeningRandomization { plies, margin: self.opening_margin }),
+        };
+        if let Some(opening) = opening {
+            let seed = if self.deterministic { Some(self.seed.unwrap_or(0)) } else { self.seed };
+            engine = engine.with_opening_randomization(opening, seed);
+        }
+        engine
+    }
+
+    /// Loads the profile of `name` for the bot to adapt to, with --adapt.
+    pub fn load_opponent(&mut self, leaderboard: &Path, name: Option<&str>) -> Result<(), String> {
+        if !self.adapt {
+            return Ok(());
+        }
+        let name = name.ok_or("--adapt needs the player's --name")?;
+        self.opponent = OpponentProfiles::load(&profiles_path(leaderboard))?.get(name).cloned();
+        Ok(())
+    }
+
+    /// The network loaded from --network, or a fresh one.
+    pub fn neural_net_engine(&self) -> Result<NeuralNetEngine, String> {
+        let network = self.network()?;
+        if self.deterministic {
+            Ok(NeuralNetEngine::new(network, 0.0).with_seed(self.seed.unwrap_or(0)))
+        } else {
+            Ok(NeuralNetEngine::new(network, self.temperature))
+        }
+    }
+
+    pub fn mcts_engine(&self) -> Result<MctsEngine, String> {
+        let network = self.network()?;
+        let config = MctsConfig {
+            simulations: self.simulations,
+            temperature: self.temperature,
+            early_stop: self.early_stop,
+            contempt: self.contempt,
+            ..MctsConfig::default()
+        };
+        if self.deterministic {
+            let config = MctsConfig { temperature: 0.0, root_noise: false, ..config };
+            Ok(MctsEngine::new(network, config).with_seed(self.seed.unwrap_or(0)))
+        } else {
+            Ok(MctsEngine::new(network, config))
+        }
+    }
+
+    /// Wraps the engines to log their searches to the --log-file, when given.
+    pub fn log_engines(&self, engines: &mut HashMap<Player, Box<dyn Engine>>) -> Result<(), String> {
+        let Some(path) = &self.log_file else {
+            return Ok(());
+        };
+        let log = Arc::new(Mutex::new(EngineLog::open(path, self.log_size_mb << 20)?));
+        *engines = std::mem::take(engines)
+            .into_iter()
+            .map(|(player, engine)| (player, Box::new(LoggingEngine::new(engine, log.clone())) as Box<dyn Engine>))
+            .collect();
+        Ok(())
+    }
+
+    /// Fails when the file given with --network isn't a network.
+    pub fn network(&self) -> Result<QuoridorNet, String> {
+        match &self.network {
+            Some(path) => QuoridorNet::load(path),
+            None => Ok(QuoridorNet::new()),
+        }
+    }
+}
+
+/// Warms the engines up for the game before its first move, printing how long each took.
+pub fn warm_up_engines(engines: &mut HashMap<Player, Box<dyn Engine>>, game: &Game) {
+    for player in [Player::White, Player::Black] {
+        let Some(engine) = engines.get_mut(&player) else {
+            continue;
+        };
+        print!("Warming up {}'s {}... ", player.to_string(), engine.name());
+        let _ = std::io::stdout().flush();
+        let start = Instant::now();
+        engine.warm_up(game);
+        println!("ready in {:.1?}", start.elapsed());
+    }
+}
+
+/// Engine options and search limits of one player, given as a single argument like
+/// "--network black.mpk --simulations 400" or "-d 6", for games between differently
+/// configured engines.
+#[derive(clap_derive::Parser, Debug, Clone)]
+#[command(no_binary_name = true)]
+pub struct PlayerOptions {
+    #[command(flatten)]
+    pub engine_options: EngineOptions,
+
+    #[command(flatten)]
+    pub limits: LimitArgs,
+}
+
+pub fn parse_player_options(input: &str) -> Result<PlayerOptions, String> {
+    <PlayerOptions as clap::Parser>::try_parse_from(input.split_whitespace()).map_err(|e| e.to_string())
+}
+
+impl PlayerOptions {
+    /// The engine of the player type with these options, searching with these limits when any are given.
+    pub fn engine(&self, player_type: PlayerType) -> Result<Option<Box<dyn Engine>>, String> {
+        let Some(engine) = player_type.engine(&self.engine_options)? else {
+            return Ok(None);
+        };
+        if !self.has_limits() {
+            return Ok(Some(engine));
+        }
+        Ok(Some(Box::new(OwnLimitsEngine::new(engine, self.limits.search_limits()))))
+    }
+
+    pub fn has_limits(&self) -> bool {
+        let LimitArgs { depth, seconds, movetime, nodes } = self.limits;
+        depth.is_some() || seconds.is_some() || movetime.is_some() || nodes.is_some()
+    }
+}
+
+/// How the leaderboard names the engine of a player type searching with these limits, or the
+/// player's own, e.g. "bot depth 4".
+pub fn engine_description(player_type: PlayerType, options: Option<&PlayerOptions>, limits: LimitArgs) -> String {
+    let limits = match options {
+        // Only the searching engines play differently with other limits
+        _ if matches!(player_type, PlayerType::Random | PlayerType::Greedy) => LimitArgs::default(),
+        Some(options) if options.has_limits() => options.limits,
+        _ => limits,
+    };
+    format!("{player_type} {limits}").trim_end().to_string()
+}
+
+impl PlayerType {
+    /// The engine playing for this player type, None for humans.
+    pub fn engine(&self, options: &EngineOptions) -> Result<Option<Box<dyn Engine>>, String> {
+        let engine: Box<dyn Engine> = match self {
+            PlayerType::Human => return Ok(None),
+            PlayerType::Bot => Box::new(options.alpha_beta_engine()),
+            PlayerType::NeuralNet => Box::new(options.neural_net_engine()?),
+            PlayerType::Mcts => Box::new(options.mcts_engine()?),
+            PlayerType::External => {
+                let command = options.engine_command.as_deref().ok_or("external players need --engine-command")?;
+                Box::new(ExternalEngine::spawn(command).map_err(|e| format!("Could not start {command}: {e}"))?)
+            }
+            PlayerType::Random => Box::new(RandomEngine::new(options.seed)),
+            PlayerType::Greedy => Box::new(GreedyEngine),
+        };
+        Ok(Some(engine))
+    }
 }
 
 impl Display for PlayerType {
@@ -12,7 +336,11 @@ impl Display for PlayerType {
         match self {
             PlayerType::Human => write!(f, "human"),
             PlayerType::Bot => write!(f, "bot"),
-            PlayerType::NeuralNet => write!(f, "neural network")
+            PlayerType::NeuralNet => write!(f, "neural network"),
+            PlayerType::Mcts => write!(f, "mcts"),
+            PlayerType::External => write!(f, "external engine"),
+            PlayerType::Random => write!(f, "random"),
+            PlayerType::Greedy => write!(f, "greedy"),
         }
     }
 }
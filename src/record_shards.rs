@@ -0,0 +1,111 @@
+// Conversion of game records to training shards, so that a change to the encoder or the
+// targets only takes a conversion of the games played so far, not new self-play. Input
+// files hold a game record per line, self-play exports or imported games alike; empty
+// lines and lines starting with '#' are skipped.
+//
+// The records keep no search statistics, so every move becomes a one-hot policy target
+// and every value target the game's result, see `record_samples`.
+
+use std::path::Path;
+
+use quoridor_core::game_record::GameRecord;
+use quoridor_core::notation::clean_line;
+use quoridor_core::rules::Rules;
+
+use crate::nn_bot::{ACTIONS, NetworkKind};
+use crate::nn_eval::input_files;
+use crate::self_play::{TargetConfig, record_samples};
+use crate::training_shard::create_shard;
+
+/// What a conversion wrote.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ConversionSummary {
+    pub files: usize,
+    pub games: usize,
+    pub samples: usize,
+}
+
+/// Writes the samples of the records in the file or directory `input` to the shard
+/// `output`, encoded for `kind` of network.
+pub fn convert_records(
+    input: &Path,
+    output: &Path,
+    kind: NetworkKind,
+    targets: &TargetConfig,
+) -> Result<ConversionSummary, String> {
+    let [channels, height, width] = kind.input_shape();
+    let write_error = |e: std::io::Error| format!("Could not write {}: {e}", output.display());
+    let mut writer = create_shard(output, channels, height, width, ACTIONS).map_err(write_error)?;
+    let mut summary = ConversionSummary::default();
+    for file in input_files(input)? {
+        let contents = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Could not read {}: {e}", file.display()))?;
+        summary.files += 1;
+        for (index, line) in contents.lines().enumerate() {
+            let line = clean_line(line);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let record = GameRecord::parse(line)
+                .ok_or_else(|| format!("{}:{}: not a game record", file.display(), index + 1))?;
+            for sample in record_samples(&record, &Rules::default(), kind, targets) {
+                writer.write(&sample).map_err(write_error)?;
+                summary.samples += 1;
+            }
+            summary.games += 1;
+        }
+    }
+    writer.finish().map_err(write_error)?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::training_shard::open_shard;
+
+    #[test]
+    fn convert_records_test() {
+        let dir = std::env::temp_dir().join(format!("record-shards-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("records")).unwrap();
+        std::fs::write(
+            dir.join("records").join("games.records"),
+            "# two games\n[result=1-0]mdu;mud;\n\nmdu;mud;h42;\n",
+        )
+        .unwrap();
+        let output = dir.join("converted.qshd");
+        let summary = convert_records(
+            &dir.join("records"),
+            &output,
+            NetworkKind::Conv,
+            &TargetConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            summary,
+            ConversionSummary {
+                files: 1,
+                games: 2,
+                samples: 5
+            }
+        );
+        let samples: Vec<_> = open_shard(&output)
+            .unwrap()
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        assert_eq!(samples.len(), 5);
+        assert_eq!(samples[0].value, 1.0);
+
+        std::fs::write(dir.join("records").join("bad.records"), "not a record\n").unwrap();
+        assert!(
+            convert_records(
+                &dir.join("records"),
+                &output,
+                NetworkKind::Conv,
+                &TargetConfig::default()
+            )
+            .is_err()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+// Replay buffer of the training loop, dropping the oldest samples beyond its capacity.
+//
+// Early self-play reaches the same opening positions over and over, which would then
+// dominate the batches. With deduplication a sample of a position already in the
+// buffer is merged into the existing one instead: its policy and value targets become
+// the averages over all games that reached the position, and the position is sampled
+// as often as any other. Positions are keyed by a hash of their encoding, which
+// identical positions share, so a buffer read back from a shard deduplicates the same.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use crate::nn_bot::EncodedState;
+use crate::training_shard::TrainingSample;
+
+pub struct ReplayBuffer {
+    capacity: usize,
+    deduplicate: bool,
+    /// Samples with the number of samples merged into them, oldest first.
+    entries: VecDeque<(TrainingSample, usize)>,
+    /// Sequence number of every entry by position key, with deduplication.
+    positions: HashMap<u64, usize>,
+    /// Sequence number of the front entry.
+    first: usize,
+}
+
+fn position_key(state: &EncodedState) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for value in &state.values {
+        value.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize, deduplicate: bool) -> Self {
+        Self {
+            capacity,
+            deduplicate,
+            entries: VecDeque::new(),
+            positions: HashMap::new(),
+            first: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &TrainingSample {
+        &self.entries[index].0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TrainingSample> {
+        self.entries.iter().map(|(sample, _)| sample)
+    }
+
+    pub fn push(&mut self, sample: TrainingSample) {
+        if !self.deduplicate {
+            self.entries.push_back((sample, 1));
+            self.truncate();
+            return;
+        }
+        let key = position_key(&sample.state);
+        if let Some(&sequence) = self.positions.get(&key) {
+            let (merged, count) = &mut self.entries[sequence - self.first];
+            *count += 1;
+            let weight = 1.0 / *count as f32;
+            for (target, new) in merged.policy.iter_mut().zip(&sample.policy) {
+                *target += (new - *target) * weight;
+            }
+            merged.value += (sample.value - merged.value) * weight;
+            return;
+        }
+        self.positions.insert(key, self.first + self.entries.len());
+        self.entries.push_back((sample, 1));
+        self.truncate();
+    }
+
+    fn truncate(&mut self) {
+        while self.entries.len() > self.capacity {
+            let (sample, _) = self.entries.pop_front().unwrap();
+            if self.deduplicate {
+                self.positions.remove(&position_key(&sample.state));
+            }
+            self.first += 1;
+        }
+    }
+}
+
+impl Extend<TrainingSample> for ReplayBuffer {
+    fn extend<T: IntoIterator<Item = TrainingSample>>(&mut self, samples: T) {
+        samples.into_iter().for_each(|sample| self.push(sample));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn_bot::encode;
+    use quoridor_core::data_model::Game;
+
+    #[test]
+    fn deduplication_test() {
+        let sample = |game: &Game, action: usize, value: f32| {
+            let mut policy = vec![0.0; 4];
+            policy[action] = 1.0;
+            TrainingSample {
+                state: encode(game),
+                policy,
+                value,
+            }
+        };
+        let start = Game::new();
+        let mut other = Game::new();
+        other.player = other.player.opponent();
+        let samples = [
+            sample(&start, 0, 1.0),
+            sample(&start, 1, -1.0),
+            sample(&other, 2, 1.0),
+            sample(&start, 1, 1.0),
+        ];
+
+        let mut buffer = ReplayBuffer::new(10, false);
+        buffer.extend(samples.clone());
+        assert_eq!(buffer.len(), 4);
+
+        let mut buffer = ReplayBuffer::new(10, true);
+        buffer.extend(samples.clone());
+        assert_eq!(buffer.len(), 2);
+        let merged = buffer.get(0);
+        let expected = [1.0 / 3.0, 2.0 / 3.0, 0.0, 0.0, 1.0 / 3.0];
+        for (actual, expected) in merged.policy.iter().chain([&merged.value]).zip(expected) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+
+        // Once evicted, a position starts over
+        let mut buffer = ReplayBuffer::new(1, true);
+        buffer.extend(samples);
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(buffer.get(0).policy, [0.0, 1.0, 0.0, 0.0]);
+    }
+}
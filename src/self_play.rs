@@ -0,0 +1,463 @@
+// Self-play data generation. The network mostly plays itself, but a fraction of the
+// games is played against the alpha-beta bot at a random depth, the opponent pool, so
+// the network also sees the play of opponents without its own blind spots. The
+// network takes White and Black in turn.
+//
+// Every position of a game becomes a training sample: the encoded position, a policy
+// target and a value target from the side to move's point of view. Positions where
+// the move played is outside the network's action space are left out.
+//
+// Without a search the policy target is the move played as a one-hot and the value
+// target the result z, 0 for games hitting the ply limit. With tree search (a number
+// of simulations configured) the targets of the network's moves come from the root of
+// its search instead, as set by `TargetConfig`:
+//
+//     policy = (1 - played_move_weight) * visits^(1 / visit_temperature) / sum
+//              + played_move_weight * one-hot of the move played
+//     value  = (1 - root_value_weight) * z + root_value_weight * q
+//
+// where q is the searched value of the root. The defaults are the raw visit counts
+// and z, as in AlphaZero.
+
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use quoridor_core::all_moves::ALL_MOVES;
+use quoridor_core::arena::{FinishedGame, Outcome, play_game};
+use quoridor_core::data_model::{Game, Player, PlayerMove};
+use quoridor_core::engine::{AlphaBetaEngine, Engine, EngineMove, GameEvent, SearchLimits};
+use quoridor_core::game_logic::winner;
+use quoridor_core::game_record::GameRecord;
+use quoridor_core::rules::Rules;
+
+use crate::mcts::{MctsConfig, MctsEngine, PuctConfig};
+use crate::nn_bot::{ACTIONS, NetworkKind, NeuralNetEngine, QuoridorNet};
+use crate::training_shard::TrainingSample;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfPlayConfig {
+    /// Share of the games played against the alpha-beta bot instead of the network.
+    pub opponent_fraction: f64,
+    /// Depths the alpha-beta opponents search, picked uniformly.
+    pub min_depth: usize,
+    pub max_depth: usize,
+    pub max_plies: usize,
+    /// Tree search simulations per move of the network, 0 to sample its policy directly.
+    #[serde(default)]
+    pub simulations: usize,
+    /// How the tree search explores, see mcts.rs.
+    #[serde(default)]
+    pub puct: PuctConfig,
+    #[serde(default)]
+    pub targets: TargetConfig,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        Self {
+            opponent_fraction: 0.25,
+            min_depth: 1,
+            max_depth: 3,
+            max_plies: 200,
+            simulations: 0,
+            puct: PuctConfig::default(),
+            targets: TargetConfig::default(),
+        }
+    }
+}
+
+/// How the training targets of searched moves are built, see the top of the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TargetConfig {
+    /// 1 for the raw visit counts, lower sharpens them towards the most visited move.
+    pub visit_temperature: f32,
+    pub played_move_weight: f32,
+    pub root_value_weight: f32,
+}
+
+impl Default for TargetConfig {
+    fn default() -> Self {
+        Self {
+            visit_temperature: 1.0,
+            played_move_weight: 0.0,
+            root_value_weight: 0.0,
+        }
+    }
+}
+
+/// A finished self-play game with the moves as the engines returned them, searched
+/// ones with their root statistics.
+pub struct TrainingGame {
+    pub finished_game: FinishedGame,
+    pub engine_moves: Vec<EngineMove>,
+}
+
+/// Passes moves through from an engine, keeping a copy of each.
+struct RecordingEngine {
+    engine: Box<dyn Engine>,
+    engine_moves: Arc<Mutex<Vec<EngineMove>>>,
+}
+
+impl Engine for RecordingEngine {
+    fn name(&self) -> String {
+        self.engine.name()
+    }
+
+    fn choose_move(&mut self, game: &Game, limits: &SearchLimits) -> EngineMove {
+        let engine_move = self.engine.choose_move(game, limits);
+        self.engine_moves.lock().unwrap().push(engine_move.clone());
+        engine_move
+    }
+
+    fn try_choose_move(
+        &mut self,
+        game: &Game,
+        limits: &SearchLimits,
+    ) -> Result<EngineMove, String> {
+        let engine_move = self.engine.try_choose_move(game, limits)?;
+        self.engine_moves.lock().unwrap().push(engine_move.clone());
+        Ok(engine_move)
+    }
+
+    fn stop(&self) {
+        self.engine.stop();
+    }
+
+    fn new_game(&mut self) {
+        self.engine.new_game();
+    }
+
+    fn warm_up(&mut self, game: &Game) {
+        self.engine.warm_up(game);
+    }
+
+    fn game_event(&mut self, event: &GameEvent) {
+        self.engine.game_event(event);
+    }
+}
+
+/// The network as a self-play player, searching when simulations are configured.
+pub fn network_player(
+    network: &QuoridorNet,
+    temperature: f32,
+    config: &SelfPlayConfig,
+) -> Box<dyn Engine> {
+    if config.simulations == 0 {
+        return Box::new(NeuralNetEngine::new(network.clone(), temperature));
+    }
+    Box::new(MctsEngine::new(
+        network.clone(),
+        MctsConfig {
+            simulations: config.simulations,
+            puct: config.puct.clone(),
+            root_noise: true,
+            temperature,
+            ..MctsConfig::default()
+        },
+    ))
+}
+
+/// Who the network plays in one game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opponent {
+    Network,
+    AlphaBeta { depth: usize },
+}
+
+impl SelfPlayConfig {
+    pub fn pick_opponent(&self, rng: &mut impl Rng) -> Opponent {
+        if rng.random_bool(self.opponent_fraction.clamp(0.0, 1.0)) {
+            Opponent::AlphaBeta {
+                depth: rng.random_range(self.min_depth..=self.max_depth.max(self.min_depth)),
+            }
+        } else {
+            Opponent::Network
+        }
+    }
+}
+
+/// Plays one game of the network, created by `new_network` for each side it plays,
+/// against `opponent`.
+pub fn play_training_game(
+    new_network: &dyn Fn() -> Box<dyn Engine>,
+    opponent: Opponent,
+    network_color: Player,
+    rules: &Rules,
+    config: &SelfPlayConfig,
+) -> TrainingGame {
+    let engine_moves = Arc::new(Mutex::new(Vec::new()));
+    let record = |engine: Box<dyn Engine>| RecordingEngine {
+        engine,
+        engine_moves: engine_moves.clone(),
+    };
+    let mut network = record(new_network());
+    let (opponent_engine, limits): (Box<dyn Engine>, _) = match opponent {
+        Opponent::Network => (new_network(), SearchLimits::default()),
+        Opponent::AlphaBeta { depth } => (
+            Box::new(AlphaBetaEngine::new()),
+            SearchLimits {
+                depth: Some(depth),
+                ..SearchLimits::default()
+            },
+        ),
+    };
+    let mut opponent_engine = record(opponent_engine);
+    let (white, black) = match network_color {
+        Player::White => (&mut network, &mut opponent_engine),
+        Player::Black => (&mut opponent_engine, &mut network),
+    };
+    let finished_game = play_game(white, black, rules, &limits, config.max_plies);
+    let engine_moves = std::mem::take(&mut *engine_moves.lock().unwrap());
+    TrainingGame {
+        finished_game,
+        engine_moves,
+    }
+}
+
+/// The policy target of a move, `action`, chosen by a search with `root_visits`.
+pub fn search_policy_target(
+    root_visits: &[(PlayerMove, u32)],
+    action: usize,
+    targets: &TargetConfig,
+) -> Vec<f32> {
+    let mut policy = vec![0.0; ACTIONS];
+    for (player_move, visits) in root_visits {
+        if let Some(action) = action_id(player_move) {
+            policy[action] = (*visits as f32).powf(1.0 / targets.visit_temperature.max(0.01));
+        }
+    }
+    let total: f32 = policy.iter().sum();
+    let played_move_weight = if total > 0.0 {
+        targets.played_move_weight.clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    for (id, target) in policy.iter_mut().enumerate() {
+        let visit_share = if total > 0.0 { *target / total } else { 0.0 };
+        let played = if id == action { 1.0 } else { 0.0 };
+        *target = (1.0 - played_move_weight) * visit_share + played_move_weight * played;
+    }
+    policy
+}
+
+fn action_id(player_move: &PlayerMove) -> Option<usize> {
+    ALL_MOVES
+        .iter()
+        .position(|candidate| candidate.to_string() == player_move.to_string())
+        .filter(|&action| action < ACTIONS)
+}
+
+/// The training samples of every position of a finished game, encoded for `kind` of network.
+pub fn training_samples(
+    training_game: &TrainingGame,
+    kind: NetworkKind,
+    targets: &TargetConfig,
+) -> Vec<TrainingSample> {
+    let finished_game = &training_game.finished_game;
+    let game_states = finished_game
+        .record
+        .game_states_with_rules(&finished_game.rules);
+    samples(
+        &game_states,
+        &training_game.engine_moves,
+        finished_game.outcome,
+        kind,
+        targets,
+    )
+}
+
+/// The training samples of a recorded game, played by any engine or person, whose moves
+/// are taken as unsearched. The outcome is the record's result tag, or the winner of its
+/// last position, and a draw without either.
+pub fn record_samples(
+    record: &GameRecord,
+    rules: &Rules,
+    kind: NetworkKind,
+    targets: &TargetConfig,
+) -> Vec<TrainingSample> {
+    let game_states = record.game_states_with_rules(rules);
+    let outcome = record
+        .tag("result")
+        .and_then(Outcome::parse)
+        .or_else(|| game_states.last().and_then(winner).map(Outcome::Win))
+        .unwrap_or(Outcome::Draw);
+    let start = Instant::now();
+    let engine_moves: Vec<EngineMove> = record
+        .player_moves()
+        .map(|player_move| EngineMove::unsearched(player_move.clone(), start))
+        .collect();
+    samples(&game_states, &engine_moves, outcome, kind, targets)
+}
+
+fn samples(
+    game_states: &[Game],
+    engine_moves: &[EngineMove],
+    outcome: Outcome,
+    kind: NetworkKind,
+    targets: &TargetConfig,
+) -> Vec<TrainingSample> {
+    game_states
+        .iter()
+        .zip(engine_moves)
+        .filter_map(|(game, engine_move)| {
+            let action = action_id(&engine_move.player_move)?;
+            let policy = match &engine_move.root_visits {
+                Some(root_visits) => search_policy_target(root_visits, action, targets),
+                None => {
+                    let mut policy = vec![0.0; ACTIONS];
+                    policy[action] = 1.0;
+                    policy
+                }
+            };
+            let outcome = match outcome {
+                Outcome::Win(winner) if winner == game.player => 1.0,
+                Outcome::Win(_) => -1.0,
+                Outcome::Draw => 0.0,
+            };
+            let value = match engine_move.root_value {
+                Some(q) => {
+                    let weight = targets.root_value_weight.clamp(0.0, 1.0);
+                    (1.0 - weight) * outcome + weight * q
+                }
+                None => outcome,
+            };
+            Some(TrainingSample {
+                state: kind.encode(game),
+                policy,
+                value,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quoridor_core::arena::Termination;
+    use quoridor_core::engine::GreedyEngine;
+    use quoridor_core::game_record::{GameRecord, MoveAnnotation, RecordedMove};
+
+    #[test]
+    fn opponent_pool_test() {
+        let config = SelfPlayConfig {
+            opponent_fraction: 1.0,
+            min_depth: 1,
+            max_depth: 1,
+            ..SelfPlayConfig::default()
+        };
+        let mut rng = rand::rng();
+        let opponent = config.pick_opponent(&mut rng);
+        assert_eq!(opponent, Opponent::AlphaBeta { depth: 1 });
+        let never = SelfPlayConfig {
+            opponent_fraction: 0.0,
+            ..config.clone()
+        };
+        assert_eq!(never.pick_opponent(&mut rng), Opponent::Network);
+
+        let training_game = play_training_game(
+            &|| Box::new(GreedyEngine),
+            opponent,
+            Player::Black,
+            &Rules::default(),
+            &config,
+        );
+        let moves = &training_game.finished_game.record.moves;
+        assert_eq!(training_game.engine_moves.len(), moves.len());
+        let samples = training_samples(&training_game, NetworkKind::Conv, &config.targets);
+        assert!(!samples.is_empty());
+        assert!(samples.len() <= moves.len());
+        // The side to move at the last position played the winning move
+        assert_eq!(samples.last().unwrap().value, 1.0);
+        assert_eq!(samples[0].policy.iter().sum::<f32>(), 1.0);
+    }
+
+    #[test]
+    fn search_targets_test() {
+        let player_move = |notation: &str| {
+            ALL_MOVES
+                .iter()
+                .find(|candidate| candidate.to_string() == notation)
+                .unwrap()
+                .clone()
+        };
+        let (first, second) = (player_move("muu"), player_move("h42"));
+        let root_visits = [(first.clone(), 30), (second.clone(), 10)];
+        let (first_action, second_action) =
+            (action_id(&first).unwrap(), action_id(&second).unwrap());
+        let target = |targets: &TargetConfig| {
+            let policy = search_policy_target(&root_visits, second_action, targets);
+            (policy[first_action], policy[second_action])
+        };
+        assert_eq!(target(&TargetConfig::default()), (0.75, 0.25));
+        let sharpened = TargetConfig {
+            visit_temperature: 0.5,
+            ..TargetConfig::default()
+        };
+        assert_eq!(target(&sharpened), (0.9, 0.1));
+        let mixed = TargetConfig {
+            played_move_weight: 0.5,
+            ..TargetConfig::default()
+        };
+        assert_eq!(target(&mixed), (0.375, 0.625));
+
+        let training_game = TrainingGame {
+            finished_game: FinishedGame {
+                record: GameRecord {
+                    tags: Vec::new(),
+                    moves: vec![RecordedMove {
+                        player_move: second.clone(),
+                        annotation: MoveAnnotation::default(),
+                    }],
+                },
+                rules: Rules::default(),
+                outcome: Outcome::Win(Player::White),
+                termination: Termination::PlyLimit,
+            },
+            engine_moves: vec![EngineMove {
+                root_visits: Some(root_visits.to_vec()),
+                root_value: Some(-0.5),
+                ..EngineMove::unsearched(second, std::time::Instant::now())
+            }],
+        };
+        let blended = TargetConfig {
+            root_value_weight: 0.5,
+            ..TargetConfig::default()
+        };
+        let samples = training_samples(&training_game, NetworkKind::Conv, &blended);
+        assert_eq!(samples[0].policy[first_action], 0.75);
+        assert_eq!(samples[0].value, 0.25);
+    }
+
+    #[test]
+    fn record_samples_test() {
+        let record = GameRecord::parse("[result=0-1]mdu;mud;h42;").unwrap();
+        let samples = record_samples(
+            &record,
+            &Rules::default(),
+            NetworkKind::Conv,
+            &TargetConfig::default(),
+        );
+        assert_eq!(samples.len(), 3);
+        assert_eq!(
+            samples
+                .iter()
+                .map(|sample| sample.value)
+                .collect::<Vec<_>>(),
+            vec![-1.0, 1.0, -1.0]
+        );
+        assert_eq!(samples[2].policy.iter().sum::<f32>(), 1.0);
+        // Unfinished games without a result are draws
+        let record = GameRecord::parse("mdu;mud;").unwrap();
+        let samples = record_samples(
+            &record,
+            &Rules::default(),
+            NetworkKind::Conv,
+            &TargetConfig::default(),
+        );
+        assert!(samples.iter().all(|sample| sample.value == 0.0));
+    }
+}
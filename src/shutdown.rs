@@ -0,0 +1,70 @@
+// Graceful shutdown of the long-running loops: self-play, training and engine matches.
+// While a `Shutdown` is held, the first SIGINT or SIGTERM only sets a flag, which the
+// loops check between games and iterations to finish what they are doing, save their
+// checkpoints and records, and return. A second signal exits at once, like Ctrl-C
+// always did. Dropping the `Shutdown` restores the default handling.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use signal_hook::SigId;
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+/// Exit status of a second interrupt, that of a process killed by SIGINT.
+const INTERRUPTED_STATUS: i32 = 130;
+
+/// Catches interrupts for a loop, `Default` for one that is never interrupted.
+#[derive(Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    signals: Vec<SigId>,
+}
+
+impl Shutdown {
+    /// Catches SIGINT and SIGTERM until dropped.
+    pub fn catch() -> Result<Self, String> {
+        let mut shutdown = Self::default();
+        for signal in [SIGINT, SIGTERM] {
+            // Registered first, so it sees the flag set by an earlier signal only
+            let exit = signal_hook::flag::register_conditional_shutdown(
+                signal,
+                INTERRUPTED_STATUS,
+                shutdown.requested.clone(),
+            );
+            let flag = signal_hook::flag::register(signal, shutdown.requested.clone());
+            for id in [exit, flag] {
+                shutdown
+                    .signals
+                    .push(id.map_err(|e| format!("Could not catch signals: {e}"))?);
+            }
+        }
+        Ok(shutdown)
+    }
+
+    /// Whether the loop should stop after its current game or iteration.
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Shutdown {
+    fn drop(&mut self) {
+        for &id in &self.signals {
+            signal_hook::low_level::unregister(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_test() {
+        let shutdown = Shutdown::catch().unwrap();
+        assert!(!shutdown.requested());
+        signal_hook::low_level::raise(SIGTERM).unwrap();
+        assert!(shutdown.requested());
+        assert!(!Shutdown::default().requested());
+    }
+}
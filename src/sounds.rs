@@ -0,0 +1,113 @@
+// Sound effects of the GUI. The sounds are short tones synthesized into WAV data at
+// startup, so no sound files have to be shipped with the binary.
+
+use std::f32::consts::PI;
+
+use ggez::audio::{SoundData, SoundSource, Source};
+use ggez::{Context, GameResult};
+
+const SAMPLE_RATE: u32 = 22050;
+const VOLUME: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Sound {
+    PawnMove,
+    WallPlacement,
+    LowTime,
+    GameEnd,
+}
+
+impl Sound {
+    const ALL: [Sound; 4] = [
+        Sound::PawnMove,
+        Sound::WallPlacement,
+        Sound::LowTime,
+        Sound::GameEnd,
+    ];
+
+    /// The notes of the sound as frequencies in Hz and durations in seconds, 0 Hz being silence.
+    fn notes(self) -> &'static [(f32, f32)] {
+        match self {
+            Sound::PawnMove => &[(660.0, 0.06)],
+            Sound::WallPlacement => &[(196.0, 0.14)],
+            Sound::LowTime => &[(880.0, 0.1), (0.0, 0.06), (880.0, 0.1)],
+            Sound::GameEnd => &[(523.25, 0.12), (659.25, 0.12), (783.99, 0.3)],
+        }
+    }
+}
+
+/// A mono 16 bit WAV file playing the notes, each fading out to avoid clicks.
+fn wav(notes: &[(f32, f32)]) -> Vec<u8> {
+    let mut samples = Vec::new();
+    for &(frequency, seconds) in notes {
+        let count = (seconds * SAMPLE_RATE as f32) as usize;
+        samples.extend((0..count).map(|i| {
+            let time = i as f32 / SAMPLE_RATE as f32;
+            let envelope = 1.0 - i as f32 / count as f32;
+            let sample = (2.0 * PI * frequency * time).sin() * envelope * VOLUME;
+            (sample * i16::MAX as f32) as i16
+        }));
+    }
+    let data_size = 2 * samples.len() as u32;
+    let mut bytes = Vec::with_capacity(44 + data_size as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    // Format chunk size, PCM, one channel, sample rate, byte rate, block size, bits per sample
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(2 * SAMPLE_RATE).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+pub struct Sounds {
+    data: Vec<SoundData>,
+    pub muted: bool,
+}
+
+impl Sounds {
+    pub fn new(muted: bool) -> Self {
+        let data = Sound::ALL
+            .iter()
+            .map(|sound| SoundData::from_bytes(&wav(sound.notes())))
+            .collect();
+        Self { data, muted }
+    }
+
+    /// Plays the sound unless muted, without waiting for it to finish.
+    pub fn play(&self, ctx: &Context, sound: Sound) -> GameResult {
+        if self.muted {
+            return Ok(());
+        }
+        let mut source = Source::from_data(ctx, self.data[sound as usize].clone())?;
+        source.play_detached(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wav_test() {
+        let bytes = wav(Sound::LowTime.notes());
+        let samples = (0.26 * SAMPLE_RATE as f32) as usize;
+        assert!((bytes.len() - 44).abs_diff(2 * samples) <= 6);
+        assert_eq!(&bytes[..4], b"RIFF");
+        assert_eq!(&bytes[36..40], b"data");
+        let data_size = u32::from_le_bytes(bytes[40..44].try_into().unwrap());
+        assert_eq!(data_size as usize, bytes.len() - 44);
+        // The pause between the beeps is silent
+        let pause = 44 + 2 * (0.13 * SAMPLE_RATE as f32) as usize;
+        assert_eq!(&bytes[pause..pause + 2], &[0, 0]);
+    }
+}
@@ -0,0 +1,131 @@
+// What the terminal the CLI writes to can show. The Windows console before Windows
+// Terminal neither understands ANSI escapes nor has box-drawing characters in its default
+// fonts, and non-UTF-8 locales garble them elsewhere, so the board falls back to plain
+// ASCII without colors there. There is no portable way to ask the terminal its width
+// without a dependency, so it comes from the COLUMNS variable most shells set.
+
+use std::io::IsTerminal;
+
+use quoridor_core::render_board::BoardStyle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Terminal {
+    /// Whether ANSI colors show as colors rather than escape codes.
+    pub color: bool,
+    /// Whether box-drawing characters show.
+    pub unicode: bool,
+    /// Columns in a line, None when unknown.
+    pub width: Option<usize>,
+}
+
+impl Terminal {
+    /// The capabilities of the terminal standard output goes to.
+    pub fn detect() -> Self {
+        Self::from_env(
+            |name| std::env::var(name).ok(),
+            std::io::stdout().is_terminal(),
+            cfg!(windows),
+        )
+    }
+
+    /// Plain ASCII without colors, whatever the terminal can show.
+    pub fn plain(self) -> Self {
+        Self {
+            color: false,
+            unicode: false,
+            ..self
+        }
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>, is_terminal: bool, windows: bool) -> Self {
+        let set = |name: &str| var(name).is_some_and(|value| !value.is_empty());
+        let term = var("TERM").unwrap_or_default();
+        // Windows Terminal, ConEmu and the terminals of MSYS2 and Git Bash, unlike the console
+        let modern_windows = set("WT_SESSION")
+            || var("ConEmuANSI").as_deref() == Some("ON")
+            || set("TERM_PROGRAM")
+            || !term.is_empty();
+        let color = !set("NO_COLOR")
+            && (set("FORCE_COLOR")
+                || is_terminal && term != "dumb" && (!windows || modern_windows));
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .find_map(|name| var(name).filter(|value| !value.is_empty()));
+        let unicode = if windows {
+            modern_windows
+        } else {
+            locale.is_none_or(|locale| {
+                let locale = locale.to_ascii_lowercase();
+                locale.contains("utf-8") || locale.contains("utf8")
+            })
+        };
+        let width = var("COLUMNS").and_then(|columns| columns.trim().parse().ok());
+        Self {
+            color,
+            unicode,
+            width,
+        }
+    }
+
+    pub fn board_style(&self) -> BoardStyle {
+        BoardStyle {
+            unicode: self.unicode,
+            color: self.color,
+        }
+    }
+
+    /// Whether a line of `columns` fits, true when the width is unknown.
+    pub fn fits(&self, columns: usize) -> bool {
+        self.width.is_none_or(|width| columns <= width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terminal(vars: &[(&str, &str)], is_terminal: bool, windows: bool) -> Terminal {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        Terminal::from_env(var, is_terminal, windows)
+    }
+
+    #[test]
+    fn detect_test() {
+        let linux = terminal(
+            &[("TERM", "xterm-256color"), ("LANG", "en_US.UTF-8")],
+            true,
+            false,
+        );
+        assert!(linux.color && linux.unicode);
+        assert_eq!(linux.width, None);
+        assert!(!terminal(&[("TERM", "xterm"), ("NO_COLOR", "1")], true, false).color);
+        assert!(!terminal(&[("TERM", "xterm")], false, false).color);
+        assert!(
+            !terminal(
+                &[("LANG", "C"), ("LC_ALL", "nb_NO.ISO-8859-1")],
+                true,
+                false
+            )
+            .unicode
+        );
+        assert!(terminal(&[("LC_ALL", "C.utf8"), ("LANG", "C")], true, false).unicode);
+
+        let console = terminal(&[], true, true);
+        assert!(!console.color && !console.unicode);
+        let windows_terminal = terminal(&[("WT_SESSION", "1f2e"), ("COLUMNS", "60")], true, true);
+        assert!(windows_terminal.color && windows_terminal.unicode);
+        assert_eq!(windows_terminal.width, Some(60));
+        assert!(!windows_terminal.fits(72));
+        assert_eq!(
+            windows_terminal.plain().board_style(),
+            BoardStyle {
+                unicode: false,
+                color: false
+            }
+        );
+    }
+}
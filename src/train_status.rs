@@ -0,0 +1,229 @@
+// Status of a training run, read from its files alone so it can be checked while the
+// run goes on: the counters in training.json, the losses in metrics.jsonl and the
+// evaluation games in the checkpoint manifests.
+//
+// Elo ratings are relative to the first checkpoint, at 0. A checkpoint promoted by its
+// evaluation games is rated from its score against the best checkpoint before it, so
+// the trend follows the best checkpoints of the run.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::checkpoint::{CheckpointManifest, list_checkpoints};
+use crate::evaluation::{is_promoted, score};
+use crate::training::{read_metrics, read_state};
+
+/// Best checkpoints shown in the Elo trend.
+const ELO_TREND_LENGTH: usize = 5;
+
+/// The Elo difference a score, as a share of the points, stands for.
+pub fn elo_difference(score: f64) -> f64 {
+    let score = score.clamp(0.01, 0.99);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// The Elo ratings of the promoted checkpoints, oldest first.
+pub fn elo_trend(manifests: &[CheckpointManifest]) -> Vec<(String, f64)> {
+    let mut ratings: HashMap<&str, f64> = HashMap::new();
+    let mut trend = Vec::new();
+    for manifest in manifests {
+        if trend.is_empty() {
+            ratings.insert(&manifest.name, 0.0);
+            trend.push((manifest.name.clone(), 0.0));
+            continue;
+        }
+        let promotion = manifest
+            .evaluations
+            .iter()
+            .rev()
+            .find(|evaluation| is_promoted(evaluation));
+        if let Some(evaluation) = promotion
+            && let Some(&opponent) = ratings.get(evaluation.opponent.as_str())
+        {
+            let rating = opponent + elo_difference(score(evaluation));
+            ratings.insert(&manifest.name, rating);
+            trend.push((manifest.name.clone(), rating));
+        }
+    }
+    trend
+}
+
+/// The evaluation games of the latest checkpoint that played any.
+pub fn last_gating(manifests: &[CheckpointManifest]) -> String {
+    let gating = manifests
+        .iter()
+        .rev()
+        .find_map(|manifest| Some((manifest, manifest.evaluations.last()?)));
+    match gating {
+        Some((manifest, evaluation)) => format!(
+            "{} against {}: +{} -{} ={} ({:.0}%, {})",
+            manifest.name,
+            evaluation.opponent,
+            evaluation.wins,
+            evaluation.losses,
+            evaluation.draws,
+            100.0 * score(evaluation),
+            if is_promoted(evaluation) {
+                "promoted"
+            } else {
+                "not promoted"
+            }
+        ),
+        None => "no evaluation games".to_string(),
+    }
+}
+
+/// The total size of the files in the directory and below.
+fn disk_usage(directory: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => disk_usage(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return format!("{size:.1} {unit}");
+        }
+        size /= 1024.0;
+    }
+    format!("{size:.1} TiB")
+}
+
+/// A summary table of the run in `run_dir`.
+pub fn train_status(run_dir: &Path) -> Result<String, String> {
+    let (config, counters) = read_state(run_dir)?;
+    let metrics = read_metrics(run_dir)?;
+    let manifests = list_checkpoints(run_dir)?;
+    let mut rows = vec![
+        ("Run", run_dir.display().to_string()),
+        (
+            "Iterations",
+            format!("{} of {}", counters.iteration, config.iterations),
+        ),
+        ("Games", counters.games.to_string()),
+        ("Training steps", counters.steps.to_string()),
+        ("Checkpoint", counters.checkpoint.clone()),
+        ("Best checkpoint", counters.best.clone()),
+    ];
+    // Iterations lost in a crash are logged again once run again, so the last line is the latest
+    match metrics.last() {
+        Some(latest) => {
+            rows.push((
+                "Latest losses",
+                format!(
+                    "policy {:.4}, value {:.4} (iteration {}, {}x{})",
+                    latest.policy_loss,
+                    latest.value_loss,
+                    latest.iteration,
+                    latest.board_size,
+                    latest.board_size
+                ),
+            ));
+            rows.push(("Replay buffer", format!("{} samples", latest.replay)));
+        }
+        None => rows.push(("Latest losses", "no iterations logged".to_string())),
+    }
+    if let Some(validated) = metrics
+        .iter()
+        .rev()
+        .find(|metrics| metrics.validation_gap().is_some())
+    {
+        rows.push((
+            "Validation losses",
+            format!(
+                "policy {:.4}, value {:.4}, {:+.4} over training (iteration {})",
+                validated.validation_policy_loss.unwrap(),
+                validated.validation_value_loss.unwrap(),
+                validated.validation_gap().unwrap(),
+                validated.iteration
+            ),
+        ));
+    }
+    rows.push(("Last gating", last_gating(&manifests)));
+    let trend = elo_trend(&manifests);
+    let trend: Vec<String> = trend[trend.len().saturating_sub(ELO_TREND_LENGTH)..]
+        .iter()
+        .map(|(name, rating)| format!("{name} {rating:+.0}"))
+        .collect();
+    rows.push(("Elo trend", trend.join(", ")));
+    rows.push(("Disk usage", format_bytes(disk_usage(run_dir))));
+
+    Ok(rows
+        .iter()
+        .map(|(label, value)| format!("{label:<18}{value}\n"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::EvaluationResult;
+    use crate::evaluation::EvaluationConfig;
+    use crate::nn_bot::NetworkKind;
+    use crate::self_play::SelfPlayConfig;
+    use crate::shutdown::Shutdown;
+    use crate::training::{Trainer, TrainingConfig};
+
+    #[test]
+    fn train_status_test() {
+        assert!(elo_difference(0.5).abs() < 1e-9);
+        assert!((elo_difference(0.75) - 190.8).abs() < 0.1);
+        let manifest = |name: &str, wins, losses| CheckpointManifest {
+            evaluations: vec![EvaluationResult {
+                opponent: "iter-0000".to_string(),
+                wins,
+                losses,
+                draws: 0,
+            }],
+            ..CheckpointManifest::new(name, Some("iter-0000"))
+        };
+        let manifests = [
+            CheckpointManifest::new("iter-0000", None),
+            manifest("iter-0001", 3, 7),
+            manifest("iter-0002", 15, 5),
+        ];
+        let trend = elo_trend(&manifests);
+        assert_eq!(trend.len(), 2);
+        assert_eq!(trend[1].0, "iter-0002");
+
+        let run_dir =
+            std::env::temp_dir().join(format!("train-status-test-{}", std::process::id()));
+        let config = TrainingConfig {
+            iterations: 1,
+            network: NetworkKind::FeatureMlp,
+            games_per_iteration: 1,
+            steps_per_iteration: 1,
+            batch_size: 2,
+            self_play: SelfPlayConfig {
+                opponent_fraction: 0.0,
+                max_plies: 4,
+                ..SelfPlayConfig::default()
+            },
+            evaluation: EvaluationConfig {
+                games: 0,
+                ..EvaluationConfig::default()
+            },
+            ..TrainingConfig::default()
+        };
+        Trainer::start(&run_dir, config)
+            .unwrap()
+            .train(&Shutdown::default())
+            .unwrap();
+        let status = train_status(&run_dir).unwrap();
+        std::fs::remove_dir_all(&run_dir).unwrap();
+        assert!(status.contains("Iterations        1 of 1\n"));
+        assert!(status.contains("Replay buffer     4 samples\n"));
+        assert!(status.contains("Elo trend         iter-0000 +0\n"));
+        assert!(status.contains("no evaluation games"));
+    }
+}
@@ -0,0 +1,837 @@
+// Training loop. Every iteration plays self-play games with the current network into
+// a replay buffer (replay_buffer.rs) and then trains on batches sampled from the
+// buffer, with Adam on a cross-entropy policy loss plus a squared-error value loss.
+// Every `checkpoint_every` iterations the network is saved as a checkpoint, next to
+// what resuming needs:
+//
+//   <name>.mpk, <name>.json   weights and manifest, see checkpoint.rs
+//   <name>-optimizer.mpk      Adam moments
+//   <name>-replay.qshd        the replay buffer as a training shard
+//   <name>-validation.qshd    the validation samples, with a validation split
+//   training.json             configuration, counters and the latest checkpoint
+//   metrics.jsonl             the losses and counters after every iteration, a JSON line each
+//
+// With a validation split, every game of a `validation_fraction` share of the self-play
+// games goes into a validation buffer instead of the replay buffer, whole games so no
+// position of them is trained on through a neighbouring one. Every `validate_every`
+// iterations the losses on the validation samples are measured and logged next to the
+// training losses. A network learning its games by heart has a growing gap between the
+// two, so a warning is printed once the gap grows by `overfit_warning` over its
+// smallest so far.
+//
+// Self-play uses the best checkpoint so far rather than the latest one. With
+// evaluation games configured, each new checkpoint has to beat the best one in the
+// evaluation arena (evaluation.rs) to take its place; without, every checkpoint does.
+//
+// With a curriculum the self-play and evaluation games of the first iterations are
+// played on smaller boards with fewer walls, stage by stage, before the full board.
+// Smaller boards sit in the top left corner of the 9x9 input planes, so the same
+// network, and its convolutional weights, carries over from one stage to the next.
+//
+// training.json is written last, to a temporary file renamed over the old one, so it
+// always names a complete checkpoint. A crash, even while saving, loses at most the
+// iterations since the last checkpoint. The optimizer and replay files of older
+// checkpoints are removed once a newer one is complete. The metrics of lost iterations
+// stay in metrics.jsonl, followed by those of the same iterations run again. Ctrl-C
+// loses nothing: training stops after the current iteration with a checkpoint.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use burn::backend::{Autodiff, NdArray};
+use burn::module::AutodiffModule;
+use burn::optim::{Adam, AdamConfig, GradientsParams, Optimizer, adaptor::OptimizerAdaptor};
+use burn::record::{FullPrecisionSettings, NamedMpkFileRecorder, Recorder};
+use burn::tensor::activation::log_softmax;
+use burn::tensor::backend::Backend;
+use burn::tensor::{ElementConversion, Tensor, TensorData};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use quoridor_core::data_model::Player;
+use quoridor_core::rules::Rules;
+
+use crate::checkpoint::{CheckpointManifest, load_checkpoint, save_checkpoint};
+use crate::evaluation::{EvaluationConfig, evaluate, is_promoted, score};
+use crate::nn_bot::{ACTIONS, NetworkKind, NetworkModel, QuoridorNet, load_model};
+use crate::replay_buffer::ReplayBuffer;
+use crate::self_play::{SelfPlayConfig, network_player, play_training_game, training_samples};
+use crate::shutdown::Shutdown;
+use crate::training_shard::{TrainingSample, create_shard, open_shard};
+
+type TrainingBackend = Autodiff<NdArray>;
+type TrainingOptimizer = OptimizerAdaptor<Adam, NetworkModel<TrainingBackend>, TrainingBackend>;
+
+const STATE_FILE: &str = "training.json";
+const METRICS_FILE: &str = "metrics.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrainingConfig {
+    /// Iterations after which training stops.
+    pub iterations: usize,
+    /// The network trained, only used when starting a run.
+    pub network: NetworkKind,
+    pub games_per_iteration: usize,
+    pub steps_per_iteration: usize,
+    pub batch_size: usize,
+    /// Samples kept in the replay buffer, the oldest dropped first.
+    pub replay_size: usize,
+    /// Merge samples of the same position in the replay buffer, averaging their targets.
+    pub deduplicate_replay: bool,
+    pub learning_rate: f64,
+    pub checkpoint_every: usize,
+    pub temperature: f32,
+    pub self_play: SelfPlayConfig,
+    pub evaluation: EvaluationConfig,
+    /// Stages played before the full board, none to train on it from the start.
+    pub curriculum: Vec<CurriculumStage>,
+    /// Share of the self-play games kept out of training as validation samples, zero
+    /// for no validation split.
+    pub validation_fraction: f64,
+    /// Iterations between measurements of the validation losses.
+    pub validate_every: usize,
+    /// Growth of the gap between the validation and training losses over its smallest
+    /// so far from which a warning is printed.
+    pub overfit_warning: f32,
+}
+
+/// Iterations of self-play on a smaller board.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurriculumStage {
+    pub board_size: usize,
+    pub walls_per_player: usize,
+    pub iterations: usize,
+}
+
+impl CurriculumStage {
+    pub fn rules(&self) -> Rules {
+        Rules {
+            board_size: self.board_size,
+            walls_per_player: self.walls_per_player,
+            ..Rules::default()
+        }
+    }
+}
+
+/// 5x5 with 3 walls each and then 7x7 with 6 walls each, `iterations` each.
+pub fn default_curriculum(iterations: usize) -> Vec<CurriculumStage> {
+    vec![
+        CurriculumStage {
+            board_size: 5,
+            walls_per_player: 3,
+            iterations,
+        },
+        CurriculumStage {
+            board_size: 7,
+            walls_per_player: 6,
+            iterations,
+        },
+    ]
+}
+
+impl TrainingConfig {
+    /// The rules of the games in iteration `iteration`, counting from 0.
+    pub fn rules(&self, iteration: usize) -> Rules {
+        let mut stage_end = 0;
+        for stage in &self.curriculum {
+            stage_end += stage.iterations;
+            if iteration < stage_end {
+                return stage.rules();
+            }
+        }
+        Rules::default()
+    }
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 100,
+            network: NetworkKind::default(),
+            games_per_iteration: 50,
+            steps_per_iteration: 200,
+            batch_size: 128,
+            replay_size: 50_000,
+            deduplicate_replay: false,
+            learning_rate: 1e-3,
+            checkpoint_every: 1,
+            temperature: 1.0,
+            self_play: SelfPlayConfig::default(),
+            evaluation: EvaluationConfig::default(),
+            curriculum: Vec::new(),
+            validation_fraction: 0.1,
+            validate_every: 1,
+            overfit_warning: 0.1,
+        }
+    }
+}
+
+/// Contents of training.json.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainingCounters {
+    /// Iterations completed.
+    pub iteration: usize,
+    pub steps: usize,
+    pub games: usize,
+    /// The checkpoint holding the network after `iteration` iterations.
+    pub checkpoint: String,
+    /// The checkpoint playing the self-play games, empty for the latest one.
+    #[serde(default)]
+    pub best: String,
+}
+
+/// A line of metrics.jsonl.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IterationMetrics {
+    /// Iterations completed.
+    pub iteration: usize,
+    pub board_size: usize,
+    pub games: usize,
+    pub steps: usize,
+    /// Samples in the replay buffer.
+    pub replay: usize,
+    pub policy_loss: f32,
+    pub value_loss: f32,
+    /// The losses on the validation samples, in the iterations measuring them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_policy_loss: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation_value_loss: Option<f32>,
+}
+
+impl IterationMetrics {
+    /// How much higher the validation losses are than the training losses.
+    pub fn validation_gap(&self) -> Option<f32> {
+        Some(
+            self.validation_policy_loss? + self.validation_value_loss?
+                - self.policy_loss
+                - self.value_loss,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrainingState {
+    config: TrainingConfig,
+    counters: TrainingCounters,
+}
+
+pub struct Trainer {
+    run_dir: PathBuf,
+    config: TrainingConfig,
+    counters: TrainingCounters,
+    model: NetworkModel<TrainingBackend>,
+    best_network: QuoridorNet,
+    optimizer: TrainingOptimizer,
+    replay: ReplayBuffer,
+    /// Samples of the validation games, never trained on.
+    validation: ReplayBuffer,
+    /// The smallest validation gap so far, see `IterationMetrics::validation_gap`.
+    smallest_gap: Option<f32>,
+}
+
+fn checkpoint_name(iteration: usize) -> String {
+    format!("iter-{iteration:04}")
+}
+
+fn optimizer_path(run_dir: &Path, name: &str) -> PathBuf {
+    run_dir.join(format!("{name}-optimizer"))
+}
+
+fn replay_file(name: &str) -> String {
+    format!("{name}-replay.qshd")
+}
+
+fn validation_file(name: &str) -> String {
+    format!("{name}-validation.qshd")
+}
+
+/// Whether self-play game `game`, counting from 0, is a validation game. Spreads the
+/// validation games evenly, one in ten with a fraction of 0.1, the tenth first.
+fn is_validation_game(game: usize, fraction: f64) -> bool {
+    let validation_games = |games: usize| (games as f64 * fraction).floor() as usize;
+    validation_games(game + 1) > validation_games(game)
+}
+
+fn validation_buffer(config: &TrainingConfig) -> ReplayBuffer {
+    let capacity = (config.replay_size as f64 * config.validation_fraction).ceil() as usize;
+    ReplayBuffer::new(capacity, config.deduplicate_replay)
+}
+
+fn read_samples(path: &Path) -> Result<Vec<TrainingSample>, String> {
+    open_shard(path)
+        .and_then(|reader| reader.collect())
+        .map_err(|e| format!("Could not read {}: {e}", path.display()))
+}
+
+/// The mean policy cross-entropy and value squared error of `model` on `batch`.
+fn losses<B: Backend>(
+    model: &NetworkModel<B>,
+    batch: &[TrainingSample],
+    device: &B::Device,
+) -> (Tensor<B, 1>, Tensor<B, 1>) {
+    let policies: Vec<f32> = batch
+        .iter()
+        .flat_map(|sample| sample.policy.iter().copied())
+        .collect();
+    let policy_target =
+        Tensor::<B, 2>::from_data(TensorData::new(policies, [batch.len(), ACTIONS]), device);
+    let values: Vec<f32> = batch.iter().map(|sample| sample.value).collect();
+    let value_target = Tensor::<B, 2>::from_data(TensorData::new(values, [batch.len(), 1]), device);
+
+    let mut states = Vec::with_capacity(batch.len() * model.kind().input_len());
+    for sample in batch {
+        states.extend_from_slice(&sample.state.values);
+    }
+    let output = model.forward_flat(states, batch.len(), device);
+    let policy_loss = (log_softmax(output.policy, 1) * policy_target)
+        .sum_dim(1)
+        .neg()
+        .mean();
+    let value_loss = (output.value - value_target).powf_scalar(2.0).mean();
+    (policy_loss, value_loss)
+}
+
+fn recorder() -> NamedMpkFileRecorder<FullPrecisionSettings> {
+    NamedMpkFileRecorder::new()
+}
+
+/// Whether `run_dir` holds a run, to resume rather than start.
+pub fn has_run(run_dir: &Path) -> bool {
+    run_dir.join(STATE_FILE).exists()
+}
+
+/// The configuration and counters of the run in training.json.
+pub fn read_state(run_dir: &Path) -> Result<(TrainingConfig, TrainingCounters), String> {
+    let state_path = run_dir.join(STATE_FILE);
+    let json = std::fs::read_to_string(&state_path)
+        .map_err(|e| format!("Could not read {}: {e}", state_path.display()))?;
+    let mut state: TrainingState = serde_json::from_str(&json)
+        .map_err(|e| format!("Could not parse {}: {e}", state_path.display()))?;
+    if state.counters.best.is_empty() {
+        state.counters.best = state.counters.checkpoint.clone();
+    }
+    Ok((state.config, state.counters))
+}
+
+/// The metrics of every iteration run, empty before the first one.
+pub fn read_metrics(run_dir: &Path) -> Result<Vec<IterationMetrics>, String> {
+    let path = run_dir.join(METRICS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| format!("{}:{}: {e}", path.display(), index + 1))
+        })
+        .collect()
+}
+
+fn append_metrics(run_dir: &Path, metrics: &IterationMetrics) -> Result<(), String> {
+    let path = run_dir.join(METRICS_FILE);
+    let line = serde_json::to_string(metrics).unwrap() + "\n";
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| format!("Could not write {}: {e}", path.display()))
+}
+
+impl Trainer {
+    /// Starts a run from a fresh network, saved as its first checkpoint.
+    pub fn start(run_dir: &Path, config: TrainingConfig) -> Result<Self, String> {
+        if has_run(run_dir) {
+            return Err(format!(
+                "{} already holds a run, resume it instead",
+                run_dir.display()
+            ));
+        }
+        for stage in &config.curriculum {
+            stage.rules().validate()?;
+        }
+        let mut trainer = Self {
+            run_dir: run_dir.to_path_buf(),
+            counters: TrainingCounters {
+                iteration: 0,
+                steps: 0,
+                games: 0,
+                checkpoint: checkpoint_name(0),
+                best: checkpoint_name(0),
+            },
+            model: NetworkModel::new(config.network, &Default::default()),
+            best_network: QuoridorNet::with_kind(config.network),
+            optimizer: AdamConfig::new().init(),
+            replay: ReplayBuffer::new(config.replay_size, config.deduplicate_replay),
+            validation: validation_buffer(&config),
+            smallest_gap: None,
+            config,
+        };
+        trainer.best_network = trainer.network();
+        trainer.save(None)?;
+        Ok(trainer)
+    }
+
+    /// Continues a run from its latest complete checkpoint.
+    pub fn resume(run_dir: &Path) -> Result<Self, String> {
+        let (config, counters) = read_state(run_dir)?;
+        let name = &counters.checkpoint;
+        let best_network = load_checkpoint(run_dir, &counters.best)?.0;
+        let device = Default::default();
+        let model = load_model(&run_dir.join(name), &device)?;
+        let optimizer_path = optimizer_path(run_dir, name);
+        let record =
+            Recorder::<TrainingBackend>::load(&recorder(), optimizer_path.clone(), &device)
+                .map_err(|e| format!("Could not load {}: {e}", optimizer_path.display()))?;
+        let optimizer = AdamConfig::new().init().load_record(record);
+        let mut replay = ReplayBuffer::new(config.replay_size, config.deduplicate_replay);
+        replay.extend(read_samples(&run_dir.join(replay_file(name)))?);
+        let mut validation = validation_buffer(&config);
+        let validation_path = run_dir.join(validation_file(name));
+        if validation_path.exists() {
+            validation.extend(read_samples(&validation_path)?);
+        }
+        let smallest_gap = read_metrics(run_dir)?
+            .iter()
+            .filter_map(IterationMetrics::validation_gap)
+            .reduce(f32::min);
+        Ok(Self {
+            run_dir: run_dir.to_path_buf(),
+            config,
+            counters,
+            model,
+            best_network,
+            optimizer,
+            replay,
+            validation,
+            smallest_gap,
+        })
+    }
+
+    pub fn config(&self) -> &TrainingConfig {
+        &self.config
+    }
+
+    /// Changes the iteration training stops after, for extending a finished run.
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.config.iterations = iterations;
+    }
+
+    pub fn counters(&self) -> &TrainingCounters {
+        &self.counters
+    }
+
+    pub fn network(&self) -> QuoridorNet {
+        QuoridorNet::from_model(self.model.valid())
+    }
+
+    /// Runs iterations until the configured number, checkpointing along the way, or until
+    /// a shutdown is requested, checkpointing the iterations so far.
+    pub fn train(&mut self, shutdown: &Shutdown) -> Result<(), String> {
+        while self.counters.iteration < self.config.iterations {
+            let board_size = self.config.rules(self.counters.iteration).board_size;
+            let (policy_loss, value_loss) = self.run_iteration();
+            let validation_losses = self
+                .counters
+                .iteration
+                .is_multiple_of(self.config.validate_every.max(1))
+                .then(|| self.validation_losses())
+                .flatten();
+            println!(
+                "Iteration {} on {}x{}: {} games, {} steps, replay {}, policy loss {:.4}, value loss {:.4}",
+                self.counters.iteration,
+                board_size,
+                board_size,
+                self.counters.games,
+                self.counters.steps,
+                self.replay.len(),
+                policy_loss,
+                value_loss
+            );
+            let metrics = IterationMetrics {
+                iteration: self.counters.iteration,
+                board_size,
+                games: self.counters.games,
+                steps: self.counters.steps,
+                replay: self.replay.len(),
+                policy_loss,
+                value_loss,
+                validation_policy_loss: validation_losses.map(|(policy, _)| policy),
+                validation_value_loss: validation_losses.map(|(_, value)| value),
+            };
+            if let Some(gap) = metrics.validation_gap() {
+                println!(
+                    "Validation on {} samples: policy loss {:.4}, value loss {:.4}, {:.4} above training",
+                    self.validation.len(),
+                    metrics.validation_policy_loss.unwrap(),
+                    metrics.validation_value_loss.unwrap(),
+                    gap
+                );
+                let smallest_gap = *self.smallest_gap.get_or_insert(gap);
+                if gap - smallest_gap > self.config.overfit_warning {
+                    println!(
+                        "Warning: the validation gap grew from {smallest_gap:.4} to {gap:.4}, \
+                         the network may be learning its games by heart"
+                    );
+                }
+                self.smallest_gap = Some(smallest_gap.min(gap));
+            }
+            append_metrics(&self.run_dir, &metrics)?;
+            if self
+                .counters
+                .iteration
+                .is_multiple_of(self.config.checkpoint_every.max(1))
+                || self.counters.iteration == self.config.iterations
+                || shutdown.requested()
+            {
+                self.checkpoint()?;
+                println!(
+                    "Saved checkpoint {}, playing self-play with {}",
+                    self.counters.checkpoint, self.counters.best
+                );
+            }
+            if shutdown.requested() {
+                println!(
+                    "Interrupted after iteration {}, resume to continue",
+                    self.counters.iteration
+                );
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Plays the self-play games and training steps of one iteration, returning the
+    /// mean policy and value losses.
+    pub fn run_iteration(&mut self) -> (f32, f32) {
+        let rules = self.config.rules(self.counters.iteration);
+        let new_network = || {
+            network_player(
+                &self.best_network,
+                self.config.temperature,
+                &self.config.self_play,
+            )
+        };
+        let mut rng = rand::rng();
+        for _ in 0..self.config.games_per_iteration {
+            let opponent = self.config.self_play.pick_opponent(&mut rng);
+            let network_color = if self.counters.games.is_multiple_of(2) {
+                Player::White
+            } else {
+                Player::Black
+            };
+            let training_game = play_training_game(
+                &new_network,
+                opponent,
+                network_color,
+                &rules,
+                &self.config.self_play,
+            );
+            let samples = training_samples(
+                &training_game,
+                self.model.kind(),
+                &self.config.self_play.targets,
+            );
+            if is_validation_game(self.counters.games, self.config.validation_fraction) {
+                self.validation.extend(samples);
+            } else {
+                self.replay.extend(samples);
+            }
+            self.counters.games += 1;
+        }
+
+        let (mut policy_total, mut value_total) = (0.0, 0.0);
+        let steps = if self.replay.is_empty() {
+            0
+        } else {
+            self.config.steps_per_iteration
+        };
+        for _ in 0..steps {
+            let batch: Vec<TrainingSample> = (0..self.config.batch_size)
+                .map(|_| {
+                    self.replay
+                        .get(rng.random_range(0..self.replay.len()))
+                        .clone()
+                })
+                .collect();
+            let (policy_loss, value_loss) = self.train_step(&batch);
+            policy_total += policy_loss;
+            value_total += value_loss;
+            self.counters.steps += 1;
+        }
+        self.counters.iteration += 1;
+        let steps = steps.max(1) as f32;
+        (policy_total / steps, value_total / steps)
+    }
+
+    fn train_step(&mut self, batch: &[TrainingSample]) -> (f32, f32) {
+        let (policy_loss, value_loss) = losses(&self.model, batch, &Default::default());
+        let loss = policy_loss.clone() + value_loss.clone();
+        let gradients = GradientsParams::from_grads(loss.backward(), &self.model);
+        self.model = self
+            .optimizer
+            .step(self.config.learning_rate, self.model.clone(), gradients);
+        (
+            policy_loss.into_scalar().elem(),
+            value_loss.into_scalar().elem(),
+        )
+    }
+
+    /// The mean policy and value losses on the validation samples, None without any.
+    pub fn validation_losses(&self) -> Option<(f32, f32)> {
+        if self.validation.is_empty() {
+            return None;
+        }
+        let model = self.model.valid();
+        let samples: Vec<TrainingSample> = self.validation.iter().cloned().collect();
+        let (mut policy_total, mut value_total) = (0.0, 0.0);
+        for batch in samples.chunks(self.config.batch_size.max(1)) {
+            let (policy_loss, value_loss) = losses(&model, batch, &Default::default());
+            // Weighted by the batch size, the last batch may be smaller
+            let weight = batch.len() as f32;
+            policy_total += policy_loss.into_scalar().elem::<f32>() * weight;
+            value_total += value_loss.into_scalar().elem::<f32>() * weight;
+        }
+        let samples = samples.len() as f32;
+        Some((policy_total / samples, value_total / samples))
+    }
+
+    /// Saves the network after the iterations so far as a new checkpoint and, when it
+    /// holds its own in the evaluation arena, promotes it to play the self-play games.
+    pub fn checkpoint(&mut self) -> Result<(), String> {
+        let parent = std::mem::replace(
+            &mut self.counters.checkpoint,
+            checkpoint_name(self.counters.iteration),
+        );
+        self.save(Some(&parent))?;
+        if parent != self.counters.checkpoint {
+            // Best effort: left over files only cost disk space
+            let _ =
+                std::fs::remove_file(optimizer_path(&self.run_dir, &parent).with_extension("mpk"));
+            let _ = std::fs::remove_file(self.run_dir.join(replay_file(&parent)));
+            let _ = std::fs::remove_file(self.run_dir.join(validation_file(&parent)));
+        }
+
+        let candidate = &self.counters.checkpoint;
+        if *candidate == self.counters.best {
+            return Ok(());
+        }
+        if self.config.evaluation.games > 0 {
+            let result = evaluate(
+                &self.run_dir,
+                candidate,
+                &self.counters.best,
+                // The rules the winner plays self-play with next
+                &self.config.rules(self.counters.iteration),
+                &self.config.evaluation,
+            )?;
+            println!(
+                "{} against {}: +{} -{} ={} ({:.0}%)",
+                candidate,
+                self.counters.best,
+                result.wins,
+                result.losses,
+                result.draws,
+                100.0 * score(&result)
+            );
+            if !is_promoted(&result) {
+                return Ok(());
+            }
+        }
+        self.counters.best = candidate.clone();
+        self.best_network = self.network();
+        self.write_state()
+    }
+
+    fn save(&self, parent: Option<&str>) -> Result<(), String> {
+        let name = &self.counters.checkpoint;
+        let mut manifest = CheckpointManifest::new(name, parent);
+        manifest.training_config = serde_json::to_value(&self.config).unwrap();
+        manifest.shards.push(replay_file(name));
+        if !self.validation.is_empty() {
+            manifest.shards.push(validation_file(name));
+        }
+        save_checkpoint(&self.run_dir, &self.network(), &manifest)?;
+
+        let optimizer_path = optimizer_path(&self.run_dir, name);
+        Recorder::<TrainingBackend>::record(
+            &recorder(),
+            self.optimizer.to_record(),
+            optimizer_path.clone(),
+        )
+        .map_err(|e| format!("Could not save {}: {e}", optimizer_path.display()))?;
+
+        self.write_samples(&replay_file(name), self.replay.iter())?;
+        if !self.validation.is_empty() {
+            self.write_samples(&validation_file(name), self.validation.iter())?;
+        }
+        self.write_state()
+    }
+
+    fn write_samples<'a>(
+        &self,
+        file: &str,
+        mut samples: impl Iterator<Item = &'a TrainingSample>,
+    ) -> Result<(), String> {
+        let path = self.run_dir.join(file);
+        let [channels, height, width] = self.model.kind().input_shape();
+        let mut writer = create_shard(&path, channels, height, width, ACTIONS)
+            .map_err(|e| format!("Could not create {}: {e}", path.display()))?;
+        samples
+            .try_for_each(|sample| writer.write(sample))
+            .and_then(|_| writer.finish())
+            .and_then(|file| file.into_inner().map_err(|e| e.into_error()))
+            .and_then(|file| file.sync_all())
+            .map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    /// Replaces training.json, making the files it names the ones a resume starts from.
+    fn write_state(&self) -> Result<(), String> {
+        let state = TrainingState {
+            config: self.config.clone(),
+            counters: self.counters.clone(),
+        };
+        let state_path = self.run_dir.join(STATE_FILE);
+        let temporary_path = state_path.with_extension("json.tmp");
+        std::fs::write(
+            &temporary_path,
+            serde_json::to_string_pretty(&state).unwrap(),
+        )
+        .and_then(|_| std::fs::rename(&temporary_path, &state_path))
+        .map_err(|e| format!("Could not write {}: {e}", state_path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nn_bot::predict_batch;
+    use quoridor_core::data_model::Game;
+
+    #[test]
+    fn resume_test() {
+        resume(NetworkKind::Conv);
+        resume(NetworkKind::FeatureMlp);
+    }
+
+    /// Trains a run of `network`, resuming it from its checkpoints.
+    fn resume(network: NetworkKind) {
+        let run_dir =
+            std::env::temp_dir().join(format!("training-test-{}-{network:?}", std::process::id()));
+        let config = TrainingConfig {
+            iterations: 2,
+            network,
+            games_per_iteration: 1,
+            steps_per_iteration: 1,
+            batch_size: 2,
+            self_play: SelfPlayConfig {
+                opponent_fraction: 0.0,
+                max_plies: 4,
+                ..SelfPlayConfig::default()
+            },
+            evaluation: EvaluationConfig {
+                games: 0,
+                ..EvaluationConfig::default()
+            },
+            ..TrainingConfig::default()
+        };
+        let mut trainer = Trainer::start(&run_dir, config.clone()).unwrap();
+        assert!(Trainer::start(&run_dir, config).is_err());
+        trainer.run_iteration();
+        trainer.checkpoint().unwrap();
+        // Progress after the last checkpoint is lost in a crash
+        trainer.run_iteration();
+
+        let resumed = Trainer::resume(&run_dir).unwrap();
+        assert_eq!(resumed.counters().iteration, 1);
+        assert_eq!(resumed.counters().steps, 1);
+        assert_eq!(resumed.counters().checkpoint, "iter-0001");
+        assert_eq!(resumed.counters().best, "iter-0001");
+        assert_eq!(resumed.replay.len(), 4);
+        assert!(!run_dir.join(replay_file("iter-0000")).exists());
+        let value = |trainer: &Trainer| {
+            predict_batch(
+                &trainer.network(),
+                &[trainer.network().encode(&Game::new())],
+            )[0]
+            .value
+        };
+        let mut trainer = Trainer::resume(&run_dir).unwrap();
+        assert_eq!(value(&trainer), value(&resumed));
+        trainer.train(&Shutdown::default()).unwrap();
+        assert_eq!(Trainer::resume(&run_dir).unwrap().counters().iteration, 2);
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+
+    #[test]
+    fn validation_test() {
+        let games: Vec<bool> = (0..20).map(|game| is_validation_game(game, 0.1)).collect();
+        assert_eq!(games.iter().filter(|&&validation| validation).count(), 2);
+        assert!(games[9] && games[19]);
+        assert!(!(0..20).any(|game| is_validation_game(game, 0.0)));
+
+        let run_dir = std::env::temp_dir().join(format!("validation-test-{}", std::process::id()));
+        let config = TrainingConfig {
+            iterations: 1,
+            network: NetworkKind::FeatureMlp,
+            games_per_iteration: 2,
+            steps_per_iteration: 1,
+            batch_size: 3,
+            self_play: SelfPlayConfig {
+                opponent_fraction: 0.0,
+                max_plies: 4,
+                ..SelfPlayConfig::default()
+            },
+            evaluation: EvaluationConfig {
+                games: 0,
+                ..EvaluationConfig::default()
+            },
+            validation_fraction: 0.5,
+            ..TrainingConfig::default()
+        };
+        let mut trainer = Trainer::start(&run_dir, config).unwrap();
+        assert_eq!(trainer.validation_losses(), None);
+        trainer.train(&Shutdown::default()).unwrap();
+        // The second game is kept out of training
+        assert_eq!(trainer.replay.len(), 4);
+        assert_eq!(trainer.validation.len(), 4);
+        let metrics = read_metrics(&run_dir).unwrap();
+        assert!(metrics[0].validation_gap().is_some());
+        let resumed = Trainer::resume(&run_dir).unwrap();
+        assert_eq!(resumed.validation.len(), 4);
+        assert_eq!(resumed.smallest_gap, metrics[0].validation_gap());
+        std::fs::remove_dir_all(&run_dir).unwrap();
+    }
+
+    #[test]
+    fn curriculum_test() {
+        let config = TrainingConfig {
+            curriculum: default_curriculum(2),
+            ..TrainingConfig::default()
+        };
+        let board_sizes: Vec<_> = (0..6)
+            .map(|iteration| config.rules(iteration).board_size)
+            .collect();
+        assert_eq!(board_sizes, [5, 5, 7, 7, 9, 9]);
+        assert_eq!(config.rules(0).walls_per_player, 3);
+        assert_eq!(config.rules(4), Rules::default());
+
+        let run_dir = std::env::temp_dir().join(format!("curriculum-test-{}", std::process::id()));
+        let invalid = TrainingConfig {
+            curriculum: vec![CurriculumStage {
+                board_size: 4,
+                walls_per_player: 2,
+                iterations: 1,
+            }],
+            ..TrainingConfig::default()
+        };
+        assert!(Trainer::start(&run_dir, invalid).is_err());
+        assert!(!run_dir.exists());
+    }
+}
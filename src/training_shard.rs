@@ -0,0 +1,316 @@
+// Binary training shards.
+//
+// Layout (all integers little endian):
+//   header: magic "QSHD", version u16, channels u16, height u16, width u16, actions u16
+//   chunks: record count u32, compressed length u32, deflate compressed records
+//   record: channels * height * width f32 planes, actions f32 policy, f32 value
+//
+// The dimensions live in the header, so shards written with an older encoding
+// stay readable after the channel count changes.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+
+use crate::nn_bot::EncodedState;
+
+const MAGIC: &[u8; 4] = b"QSHD";
+pub const SHARD_VERSION: u16 = 1;
+const RECORDS_PER_CHUNK: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardHeader {
+    pub version: u16,
+    pub channels: u16,
+    pub height: u16,
+    pub width: u16,
+    pub actions: u16,
+}
+
+/// One (encoded planes, policy target, value target) training record.
+#[derive(Clone)]
+pub struct TrainingSample {
+    pub state: EncodedState,
+    pub policy: Vec<f32>,
+    pub value: f32,
+}
+
+impl ShardHeader {
+    /// Channels, height and width of the encoded states.
+    fn shape(&self) -> [usize; 3] {
+        [self.channels, self.height, self.width].map(usize::from)
+    }
+
+    fn plane_len(&self) -> usize {
+        self.shape().iter().product()
+    }
+
+    fn record_len(&self) -> usize {
+        4 * (self.plane_len() + self.actions as usize + 1)
+    }
+
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        for field in [
+            self.version,
+            self.channels,
+            self.height,
+            self.width,
+            self.actions,
+        ] {
+            writer.write_all(&field.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a training shard"));
+        }
+        let mut fields = [0u16; 5];
+        for field in fields.iter_mut() {
+            let mut bytes = [0; 2];
+            reader.read_exact(&mut bytes)?;
+            *field = u16::from_le_bytes(bytes);
+        }
+        let [version, channels, height, width, actions] = fields;
+        if version > SHARD_VERSION {
+            return Err(invalid_data(&format!(
+                "shard version {version} is newer than supported version {SHARD_VERSION}"
+            )));
+        }
+        Ok(Self {
+            version,
+            channels,
+            height,
+            width,
+            actions,
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub struct ShardWriter<W: Write> {
+    inner: W,
+    header: ShardHeader,
+    chunk: Vec<u8>,
+    chunk_records: u32,
+}
+
+impl<W: Write> ShardWriter<W> {
+    pub fn new(
+        mut inner: W,
+        channels: usize,
+        height: usize,
+        width: usize,
+        actions: usize,
+    ) -> io::Result<Self> {
+        let header = ShardHeader {
+            version: SHARD_VERSION,
+            channels: channels as u16,
+            height: height as u16,
+            width: width as u16,
+            actions: actions as u16,
+        };
+        header.write(&mut inner)?;
+        Ok(Self {
+            inner,
+            header,
+            chunk: Vec::new(),
+            chunk_records: 0,
+        })
+    }
+
+    pub fn header(&self) -> ShardHeader {
+        self.header
+    }
+
+    pub fn write(&mut self, sample: &TrainingSample) -> io::Result<()> {
+        let header = self.header;
+        if sample.state.shape != header.shape() || sample.policy.len() != header.actions as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "sample does not match the shard dimensions",
+            ));
+        }
+        for value in sample
+            .state
+            .values
+            .iter()
+            .chain(&sample.policy)
+            .chain(std::iter::once(&sample.value))
+        {
+            self.chunk.extend_from_slice(&value.to_le_bytes());
+        }
+        self.chunk_records += 1;
+        if self.chunk_records == RECORDS_PER_CHUNK {
+            self.flush_chunk()?;
+        }
+        Ok(())
+    }
+
+    fn flush_chunk(&mut self) -> io::Result<()> {
+        if self.chunk_records == 0 {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.chunk)?;
+        let compressed = encoder.finish()?;
+        self.inner.write_all(&self.chunk_records.to_le_bytes())?;
+        self.inner
+            .write_all(&(compressed.len() as u32).to_le_bytes())?;
+        self.inner.write_all(&compressed)?;
+        self.chunk.clear();
+        self.chunk_records = 0;
+        Ok(())
+    }
+
+    /// Writes any pending records and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_chunk()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Streams samples from a shard, decompressing one chunk at a time.
+pub struct ShardReader<R: Read> {
+    inner: R,
+    header: ShardHeader,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> ShardReader<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let header = ShardHeader::read(&mut inner)?;
+        Ok(Self {
+            inner,
+            header,
+            chunk: Vec::new(),
+            position: 0,
+        })
+    }
+
+    pub fn header(&self) -> ShardHeader {
+        self.header
+    }
+
+    /// Loads the next chunk, returning false at the end of the shard.
+    fn next_chunk(&mut self) -> io::Result<bool> {
+        let mut bytes = [0; 4];
+        match self.inner.read_exact(&mut bytes) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        let records = u32::from_le_bytes(bytes) as usize;
+        self.inner.read_exact(&mut bytes)?;
+        let compressed_len = u32::from_le_bytes(bytes) as u64;
+        self.chunk.clear();
+        DeflateDecoder::new((&mut self.inner).take(compressed_len)).read_to_end(&mut self.chunk)?;
+        self.position = 0;
+        if self.chunk.len() != records * self.header.record_len() {
+            return Err(invalid_data("chunk length does not match its record count"));
+        }
+        Ok(true)
+    }
+
+    fn read_sample(&mut self) -> TrainingSample {
+        let header = self.header;
+        let record = &self.chunk[self.position..self.position + header.record_len()];
+        self.position += header.record_len();
+        let mut values = record
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()));
+        let planes = values.by_ref().take(header.plane_len()).collect();
+        let policy = values.by_ref().take(header.actions as usize).collect();
+        let value = values.next().unwrap();
+        TrainingSample {
+            state: EncodedState::new(planes, header.shape()),
+            policy,
+            value,
+        }
+    }
+}
+
+impl<R: Read> Iterator for ShardReader<R> {
+    type Item = io::Result<TrainingSample>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position >= self.chunk.len() {
+            match self.next_chunk() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok(self.read_sample()))
+    }
+}
+
+pub fn create_shard(
+    path: &Path,
+    channels: usize,
+    height: usize,
+    width: usize,
+    actions: usize,
+) -> io::Result<ShardWriter<BufWriter<File>>> {
+    ShardWriter::new(
+        BufWriter::new(File::create(path)?),
+        channels,
+        height,
+        width,
+        actions,
+    )
+}
+
+pub fn open_shard(path: &Path) -> io::Result<ShardReader<BufReader<File>>> {
+    ShardReader::new(BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(i: usize) -> TrainingSample {
+        TrainingSample {
+            state: EncodedState::new(vec![i as f32; 12], [2, 2, 3]),
+            policy: vec![0.5, 0.25, 0.25],
+            value: if i.is_multiple_of(2) { 1.0 } else { -1.0 },
+        }
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let mut writer = ShardWriter::new(Vec::new(), 2, 2, 3, 3).unwrap();
+        let count = RECORDS_PER_CHUNK as usize + 10;
+        for i in 0..count {
+            writer.write(&sample(i)).unwrap();
+        }
+        let bytes = writer.finish().unwrap();
+        let reader = ShardReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.header().channels, 2);
+        let samples: Vec<_> = reader.collect::<io::Result<_>>().unwrap();
+        assert_eq!(samples.len(), count);
+        assert_eq!(samples[7].state.plane(1)[5], 7.0);
+        assert_eq!(samples[7].policy, vec![0.5, 0.25, 0.25]);
+        assert_eq!(samples[7].value, -1.0);
+    }
+
+    #[test]
+    fn mismatched_sample_test() {
+        let mut writer = ShardWriter::new(Vec::new(), 3, 2, 3, 3).unwrap();
+        assert!(writer.write(&sample(0)).is_err());
+        assert!(ShardReader::new(&b"nope"[..]).is_err());
+    }
+}